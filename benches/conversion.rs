@@ -0,0 +1,191 @@
+//! Benchmarks for the Bencode to JSON conversion.
+//!
+//! Run them with:
+//!
+//! ```text
+//! cargo bench
+//! ```
+//!
+//! The `library` group measures the parser alone, reading from memory and
+//! writing to a sink, with and without capturing the latest bytes for
+//! errors, and with the scalar scanning of integers and string lengths.
+//!
+//! The `cli_vs_be2json` group runs the console command and the C
+//! implementation in [`contrib/be2json.c`](../contrib/be2json.c) as child
+//! processes with the same input. The C implementation is compiled with `cc`
+//! the first time; the group is skipped if it can't be compiled.
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use torrust_bencode2json::parsers::{
+    options::{CaptureMode, ScanMode},
+    BencodeParser,
+};
+
+/// It returns a bencoded torrent-like dictionary with a `pieces` value of
+/// `pieces_length` bytes that are not valid UTF-8 (written in hexadecimal)
+/// and a list of `number_of_files` files.
+fn torrent(pieces_length: usize, number_of_files: usize) -> Vec<u8> {
+    let announce = "udp://tracker.example.com:6969/announce";
+    let mut input = format!("d8:announce{}:{announce}4:infod5:filesl", announce.len()).into_bytes();
+
+    for i in 0..number_of_files {
+        let path = format!("file-{i}.txt");
+        input.extend_from_slice(
+            format!("d6:lengthi{}e4:pathl{}:{path}ee", i * 1024, path.len()).as_bytes(),
+        );
+    }
+
+    input.extend_from_slice(b"e4:name7:example12:piece lengthi262144e6:pieces");
+    input.extend_from_slice(format!("{pieces_length}:").as_bytes());
+    #[allow(clippy::cast_possible_truncation)]
+    input.extend((0..pieces_length).map(|i| (i % 256) as u8 | 0x80));
+    input.extend_from_slice(b"ee");
+
+    input
+}
+
+/// It returns a bencoded UTF-8 string of `length` bytes.
+fn long_utf8_string(length: usize) -> Vec<u8> {
+    let mut input = format!("{length}:").into_bytes();
+    input.extend(std::iter::repeat_n(b'a', length));
+    input
+}
+
+/// It returns a bencoded list of `count` integers.
+fn many_integers(count: usize) -> Vec<u8> {
+    let mut input = b"l".to_vec();
+    for i in 0..count {
+        input.extend_from_slice(format!("i{}e", i * 7919).as_bytes());
+    }
+    input.push(b'e');
+    input
+}
+
+fn inputs() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("torrent", torrent(1024 * 1024, 1000)),
+        ("utf8_string", long_utf8_string(4 * 1024 * 1024)),
+        ("integers", many_integers(100_000)),
+    ]
+}
+
+fn library(c: &mut Criterion) {
+    let mut group = c.benchmark_group("library");
+
+    for (name, input) in inputs() {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("new", name), &input, |b, input| {
+            b.iter(|| {
+                BencodeParser::new(input.as_slice())
+                    .write_bytes(io::sink())
+                    .unwrap();
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("capture_off", name), &input, |b, input| {
+            b.iter(|| {
+                BencodeParser::builder(input.as_slice())
+                    .capture_mode(CaptureMode::Off)
+                    .build_from_buf_read()
+                    .write_bytes(io::sink())
+                    .unwrap();
+            });
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("from_buf_read", name),
+            &input,
+            |b, input| {
+                b.iter(|| {
+                    BencodeParser::from_buf_read(input.as_slice())
+                        .write_bytes(io::sink())
+                        .unwrap();
+                });
+            },
+        );
+
+        group.bench_with_input(BenchmarkId::new("scalar_scan", name), &input, |b, input| {
+            b.iter(|| {
+                BencodeParser::builder(input.as_slice())
+                    .scan_mode(ScanMode::Scalar)
+                    .build_from_buf_read()
+                    .write_bytes(io::sink())
+                    .unwrap();
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// It compiles `contrib/be2json.c` into the target directory. It returns
+/// `None` if it can't be compiled.
+fn compile_be2json() -> Option<PathBuf> {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let binary = manifest_dir.join("target").join("be2json");
+
+    if binary.exists() {
+        return Some(binary);
+    }
+
+    let status = Command::new("cc")
+        .arg("-O2")
+        .arg(manifest_dir.join("contrib").join("be2json.c"))
+        .arg("-o")
+        .arg(&binary)
+        .status()
+        .ok()?;
+
+    status.success().then_some(binary)
+}
+
+/// It runs the command with the input on stdin, discarding the output.
+fn run(program: &Path, input: &[u8]) {
+    let mut child = Command::new(program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input).unwrap();
+
+    child.wait().unwrap();
+}
+
+fn cli_vs_be2json(c: &mut Criterion) {
+    let Some(be2json) = compile_be2json() else {
+        eprintln!("Skipping `cli_vs_be2json`: `contrib/be2json.c` could not be compiled");
+        return;
+    };
+
+    let cli = PathBuf::from(env!("CARGO_BIN_EXE_torrust-bencode2json"));
+
+    let mut group = c.benchmark_group("cli_vs_be2json");
+    group.sample_size(20);
+
+    for (name, input) in inputs() {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("bencode2json", name),
+            &input,
+            |b, input| {
+                b.iter(|| run(&cli, input));
+            },
+        );
+
+        group.bench_with_input(BenchmarkId::new("be2json.c", name), &input, |b, input| {
+            b.iter(|| run(&be2json, input));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, library, cli_vs_be2json);
+criterion_main!(benches);
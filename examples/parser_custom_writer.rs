@@ -0,0 +1,52 @@
+//! Run with:
+//!
+//! ```not_rust
+//! cargo run --example parser_custom_writer
+//! ```
+//!
+//! It writes the JSON to a custom writer that counts the bytes, and prints
+//! `18 bytes: {"spam":["a","b"]}`.
+use torrust_bencode2json::{
+    parsers::BencodeParser,
+    rw::{error::Error, writer::Writer},
+};
+
+/// A writer that keeps the JSON in a string and counts the bytes.
+#[derive(Default)]
+struct CountingWriter {
+    json: String,
+    output_byte_counter: u64,
+}
+
+impl Writer for CountingWriter {
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        self.json.push(char::from(byte));
+        self.output_byte_counter += 1;
+        Ok(())
+    }
+
+    fn write_str(&mut self, value: &str) -> Result<(), Error> {
+        self.json.push_str(value);
+        self.output_byte_counter += value.len() as u64;
+        Ok(())
+    }
+
+    fn output_byte_counter(&self) -> u64 {
+        self.output_byte_counter
+    }
+
+    fn captured_bytes(&self) -> Vec<u8> {
+        Vec::new()
+    }
+}
+
+fn main() {
+    let mut writer = CountingWriter::default();
+
+    if let Err(e) = BencodeParser::new(&b"d4:spaml1:a1:bee"[..]).write_to(&mut writer) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+
+    println!("{} bytes: {}", writer.output_byte_counter, writer.json);
+}
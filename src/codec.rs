@@ -0,0 +1,668 @@
+//! Codecs for [tokio-util](https://docs.rs/tokio-util) to frame streams of
+//! bencoded values.
+//!
+//! It's only available with the `codec` feature.
+//!
+//! Some protocols send bencoded values back to back, without a length prefix
+//! or a delimiter. The codecs split the stream into frames with one complete
+//! top-level value each. They use the [`PushParser`] to know when a value is
+//! complete, so the parser options apply, including the limits:
+//!
+//! - [`BencodeCodec`] decodes the raw bytes of each value and encodes bytes
+//!   as they are.
+//! - [`JsonCodec`] decodes the JSON of each value and encodes serializable
+//!   values into Bencode.
+//! - [`ValueCodec`] decodes each value into a [`serde_json::Value`] and
+//!   encodes serializable values into Bencode.
+//!
+//! ```rust
+//! use bytes::BytesMut;
+//! use serde_json::json;
+//! use tokio_util::codec::{Decoder, Encoder};
+//! use torrust_bencode2json::codec::ValueCodec;
+//!
+//! let mut codec = ValueCodec::new();
+//!
+//! let mut buffer = BytesMut::new();
+//! codec.encode(json!({"t": "aa", "y": "q"}), &mut buffer).unwrap();
+//! codec.encode(json!({"t": "bb", "y": "r"}), &mut buffer).unwrap();
+//!
+//! assert_eq!(&buffer[..], b"d1:t2:aa1:y1:qed1:t2:bb1:y1:re");
+//!
+//! assert_eq!(codec.decode(&mut buffer).unwrap(), Some(json!({"t": "aa", "y": "q"})));
+//! assert_eq!(codec.decode(&mut buffer).unwrap(), Some(json!({"t": "bb", "y": "r"})));
+//! assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+//! ```
+//!
+//! Line breaks between values are skipped when they are ignored by the parser
+//! options. They are not part of the frames.
+use std::{collections::VecDeque, io};
+
+use bytes::{Bytes, BytesMut};
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{
+    encoder,
+    parsers::{
+        error::Error as ParserError,
+        options::ParserOptions,
+        push::{CompletedValue, Progress, PushParser},
+    },
+};
+
+/// Errors that can occur while decoding or encoding frames.
+#[derive(Debug, Error)]
+pub enum CodecError {
+    /// I/O error.
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    /// The input is invalid Bencode, or it exceeds the parser limits.
+    #[error("{0}")]
+    Parser(#[from] ParserError),
+
+    /// The value being decoded is longer than the maximum frame length.
+    #[error("Frame longer than the maximum length of {0} bytes")]
+    FrameTooLong(usize),
+
+    /// The JSON output can't be decoded into a value.
+    #[error("Invalid JSON output: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The item can't be encoded into Bencode.
+    #[error("{0}")]
+    Encoder(#[from] encoder::Error),
+}
+
+/// It defines a codec that splits the stream into frames with a [`Framer`],
+/// and decodes each frame with the given function.
+macro_rules! codec {
+    ($(#[$attr:meta])* $name:ident, $item:ty, $decode_frame:expr) => {
+        $(#[$attr])*
+        #[derive(Default)]
+        pub struct $name {
+            framer: Framer,
+        }
+
+        impl $name {
+            #[must_use]
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            /// It creates a codec with custom parser options.
+            #[must_use]
+            pub fn with_options(options: ParserOptions) -> Self {
+                Self {
+                    framer: Framer::new(options),
+                }
+            }
+
+            /// It sets the maximum length of a frame. Values longer than it
+            /// fail to decode, even if they are not complete yet, so it
+            /// also limits the bytes buffered. There is no maximum by
+            /// default.
+            #[must_use]
+            pub fn with_max_frame_length(mut self, max_frame_length: usize) -> Self {
+                self.framer.max_frame_length = Some(max_frame_length);
+                self
+            }
+        }
+
+        impl Decoder for $name {
+            type Item = $item;
+            type Error = CodecError;
+
+            fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+                self.framer.decode(src)?.map($decode_frame).transpose()
+            }
+
+            fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+                self.framer.decode_eof(src)?.map($decode_frame).transpose()
+            }
+        }
+    };
+}
+
+/// It implements the encoder of a codec for serializable items.
+macro_rules! serializing_encoder {
+    ($name:ident) => {
+        /// Items are encoded with [`encoder::encode`]. A `String` item is
+        /// encoded as a Bencode string: to encode JSON text, decode it into a
+        /// [`serde_json::Value`] first.
+        impl<T: Serialize> Encoder<T> for $name {
+            type Error = CodecError;
+
+            fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+                dst.extend_from_slice(&encoder::encode(&item)?);
+                Ok(())
+            }
+        }
+    };
+}
+
+codec!(
+    /// A codec for the raw bytes of bencoded values.
+    ///
+    /// The encoder writes the bytes as they are, without checking them.
+    BencodeCodec,
+    Bytes,
+    |frame: Frame| Ok(frame.bytes.freeze())
+);
+
+impl Encoder<Bytes> for BencodeCodec {
+    type Error = CodecError;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(&item);
+        Ok(())
+    }
+}
+
+codec!(
+    /// A codec for the JSON of bencoded values.
+    JsonCodec,
+    String,
+    |frame: Frame| Ok(frame.json)
+);
+
+serializing_encoder!(JsonCodec);
+
+codec!(
+    /// A codec for bencoded values decoded into [`serde_json::Value`]s.
+    ///
+    /// Integers that don't fit in 64 bits are decoded as floats by `serde_json`.
+    /// Use [`IntegerPolicy::StringIfUnsafe`](crate::parsers::options::IntegerPolicy::StringIfUnsafe)
+    /// to keep all their digits.
+    ValueCodec,
+    Value,
+    |frame: Frame| Ok(serde_json::from_str(&frame.json)?)
+);
+
+serializing_encoder!(ValueCodec);
+
+/// A complete top-level value.
+struct Frame {
+    bytes: BytesMut,
+    json: String,
+}
+
+/// It splits the decoder buffer into frames with the push parser.
+///
+/// The buffer keeps the bytes of the values that have not been returned yet.
+/// The parser may complete several values with one read, so the completed
+/// values wait in a queue until they are returned. Bytes that are not part
+/// of any value, like ignored line breaks, are dropped from the buffer.
+#[derive(Default)]
+struct Framer {
+    parser: PushParser,
+
+    /// Stream position of the first byte in the decoder buffer.
+    offset: u64,
+
+    /// Number of bytes at the beginning of the decoder buffer that have
+    /// already been fed to the parser.
+    fed: usize,
+
+    /// The JSON output of the values in the queue, and the partial output of
+    /// the next one.
+    json: String,
+
+    /// The completed values that have not been returned yet. The end of
+    /// their JSON output is a position in `json`.
+    completed_values: VecDeque<CompletedValue>,
+
+    /// Index in the decoder buffer where the value that is not complete yet
+    /// begins, once it has been found.
+    pending_value_start: Option<usize>,
+
+    /// True when the end of the stream has been fed to the parser.
+    finished: bool,
+
+    max_frame_length: Option<usize>,
+}
+
+impl Framer {
+    fn new(options: ParserOptions) -> Self {
+        Self {
+            parser: PushParser::with_options(options),
+            ..Self::default()
+        }
+    }
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Frame>, CodecError> {
+        if self.completed_values.is_empty() && !self.finished && src.len() > self.fed {
+            let progress = self.parser.feed(&src[self.fed..])?;
+            self.fed = src.len();
+            self.push(progress);
+        }
+
+        if let Some(frame) = self.next_frame(src)? {
+            return Ok(Some(frame));
+        }
+
+        if !self.parser.is_within_value() {
+            self.skip(src, self.fed);
+            return Ok(None);
+        }
+
+        // Line breaks before the value are not part of the frame
+        let start = *self.pending_value_start.get_or_insert_with(|| {
+            src[..self.fed]
+                .iter()
+                .take_while(|&&byte| byte == b'\n')
+                .count()
+        });
+
+        self.check_frame_length(self.fed - start)?;
+
+        Ok(None)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Frame>, CodecError> {
+        if let Some(frame) = self.decode(src)? {
+            return Ok(Some(frame));
+        }
+
+        if !self.finished {
+            self.finished = true;
+
+            let progress = self.parser.finish()?;
+            self.push(progress);
+
+            if let Some(frame) = self.next_frame(src)? {
+                return Ok(Some(frame));
+            }
+        }
+
+        // Only bytes that are not part of any value are left, like ignored
+        // line breaks
+        src.clear();
+
+        Ok(None)
+    }
+
+    fn push(&mut self, progress: Progress) {
+        let json_start = self.json.len();
+
+        self.json.push_str(&progress.json);
+
+        self.completed_values
+            .extend(
+                progress
+                    .completed_values
+                    .into_iter()
+                    .map(|value| CompletedValue {
+                        json_end: json_start + value.json_end,
+                        ..value
+                    }),
+            );
+    }
+
+    fn next_frame(&mut self, src: &mut BytesMut) -> Result<Option<Frame>, CodecError> {
+        let Some(value) = self.completed_values.pop_front() else {
+            return Ok(None);
+        };
+
+        let start = self.index(value.input_start)?;
+        let end = self.index(value.input_end)?;
+
+        self.check_frame_length(end - start)?;
+
+        self.skip(src, start);
+
+        let bytes = src.split_to(end - start);
+
+        self.offset = value.input_end;
+        self.fed -= end - start;
+        self.pending_value_start = None;
+
+        let json = self.json.drain(..value.json_end).collect();
+
+        for pending_value in &mut self.completed_values {
+            pending_value.json_end -= value.json_end;
+        }
+
+        Ok(Some(Frame { bytes, json }))
+    }
+
+    /// It drops the first bytes of the decoder buffer, which are not part of
+    /// any value.
+    fn skip(&mut self, src: &mut BytesMut, length: usize) {
+        let _skipped = src.split_to(length);
+
+        self.offset += length as u64;
+        self.fed -= length;
+    }
+
+    fn check_frame_length(&self, length: usize) -> Result<(), CodecError> {
+        match self.max_frame_length {
+            Some(max_frame_length) if length > max_frame_length => {
+                Err(CodecError::FrameTooLong(max_frame_length))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The index in the decoder buffer of a stream position.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the position is not in the buffer, which
+    /// can't be longer than the memory.
+    fn index(&self, position: u64) -> Result<usize, CodecError> {
+        usize::try_from(position - self.offset).map_err(|_| CodecError::FrameTooLong(usize::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use tokio_util::codec::Decoder;
+
+    /// It feeds the input to the decoder in chunks and returns all the
+    /// frames, or the error.
+    fn decode_in_chunks<D: Decoder>(
+        decoder: &mut D,
+        input: &[u8],
+        chunk_size: usize,
+    ) -> Result<Vec<D::Item>, D::Error> {
+        let mut buffer = BytesMut::new();
+        let mut frames = Vec::new();
+
+        for chunk in input.chunks(chunk_size) {
+            buffer.extend_from_slice(chunk);
+
+            while let Some(frame) = decoder.decode(&mut buffer)? {
+                frames.push(frame);
+            }
+        }
+
+        while let Some(frame) = decoder.decode_eof(&mut buffer)? {
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    mod the_decoder_should {
+        use std::time::{Duration, Instant};
+
+        use bytes::{Bytes, BytesMut};
+        use serde_json::json;
+        use tokio_util::codec::Decoder;
+
+        use super::decode_in_chunks;
+        use crate::{
+            codec::{BencodeCodec, CodecError, JsonCodec, ValueCodec},
+            parsers::{
+                error::ErrorKind,
+                options::{Limits, NewlineHandling, ParserOptions},
+            },
+        };
+
+        const INPUT: &[u8] = b"d1:t2:aa1:y1:qei42e\n4:spamli1ei2ee";
+
+        #[test]
+        fn yield_the_raw_bytes_of_each_value() {
+            for chunk_size in 1..INPUT.len() {
+                assert_eq!(
+                    decode_in_chunks(&mut BencodeCodec::new(), INPUT, chunk_size).unwrap(),
+                    vec![
+                        Bytes::from_static(b"d1:t2:aa1:y1:qe"),
+                        Bytes::from_static(b"i42e"),
+                        Bytes::from_static(b"4:spam"),
+                        Bytes::from_static(b"li1ei2ee"),
+                    ]
+                );
+            }
+        }
+
+        #[test]
+        fn yield_the_json_of_each_value() {
+            for chunk_size in 1..INPUT.len() {
+                assert_eq!(
+                    decode_in_chunks(&mut JsonCodec::new(), INPUT, chunk_size).unwrap(),
+                    vec![r#"{"t":"aa","y":"q"}"#, "42", r#""spam""#, "[1,2]"]
+                );
+            }
+        }
+
+        #[test]
+        fn yield_each_value() {
+            assert_eq!(
+                decode_in_chunks(&mut ValueCodec::new(), INPUT, 3).unwrap(),
+                vec![
+                    json!({"t": "aa", "y": "q"}),
+                    json!(42),
+                    json!("spam"),
+                    json!([1, 2])
+                ]
+            );
+        }
+
+        #[test]
+        fn wait_for_values_that_are_not_complete() {
+            let mut codec = BencodeCodec::new();
+            let mut buffer = BytesMut::from(&b"li1ei2"[..]);
+
+            assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+            assert_eq!(&buffer[..], b"li1ei2");
+
+            buffer.extend_from_slice(b"eeli3");
+
+            assert_eq!(
+                codec.decode(&mut buffer).unwrap(),
+                Some(Bytes::from_static(b"li1ei2ee"))
+            );
+            assert_eq!(&buffer[..], b"li3");
+        }
+
+        #[test]
+        fn fail_when_the_stream_ends_within_a_value() {
+            let result = decode_in_chunks(&mut BencodeCodec::new(), b"i1eli2e", 2);
+
+            assert!(matches!(result, Err(CodecError::Parser(_))));
+        }
+
+        #[test]
+        fn fail_when_the_input_is_invalid() {
+            let result = decode_in_chunks(&mut JsonCodec::new(), b"i1exe", 1);
+
+            assert!(matches!(result, Err(CodecError::Parser(_))));
+        }
+
+        #[test]
+        fn apply_the_parser_limits() {
+            let mut codec = ValueCodec::with_options(ParserOptions {
+                limits: Limits {
+                    max_depth: Some(2),
+                    ..Limits::default()
+                },
+                ..ParserOptions::default()
+            });
+
+            let result = decode_in_chunks(&mut codec, b"lleelllee", 4);
+
+            match result {
+                Err(CodecError::Parser(err)) => assert_eq!(err.kind(), ErrorKind::LimitExceeded),
+                result => panic!("unexpected result: {result:?}"),
+            }
+        }
+
+        #[test]
+        fn apply_the_parser_newline_handling() {
+            let mut codec = JsonCodec::with_options(ParserOptions {
+                newline_handling: NewlineHandling::Reject,
+                ..ParserOptions::default()
+            });
+
+            let result = decode_in_chunks(&mut codec, b"i1e\ni2e", 4);
+
+            assert!(matches!(result, Err(CodecError::Parser(_))));
+        }
+
+        #[test]
+        fn fail_when_a_value_is_longer_than_the_maximum_frame_length() {
+            let mut codec = BencodeCodec::new().with_max_frame_length(8);
+
+            assert_eq!(
+                decode_in_chunks(&mut codec, b"l1:a1:bel1:ae", 4).unwrap(),
+                vec![
+                    Bytes::from_static(b"l1:a1:be"),
+                    Bytes::from_static(b"l1:ae")
+                ]
+            );
+
+            let mut codec = BencodeCodec::new().with_max_frame_length(8);
+
+            assert!(matches!(
+                decode_in_chunks(&mut codec, b"l1:a1:b1:c1:de", 4),
+                Err(CodecError::FrameTooLong(8))
+            ));
+        }
+
+        #[test]
+        fn fail_when_a_complete_value_is_longer_than_the_maximum_frame_length() {
+            let mut codec = BencodeCodec::new().with_max_frame_length(8);
+
+            assert!(matches!(
+                decode_in_chunks(&mut codec, b"l1:a1:b1:c1:de", 14),
+                Err(CodecError::FrameTooLong(8))
+            ));
+        }
+
+        #[test]
+        fn not_count_skipped_line_breaks_in_the_frame_length() {
+            let mut codec = BencodeCodec::new().with_max_frame_length(8);
+
+            assert_eq!(
+                decode_in_chunks(
+                    &mut codec,
+                    b"\n\n\n\n\n\n\n\nl1:a1:be\n\n\n\n\n\n\n\nl1:ae",
+                    5
+                )
+                .unwrap(),
+                vec![
+                    Bytes::from_static(b"l1:a1:be"),
+                    Bytes::from_static(b"l1:ae")
+                ]
+            );
+        }
+
+        #[test]
+        fn not_parse_long_integers_again_every_time_a_digit_arrives() {
+            let started = Instant::now();
+
+            let mut input = b"i1".to_vec();
+            input.extend(std::iter::repeat_n(b'0', 100_000));
+            input.push(b'e');
+
+            let frames = decode_in_chunks(&mut JsonCodec::new(), &input, 1).unwrap();
+
+            assert_eq!(frames[0].len(), 100_001);
+            assert!(started.elapsed() < Duration::from_secs(5));
+        }
+
+        #[test]
+        fn not_yield_anything_for_an_empty_stream() {
+            assert_eq!(
+                decode_in_chunks(&mut BencodeCodec::new(), b"", 1).unwrap(),
+                Vec::<Bytes>::new()
+            );
+            assert_eq!(
+                decode_in_chunks(&mut BencodeCodec::new(), b"\n\n", 1).unwrap(),
+                Vec::<Bytes>::new()
+            );
+        }
+    }
+
+    mod the_encoder_should {
+        use bytes::{Bytes, BytesMut};
+        use serde_json::json;
+        use tokio_util::codec::Encoder;
+
+        use crate::codec::{BencodeCodec, CodecError, JsonCodec, ValueCodec};
+
+        #[test]
+        fn write_raw_bytes_as_they_are() {
+            let mut buffer = BytesMut::new();
+
+            BencodeCodec::new()
+                .encode(Bytes::from_static(b"i42e"), &mut buffer)
+                .unwrap();
+
+            assert_eq!(&buffer[..], b"i42e");
+        }
+
+        #[test]
+        fn encode_values_and_serializable_types() {
+            let mut buffer = BytesMut::new();
+
+            ValueCodec::new()
+                .encode(json!({"spam": [1, "<hex>ff</hex>"]}), &mut buffer)
+                .unwrap();
+            JsonCodec::new().encode(vec![1, 2], &mut buffer).unwrap();
+
+            assert_eq!(&buffer[..], b"d4:spamli1e1:\xffeeli1ei2ee");
+        }
+
+        #[test]
+        fn fail_encoding_values_bencode_does_not_have() {
+            let mut buffer = BytesMut::new();
+
+            assert!(matches!(
+                ValueCodec::new().encode(json!([null]), &mut buffer),
+                Err(CodecError::Encoder(_))
+            ));
+        }
+    }
+
+    mod it_should_round_trip {
+        use bytes::BytesMut;
+        use proptest::prelude::*;
+        use tokio_util::codec::{Decoder, Encoder};
+
+        use super::decode_in_chunks;
+        use crate::codec::ValueCodec;
+
+        fn arbitrary_value() -> impl Strategy<Value = serde_json::Value> {
+            let leaf = prop_oneof![
+                any::<i64>().prop_map(serde_json::Value::from),
+                "[a-z]{0,8}".prop_map(serde_json::Value::from),
+            ];
+
+            leaf.prop_recursive(4, 32, 4, |inner| {
+                prop_oneof![
+                    proptest::collection::vec(inner.clone(), 0..4)
+                        .prop_map(serde_json::Value::from),
+                    proptest::collection::btree_map("[a-z]{0,4}", inner, 0..4)
+                        .prop_map(|map| serde_json::Value::Object(map.into_iter().collect())),
+                ]
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn sequences_of_values(
+                values in proptest::collection::vec(arbitrary_value(), 0..8),
+                chunk_size in 1..16usize,
+            ) {
+                let mut codec = ValueCodec::new();
+                let mut buffer = BytesMut::new();
+
+                for value in &values {
+                    codec.encode(value, &mut buffer).unwrap();
+                }
+
+                prop_assert_eq!(decode_in_chunks(&mut codec, &buffer, chunk_size).unwrap(), values);
+
+                let mut empty = BytesMut::new();
+                prop_assert_eq!(codec.decode_eof(&mut empty).unwrap(), None);
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Compact peer addresses.
+//!
+//! Trackers ([BEP 23](https://www.bittorrent.org/beps/bep_0023.html) and
+//! [BEP 7](https://www.bittorrent.org/beps/bep_0007.html)) and the DHT
+//! ([BEP 5](https://www.bittorrent.org/beps/bep_0005.html)) send peer
+//! addresses as strings with the IP address and the port in network byte
+//! order: 6 bytes for IPv4 and 18 bytes for IPv6. DHT nodes are sent the
+//! same way, with the 20-byte node ID before the address.
+//!
+//! ```rust
+//! use torrust_bencode2json::compact::decode_peers;
+//!
+//! let peers = decode_peers(&[127, 0, 0, 1, 0x1a, 0xe1]).unwrap();
+//!
+//! assert_eq!(peers[0].to_string(), "127.0.0.1:6881");
+//! ```
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Length of a compact IPv4 address and port.
+pub const PEER_V4_LENGTH: usize = 6;
+
+/// Length of a compact IPv6 address and port.
+pub const PEER_V6_LENGTH: usize = 18;
+
+/// Length of a DHT node ID.
+pub const NODE_ID_LENGTH: usize = 20;
+
+/// Length of a compact IPv4 node: the node ID, the address and the port.
+pub const NODE_V4_LENGTH: usize = NODE_ID_LENGTH + PEER_V4_LENGTH;
+
+/// Length of a compact IPv6 node: the node ID, the address and the port.
+pub const NODE_V6_LENGTH: usize = NODE_ID_LENGTH + PEER_V6_LENGTH;
+
+/// A DHT node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Node {
+    /// The node ID.
+    pub id: [u8; NODE_ID_LENGTH],
+
+    /// The address and port of the node.
+    pub address: SocketAddr,
+}
+
+/// It decodes one compact IPv4 or IPv6 address and port, depending on the
+/// length.
+#[must_use]
+pub fn decode_peer(bytes: &[u8]) -> Option<SocketAddr> {
+    match bytes.len() {
+        PEER_V4_LENGTH => {
+            let ip: [u8; 4] = bytes[..4].try_into().ok()?;
+            Some(SocketAddr::new(
+                Ipv4Addr::from(ip).into(),
+                port(&bytes[4..]),
+            ))
+        }
+        PEER_V6_LENGTH => {
+            let ip: [u8; 16] = bytes[..16].try_into().ok()?;
+            Some(SocketAddr::new(
+                Ipv6Addr::from(ip).into(),
+                port(&bytes[16..]),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// It decodes a string of compact IPv4 addresses (`peers`). It returns
+/// `None` if the length is not a multiple of 6.
+#[must_use]
+pub fn decode_peers(bytes: &[u8]) -> Option<Vec<SocketAddr>> {
+    decode_chunks(bytes, PEER_V4_LENGTH)
+}
+
+/// It decodes a string of compact IPv6 addresses (`peers6`). It returns
+/// `None` if the length is not a multiple of 18.
+#[must_use]
+pub fn decode_peers6(bytes: &[u8]) -> Option<Vec<SocketAddr>> {
+    decode_chunks(bytes, PEER_V6_LENGTH)
+}
+
+/// It decodes a string of compact IPv4 nodes (`nodes`). It returns `None`
+/// if the length is not a multiple of 26.
+#[must_use]
+pub fn decode_nodes(bytes: &[u8]) -> Option<Vec<Node>> {
+    decode_node_chunks(bytes, NODE_V4_LENGTH)
+}
+
+/// It decodes a string of compact IPv6 nodes (`nodes6`). It returns `None`
+/// if the length is not a multiple of 38.
+#[must_use]
+pub fn decode_nodes6(bytes: &[u8]) -> Option<Vec<Node>> {
+    decode_node_chunks(bytes, NODE_V6_LENGTH)
+}
+
+fn decode_node_chunks(bytes: &[u8], chunk_length: usize) -> Option<Vec<Node>> {
+    if !bytes.len().is_multiple_of(chunk_length) {
+        return None;
+    }
+
+    bytes
+        .chunks_exact(chunk_length)
+        .map(|chunk| {
+            let (id, address) = chunk.split_at(NODE_ID_LENGTH);
+
+            Some(Node {
+                id: id.try_into().ok()?,
+                address: decode_peer(address)?,
+            })
+        })
+        .collect()
+}
+
+fn decode_chunks(bytes: &[u8], chunk_length: usize) -> Option<Vec<SocketAddr>> {
+    if !bytes.len().is_multiple_of(chunk_length) {
+        return None;
+    }
+
+    bytes.chunks_exact(chunk_length).map(decode_peer).collect()
+}
+
+fn port(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should_decode {
+        use crate::compact::{
+            decode_nodes, decode_nodes6, decode_peer, decode_peers, decode_peers6,
+        };
+
+        #[test]
+        fn ipv4_peers() {
+            let peers = decode_peers(&[10, 0, 0, 1, 0x1a, 0xe1, 192, 168, 1, 2, 0, 80]).unwrap();
+
+            let peers: Vec<String> = peers.iter().map(ToString::to_string).collect();
+
+            assert_eq!(peers, vec!["10.0.0.1:6881", "192.168.1.2:80"]);
+        }
+
+        #[test]
+        fn ipv6_peers() {
+            let mut bytes = vec![0; 15];
+            bytes.extend_from_slice(&[1, 0x1a, 0xe1]);
+
+            assert_eq!(decode_peers6(&bytes).unwrap()[0].to_string(), "[::1]:6881");
+        }
+
+        #[test]
+        fn nothing_when_the_length_is_not_a_multiple_of_the_peer_length() {
+            assert_eq!(decode_peers(&[1, 2, 3, 4, 5]), None);
+            assert_eq!(decode_peers6(&[0; 6]), None);
+            assert_eq!(decode_peer(&[0; 7]), None);
+        }
+
+        #[test]
+        fn ipv4_and_ipv6_nodes() {
+            let mut bytes = [b'a'; 20].to_vec();
+            bytes.extend_from_slice(&[10, 0, 0, 1, 0x1a, 0xe1]);
+
+            let nodes = decode_nodes(&bytes).unwrap();
+
+            assert_eq!(nodes[0].id, [b'a'; 20]);
+            assert_eq!(nodes[0].address.to_string(), "10.0.0.1:6881");
+
+            let mut bytes = [b'b'; 20].to_vec();
+            bytes.extend_from_slice(&[0; 15]);
+            bytes.extend_from_slice(&[1, 0x1a, 0xe1]);
+
+            assert_eq!(
+                decode_nodes6(&bytes).unwrap()[0].address.to_string(),
+                "[::1]:6881"
+            );
+            assert_eq!(decode_nodes(&bytes), None);
+        }
+
+        #[test]
+        fn empty_lists_of_peers() {
+            assert_eq!(decode_peers(&[]), Some(vec![]));
+        }
+    }
+}
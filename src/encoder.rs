@@ -0,0 +1,318 @@
+//! Bencode encoder for JSON values.
+//!
+//! It's the reverse of the conversion: it encodes a [`serde_json::Value`], or
+//! any value that can be serialized with serde, into Bencode.
+//!
+//! ```rust
+//! use serde_json::json;
+//! use torrust_bencode2json::encoder::encode;
+//!
+//! let bencode = encode(&json!({"spam": ["a", 42]})).unwrap();
+//!
+//! assert_eq!(bencode, b"d4:spaml1:ai42eee");
+//! ```
+//!
+//! Values are encoded as follows:
+//!
+//! - Strings are encoded as Bencode strings with their UTF-8 bytes. Strings
+//!   in the `<hex>fafb</hex>` format, the default format for non UTF-8
+//!   strings in the JSON output, are encoded as the original bytes. This way
+//!   converting to JSON and back gives the original Bencode.
+//!
+//!   The format is ambiguous: a UTF-8 string like `<hex>fafb</hex>` could be
+//!   text or bytes. With the default options, the parser writes UTF-8
+//!   strings that look like the format in the format too, so they are
+//!   encoded back as the same text. JSON from other sources can't tell them
+//!   apart, and they are always encoded as bytes.
+//! - Integers are encoded as Bencode integers.
+//! - Arrays are encoded as lists.
+//! - Objects are encoded as dictionaries with the keys sorted as raw bytes,
+//!   as Bencode requires.
+//!
+//! Bencode has no floats, booleans or nulls, so they can't be encoded.
+use std::fmt::Write;
+
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+
+const TAGGED_HEX_PREFIX: &str = "<hex>";
+const TAGGED_HEX_SUFFIX: &str = "</hex>";
+
+/// Errors that can occur while encoding a value into Bencode.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The value can't be serialized into a JSON value.
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    /// The value contains a float, a boolean or a null.
+    #[error(
+        "Unsupported {kind} at {path}: Bencode only has strings, integers, lists and dictionaries"
+    )]
+    UnsupportedValue {
+        /// The kind of value: `float`, `boolean` or `null`.
+        kind: &'static str,
+
+        /// The path of the value, like `$.info.files[0].length`.
+        path: String,
+    },
+}
+
+/// It encodes a serializable value into Bencode.
+///
+/// # Errors
+///
+/// Will return an error if the value can't be serialized, or it contains
+/// values Bencode can't represent.
+pub fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let value = serde_json::to_value(value)?;
+
+    encode_value(&value)
+}
+
+/// It encodes a JSON value into Bencode.
+///
+/// # Errors
+///
+/// Will return an error if the value contains floats, booleans or nulls.
+pub fn encode_value(value: &Value) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+
+    write_value(value, &mut output)?;
+
+    Ok(output)
+}
+
+/// It encodes a JSON value into Bencode, appending it to the output.
+///
+/// # Errors
+///
+/// Will return an error if the value contains floats, booleans or nulls. The
+/// output may contain part of the value then.
+pub fn write_value(value: &Value, output: &mut Vec<u8>) -> Result<(), Error> {
+    let mut path = String::from("$");
+
+    write_value_at(value, output, &mut path)
+}
+
+fn write_value_at(value: &Value, output: &mut Vec<u8>, path: &mut String) -> Result<(), Error> {
+    match value {
+        Value::String(string) => write_string(&string_bytes(string), output),
+        Value::Number(number) => {
+            if let Some(integer) = number.as_i64() {
+                write_integer(&integer.to_string(), output);
+            } else if let Some(integer) = number.as_u64() {
+                write_integer(&integer.to_string(), output);
+            } else {
+                return Err(unsupported("float", path));
+            }
+        }
+        Value::Array(items) => {
+            output.push(b'l');
+
+            let path_length = path.len();
+
+            for (index, item) in items.iter().enumerate() {
+                write!(path, "[{index}]").expect("writing to a string should not fail");
+                write_value_at(item, output, path)?;
+                path.truncate(path_length);
+            }
+
+            output.push(b'e');
+        }
+        Value::Object(object) => {
+            output.push(b'd');
+
+            let mut entries: Vec<_> = object
+                .iter()
+                .map(|(key, value)| (string_bytes(key), key, value))
+                .collect();
+
+            entries.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+
+            let path_length = path.len();
+
+            for (key_bytes, key, value) in entries {
+                write_string(&key_bytes, output);
+
+                write!(path, ".{key}").expect("writing to a string should not fail");
+                write_value_at(value, output, path)?;
+                path.truncate(path_length);
+            }
+
+            output.push(b'e');
+        }
+        Value::Bool(_) => return Err(unsupported("boolean", path)),
+        Value::Null => return Err(unsupported("null", path)),
+    }
+
+    Ok(())
+}
+
+fn write_string(bytes: &[u8], output: &mut Vec<u8>) {
+    output.extend_from_slice(bytes.len().to_string().as_bytes());
+    output.push(b':');
+    output.extend_from_slice(bytes);
+}
+
+fn write_integer(integer: &str, output: &mut Vec<u8>) {
+    output.push(b'i');
+    output.extend_from_slice(integer.as_bytes());
+    output.push(b'e');
+}
+
+/// It returns the bytes of a JSON string, decoding the `<hex>fafb</hex>`
+/// format.
+fn string_bytes(string: &str) -> Vec<u8> {
+    decode_tagged_hex(string).unwrap_or_else(|| string.as_bytes().to_vec())
+}
+
+/// It returns the bytes of a string in the `<hex>fafb</hex>` format, or
+/// `None` if it's not in the format.
+pub(crate) fn decode_tagged_hex(string: &str) -> Option<Vec<u8>> {
+    string
+        .strip_prefix(TAGGED_HEX_PREFIX)
+        .and_then(|rest| rest.strip_suffix(TAGGED_HEX_SUFFIX))
+        .and_then(|hex| hex::decode(hex).ok())
+}
+
+fn unsupported(kind: &'static str, path: &str) -> Error {
+    Error::UnsupportedValue {
+        kind,
+        path: path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should_encode {
+        use serde_json::json;
+
+        use crate::encoder::encode;
+
+        #[test]
+        fn strings() {
+            assert_eq!(encode(&json!("spam")).unwrap(), b"4:spam");
+            assert_eq!(encode(&json!("")).unwrap(), b"0:");
+            assert_eq!(encode(&json!("ñ")).unwrap(), b"2:\xc3\xb1");
+        }
+
+        #[test]
+        fn strings_in_the_tagged_hex_format_as_the_original_bytes() {
+            assert_eq!(
+                encode(&json!("<hex>fffefdfc</hex>")).unwrap(),
+                b"4:\xff\xfe\xfd\xfc"
+            );
+        }
+
+        #[test]
+        fn strings_that_only_look_like_the_tagged_hex_format_as_they_are() {
+            assert_eq!(
+                encode(&json!("<hex>xy</hex>")).unwrap(),
+                b"13:<hex>xy</hex>"
+            );
+        }
+
+        #[test]
+        fn integers() {
+            assert_eq!(encode(&json!(42)).unwrap(), b"i42e");
+            assert_eq!(encode(&json!(-42)).unwrap(), b"i-42e");
+            assert_eq!(encode(&json!(0)).unwrap(), b"i0e");
+            assert_eq!(encode(&json!(u64::MAX)).unwrap(), b"i18446744073709551615e");
+        }
+
+        #[test]
+        fn lists() {
+            assert_eq!(encode(&json!([])).unwrap(), b"le");
+            assert_eq!(encode(&json!(["a", 1, [2]])).unwrap(), b"l1:ai1eli2eee");
+        }
+
+        #[test]
+        fn dictionaries_with_the_keys_sorted_as_raw_bytes() {
+            assert_eq!(encode(&json!({})).unwrap(), b"de");
+            assert_eq!(
+                encode(&json!({"b": 1, "a": {"c": "d"}})).unwrap(),
+                b"d1:ad1:c1:de1:bi1ee"
+            );
+            assert_eq!(
+                encode(&json!({"<hex>ff</hex>": 1, "z": 2})).unwrap(),
+                b"d1:zi2e1:\xffi1ee"
+            );
+        }
+
+        #[test]
+        fn serializable_types() {
+            #[derive(serde::Serialize)]
+            struct Ping<'a> {
+                t: &'a str,
+                y: &'a str,
+                q: &'a str,
+            }
+
+            assert_eq!(
+                encode(&Ping {
+                    t: "aa",
+                    y: "q",
+                    q: "ping"
+                })
+                .unwrap(),
+                b"d1:q4:ping1:t2:aa1:y1:qe"
+            );
+        }
+
+        #[test]
+        fn the_json_output_back_to_the_original_bencode() {
+            let bencode = b"d4:infod6:lengthi42e6:pieces2:\xff\x00e4:spaml1:ai-1eee";
+
+            let json = crate::try_bencode_to_json(bencode).unwrap();
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(encode(&value).unwrap(), bencode);
+        }
+
+        #[test]
+        fn the_json_output_of_text_in_the_tagged_hex_format_back_to_the_original_bencode() {
+            let bencode = b"d13:<hex>ff</hex>l11:<hex></hex>12:<hex>x</hex>ee";
+
+            let json = crate::try_bencode_to_json(bencode).unwrap();
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(encode(&value).unwrap(), bencode);
+        }
+    }
+
+    mod it_should_fail_encoding {
+        use serde_json::json;
+
+        use crate::encoder::{encode, Error};
+
+        fn unsupported(value: &serde_json::Value) -> (&'static str, String) {
+            match encode(value) {
+                Err(Error::UnsupportedValue { kind, path }) => (kind, path),
+                result => panic!("unexpected result: {result:?}"),
+            }
+        }
+
+        #[test]
+        fn floats() {
+            assert_eq!(unsupported(&json!(1.5)), ("float", "$".to_string()));
+        }
+
+        #[test]
+        fn booleans() {
+            assert_eq!(
+                unsupported(&json!({"a": [1, true]})),
+                ("boolean", "$.a[1]".to_string())
+            );
+        }
+
+        #[test]
+        fn nulls() {
+            assert_eq!(
+                unsupported(&json!({"info": {"files": [{"length": null}]}})),
+                ("null", "$.info.files[0].length".to_string())
+            );
+        }
+    }
+}
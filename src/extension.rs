@@ -0,0 +1,557 @@
+//! Peer-wire extension messages.
+//!
+//! It decodes the payloads of the extended messages of the
+//! [BEP 10](https://www.bittorrent.org/beps/bep_0010.html) extension
+//! protocol, after the extended message ID:
+//!
+//! - The extension handshake ([`Handshake`]).
+//! - The `ut_metadata` messages of
+//!   [BEP 9](https://www.bittorrent.org/beps/bep_0009.html)
+//!   ([`MetadataMessage`]). The `data` messages have a piece of the info
+//!   dictionary after the bencoded header.
+//! - The `ut_pex` messages of
+//!   [BEP 11](https://www.bittorrent.org/beps/bep_0011.html) ([`Pex`]).
+//!
+//! ```rust
+//! use torrust_bencode2json::extension::{MetadataMessage, MetadataMessageType};
+//!
+//! let message = MetadataMessage::from_bytes(b"d8:msg_typei1e5:piecei0e10:total_sizei4eeDATA").unwrap();
+//!
+//! assert_eq!(message.msg_type, MetadataMessageType::Data);
+//! assert_eq!(message.data, b"DATA");
+//! ```
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use serde_json::json;
+use thiserror::Error;
+
+use crate::{
+    compact::{decode_peers, decode_peers6},
+    value::{self, Value},
+};
+
+/// The name of the metadata extension in the handshake `m` dictionary.
+pub const UT_METADATA: &str = "ut_metadata";
+
+/// The name of the peer exchange extension in the handshake `m` dictionary.
+pub const UT_PEX: &str = "ut_pex";
+
+/// The length of the pieces of the info dictionary sent with `ut_metadata`,
+/// except the last one.
+pub const METADATA_PIECE_LENGTH: usize = 16 * 1024;
+
+/// Errors that can occur while decoding extension messages.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The message is not valid Bencode.
+    #[error("{0}")]
+    Value(#[from] value::Error),
+
+    /// The bencoded value is not a dictionary.
+    #[error("The message is a {0} instead of a dictionary")]
+    NotADict(&'static str),
+
+    /// A required field is missing or has the wrong type.
+    #[error("Missing or invalid required field {0}")]
+    MissingField(&'static str),
+
+    /// A field has an invalid value.
+    #[error("Invalid value for field {field}: {reason}")]
+    InvalidValue {
+        field: &'static str,
+        reason: &'static str,
+    },
+
+    /// There are bytes after the bencoded value in a message that can't have
+    /// them.
+    #[error("The message has {0} bytes after the bencoded value")]
+    TrailingBytes(usize),
+}
+
+/// The extension handshake, the extended message with ID 0.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Handshake {
+    /// The extended message IDs of the supported extensions, like
+    /// `ut_metadata` (`m`). An ID of 0 disables the extension.
+    pub extensions: BTreeMap<String, i64>,
+
+    /// The client name and version (`v`).
+    pub client: Option<String>,
+
+    /// The IP address of the receiver as seen by the sender (`yourip`).
+    pub your_ip: Option<IpAddr>,
+
+    /// The length of the info dictionary (`metadata_size`).
+    pub metadata_size: Option<u64>,
+
+    /// The TCP port the sender listens on (`p`).
+    pub port: Option<u16>,
+
+    /// The number of outstanding requests the sender supports (`reqq`).
+    pub request_queue_length: Option<i64>,
+}
+
+impl Handshake {
+    /// It decodes the payload of an extension handshake.
+    ///
+    /// Fields with an invalid type or value are ignored, as the extension
+    /// protocol requires, except the `m` dictionary.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the payload is not a bencoded dictionary, or
+    /// it doesn't have the `m` dictionary.
+    pub fn from_bytes(payload: &[u8]) -> Result<Self, Error> {
+        Self::from_value(&decode_dict(payload)?)
+    }
+
+    /// It decodes a bencoded extension handshake.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the value doesn't have the `m` dictionary.
+    pub fn from_value(handshake: &Value) -> Result<Self, Error> {
+        let extensions = handshake
+            .get("m")
+            .and_then(Value::as_dict)
+            .ok_or(Error::MissingField("m"))?
+            .iter()
+            .filter_map(|(name, id)| {
+                Some((String::from_utf8_lossy(name).into_owned(), id.as_integer()?))
+            })
+            .collect();
+
+        Ok(Self {
+            extensions,
+            client: handshake
+                .get("v")
+                .and_then(Value::as_bytes)
+                .map(|client| String::from_utf8_lossy(client).into_owned()),
+            your_ip: handshake
+                .get("yourip")
+                .and_then(Value::as_bytes)
+                .and_then(decode_ip),
+            metadata_size: handshake
+                .get("metadata_size")
+                .and_then(Value::as_integer)
+                .and_then(|size| u64::try_from(size).ok()),
+            port: handshake
+                .get("p")
+                .and_then(Value::as_integer)
+                .and_then(|port| u16::try_from(port).ok()),
+            request_queue_length: handshake.get("reqq").and_then(Value::as_integer),
+        })
+    }
+
+    /// It returns the extended message ID of an extension, if the sender
+    /// supports it.
+    #[must_use]
+    pub fn extension_id(&self, name: &str) -> Option<u8> {
+        self.extensions
+            .get(name)
+            .and_then(|id| u8::try_from(*id).ok())
+            .filter(|id| *id != 0)
+    }
+}
+
+/// It returns the JSON of an extension handshake, with `yourip`, `ipv4` and
+/// `ipv6` written as IP addresses. Other values are written like the parser
+/// does.
+#[must_use]
+pub fn render_handshake(handshake: &Value) -> serde_json::Value {
+    let Value::Dict(entries) = handshake else {
+        return handshake.to_json();
+    };
+
+    serde_json::Value::Object(
+        entries
+            .iter()
+            .map(|(key, value)| {
+                let json = match (key.as_slice(), value) {
+                    (b"yourip" | b"ipv4" | b"ipv6", Value::String(bytes)) => {
+                        decode_ip(bytes).map_or_else(|| value.to_json(), |ip| ip.to_string().into())
+                    }
+                    _ => value.to_json(),
+                };
+
+                (value::bytes_to_json_string(key), json)
+            })
+            .collect(),
+    )
+}
+
+/// The type of a `ut_metadata` message (`msg_type`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataMessageType {
+    /// A request for a piece (0).
+    Request,
+
+    /// A piece, after the bencoded header (1).
+    Data,
+
+    /// The sender doesn't have the requested piece (2).
+    Reject,
+}
+
+impl MetadataMessageType {
+    /// It returns the name of the type, like `request`.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Request => "request",
+            Self::Data => "data",
+            Self::Reject => "reject",
+        }
+    }
+}
+
+/// A `ut_metadata` message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataMessage<'a> {
+    /// The type of message (`msg_type`).
+    pub msg_type: MetadataMessageType,
+
+    /// The index of the piece (`piece`).
+    pub piece: u64,
+
+    /// The length of the info dictionary (`total_size`). Only in `data`
+    /// messages.
+    pub total_size: Option<u64>,
+
+    /// The piece of the info dictionary after the bencoded header. Empty
+    /// except in `data` messages.
+    pub data: &'a [u8],
+}
+
+impl<'a> MetadataMessage<'a> {
+    /// It decodes the payload of a `ut_metadata` message.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the payload doesn't start with a bencoded
+    /// dictionary with a valid `msg_type` and `piece`, or a message that is
+    /// not a `data` message has bytes after the dictionary.
+    pub fn from_bytes(payload: &'a [u8]) -> Result<Self, Error> {
+        let (header, end) = value::from_prefix(payload)?;
+
+        if !matches!(header, Value::Dict(_)) {
+            return Err(Error::NotADict(header.type_name()));
+        }
+
+        let msg_type = match header.get("msg_type").and_then(Value::as_integer) {
+            Some(0) => MetadataMessageType::Request,
+            Some(1) => MetadataMessageType::Data,
+            Some(2) => MetadataMessageType::Reject,
+            Some(_) => {
+                return Err(Error::InvalidValue {
+                    field: "msg_type",
+                    reason: "unknown message type",
+                })
+            }
+            None => return Err(Error::MissingField("msg_type")),
+        };
+
+        let piece = header
+            .get("piece")
+            .and_then(Value::as_integer)
+            .ok_or(Error::MissingField("piece"))?;
+
+        let piece = u64::try_from(piece).map_err(|_| Error::InvalidValue {
+            field: "piece",
+            reason: "the index is negative",
+        })?;
+
+        let data = &payload[end..];
+
+        if msg_type != MetadataMessageType::Data && !data.is_empty() {
+            return Err(Error::TrailingBytes(data.len()));
+        }
+
+        Ok(Self {
+            msg_type,
+            piece,
+            total_size: header
+                .get("total_size")
+                .and_then(Value::as_integer)
+                .and_then(|size| u64::try_from(size).ok()),
+            data,
+        })
+    }
+
+    /// It returns the JSON of the message, with the length of the data
+    /// instead of the data.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "msg_type": self.msg_type.as_str(),
+            "piece": self.piece,
+            "total_size": self.total_size,
+            "data_length": self.data.len(),
+        })
+    }
+}
+
+/// A `ut_pex` message with the peers connected and disconnected since the
+/// previous message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pex {
+    /// The IPv4 peers connected (`added`).
+    pub added: Vec<SocketAddr>,
+
+    /// The flags of each IPv4 peer connected (`added.f`), like `0x02` for
+    /// seeds.
+    pub added_flags: Vec<u8>,
+
+    /// The IPv4 peers disconnected (`dropped`).
+    pub dropped: Vec<SocketAddr>,
+
+    /// The IPv6 peers connected (`added6`).
+    pub added6: Vec<SocketAddr>,
+
+    /// The flags of each IPv6 peer connected (`added6.f`).
+    pub added6_flags: Vec<u8>,
+
+    /// The IPv6 peers disconnected (`dropped6`).
+    pub dropped6: Vec<SocketAddr>,
+}
+
+impl Pex {
+    /// It decodes the payload of a `ut_pex` message. Missing fields are
+    /// empty.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the payload is not a bencoded dictionary, or
+    /// the compact peers have an invalid length.
+    pub fn from_bytes(payload: &[u8]) -> Result<Self, Error> {
+        Self::from_value(&decode_dict(payload)?)
+    }
+
+    /// It decodes a bencoded `ut_pex` message.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the compact peers have an invalid length.
+    pub fn from_value(pex: &Value) -> Result<Self, Error> {
+        let peers = |field: &'static str, decode: fn(&[u8]) -> Option<Vec<SocketAddr>>| match pex
+            .get(field)
+            .and_then(Value::as_bytes)
+        {
+            Some(bytes) => decode(bytes).ok_or(Error::InvalidValue {
+                field,
+                reason: "the length is not a multiple of the compact address length",
+            }),
+            None => Ok(Vec::new()),
+        };
+
+        let flags = |field: &str| {
+            pex.get(field)
+                .and_then(Value::as_bytes)
+                .map(<[u8]>::to_vec)
+                .unwrap_or_default()
+        };
+
+        Ok(Self {
+            added: peers("added", decode_peers)?,
+            added_flags: flags("added.f"),
+            dropped: peers("dropped", decode_peers)?,
+            added6: peers("added6", decode_peers6)?,
+            added6_flags: flags("added6.f"),
+            dropped6: peers("dropped6", decode_peers6)?,
+        })
+    }
+
+    /// It returns the JSON of the message, with the peers as `"ip:port"`
+    /// strings.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        let addresses = |peers: &[SocketAddr]| -> Vec<String> {
+            peers.iter().map(ToString::to_string).collect()
+        };
+
+        json!({
+            "added": addresses(&self.added),
+            "added.f": self.added_flags,
+            "dropped": addresses(&self.dropped),
+            "added6": addresses(&self.added6),
+            "added6.f": self.added6_flags,
+            "dropped6": addresses(&self.dropped6),
+        })
+    }
+}
+
+/// It decodes a payload that must be only a bencoded dictionary.
+fn decode_dict(payload: &[u8]) -> Result<Value, Error> {
+    let (value, end) = value::from_prefix(payload)?;
+
+    if end < payload.len() {
+        return Err(Error::TrailingBytes(payload.len() - end));
+    }
+
+    if !matches!(value, Value::Dict(_)) {
+        return Err(Error::NotADict(value.type_name()));
+    }
+
+    Ok(value)
+}
+
+/// It decodes an IPv4 (4 bytes) or IPv6 (16 bytes) address.
+fn decode_ip(bytes: &[u8]) -> Option<IpAddr> {
+    if let Ok(ip) = <[u8; 4]>::try_from(bytes) {
+        return Some(Ipv4Addr::from(ip).into());
+    }
+
+    <[u8; 16]>::try_from(bytes)
+        .ok()
+        .map(|ip| Ipv6Addr::from(ip).into())
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should_decode {
+        use std::collections::BTreeMap;
+
+        use serde_json::json;
+
+        use crate::{
+            extension::{
+                render_handshake, Handshake, MetadataMessage, MetadataMessageType, Pex,
+                UT_METADATA, UT_PEX,
+            },
+            value::from_bytes,
+        };
+
+        const HANDSHAKE: &[u8] =
+            b"d1:md11:ut_metadatai3e6:ut_pexi0ee13:metadata_sizei31235e1:pi6881e4:reqqi250e1:v6:qB 4.66:yourip4:\x0a\x00\x00\x01e";
+
+        #[test]
+        fn extension_handshakes() {
+            let handshake = Handshake::from_bytes(HANDSHAKE).unwrap();
+
+            assert_eq!(
+                handshake,
+                Handshake {
+                    extensions: BTreeMap::from([
+                        (UT_METADATA.to_string(), 3),
+                        (UT_PEX.to_string(), 0)
+                    ]),
+                    client: Some("qB 4.6".to_string()),
+                    your_ip: Some("10.0.0.1".parse().unwrap()),
+                    metadata_size: Some(31235),
+                    port: Some(6881),
+                    request_queue_length: Some(250),
+                }
+            );
+            assert_eq!(handshake.extension_id(UT_METADATA), Some(3));
+            assert_eq!(handshake.extension_id(UT_PEX), None);
+        }
+
+        #[test]
+        fn extension_handshakes_to_json_with_ip_addresses() {
+            let json = render_handshake(&from_bytes(HANDSHAKE).unwrap());
+
+            assert_eq!(json["yourip"], json!("10.0.0.1"));
+            assert_eq!(json["m"], json!({"ut_metadata": 3, "ut_pex": 0}));
+        }
+
+        #[test]
+        fn metadata_data_messages_with_the_piece_after_the_header() {
+            let message =
+                MetadataMessage::from_bytes(b"d8:msg_typei1e5:piecei2e10:total_sizei34000ee\x00d5")
+                    .unwrap();
+
+            assert_eq!(
+                message,
+                MetadataMessage {
+                    msg_type: MetadataMessageType::Data,
+                    piece: 2,
+                    total_size: Some(34000),
+                    data: b"\x00d5",
+                }
+            );
+            assert_eq!(
+                message.to_json(),
+                json!({"msg_type": "data", "piece": 2, "total_size": 34000, "data_length": 3})
+            );
+        }
+
+        #[test]
+        fn metadata_requests_and_rejects() {
+            let request = MetadataMessage::from_bytes(b"d8:msg_typei0e5:piecei0ee").unwrap();
+            let reject = MetadataMessage::from_bytes(b"d8:msg_typei2e5:piecei0ee").unwrap();
+
+            assert_eq!(request.msg_type, MetadataMessageType::Request);
+            assert_eq!(reject.msg_type, MetadataMessageType::Reject);
+        }
+
+        #[test]
+        fn pex_messages() {
+            let pex = Pex::from_bytes(
+                b"d5:added12:\x0a\x00\x00\x01\x1a\xe1\x0a\x00\x00\x02\x1a\xe17:added.f2:\x02\x007:dropped6:\x0a\x00\x00\x03\x00\x50e",
+            )
+            .unwrap();
+
+            assert_eq!(
+                pex.to_json(),
+                json!({
+                    "added": ["10.0.0.1:6881", "10.0.0.2:6881"],
+                    "added.f": [2, 0],
+                    "dropped": ["10.0.0.3:80"],
+                    "added6": [],
+                    "added6.f": [],
+                    "dropped6": [],
+                })
+            );
+        }
+    }
+
+    mod it_should_fail {
+        use crate::extension::{Error, Handshake, MetadataMessage, Pex};
+
+        #[test]
+        fn when_the_handshake_does_not_have_the_extensions() {
+            assert!(matches!(
+                Handshake::from_bytes(b"d1:v1:ae"),
+                Err(Error::MissingField("m"))
+            ));
+        }
+
+        #[test]
+        fn when_a_message_is_not_a_dictionary() {
+            assert!(matches!(
+                Handshake::from_bytes(b"le"),
+                Err(Error::NotADict("list"))
+            ));
+        }
+
+        #[test]
+        fn when_a_metadata_message_has_an_unknown_type() {
+            assert!(matches!(
+                MetadataMessage::from_bytes(b"d8:msg_typei7e5:piecei0ee"),
+                Err(Error::InvalidValue {
+                    field: "msg_type",
+                    ..
+                })
+            ));
+        }
+
+        #[test]
+        fn when_a_metadata_request_has_trailing_bytes() {
+            assert!(matches!(
+                MetadataMessage::from_bytes(b"d8:msg_typei0e5:piecei0eeXY"),
+                Err(Error::TrailingBytes(2))
+            ));
+        }
+
+        #[test]
+        fn when_pex_peers_have_an_invalid_length() {
+            assert!(matches!(
+                Pex::from_bytes(b"d5:added5:abcdee"),
+                Err(Error::InvalidValue { field: "added", .. })
+            ));
+        }
+    }
+}
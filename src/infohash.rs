@@ -0,0 +1,517 @@
+//! Infohashes computed while parsing.
+//!
+//! The infohash of a torrent is the hash of the exact bytes of its `info`
+//! dictionary:
+//!
+//! - v1 ([BEP 3](https://www.bittorrent.org/beps/bep_0003.html)): SHA-1.
+//! - v2 ([BEP 52](https://www.bittorrent.org/beps/bep_0052.html)): SHA-256,
+//!   and the first 20 bytes of it for protocols that only have room for a v1
+//!   hash.
+//!
+//! [`InfohashWriter`] wraps another writer and hashes the value of a key in
+//! top-level dictionaries (`info` by default) from the tokens reported by the
+//! parser, so the hashes are computed in the same pass as the JSON
+//! conversion:
+//!
+//! ```rust
+//! use torrust_bencode2json::{
+//!     infohash::InfohashWriter, parsers::BencodeParser, rw::string_writer::StringWriter,
+//! };
+//!
+//! let mut parser = BencodeParser::new(&b"d4:infod4:name1:aee"[..]);
+//!
+//! let mut json = String::new();
+//! let mut writer = InfohashWriter::new(StringWriter::new(&mut json));
+//!
+//! parser.write_to(&mut writer).unwrap();
+//!
+//! assert_eq!(
+//!     writer.infohashes()[0].v1_hex(),
+//!     "9e3f71178c577dcb032d2d7dfbb436d21769d456"
+//! );
+//! ```
+//!
+//! With [`InfohashWriter::with_output_key`] the hashes are also added to the
+//! JSON of the dictionary.
+//!
+//! The hashed bytes are the tokens as they are in the input. The parser can
+//! skip bytes between tokens, like the line breaks it ignores, or invalid
+//! bytes when it recovers from errors. Hashing the value without them would
+//! not give the hash of the input, so the writer fails if the parser skips
+//! bytes within the hashed value.
+use std::io;
+
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+use crate::{
+    parsers::token::{Token, TokenKind},
+    rw::{error::Error, writer::Writer},
+};
+
+/// The default key of the hashed value.
+pub const DEFAULT_KEY: &str = "info";
+
+/// The v1 and v2 hashes of a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Infohash {
+    /// The SHA-1 hash.
+    pub v1: [u8; 20],
+
+    /// The SHA-256 hash.
+    pub v2: [u8; 32],
+}
+
+impl Infohash {
+    /// It hashes the bytes.
+    #[must_use]
+    pub fn of(bytes: &[u8]) -> Self {
+        let mut hasher = Hasher::default();
+
+        hasher.update(bytes);
+
+        hasher.finalize()
+    }
+
+    /// It returns the v2 hash truncated to 20 bytes.
+    #[must_use]
+    pub fn v2_truncated(&self) -> [u8; 20] {
+        let mut truncated = [0; 20];
+        truncated.copy_from_slice(&self.v2[..20]);
+        truncated
+    }
+
+    #[must_use]
+    pub fn v1_hex(&self) -> String {
+        hex::encode(self.v1)
+    }
+
+    #[must_use]
+    pub fn v2_hex(&self) -> String {
+        hex::encode(self.v2)
+    }
+
+    #[must_use]
+    pub fn v2_truncated_hex(&self) -> String {
+        hex::encode(self.v2_truncated())
+    }
+
+    /// It returns the hashes as a JSON object with the `v1`, `v2` and
+    /// `v2_truncated` hex hashes.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"v1":"{}","v2":"{}","v2_truncated":"{}"}}"#,
+            self.v1_hex(),
+            self.v2_hex(),
+            self.v2_truncated_hex()
+        )
+    }
+}
+
+#[derive(Default)]
+struct Hasher {
+    v1: Sha1,
+    v2: Sha256,
+
+    /// Input position of the end of the latest hashed token.
+    input_end: Option<u64>,
+}
+
+impl Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        self.v1.update(bytes);
+        self.v2.update(bytes);
+    }
+
+    /// It hashes the token.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the parser skipped bytes after the previous
+    /// hashed token.
+    fn update_with_token(&mut self, token: &Token<'_>) -> Result<(), Error> {
+        if self
+            .input_end
+            .is_some_and(|input_end| input_end != token.input_start)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the parser skipped bytes within the hashed value, like line breaks, so its hash would not be the hash of the input",
+            )
+            .into());
+        }
+
+        self.input_end = Some(token.input_end);
+
+        token.write_raw_bytes(|chunk| self.update(chunk));
+
+        Ok(())
+    }
+
+    fn finalize(self) -> Infohash {
+        Infohash {
+            v1: self.v1.finalize().into(),
+            v2: self.v2.finalize().into(),
+        }
+    }
+}
+
+/// A [`Writer`] that writes to another writer and hashes the value of a key
+/// in top-level dictionaries.
+///
+/// There is one [`Infohash`] for every top-level dictionary with the key,
+/// in the same order.
+pub struct InfohashWriter<W: Writer> {
+    inner: W,
+
+    /// The key of the hashed value.
+    key: Vec<u8>,
+
+    /// The JSON key to add the hashes to the dictionary, if any.
+    output_key: Option<String>,
+
+    /// Number of lists and dictionaries open.
+    depth: usize,
+
+    /// True when the top-level value being parsed is a dictionary.
+    in_top_level_dict: bool,
+
+    /// True when the next item of the top-level dictionary is a key.
+    expecting_key: bool,
+
+    /// True when the next item of the top-level dictionary is the value of
+    /// the key.
+    key_found: bool,
+
+    /// The hasher of the value being hashed, if any.
+    hasher: Option<Hasher>,
+
+    /// The hashes for the current top-level dictionary.
+    current: Option<Infohash>,
+
+    infohashes: Vec<Infohash>,
+}
+
+impl<W: Writer> InfohashWriter<W> {
+    /// It creates a writer that hashes the value of the `info` key.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            key: DEFAULT_KEY.as_bytes().to_vec(),
+            output_key: None,
+            depth: 0,
+            in_top_level_dict: false,
+            expecting_key: false,
+            key_found: false,
+            hasher: None,
+            current: None,
+            infohashes: Vec::new(),
+        }
+    }
+
+    /// It sets the key of the hashed value.
+    #[must_use]
+    pub fn with_key(mut self, key: &[u8]) -> Self {
+        self.key = key.to_vec();
+        self
+    }
+
+    /// It adds the hashes (see [`Infohash::to_json`]) to the JSON of each
+    /// dictionary with the hashed key, as the last field with this key.
+    ///
+    /// The field is added even if the dictionary already has this key.
+    #[must_use]
+    pub fn with_output_key(mut self, output_key: &str) -> Self {
+        self.output_key = Some(output_key.to_string());
+        self
+    }
+
+    /// It returns the hashes of the values found so far.
+    #[must_use]
+    pub fn infohashes(&self) -> &[Infohash] {
+        &self.infohashes
+    }
+
+    /// It returns the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// It updates the state after a complete item of the top-level
+    /// dictionary.
+    fn end_top_level_dict_item(&mut self) {
+        if let Some(hasher) = self.hasher.take() {
+            self.current = Some(hasher.finalize());
+        }
+
+        self.expecting_key = !self.expecting_key;
+    }
+
+    /// True when the closing brace of a top-level dictionary with hashes to
+    /// add is about to be written.
+    fn is_closing_dict_with_hashes(&self, byte: u8) -> bool {
+        byte == b'}'
+            && self.depth == 1
+            && self.in_top_level_dict
+            && self.current.is_some()
+            && self.output_key.is_some()
+    }
+}
+
+impl<W: Writer> Writer for InfohashWriter<W> {
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        if self.is_closing_dict_with_hashes(byte) {
+            if let (Some(output_key), Some(infohash)) = (&self.output_key, &self.current) {
+                let key = serde_json::to_string(output_key)
+                    .expect("serializing a string should not fail");
+
+                self.inner
+                    .write_str(&format!(",{key}:{}", infohash.to_json()))?;
+            }
+        }
+
+        self.inner.write_byte(byte)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.inner.write_bytes(bytes)
+    }
+
+    fn write_str(&mut self, value: &str) -> Result<(), Error> {
+        self.inner.write_str(value)
+    }
+
+    fn output_byte_counter(&self) -> u64 {
+        self.inner.output_byte_counter()
+    }
+
+    fn captured_bytes(&self) -> Vec<u8> {
+        self.inner.captured_bytes()
+    }
+
+    fn token(&mut self, token: &Token<'_>) -> Result<(), Error> {
+        match token.kind {
+            TokenKind::BeginList | TokenKind::BeginDict => {
+                if self.depth == 0 {
+                    self.in_top_level_dict = token.kind == TokenKind::BeginDict;
+                    self.expecting_key = true;
+                    self.key_found = false;
+                    self.current = None;
+                } else if self.depth == 1 && self.key_found {
+                    self.hasher = Some(Hasher::default());
+                }
+
+                if let Some(hasher) = &mut self.hasher {
+                    hasher.update_with_token(token)?;
+                }
+
+                self.depth += 1;
+            }
+            TokenKind::End => {
+                if let Some(hasher) = &mut self.hasher {
+                    hasher.update_with_token(token)?;
+                }
+
+                self.depth = self.depth.saturating_sub(1);
+
+                match self.depth {
+                    0 => {
+                        if let Some(infohash) = self.current.take() {
+                            self.infohashes.push(infohash);
+                        }
+                    }
+                    1 if self.in_top_level_dict => self.end_top_level_dict_item(),
+                    _ => {}
+                }
+            }
+            TokenKind::Integer(_) | TokenKind::String(_) => {
+                if self.depth == 1 && self.in_top_level_dict {
+                    if self.expecting_key {
+                        self.key_found =
+                            matches!(token.kind, TokenKind::String(key) if key == self.key);
+                    } else if self.key_found {
+                        self.hasher = Some(Hasher::default());
+                    }
+                }
+
+                if let Some(hasher) = &mut self.hasher {
+                    hasher.update_with_token(token)?;
+                }
+
+                if self.depth == 1 && self.in_top_level_dict {
+                    self.end_top_level_dict_item();
+                }
+            }
+        }
+
+        self.inner.token(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        infohash::{Infohash, InfohashWriter},
+        parsers::BencodeParser,
+        rw::string_writer::StringWriter,
+    };
+
+    /// It returns the JSON output and the hashes.
+    fn parse(
+        input: &[u8],
+        build: impl FnOnce(
+            InfohashWriter<StringWriter<&mut String>>,
+        ) -> InfohashWriter<StringWriter<&mut String>>,
+    ) -> (String, Vec<Infohash>) {
+        let mut json = String::new();
+
+        let mut writer = build(InfohashWriter::new(StringWriter::new(&mut json)));
+
+        BencodeParser::new(input).write_to(&mut writer).unwrap();
+
+        let infohashes = writer.infohashes().to_vec();
+
+        drop(writer);
+
+        (json, infohashes)
+    }
+
+    mod it_should {
+        use crate::{
+            infohash::{tests::parse, Infohash, InfohashWriter},
+            parsers::BencodeParser,
+            rw::string_writer::StringWriter,
+        };
+
+        #[test]
+        fn hash_the_raw_bytes_of_the_info_dictionary() {
+            let info =
+                b"d6:lengthi5e4:name5:a.txt12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+            let input = [
+                &b"d8:announce9:udp://t:14:info"[..],
+                info,
+                b"7:comment2:hie",
+            ]
+            .concat();
+
+            let (_, infohashes) = parse(&input, |writer| writer);
+
+            assert_eq!(infohashes, vec![Infohash::of(info)]);
+        }
+
+        #[test]
+        fn give_the_v1_v2_and_truncated_v2_hashes() {
+            let infohash = Infohash::of(b"d4:name1:ae");
+
+            assert_eq!(
+                infohash.v1_hex(),
+                "9e3f71178c577dcb032d2d7dfbb436d21769d456"
+            );
+            assert_eq!(
+                infohash.v2_hex(),
+                "16f85be49fdd221d9059d41f54def75638d67eda48d423b66f47807af73e5fc5"
+            );
+            assert_eq!(
+                infohash.v2_truncated_hex(),
+                "16f85be49fdd221d9059d41f54def75638d67eda"
+            );
+        }
+
+        #[test]
+        fn keep_leading_zeros_of_string_lengths_in_the_hashed_bytes() {
+            let info = b"d4:name02:abe";
+
+            let (_, infohashes) = parse(&[&b"d4:info"[..], info, b"e"].concat(), |writer| writer);
+
+            assert_eq!(infohashes, vec![Infohash::of(info)]);
+        }
+
+        #[test]
+        fn hash_the_value_of_a_custom_key() {
+            let (_, infohashes) = parse(b"d1:ai1e1:bli2eee", |writer| writer.with_key(b"b"));
+
+            assert_eq!(infohashes, vec![Infohash::of(b"li2ee")]);
+        }
+
+        #[test]
+        fn hash_scalar_values() {
+            let (_, infohashes) = parse(b"d4:infoi42ee", |writer| writer);
+
+            assert_eq!(infohashes, vec![Infohash::of(b"i42e")]);
+        }
+
+        #[test]
+        fn not_hash_the_key_in_nested_dictionaries_or_in_values() {
+            let (_, infohashes) = parse(b"d1:ad4:infoi1ee1:b4:infoel4:infoe", |writer| writer);
+
+            assert!(infohashes.is_empty());
+        }
+
+        #[test]
+        fn give_one_hash_for_each_top_level_dictionary_with_the_key() {
+            let (_, infohashes) = parse(b"d4:infoi1ee\nd1:ai1ee\nd4:infoi2ee", |writer| writer);
+
+            assert_eq!(infohashes, vec![Infohash::of(b"i1e"), Infohash::of(b"i2e")]);
+        }
+
+        #[test]
+        fn not_change_the_json_output_by_default() {
+            let (json, _) = parse(b"d4:infod4:name1:aee", |writer| writer);
+
+            assert_eq!(json, r#"{"info":{"name":"a"}}"#);
+        }
+
+        #[test]
+        fn add_the_hashes_to_the_json_output_under_the_output_key() {
+            let (json, infohashes) = parse(b"d4:infod4:name1:ae1:xlee", |writer| {
+                writer.with_output_key("infohash")
+            });
+
+            assert_eq!(
+                json,
+                format!(
+                    r#"{{"info":{{"name":"a"}},"x":[],"infohash":{}}}"#,
+                    infohashes[0].to_json()
+                )
+            );
+
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(
+                value["infohash"]["v1"],
+                "9e3f71178c577dcb032d2d7dfbb436d21769d456"
+            );
+        }
+
+        #[test]
+        fn not_add_the_output_key_to_dictionaries_without_the_key() {
+            let (json, _) = parse(b"d1:ad4:infoi1eee", |writer| {
+                writer.with_output_key("infohash")
+            });
+
+            assert_eq!(json, r#"{"a":{"info":1}}"#);
+        }
+
+        #[test]
+        fn fail_when_the_parser_skips_bytes_within_the_hashed_value() {
+            let mut json = String::new();
+
+            let mut writer = InfohashWriter::new(StringWriter::new(&mut json));
+
+            let result = BencodeParser::new(&b"d4:infod4:name\n1:aee"[..]).write_to(&mut writer);
+
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn allow_skipped_bytes_outside_the_hashed_value() {
+            let (_, infohashes) = parse(b"d4:info\nd4:name1:ae\ne\nd4:infoi1ee\n", |writer| writer);
+
+            assert_eq!(
+                infohashes,
+                vec![Infohash::of(b"d4:name1:ae"), Infohash::of(b"i1e")]
+            );
+        }
+    }
+}
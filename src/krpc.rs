@@ -0,0 +1,320 @@
+//! DHT KRPC messages.
+//!
+//! It renders the messages of the distributed hash table (DHT)
+//! ([BEP 5](https://www.bittorrent.org/beps/bep_0005.html)) as JSON, with the
+//! binary fields decoded:
+//!
+//! - The transaction ID (`t`), node IDs (`id`), targets, infohashes and
+//!   tokens are written in hex.
+//! - Compact `nodes` and `nodes6` are written as lists of `{id, ip, port}`
+//!   objects, and the peers in `values` as `"ip:port"` strings.
+//! - The requester address in `ip`
+//!   ([BEP 42](https://www.bittorrent.org/beps/bep_0042.html)) is written as
+//!   an `"ip:port"` string.
+//! - The bloom filters of scrapes (`BFsd` and `BFpe`,
+//!   [BEP 33](https://www.bittorrent.org/beps/bep_0033.html)) are written in
+//!   hex.
+//! - The infohash `samples`
+//!   ([BEP 51](https://www.bittorrent.org/beps/bep_0051.html)) are written as
+//!   a list of hex strings.
+//! - Errors (`e`) are written as an object with the code, its meaning and the
+//!   message.
+//!
+//! ```rust
+//! use torrust_bencode2json::{krpc, value};
+//!
+//! let message = value::from_bytes(b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe").unwrap();
+//!
+//! assert_eq!(
+//!     krpc::render(&message).to_string(),
+//!     r#"{"kind":"query","message":{"a":{"id":"6162636465666768696a30313233343536373839"},"q":"ping","t":"6161","y":"q"},"method":"ping"}"#
+//! );
+//! ```
+//!
+//! Responses don't include the method of the query. It can be found by
+//! matching the transaction ID with the one of the query.
+use std::fmt;
+
+use serde_json::json;
+
+use crate::{
+    compact::{decode_nodes, decode_nodes6, decode_peer, Node, NODE_ID_LENGTH},
+    value::{bytes_to_json_string, Value},
+};
+
+/// The kind of a KRPC message, in the `y` key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A query (`q`).
+    Query,
+
+    /// A response to a query (`r`).
+    Response,
+
+    /// An error (`e`).
+    Error,
+}
+
+impl Kind {
+    /// It returns the kind of a message, or `None` if the message doesn't
+    /// have a valid `y` key.
+    #[must_use]
+    pub fn of(message: &Value) -> Option<Self> {
+        match message.get("y").and_then(Value::as_bytes)? {
+            b"q" => Some(Self::Query),
+            b"r" => Some(Self::Response),
+            b"e" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    /// It returns the name of the kind, like `query`.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Query => "query",
+            Self::Response => "response",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// It returns the meaning of a KRPC error code, or `None` for unknown codes.
+#[must_use]
+pub fn error_meaning(code: i64) -> Option<&'static str> {
+    match code {
+        201 => Some("Generic Error"),
+        202 => Some("Server Error"),
+        203 => Some("Protocol Error"),
+        204 => Some("Method Unknown"),
+        _ => None,
+    }
+}
+
+/// It returns the JSON of a KRPC message: an object with the `kind` of the
+/// message, the `method` of queries (`ping`, `find_node`, `get_peers`,
+/// `announce_peer`, `sample_infohashes`, ...) and the decoded `message`.
+///
+/// The kind and the method are `null` when they are missing or invalid.
+#[must_use]
+pub fn render(message: &Value) -> serde_json::Value {
+    let kind = Kind::of(message);
+
+    let method = match kind {
+        Some(Kind::Query) => message.get("q").and_then(Value::as_bytes),
+        _ => None,
+    };
+
+    json!({
+        "kind": kind.map(|kind| kind.as_str()),
+        "method": method.map(bytes_to_json_string),
+        "message": render_dict(message, render_message_entry),
+    })
+}
+
+fn render_message_entry(key: &[u8], value: &Value) -> serde_json::Value {
+    match (key, value) {
+        (b"t", Value::String(bytes)) => hex::encode(bytes).into(),
+        (b"ip", Value::String(bytes)) => render_address(bytes, value),
+        (b"a" | b"r", Value::Dict(_)) => render_dict(value, render_body_entry),
+        (b"e", Value::List(items)) => render_error(items, value),
+        _ => value.to_json(),
+    }
+}
+
+/// It renders the entries of the arguments of queries (`a`) and the return
+/// values of responses (`r`).
+fn render_body_entry(key: &[u8], value: &Value) -> serde_json::Value {
+    match (key, value) {
+        (b"id" | b"target" | b"info_hash" | b"token" | b"BFsd" | b"BFpe", Value::String(bytes)) => {
+            hex::encode(bytes).into()
+        }
+        (b"nodes", Value::String(bytes)) => render_nodes(decode_nodes(bytes), value),
+        (b"nodes6", Value::String(bytes)) => render_nodes(decode_nodes6(bytes), value),
+        (b"values", Value::List(peers)) => peers
+            .iter()
+            .map(|peer| match peer {
+                Value::String(bytes) => render_address(bytes, peer),
+                _ => peer.to_json(),
+            })
+            .collect(),
+        (b"samples", Value::String(bytes)) if bytes.len().is_multiple_of(NODE_ID_LENGTH) => bytes
+            .chunks_exact(NODE_ID_LENGTH)
+            .map(hex::encode)
+            .collect(),
+        _ => value.to_json(),
+    }
+}
+
+fn render_dict(
+    value: &Value,
+    render_entry: fn(&[u8], &Value) -> serde_json::Value,
+) -> serde_json::Value {
+    match value {
+        Value::Dict(entries) => serde_json::Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| (bytes_to_json_string(key), render_entry(key, value)))
+                .collect(),
+        ),
+        _ => value.to_json(),
+    }
+}
+
+fn render_address(bytes: &[u8], value: &Value) -> serde_json::Value {
+    match decode_peer(bytes) {
+        Some(address) => address.to_string().into(),
+        None => value.to_json(),
+    }
+}
+
+fn render_nodes(nodes: Option<Vec<Node>>, value: &Value) -> serde_json::Value {
+    match nodes {
+        Some(nodes) => nodes
+            .iter()
+            .map(|node| {
+                json!({
+                    "id": hex::encode(node.id),
+                    "ip": node.address.ip().to_string(),
+                    "port": node.address.port(),
+                })
+            })
+            .collect(),
+        None => value.to_json(),
+    }
+}
+
+/// It renders the `[code, message]` list of errors.
+fn render_error(items: &[Value], value: &Value) -> serde_json::Value {
+    match items {
+        [Value::Integer(code), Value::String(message)] => json!({
+            "code": code,
+            "meaning": error_meaning(*code),
+            "message": bytes_to_json_string(message),
+        }),
+        _ => value.to_json(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should {
+        use serde_json::json;
+
+        use crate::{
+            krpc::{render, Kind},
+            value::from_bytes,
+        };
+
+        const ID: &str = "6162636465666768696a30313233343536373839";
+
+        #[test]
+        fn classify_queries_responses_and_errors() {
+            assert_eq!(
+                Kind::of(&from_bytes(b"d1:y1:qe").unwrap()),
+                Some(Kind::Query)
+            );
+            assert_eq!(
+                Kind::of(&from_bytes(b"d1:y1:re").unwrap()),
+                Some(Kind::Response)
+            );
+            assert_eq!(
+                Kind::of(&from_bytes(b"d1:y1:ee").unwrap()),
+                Some(Kind::Error)
+            );
+            assert_eq!(Kind::of(&from_bytes(b"d1:y1:xe").unwrap()), None);
+            assert_eq!(Kind::of(&from_bytes(b"le").unwrap()), None);
+        }
+
+        #[test]
+        fn render_the_method_and_the_ids_of_queries_in_hex() {
+            let input = b"d1:ad2:id20:abcdefghij01234567899:info_hash20:mnopqrstuvwxyz123456e1:q9:get_peers1:t2:aa1:y1:qe";
+
+            assert_eq!(
+                render(&from_bytes(input).unwrap()),
+                json!({
+                    "kind": "query",
+                    "method": "get_peers",
+                    "message": {
+                        "a": {"id": ID, "info_hash": "6d6e6f707172737475767778797a313233343536"},
+                        "q": "get_peers",
+                        "t": "6161",
+                        "y": "q",
+                    },
+                })
+            );
+        }
+
+        #[test]
+        fn render_compact_nodes_values_and_tokens_of_responses() {
+            let input = [
+                &b"d2:ip6:\x0a\x00\x00\x02\x00\x501:rd2:id20:abcdefghij01234567895:nodes26:abcdefghij0123456789"[..],
+                b"\x0a\x00\x00\x01\x1a\xe15:token2:\xff\x006:valuesl6:\xc0\xa8\x01\x02\x00\x50ee1:t2:aa1:y1:re",
+            ]
+            .concat();
+
+            assert_eq!(
+                render(&from_bytes(&input).unwrap()),
+                json!({
+                    "kind": "response",
+                    "method": null,
+                    "message": {
+                        "ip": "10.0.0.2:80",
+                        "r": {
+                            "id": ID,
+                            "nodes": [{"id": ID, "ip": "10.0.0.1", "port": 6881}],
+                            "token": "ff00",
+                            "values": ["192.168.1.2:80"],
+                        },
+                        "t": "6161",
+                        "y": "r",
+                    },
+                })
+            );
+        }
+
+        #[test]
+        fn render_the_samples_of_sample_infohashes_responses() {
+            let input = b"d1:rd2:id20:abcdefghij01234567898:intervali21600e3:numi2e7:samples40:abcdefghij0123456789abcdefghij0123456789e1:t2:aa1:y1:re";
+
+            assert_eq!(
+                render(&from_bytes(input).unwrap())["message"]["r"]["samples"],
+                json!([ID, ID])
+            );
+        }
+
+        #[test]
+        fn render_error_codes_with_their_meanings() {
+            let input = b"d1:eli204e14:Method Unknowne1:t2:aa1:y1:ee";
+
+            assert_eq!(
+                render(&from_bytes(input).unwrap()),
+                json!({
+                    "kind": "error",
+                    "method": null,
+                    "message": {
+                        "e": {"code": 204, "meaning": "Method Unknown", "message": "Method Unknown"},
+                        "t": "6161",
+                        "y": "e",
+                    },
+                })
+            );
+        }
+
+        #[test]
+        fn keep_compact_values_with_an_invalid_length_as_strings() {
+            let input = b"d1:rd5:nodes3:abc6:valuesl3:abcee1:y1:re";
+
+            assert_eq!(
+                render(&from_bytes(input).unwrap())["message"]["r"],
+                json!({"nodes": "abc", "values": ["abc"]})
+            );
+        }
+    }
+}
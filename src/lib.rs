@@ -36,9 +36,24 @@
 //! > containing bencoded data and produces a JSON output (raw bytes or UTF-8 string).
 use parsers::{error::Error, BencodeParser};
 
+#[cfg(feature = "codec")]
+pub mod codec;
+pub mod compact;
+pub mod encoder;
+pub mod extension;
+pub mod infohash;
+pub mod krpc;
+pub mod lint;
+pub mod magnet;
+pub mod metadata;
+pub mod metainfo;
 pub mod parsers;
+pub mod pcap;
 pub mod rw;
+pub mod security;
 mod test;
+pub mod tracker;
+pub mod value;
 
 /// It converts bencoded bytes into a JSON string.
 ///
@@ -48,7 +63,7 @@ mod test;
 pub fn try_bencode_to_json(input_buffer: &[u8]) -> Result<String, Error> {
     let mut output = String::new();
 
-    let mut parser = BencodeParser::new(input_buffer);
+    let mut parser = BencodeParser::from_buf_read(input_buffer);
 
     match parser.write_str(&mut output) {
         Ok(()) => Ok(output),
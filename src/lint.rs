@@ -0,0 +1,818 @@
+//! Conformance checks for `.torrent` files.
+//!
+//! It reports the problems found in a `.torrent` file, instead of stopping at
+//! the first one like [`metainfo`](crate::metainfo) does:
+//!
+//! ```rust
+//! use torrust_bencode2json::lint::{lint_bytes, Severity};
+//!
+//! let findings = lint_bytes(b"d4:infod6:lengthi5e4:name1:a12:piece lengthi1e6:pieces3:abcee");
+//!
+//! assert_eq!(findings[0].severity, Severity::Error);
+//! assert_eq!(findings[0].path, "$.info.pieces");
+//! assert_eq!(findings[0].to_string(), "error: $.info.pieces: the length is not a multiple of 20");
+//! ```
+//!
+//! It checks:
+//!
+//! - The required fields and their types (BEP 3). v2-only torrents (BEP 52)
+//!   need a `file tree` instead of `pieces` and `length` or `files`.
+//! - The length of `pieces`, and the number of pieces for the total length.
+//! - Empty file paths and path components.
+//! - Encodings that are not canonical: unsorted or duplicate keys, and
+//!   integers or string lengths with leading zeros. They are errors in the
+//!   info dictionary because re-encoding it would change the infohash.
+//! - The schemes of the tracker URLs (`http`, `https` and `udp`).
+//! - The `private` flag (BEP 27), which must be 0 or 1.
+//!
+//! Invalid Bencode is reported as an error with the path where the parser
+//! stopped.
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    io::BufRead,
+};
+
+use crate::{
+    metainfo::SHA1_LENGTH,
+    parsers::{
+        token::{Token, TokenKind},
+        BencodeParser,
+    },
+    rw::{error::Error as RwError, writer::Writer},
+    value::{Value, ValueBuilder},
+};
+
+/// The tracker URL schemes supported.
+pub const SUPPORTED_TRACKER_SCHEMES: [&str; 3] = ["http", "https", "udp"];
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The file is invalid, or clients may get a different infohash.
+    Error,
+
+    /// The file is valid, but some clients may not handle it.
+    Warning,
+}
+
+impl Severity {
+    /// It returns the name of the severity: `error` or `warning`.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A problem found in a `.torrent` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+
+    /// The path of the value with the problem, like `$.info.files[0].path`.
+    pub path: String,
+
+    pub message: String,
+}
+
+impl Finding {
+    fn error(path: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(path: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.path, self.message)
+    }
+}
+
+/// It returns true if any of the findings is an error.
+#[must_use]
+pub fn has_errors(findings: &[Finding]) -> bool {
+    findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error)
+}
+
+/// It checks a `.torrent` file.
+#[must_use]
+pub fn lint_bytes(input: &[u8]) -> Vec<Finding> {
+    lint(&mut BencodeParser::from_buf_read(input))
+}
+
+/// It checks a `.torrent` file parsed with a parser.
+///
+/// The parser should stop at the first error (the default), because the
+/// values it recovers from are not checked.
+pub fn lint<R: BufRead>(parser: &mut BencodeParser<R>) -> Vec<Finding> {
+    let mut writer = LintWriter::default();
+
+    let result = parser.write_to(&mut writer);
+
+    let mut findings = Vec::new();
+
+    let parse_error = writer
+        .builder
+        .take_error()
+        .map(|err| err.to_string())
+        .or_else(|| result.err().map(|err| err.to_string()));
+
+    if let Some(message) = parse_error {
+        findings.push(Finding::error(&writer.path(), message));
+    } else {
+        match writer.builder.into_value() {
+            Ok(value) => check_torrent(&value, &mut findings),
+            Err(err) => findings.push(Finding::error("$", err.to_string())),
+        }
+    }
+
+    findings.append(&mut writer.findings);
+
+    findings
+}
+
+/// A [`Writer`] that builds the value and checks the encoding of the tokens.
+#[derive(Debug, Default)]
+struct LintWriter {
+    builder: ValueBuilder,
+
+    /// The lists and dictionaries that have not been closed yet.
+    frames: Vec<Frame>,
+
+    findings: Vec<Finding>,
+}
+
+#[derive(Debug)]
+enum Frame {
+    List {
+        index: usize,
+    },
+    Dict {
+        /// The key of the current value, or the previous key when the next
+        /// item is a key.
+        key: Option<Vec<u8>>,
+        expecting_key: bool,
+    },
+}
+
+impl LintWriter {
+    /// It returns the path of the current value.
+    fn path(&self) -> String {
+        let mut path = String::from("$");
+
+        for frame in &self.frames {
+            match frame {
+                Frame::List { index } => {
+                    write!(path, "[{index}]").expect("writing to a string should not fail");
+                }
+                Frame::Dict { key: Some(key), .. } => {
+                    path.push('.');
+                    path.push_str(&String::from_utf8_lossy(key));
+                }
+                Frame::Dict { key: None, .. } => {}
+            }
+        }
+
+        path
+    }
+
+    /// True when the current value is in the info dictionary, so its
+    /// encoding is part of the infohash.
+    fn in_info(&self) -> bool {
+        matches!(
+            self.frames.first(),
+            Some(Frame::Dict { key: Some(key), expecting_key: false }) if key == b"info"
+        ) && self.frames.len() > 1
+    }
+
+    fn not_canonical(&mut self, path: &str, message: &str) {
+        let finding = if self.in_info() {
+            Finding::error(
+                path,
+                format!("{message}, so the infohash is not reproducible"),
+            )
+        } else {
+            Finding::warning(path, message)
+        };
+
+        self.findings.push(finding);
+    }
+
+    /// It checks the order of a dictionary key, and sets it as the key of
+    /// the current value.
+    fn check_key(&mut self, new_key: &[u8]) {
+        let Some(Frame::Dict { key, .. }) = self.frames.last() else {
+            return;
+        };
+
+        let problem = match key {
+            Some(previous) if previous.as_slice() == new_key => Some("duplicate key"),
+            Some(previous) if previous.as_slice() > new_key => Some("keys are not sorted"),
+            _ => None,
+        };
+
+        if let Some(Frame::Dict { key, expecting_key }) = self.frames.last_mut() {
+            *key = Some(new_key.to_vec());
+            *expecting_key = false;
+        }
+
+        if let Some(message) = problem {
+            self.not_canonical(&self.path(), message);
+        }
+    }
+
+    /// It checks the encoding of a value.
+    fn check_value(&mut self, token: &Token<'_>) {
+        match token.kind {
+            TokenKind::Integer(digits) => {
+                let unsigned = digits.strip_prefix('-').unwrap_or(digits);
+
+                if digits == "-0" {
+                    self.not_canonical(&self.path(), "negative zero");
+                } else if unsigned.len() > 1 && unsigned.starts_with('0') {
+                    self.not_canonical(&self.path(), "integer with leading zeros");
+                }
+            }
+            TokenKind::String(bytes) => {
+                if has_string_length_with_leading_zeros(token, bytes) {
+                    self.not_canonical(&self.path(), "string length with leading zeros");
+                }
+            }
+            TokenKind::BeginList | TokenKind::BeginDict | TokenKind::End => {}
+        }
+    }
+
+    /// It moves to the next item of the current list or dictionary.
+    fn end_value(&mut self) {
+        match self.frames.last_mut() {
+            Some(Frame::List { index }) => *index += 1,
+            Some(Frame::Dict { expecting_key, .. }) => *expecting_key = true,
+            None => {}
+        }
+    }
+}
+
+fn has_string_length_with_leading_zeros(token: &Token<'_>, bytes: &[u8]) -> bool {
+    let canonical_length = bytes.len().to_string().len() + 1 + bytes.len();
+
+    usize::try_from(token.input_end - token.input_start)
+        .is_ok_and(|length| length > canonical_length)
+}
+
+impl Writer for LintWriter {
+    fn write_byte(&mut self, _byte: u8) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, _bytes: &[u8]) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn write_str(&mut self, _value: &str) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn output_byte_counter(&self) -> u64 {
+        0
+    }
+
+    fn captured_bytes(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn token(&mut self, token: &Token<'_>) -> Result<(), RwError> {
+        let is_key = matches!(
+            self.frames.last(),
+            Some(Frame::Dict {
+                expecting_key: true,
+                ..
+            })
+        );
+
+        match token.kind {
+            TokenKind::String(key) if is_key => {
+                self.check_key(key);
+
+                if has_string_length_with_leading_zeros(token, key) {
+                    self.not_canonical(&self.path(), "key length with leading zeros");
+                }
+            }
+            TokenKind::BeginList => {
+                self.frames.push(Frame::List { index: 0 });
+            }
+            TokenKind::BeginDict => {
+                self.frames.push(Frame::Dict {
+                    key: None,
+                    expecting_key: true,
+                });
+            }
+            TokenKind::End => {
+                self.frames.pop();
+                self.end_value();
+            }
+            TokenKind::Integer(_) | TokenKind::String(_) => {
+                self.check_value(token);
+                self.end_value();
+            }
+        }
+
+        self.builder.token(token)
+    }
+}
+
+/// It checks the fields of the torrent.
+fn check_torrent(torrent: &Value, findings: &mut Vec<Finding>) {
+    let Some(dict) = torrent.as_dict() else {
+        findings.push(type_error("$", "dictionary", torrent));
+        return;
+    };
+
+    if let Some(announce) = dict.get(&b"announce"[..]) {
+        check_tracker_url("$.announce", announce, findings);
+    }
+
+    if let Some(announce_list) = dict.get(&b"announce-list"[..]) {
+        for_each_item(
+            "$.announce-list",
+            announce_list,
+            findings,
+            |path, tier, findings| {
+                for_each_item(path, tier, findings, check_tracker_url);
+            },
+        );
+    }
+
+    match dict.get(&b"info"[..]) {
+        None => findings.push(Finding::error("$.info", "missing required field")),
+        Some(info) => check_info(info, findings),
+    }
+}
+
+fn check_info(info: &Value, findings: &mut Vec<Finding>) {
+    let Some(dict) = info.as_dict() else {
+        findings.push(type_error("$.info", "dictionary", info));
+        return;
+    };
+
+    if let Some(name) = required(dict, "$.info", "name", findings) {
+        if name.as_bytes().is_none() {
+            findings.push(type_error("$.info.name", "string", name));
+        }
+    }
+
+    let piece_length = required(dict, "$.info", "piece length", findings)
+        .and_then(|value| positive_integer("$.info.piece length", value, findings));
+
+    // v2-only torrents (BEP 52) have a file tree instead of the v1 fields
+    let is_v2_only = dict.get(&b"meta version"[..]).and_then(Value::as_integer) == Some(2)
+        && ["pieces", "length", "files"]
+            .iter()
+            .all(|key| !dict.contains_key(key.as_bytes()));
+
+    if is_v2_only {
+        if let Some(file_tree) = required(dict, "$.info", "file tree", findings) {
+            if file_tree.as_dict().is_none() {
+                findings.push(type_error("$.info.file tree", "dictionary", file_tree));
+            }
+        }
+    } else {
+        check_v1_pieces_and_files(dict, piece_length, findings);
+    }
+
+    if let Some(private) = dict.get(&b"private"[..]) {
+        if !matches!(private.as_integer(), Some(0 | 1)) {
+            findings.push(Finding::error("$.info.private", "it should be 0 or 1"));
+        }
+    }
+}
+
+/// It checks the pieces and the files of a torrent with the v1 fields, and
+/// that the number of pieces matches the total length.
+fn check_v1_pieces_and_files(
+    dict: &BTreeMap<Vec<u8>, Value>,
+    piece_length: Option<u64>,
+    findings: &mut Vec<Finding>,
+) {
+    let piece_count = required(dict, "$.info", "pieces", findings).and_then(|pieces| {
+        let Some(bytes) = pieces.as_bytes() else {
+            findings.push(type_error("$.info.pieces", "string", pieces));
+            return None;
+        };
+
+        if bytes.len() % SHA1_LENGTH != 0 {
+            findings.push(Finding::error(
+                "$.info.pieces",
+                "the length is not a multiple of 20",
+            ));
+            return None;
+        }
+
+        Some(bytes.len() / SHA1_LENGTH)
+    });
+
+    let total_length = match (dict.get(&b"length"[..]), dict.get(&b"files"[..])) {
+        (Some(length), None) => non_negative_integer("$.info.length", length, findings),
+        (None, Some(files)) => check_files(files, findings),
+        (Some(_), Some(_)) => {
+            findings.push(Finding::error(
+                "$.info",
+                "it has both a length and a list of files",
+            ));
+            None
+        }
+        (None, None) => {
+            findings.push(Finding::error("$.info.length", "missing required field"));
+            None
+        }
+    };
+
+    if let (Some(piece_length), Some(piece_count), Some(total_length)) =
+        (piece_length, piece_count, total_length)
+    {
+        let expected = total_length.div_ceil(piece_length);
+
+        if u64::try_from(piece_count) != Ok(expected) {
+            findings.push(Finding::error(
+                "$.info.pieces",
+                format!(
+                    "it has {piece_count} pieces, but the total length needs {expected} pieces"
+                ),
+            ));
+        }
+    }
+}
+
+/// It checks the files of a multi-file torrent and returns their total
+/// length, when all the lengths are valid and their sum fits in 64 bits.
+fn check_files(files: &Value, findings: &mut Vec<Finding>) -> Option<u64> {
+    let Some(items) = files.as_list() else {
+        findings.push(type_error("$.info.files", "list", files));
+        return None;
+    };
+
+    let mut total_length = Some(0u64);
+    let mut overflows = false;
+
+    for (index, file) in items.iter().enumerate() {
+        let path = format!("$.info.files[{index}]");
+
+        let Some(dict) = file.as_dict() else {
+            findings.push(type_error(&path, "dictionary", file));
+            total_length = None;
+            continue;
+        };
+
+        let length = required(dict, &path, "length", findings)
+            .and_then(|length| non_negative_integer(&format!("{path}.length"), length, findings));
+
+        total_length = match (total_length, length) {
+            (Some(total), Some(length)) => {
+                let sum = total.checked_add(length);
+                overflows |= sum.is_none();
+                sum
+            }
+            _ => None,
+        };
+
+        if let Some(file_path) = required(dict, &path, "path", findings) {
+            check_file_path(&format!("{path}.path"), file_path, findings);
+        }
+    }
+
+    if overflows {
+        findings.push(Finding::error(
+            "$.info.files",
+            "the total length doesn't fit in 64 bits",
+        ));
+    }
+
+    total_length
+}
+
+fn check_file_path(path: &str, file_path: &Value, findings: &mut Vec<Finding>) {
+    match file_path.as_list() {
+        None => findings.push(type_error(path, "list", file_path)),
+        Some([]) => findings.push(Finding::error(path, "the path is empty")),
+        Some(_) => {
+            for_each_item(
+                path,
+                file_path,
+                findings,
+                |path, component, findings| match component.as_bytes() {
+                    None => findings.push(type_error(path, "string", component)),
+                    Some([]) => findings.push(Finding::error(path, "empty path component")),
+                    Some(_) => {}
+                },
+            );
+        }
+    }
+}
+
+fn check_tracker_url(path: &str, url: &Value, findings: &mut Vec<Finding>) {
+    let Some(url) = url.as_str() else {
+        findings.push(type_error(path, "string", url));
+        return;
+    };
+
+    let scheme = url
+        .split_once("://")
+        .map(|(scheme, _)| scheme.to_lowercase());
+
+    match scheme {
+        Some(scheme) if SUPPORTED_TRACKER_SCHEMES.contains(&scheme.as_str()) => {}
+        Some(scheme) => findings.push(Finding::warning(
+            path,
+            format!("unsupported tracker URL scheme `{scheme}`"),
+        )),
+        None => findings.push(Finding::warning(path, "the tracker URL has no scheme")),
+    }
+}
+
+/// It checks each item of a list, which must be a list.
+fn for_each_item(
+    path: &str,
+    list: &Value,
+    findings: &mut Vec<Finding>,
+    mut check: impl FnMut(&str, &Value, &mut Vec<Finding>),
+) {
+    let Some(items) = list.as_list() else {
+        findings.push(type_error(path, "list", list));
+        return;
+    };
+
+    for (index, item) in items.iter().enumerate() {
+        check(&format!("{path}[{index}]"), item, findings);
+    }
+}
+
+fn required<'a>(
+    dict: &'a BTreeMap<Vec<u8>, Value>,
+    path: &str,
+    key: &str,
+    findings: &mut Vec<Finding>,
+) -> Option<&'a Value> {
+    let value = dict.get(key.as_bytes());
+
+    if value.is_none() {
+        findings.push(Finding::error(
+            &format!("{path}.{key}"),
+            "missing required field",
+        ));
+    }
+
+    value
+}
+
+fn non_negative_integer(path: &str, value: &Value, findings: &mut Vec<Finding>) -> Option<u64> {
+    let Some(integer) = value.as_integer() else {
+        findings.push(type_error(path, "integer", value));
+        return None;
+    };
+
+    let unsigned = u64::try_from(integer).ok();
+
+    if unsigned.is_none() {
+        findings.push(Finding::error(path, "it should not be negative"));
+    }
+
+    unsigned
+}
+
+fn positive_integer(path: &str, value: &Value, findings: &mut Vec<Finding>) -> Option<u64> {
+    let integer = non_negative_integer(path, value, findings)?;
+
+    if integer == 0 {
+        findings.push(Finding::error(path, "it should be greater than 0"));
+        return None;
+    }
+
+    Some(integer)
+}
+
+fn type_error(path: &str, expected: &str, value: &Value) -> Finding {
+    Finding::error(
+        path,
+        format!("expected {expected}, found {}", value.type_name()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::lint_bytes;
+
+    /// It returns the findings as text.
+    fn findings(input: &[u8]) -> Vec<String> {
+        lint_bytes(input).iter().map(ToString::to_string).collect()
+    }
+
+    /// It returns a torrent with the info dictionary.
+    fn torrent(info: &str) -> Vec<u8> {
+        format!("d4:info{info}e").into_bytes()
+    }
+
+    mod it_should_not_report {
+        use crate::lint::tests::findings;
+
+        #[test]
+        fn valid_torrents() {
+            assert!(findings(
+                b"d8:announce9:udp://t:113:announce-listll12:http://t/annee4:infod6:lengthi5e4:name1:a12:piece lengthi4e6:pieces40:aaaaaaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbbbbb7:privatei1eee"
+            )
+            .is_empty());
+        }
+
+        #[test]
+        fn v2_only_torrents_without_the_v1_fields() {
+            assert!(findings(
+                b"d4:infod9:file treed1:ad0:d6:lengthi5e11:pieces root32:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaeee12:meta versioni2e4:name1:a12:piece lengthi16384eee"
+            )
+            .is_empty());
+        }
+    }
+
+    mod it_should_report {
+        use crate::lint::tests::{findings, torrent};
+
+        #[test]
+        fn a_missing_info_dictionary() {
+            assert_eq!(
+                findings(b"de"),
+                vec!["error: $.info: missing required field"]
+            );
+        }
+
+        #[test]
+        fn a_missing_file_tree_in_v2_only_torrents() {
+            assert_eq!(
+                findings(&torrent("d12:meta versioni2e4:name1:a12:piece lengthi4ee")),
+                vec!["error: $.info.file tree: missing required field"]
+            );
+        }
+
+        #[test]
+        fn missing_v1_fields_in_torrents_that_are_not_v2_only() {
+            assert_eq!(
+                findings(&torrent(
+                    "d6:lengthi5e12:meta versioni2e4:name1:a12:piece lengthi4ee"
+                )),
+                vec!["error: $.info.pieces: missing required field"]
+            );
+        }
+
+        #[test]
+        fn a_missing_piece_length() {
+            assert_eq!(
+                findings(&torrent(
+                    "d6:lengthi5e4:name1:a6:pieces20:aaaaaaaaaaaaaaaaaaaae"
+                )),
+                vec!["error: $.info.piece length: missing required field"]
+            );
+        }
+
+        #[test]
+        fn pieces_that_are_not_a_multiple_of_20() {
+            assert_eq!(
+                findings(&torrent(
+                    "d6:lengthi5e4:name1:a12:piece lengthi4e6:pieces3:abce"
+                )),
+                vec!["error: $.info.pieces: the length is not a multiple of 20"]
+            );
+        }
+
+        #[test]
+        fn a_piece_count_inconsistent_with_the_total_length() {
+            assert_eq!(
+                findings(&torrent(
+                    "d5:filesld6:lengthi4e4:pathl1:aeed6:lengthi1e4:pathl1:beee4:name1:d12:piece lengthi2e6:pieces20:aaaaaaaaaaaaaaaaaaaae"
+                )),
+                vec!["error: $.info.pieces: it has 1 pieces, but the total length needs 3 pieces"]
+            );
+        }
+
+        #[test]
+        fn a_total_length_that_does_not_fit_in_64_bits() {
+            assert_eq!(
+                findings(&torrent(
+                    "d5:filesld6:lengthi9223372036854775807e4:pathl1:aeed6:lengthi9223372036854775807e4:pathl1:beed6:lengthi9223372036854775807e4:pathl1:ceee4:name1:d12:piece lengthi4e6:pieces0:e"
+                )),
+                vec!["error: $.info.files: the total length doesn't fit in 64 bits"]
+            );
+        }
+
+        #[test]
+        fn empty_paths_and_path_components() {
+            assert_eq!(
+                findings(&torrent(
+                    "d5:filesld6:lengthi0e4:pathl1:a0:eed6:lengthi0e4:pathleee4:name1:d12:piece lengthi4e6:pieces0:e"
+                )),
+                vec![
+                    "error: $.info.files[0].path[1]: empty path component",
+                    "error: $.info.files[1].path: the path is empty",
+                ]
+            );
+        }
+
+        #[test]
+        fn non_canonical_encodings_in_the_info_dictionary_as_errors() {
+            assert_eq!(
+                findings(&torrent(
+                    "d4:name1:a6:lengthi05e12:piece lengthi8e6:pieces020:aaaaaaaaaaaaaaaaaaaae"
+                )),
+                vec![
+                    "error: $.info.length: keys are not sorted, so the infohash is not reproducible",
+                    "error: $.info.length: integer with leading zeros, so the infohash is not reproducible",
+                    "error: $.info.pieces: string length with leading zeros, so the infohash is not reproducible",
+                ]
+            );
+        }
+
+        #[test]
+        fn duplicate_keys() {
+            assert_eq!(
+                findings(&torrent(
+                    "d6:lengthi5e6:lengthi5e4:name1:a12:piece lengthi8e6:pieces20:aaaaaaaaaaaaaaaaaaaae"
+                )),
+                vec!["error: $.info.length: duplicate key, so the infohash is not reproducible"]
+            );
+        }
+
+        #[test]
+        fn non_canonical_encodings_outside_the_info_dictionary_as_warnings() {
+            assert_eq!(
+                findings(
+                    b"d13:creation datei-0e4:infod6:lengthi5e4:name1:a12:piece lengthi8e6:pieces20:aaaaaaaaaaaaaaaaaaaae7:comment0:e"
+                ),
+                vec![
+                    "warning: $.creation date: negative zero",
+                    "warning: $.comment: keys are not sorted",
+                ]
+            );
+        }
+
+        #[test]
+        fn unsupported_tracker_url_schemes() {
+            assert_eq!(
+                findings(
+                    b"d8:announce9:wss://t:113:announce-listll3:t:1ee4:infod6:lengthi5e4:name1:a12:piece lengthi8e6:pieces20:aaaaaaaaaaaaaaaaaaaaee"
+                ),
+                vec![
+                    "warning: $.announce: unsupported tracker URL scheme `wss`",
+                    "warning: $.announce-list[0][0]: the tracker URL has no scheme",
+                ]
+            );
+        }
+
+        #[test]
+        fn a_private_flag_other_than_0_or_1() {
+            assert_eq!(
+                findings(&torrent(
+                    "d6:lengthi5e4:name1:a12:piece lengthi8e6:pieces20:aaaaaaaaaaaaaaaaaaaa7:privatei2ee"
+                )),
+                vec!["error: $.info.private: it should be 0 or 1"]
+            );
+        }
+
+        #[test]
+        fn mistyped_fields() {
+            assert_eq!(
+                findings(&torrent(
+                    "d6:lengthi5e4:namei1e12:piece length1:a6:pieces0:e"
+                )),
+                vec![
+                    "error: $.info.name: expected string, found integer",
+                    "error: $.info.piece length: expected integer, found string",
+                ]
+            );
+        }
+
+        #[test]
+        fn invalid_bencode_with_the_path_where_the_parser_stopped() {
+            let findings = findings(&torrent("d5:filesld6:lengthi1x"));
+
+            assert_eq!(findings.len(), 1);
+            assert!(findings[0].starts_with("error: $.info.files[0].length: "));
+        }
+    }
+}
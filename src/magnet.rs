@@ -0,0 +1,451 @@
+//! Magnet URIs.
+//!
+//! It builds [BEP 9](https://www.bittorrent.org/beps/bep_0009.html) magnet
+//! URIs from `.torrent` files:
+//!
+//! ```rust
+//! use torrust_bencode2json::{magnet::Magnet, metainfo::Torrent};
+//!
+//! let (torrent, infohash) = Torrent::from_bytes_with_infohash(
+//!     b"d8:announce9:udp://t:14:infod6:lengthi5e4:name5:a.txt12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee",
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(
+//!     Magnet::from_torrent(&torrent, &infohash).to_string(),
+//!     "magnet:?xt=urn:btih:7faf75b2447f88700c68f1eceda713cd90a0127a&dn=a.txt&xl=5&tr=udp%3A%2F%2Ft%3A1"
+//! );
+//! ```
+//!
+//! The URI has these parameters:
+//!
+//! - `xt`: `urn:btih:` with the v1 infohash, and `urn:btmh:` with the v2
+//!   infohash (as a SHA-256 multihash) for v2 torrents
+//!   ([BEP 52](https://www.bittorrent.org/beps/bep_0052.html)). v2-only
+//!   torrents only have the v2 infohash.
+//! - `dn`: the name in the info dictionary.
+//! - `xl`: the total length of the content, when it fits in 64 bits.
+//! - `tr`: the trackers, from `announce` and `announce-list`, without
+//!   duplicates.
+//! - `ws`: the web seeds
+//!   ([BEP 19](https://www.bittorrent.org/beps/bep_0019.html)).
+//!
+//! Values are percent-encoded, including the UTF-8 bytes of non-ASCII names.
+//!
+//! Magnet URIs can also be parsed, with the v1 infohash in hex or base32:
+//!
+//! ```rust
+//! use torrust_bencode2json::magnet::Magnet;
+//!
+//! let magnet: Magnet = "magnet:?xt=urn:btih:7faf75b2447f88700c68f1eceda713cd90a0127a&tr=udp%3A%2F%2Ft%3A1"
+//!     .parse()
+//!     .unwrap();
+//!
+//! assert_eq!(magnet.trackers, vec!["udp://t:1"]);
+//! ```
+use std::{
+    fmt::{self, Write},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::{infohash::Infohash, metainfo::Torrent};
+
+/// The prefix of magnet URIs.
+const MAGNET_PREFIX: &str = "magnet:?";
+
+/// The RFC 4648 base32 alphabet, used for v1 infohashes in old magnet URIs.
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Errors that can occur while parsing magnet URIs.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    /// The URI doesn't start with `magnet:?`.
+    #[error("The URI doesn't start with {MAGNET_PREFIX}")]
+    NotAMagnetUri,
+
+    /// An exact topic (`xt`) is not a valid v1 or v2 infohash.
+    #[error("Invalid exact topic {0}")]
+    InvalidExactTopic(String),
+
+    /// The exact length (`xl`) is not a number.
+    #[error("Invalid exact length {0}")]
+    InvalidExactLength(String),
+
+    /// A value has an invalid percent-encoding, or it's not UTF-8.
+    #[error("Invalid percent-encoding in {0}")]
+    InvalidPercentEncoding(String),
+}
+
+/// The multihash prefix of SHA-256 hashes: the hash function code (`0x12`)
+/// and the hash length (`0x20`).
+const SHA256_MULTIHASH_PREFIX: &str = "1220";
+
+/// A magnet URI.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Magnet {
+    /// The v1 infohash (`xt=urn:btih:`).
+    pub v1: Option<[u8; 20]>,
+
+    /// The v2 infohash (`xt=urn:btmh:`).
+    pub v2: Option<[u8; 32]>,
+
+    /// The display name (`dn`).
+    pub display_name: Option<String>,
+
+    /// The exact length of the content (`xl`).
+    pub exact_length: Option<u64>,
+
+    /// Tracker URLs (`tr`).
+    pub trackers: Vec<String>,
+
+    /// Web seed URLs (`ws`).
+    pub web_seeds: Vec<String>,
+}
+
+impl Magnet {
+    /// It builds the magnet URI of a torrent with its infohash.
+    ///
+    /// The v2 infohash is only included when the torrent is a v2 torrent
+    /// (`meta version` 2), and the v1 infohash when it has the v1 fields
+    /// (it's not a v2-only torrent).
+    #[must_use]
+    pub fn from_torrent(torrent: &Torrent, infohash: &Infohash) -> Self {
+        let mut trackers: Vec<String> = Vec::new();
+
+        for tracker in torrent
+            .announce
+            .iter()
+            .chain(torrent.announce_list.iter().flatten())
+        {
+            if !trackers.contains(tracker) {
+                trackers.push(tracker.clone());
+            }
+        }
+
+        Self {
+            v1: torrent.info.pieces.is_some().then_some(infohash.v1),
+            v2: (torrent.info.meta_version == Some(2)).then_some(infohash.v2),
+            display_name: Some(torrent.info.name.clone()),
+            exact_length: torrent.total_length(),
+            trackers,
+            web_seeds: torrent.url_list.clone(),
+        }
+    }
+}
+
+impl FromStr for Magnet {
+    type Err = Error;
+
+    /// It parses a magnet URI. Unknown parameters are ignored.
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let query = uri
+            .get(..MAGNET_PREFIX.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(MAGNET_PREFIX))
+            .map(|_| &uri[MAGNET_PREFIX.len()..])
+            .ok_or(Error::NotAMagnetUri)?;
+
+        let mut magnet = Self::default();
+
+        for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+
+            let value = percent_decode(value)
+                .ok_or_else(|| Error::InvalidPercentEncoding(parameter.to_string()))?;
+
+            match name {
+                "xt" => parse_exact_topic(&value, &mut magnet)?,
+                "dn" => magnet.display_name = Some(value),
+                "xl" => {
+                    magnet.exact_length = Some(
+                        value
+                            .parse()
+                            .map_err(|_| Error::InvalidExactLength(value.clone()))?,
+                    );
+                }
+                "tr" => magnet.trackers.push(value),
+                "ws" => magnet.web_seeds.push(value),
+                _ => {}
+            }
+        }
+
+        Ok(magnet)
+    }
+}
+
+/// It sets the infohash in an exact topic: `urn:btih:` with a v1 infohash
+/// in hex or base32, or `urn:btmh:` with a SHA-256 multihash.
+fn parse_exact_topic(topic: &str, magnet: &mut Magnet) -> Result<(), Error> {
+    let invalid = || Error::InvalidExactTopic(topic.to_string());
+
+    if let Some(hash) = topic.strip_prefix("urn:btih:") {
+        let v1 = match hash.len() {
+            40 => hex::decode(hash).ok(),
+            32 => base32_decode(hash),
+            _ => None,
+        };
+
+        magnet.v1 = Some(v1.and_then(|v1| v1.try_into().ok()).ok_or_else(invalid)?);
+    } else if let Some(multihash) = topic.strip_prefix("urn:btmh:") {
+        let v2 = multihash
+            .strip_prefix(SHA256_MULTIHASH_PREFIX)
+            .and_then(|hash| hex::decode(hash).ok())
+            .and_then(|v2| v2.try_into().ok());
+
+        magnet.v2 = Some(v2.ok_or_else(invalid)?);
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Magnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parameters = Vec::new();
+
+        if let Some(v1) = &self.v1 {
+            parameters.push(format!("xt=urn:btih:{}", hex::encode(v1)));
+        }
+
+        if let Some(v2) = &self.v2 {
+            parameters.push(format!(
+                "xt=urn:btmh:{SHA256_MULTIHASH_PREFIX}{}",
+                hex::encode(v2)
+            ));
+        }
+
+        if let Some(display_name) = &self.display_name {
+            parameters.push(format!("dn={}", percent_encode(display_name)));
+        }
+
+        if let Some(exact_length) = self.exact_length {
+            parameters.push(format!("xl={exact_length}"));
+        }
+
+        for tracker in &self.trackers {
+            parameters.push(format!("tr={}", percent_encode(tracker)));
+        }
+
+        for web_seed in &self.web_seeds {
+            parameters.push(format!("ws={}", percent_encode(web_seed)));
+        }
+
+        write!(f, "magnet:?{}", parameters.join("&"))
+    }
+}
+
+/// It percent-encodes all the bytes except the unreserved characters of
+/// [RFC 3986](https://www.rfc-editor.org/rfc/rfc3986#section-2.3).
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            write!(encoded, "%{byte:02X}").expect("writing to a string should not fail");
+        }
+    }
+
+    encoded
+}
+
+/// It decodes a percent-encoded value, with `+` as a space. It returns
+/// `None` if the encoding is invalid or the value is not UTF-8.
+fn percent_decode(value: &str) -> Option<String> {
+    let mut decoded = Vec::with_capacity(value.len());
+    let mut bytes = value.bytes();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'%' => {
+                let digits = [bytes.next()?, bytes.next()?];
+
+                // `from_str_radix` would accept a sign, like in `%+1`
+                if !digits.iter().all(u8::is_ascii_hexdigit) {
+                    return None;
+                }
+
+                let digits = std::str::from_utf8(&digits).ok()?;
+                decoded.push(u8::from_str_radix(digits, 16).ok()?);
+            }
+            b'+' => decoded.push(b' '),
+            _ => decoded.push(byte),
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// It decodes an RFC 4648 base32 value without padding. It returns `None` if
+/// the value has characters outside the alphabet.
+fn base32_decode(value: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(value.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for byte in value.bytes() {
+        let index = BASE32_ALPHABET
+            .iter()
+            .position(|symbol| *symbol == byte.to_ascii_uppercase())?;
+
+        buffer = (buffer << 5) | u32::try_from(index).ok()?;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push(u8::try_from((buffer >> bits) & 0xff).ok()?);
+        }
+    }
+
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should {
+        use crate::{
+            infohash::Infohash,
+            magnet::{percent_encode, Magnet},
+            metainfo::Torrent,
+        };
+
+        fn magnet(input: &[u8]) -> String {
+            let (torrent, infohash) = Torrent::from_bytes_with_infohash(input).unwrap();
+
+            Magnet::from_torrent(&torrent, &infohash).to_string()
+        }
+
+        #[test]
+        fn include_the_v1_infohash_name_and_length() {
+            let info = b"d6:lengthi5e4:name5:a.txt12:piece lengthi1e6:pieces0:e";
+
+            assert_eq!(
+                magnet(&[&b"d4:info"[..], info, b"e"].concat()),
+                format!(
+                    "magnet:?xt=urn:btih:{}&dn=a.txt&xl=5",
+                    Infohash::of(info).v1_hex()
+                )
+            );
+        }
+
+        #[test]
+        fn include_the_v2_infohash_of_v2_torrents() {
+            let info = b"d6:lengthi5e12:meta versioni2e4:name1:a12:piece lengthi1e6:pieces0:e";
+
+            let infohash = Infohash::of(info);
+
+            assert_eq!(
+                magnet(&[&b"d4:info"[..], info, b"e"].concat()),
+                format!(
+                    "magnet:?xt=urn:btih:{}&xt=urn:btmh:1220{}&dn=a&xl=5",
+                    infohash.v1_hex(),
+                    infohash.v2_hex()
+                )
+            );
+        }
+
+        #[test]
+        fn include_only_the_v2_infohash_of_v2_only_torrents() {
+            let info = b"d9:file treed1:ad0:d6:lengthi5e11:pieces root32:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaeee12:meta versioni2e4:name1:a12:piece lengthi16384ee";
+
+            assert_eq!(
+                magnet(&[&b"d4:info"[..], info, b"e"].concat()),
+                format!(
+                    "magnet:?xt=urn:btmh:1220{}&dn=a&xl=5",
+                    Infohash::of(info).v2_hex()
+                )
+            );
+        }
+
+        #[test]
+        fn include_the_trackers_without_duplicates_and_the_web_seeds() {
+            let uri = magnet(b"d8:announce9:udp://t:113:announce-listll9:udp://t:1el9:udp://u:1ee4:infod6:lengthi5e4:name1:a12:piece lengthi1e6:pieces0:e8:url-list10:http://w/ae");
+
+            assert!(
+                uri.ends_with("&tr=udp%3A%2F%2Ft%3A1&tr=udp%3A%2F%2Fu%3A1&ws=http%3A%2F%2Fw%2Fa")
+            );
+        }
+
+        #[test]
+        fn add_the_total_length_of_multi_file_torrents() {
+            let uri = magnet(b"d4:infod5:filesld6:lengthi3e4:pathl1:aeed6:lengthi4e4:pathl1:beee4:name1:d12:piece lengthi1e6:pieces0:ee");
+
+            assert!(uri.ends_with("&dn=d&xl=7"));
+        }
+
+        #[test]
+        fn leave_out_the_total_length_when_it_does_not_fit_in_64_bits() {
+            let uri = magnet(b"d4:infod5:filesld6:lengthi9223372036854775807e4:pathl1:aeed6:lengthi9223372036854775807e4:pathl1:beed6:lengthi9223372036854775807e4:pathl1:ceee4:name1:d12:piece lengthi1e6:pieces0:ee");
+
+            assert!(uri.ends_with("&dn=d"));
+        }
+
+        #[test]
+        fn percent_encode_non_ascii_names_and_reserved_characters() {
+            assert_eq!(percent_encode("a b&c=ñ~"), "a%20b%26c%3D%C3%B1~");
+        }
+    }
+
+    mod it_should_parse {
+        use crate::magnet::{Error, Magnet};
+
+        #[test]
+        fn the_uris_it_generates() {
+            let magnet = Magnet {
+                v1: Some([0xab; 20]),
+                v2: Some([0xcd; 32]),
+                display_name: Some("a b&ñ".to_string()),
+                exact_length: Some(42),
+                trackers: vec!["udp://t:1".to_string(), "http://u/announce".to_string()],
+                web_seeds: vec!["http://w/a".to_string()],
+            };
+
+            assert_eq!(magnet.to_string().parse::<Magnet>().unwrap(), magnet);
+        }
+
+        #[test]
+        fn v1_infohashes_in_base32() {
+            let magnet: Magnet = "magnet:?xt=urn:btih:VOV2XK5LVOV2XK5LVOV2XK5LVOV2XK5L"
+                .parse()
+                .unwrap();
+
+            assert_eq!(magnet.v1, Some([0xab; 20]));
+        }
+
+        #[test]
+        fn plus_signs_as_spaces_and_ignore_unknown_parameters() {
+            let magnet: Magnet = "magnet:?dn=a+b&x.pe=10.0.0.1:6881".parse().unwrap();
+
+            assert_eq!(magnet.display_name.as_deref(), Some("a b"));
+        }
+
+        #[test]
+        fn failing_when_the_uri_is_not_a_magnet_uri() {
+            assert_eq!(
+                "http://example.com".parse::<Magnet>(),
+                Err(Error::NotAMagnetUri)
+            );
+        }
+
+        #[test]
+        fn failing_when_the_infohash_is_invalid() {
+            assert_eq!(
+                "magnet:?xt=urn:btih:abc".parse::<Magnet>(),
+                Err(Error::InvalidExactTopic("urn:btih:abc".to_string()))
+            );
+        }
+
+        #[test]
+        fn failing_when_the_percent_encoding_is_invalid() {
+            assert!(matches!(
+                "magnet:?dn=%zz".parse::<Magnet>(),
+                Err(Error::InvalidPercentEncoding(_))
+            ));
+            assert!(matches!(
+                "magnet:?dn=%+1".parse::<Magnet>(),
+                Err(Error::InvalidPercentEncoding(_))
+            ));
+        }
+    }
+}
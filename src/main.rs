@@ -13,17 +13,313 @@
 //! ```text
 //! cargo run -- -i ./tests/fixtures/sample.bencode -o output.json
 //! ```
-use clap::{Arg, Command};
-use std::fs::File;
+//!
+//! Errors are printed to stderr. They can be printed as a JSON object with
+//! `--error-format json`:
+//!
+//! ```text
+//! printf "i42" | cargo run -- --error-format json
+//! ```
+//!
+//! With `--recover` the parser does not stop at the first error. It prints
+//! all the errors and writes the best JSON it can, using `"<unreadable>"`
+//! for the values it can't read:
+//!
+//! ```text
+//! printf "li1ei00ei3ee" | cargo run -- --recover
+//! ```
+//!
+//! With `--infohash` the v1 (SHA-1), v2 (SHA-256) and truncated v2 hashes of
+//! the `info` dictionary are printed to stderr and added to the JSON output
+//! under the `infohash` key, or the key set with `--infohash-key`:
+//!
+//! ```text
+//! cargo run -- -i ./tests/fixtures/sample.torrent --infohash
+//! ```
+//!
+//! With `--magnet` the input is decoded as a `.torrent` file and its magnet
+//! URI is written to the output instead of the JSON:
+//!
+//! ```text
+//! cargo run -- -i ./tests/fixtures/sample.torrent --magnet
+//! ```
+//!
+//! With `--lint` the input `.torrent` file is checked, and the problems found
+//! are written to the output, with their severity and JSON path. They are
+//! written as a JSON array with `--lint-format json`:
+//!
+//! ```text
+//! cargo run -- -i ./tests/fixtures/sample.torrent --lint
+//! ```
+//!
+//! With `--tracker` the input is decoded as an HTTP tracker announce or
+//! scrape response. Compact peers are written as `"ip:port"` strings and
+//! scrape infohashes in hex, and the failure reason, warning message and
+//! intervals are printed to stderr:
+//!
+//! ```text
+//! printf "d8:intervali1800e5:peers0:e" | cargo run -- --tracker
+//! ```
+//!
+//! With `--krpc` the input is decoded as a DHT KRPC message. The JSON has
+//! the kind of message, the method of queries, and the message with node
+//! IDs and tokens in hex and compact nodes and peers decoded:
+//!
+//! ```text
+//! printf "d1:eli201e5:Oops!e1:t2:aa1:y1:ee" | cargo run -- --krpc
+//! ```
+//!
+//! With `--rebuild-metadata` the pieces of the info dictionary in
+//! `ut_metadata` data messages (one message per file) are put back together,
+//! checked against the infohash given with `--expected-infohash` or
+//! `--magnet-uri`, and written as a `.torrent` file with the trackers of the
+//! magnet URI:
+//!
+//! ```text
+//! cargo run -- --rebuild-metadata piece0.bin piece1.bin --magnet-uri "magnet:?xt=urn:btih:..." -o out.torrent
+//! ```
+//!
+//! With `--pcap` the input is read as a `.pcap` or `.pcapng` capture, and
+//! the bencoded payloads found in it (DHT KRPC messages, HTTP tracker
+//! responses and peer-wire extension messages) are written as one JSON
+//! object per line, with the timestamp, the flow and the decoded payload:
+//!
+//! ```text
+//! cargo run -- -i ./tests/fixtures/dht.pcap --pcap
+//! ```
+//!
+//! Exit codes (with `--recover`, the code for the first error):
+//!
+//! - `0`: success.
+//! - `1`: I/O error (opening, reading or writing files or streams), invalid
+//!   capture with `--pcap`, or any other error.
+//! - `2`: invalid command-line arguments.
+//! - `3`: unexpected end of input (truncated bencoded value).
+//! - `4`: syntax error (malformed bencoded value).
+//! - `5`: limit exceeded.
+//! - `6`: strictness violation (the value is not in canonical bencode form).
+//! - `7`: invalid torrent (with `--magnet`, errors found with `--lint`, or
+//!   missing or mismatched pieces with `--rebuild-metadata`).
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde_json::json;
+use std::cell::Cell;
+use std::fs::{self, File};
 use std::io::{self, Read, Write};
-use torrust_bencode2json::parsers::BencodeParser;
+use std::rc::Rc;
+use torrust_bencode2json::{
+    infohash::InfohashWriter,
+    krpc, lint,
+    magnet::Magnet,
+    metadata::{self, ExpectedInfohash},
+    metainfo::{self, Torrent},
+    parsers::{
+        error::{Error, ErrorKind, ReadContext, WriteContext},
+        options::ErrorHandling,
+        BencodeParser,
+    },
+    pcap, tracker, value,
+};
+
+const EXIT_CODE_IO_ERROR: i32 = 1;
+const EXIT_CODE_UNEXPECTED_EOF: i32 = 3;
+const EXIT_CODE_SYNTAX_ERROR: i32 = 4;
+const EXIT_CODE_LIMIT_EXCEEDED: i32 = 5;
+const EXIT_CODE_STRICTNESS_VIOLATION: i32 = 6;
+const EXIT_CODE_INVALID_TORRENT: i32 = 7;
+
+/// The format used to print errors to stderr.
+#[derive(Clone, Copy, PartialEq)]
+enum ErrorFormat {
+    Text,
+    Json,
+}
+
+/// An error reported by the console command.
+struct CliError {
+    /// The short name of the error category, like `unexpected_eof`.
+    kind: &'static str,
+    exit_code: i32,
+    message: String,
+    read_context: Option<ReadContext>,
+    write_context: Option<WriteContext>,
+
+    /// The input or output file.
+    file: Option<String>,
+
+    /// The JSON path of the value being parsed, like `$.info.files[0]`.
+    value_path: Option<String>,
+}
+
+impl CliError {
+    fn io(err: &io::Error, file: Option<&String>) -> Self {
+        Self {
+            kind: ErrorKind::Io.as_str(),
+            exit_code: EXIT_CODE_IO_ERROR,
+            message: err.to_string(),
+            read_context: None,
+            write_context: None,
+            file: file.cloned(),
+            value_path: None,
+        }
+    }
+
+    fn parser(err: &Error, file: Option<&String>) -> Self {
+        // Each error category has its own exit code
+        let exit_code = match err.kind() {
+            ErrorKind::UnexpectedEof => EXIT_CODE_UNEXPECTED_EOF,
+            ErrorKind::Syntax => EXIT_CODE_SYNTAX_ERROR,
+            ErrorKind::LimitExceeded => EXIT_CODE_LIMIT_EXCEEDED,
+            ErrorKind::Strictness => EXIT_CODE_STRICTNESS_VIOLATION,
+            _ => EXIT_CODE_IO_ERROR,
+        };
+
+        Self {
+            kind: err.kind().as_str(),
+            exit_code,
+            message: err.to_string(),
+            read_context: err.read_context().cloned(),
+            write_context: err.write_context().cloned(),
+            file: file.cloned(),
+            value_path: None,
+        }
+    }
+
+    /// An error writing the JSON output.
+    fn output(err: &Error, file: Option<&String>) -> Self {
+        Self {
+            kind: ErrorKind::Io.as_str(),
+            exit_code: EXIT_CODE_IO_ERROR,
+            message: err.to_string(),
+            read_context: None,
+            write_context: err.write_context().cloned(),
+            file: file.cloned(),
+            value_path: None,
+        }
+    }
+
+    fn metainfo(err: &metainfo::Error, file: Option<&String>) -> Self {
+        match err {
+            metainfo::Error::Value(value::Error::Parser(err)) => Self::parser(err, file),
+            _ => Self {
+                kind: "invalid_torrent",
+                exit_code: EXIT_CODE_INVALID_TORRENT,
+                message: err.to_string(),
+                read_context: None,
+                write_context: None,
+                file: file.cloned(),
+                value_path: None,
+            },
+        }
+    }
+
+    fn value(err: &value::Error, file: Option<&String>) -> Self {
+        match err {
+            value::Error::Parser(err) => Self::parser(err, file),
+            _ => Self {
+                kind: "invalid_value",
+                exit_code: EXIT_CODE_IO_ERROR,
+                message: err.to_string(),
+                read_context: None,
+                write_context: None,
+                file: file.cloned(),
+                value_path: None,
+            },
+        }
+    }
+
+    fn metadata(err: &metadata::Error, message_paths: &[&String]) -> Self {
+        let file = match err {
+            metadata::Error::InvalidMessage { index, .. } => message_paths.get(*index).copied(),
+            _ => None,
+        };
+
+        Self {
+            kind: "invalid_metadata",
+            exit_code: EXIT_CODE_INVALID_TORRENT,
+            message: err.to_string(),
+            read_context: None,
+            write_context: None,
+            file: file.cloned(),
+            value_path: None,
+        }
+    }
+
+    fn capture(err: &pcap::Error, file: Option<&String>) -> Self {
+        match err {
+            pcap::Error::Io(err) => Self::io(err, file),
+            _ => Self {
+                kind: "invalid_capture",
+                exit_code: EXIT_CODE_IO_ERROR,
+                message: err.to_string(),
+                read_context: None,
+                write_context: None,
+                file: file.cloned(),
+                value_path: None,
+            },
+        }
+    }
+
+    /// It sets the JSON path of the value being parsed when the error was
+    /// found.
+    fn at_value(mut self, value_path: String) -> Self {
+        self.value_path = Some(value_path);
+        self
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "kind": self.kind,
+            "message": self.message,
+            "input_position": self.read_context.as_ref().map(|context| context.pos),
+            "output_position": self.write_context.as_ref().map(|context| context.pos),
+            "file": self.file,
+            "value_path": self.value_path,
+            "context": {
+                "input": self.read_context.as_ref().map(|context| hex::encode(&context.latest_bytes)),
+                "output": self.write_context.as_ref().map(|context| hex::encode(&context.latest_bytes)),
+            },
+        })
+    }
+
+    /// It prints the error to stderr.
+    fn print(&self, format: ErrorFormat) {
+        match format {
+            ErrorFormat::Text => eprintln!("Error: {}", self.message),
+            ErrorFormat::Json => eprintln!("{}", self.to_json()),
+        }
+    }
+
+    /// It prints the error to stderr and exits with the exit code for the
+    /// error category.
+    fn exit(&self, format: ErrorFormat) -> ! {
+        self.print(format);
+        std::process::exit(self.exit_code);
+    }
+}
+
+/// An output that records whether writing to it failed, to report output
+/// errors apart from input errors. Both are I/O errors for the parser.
+struct Output {
+    inner: Box<dyn Write>,
+    failed: Rc<Cell<bool>>,
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf).inspect_err(|_| self.failed.set(true))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().inspect_err(|_| self.failed.set(true))
+    }
+}
 
 fn main() {
     run();
 }
 
-fn run() {
-    let matches = Command::new("torrust-bencode2json")
+fn command() -> Command {
+    Command::new("torrust-bencode2json")
         .version("0.1.0")
         .author("Torrust Organization")
         .about("Converts Bencode to JSON")
@@ -41,37 +337,447 @@ fn run() {
                 .default_value(None)
                 .help("Optional output file (defaults to stdout)"),
         )
-        .get_matches();
+        .arg(
+            Arg::new("error-format")
+                .long("error-format")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Format used to print errors to stderr"),
+        )
+        .arg(
+            Arg::new("recover")
+                .long("recover")
+                .action(ArgAction::SetTrue)
+                .help("Report all errors instead of stopping at the first one"),
+        )
+        .arg(
+            Arg::new("infohash")
+                .long("infohash")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("recover")
+                .help("Print the infohashes of the info dictionary and add them to the JSON"),
+        )
+        .arg(
+            Arg::new("infohash-key")
+                .long("infohash-key")
+                .default_value("infohash")
+                .help("JSON key for the infohashes added with --infohash"),
+        )
+        .arg(
+            Arg::new("magnet")
+                .long("magnet")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["infohash", "recover"])
+                .help("Write the magnet URI of the input .torrent file instead of the JSON"),
+        )
+        .arg(
+            Arg::new("lint")
+                .long("lint")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["infohash", "recover", "magnet"])
+                .help("Check the input .torrent file and write the problems found instead of the JSON"),
+        )
+        .arg(
+            Arg::new("lint-format")
+                .long("lint-format")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Format used to write the problems found with --lint"),
+        )
+        .arg(
+            Arg::new("tracker")
+                .long("tracker")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["infohash", "recover", "magnet", "lint"])
+                .help("Decode the input as a tracker response, with compact peers as addresses"),
+        )
+        .arg(
+            Arg::new("krpc")
+                .long("krpc")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["infohash", "recover", "magnet", "lint", "tracker"])
+                .help("Decode the input as a DHT KRPC message, with node IDs and compact nodes decoded"),
+        )
+        .arg(
+            Arg::new("pcap")
+                .long("pcap")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "infohash",
+                    "recover",
+                    "magnet",
+                    "lint",
+                    "tracker",
+                    "krpc",
+                    "rebuild-metadata",
+                ])
+                .help("Read the input as a pcap or pcapng capture and write the bencoded payloads found as JSON lines"),
+        )
+        .args(rebuild_metadata_args())
+}
+
+/// The arguments to rebuild `.torrent` files from `ut_metadata` messages.
+fn rebuild_metadata_args() -> [Arg; 3] {
+    [
+        Arg::new("rebuild-metadata")
+            .long("rebuild-metadata")
+            .value_name("MESSAGE_FILE")
+            .num_args(1..)
+            .action(ArgAction::Append)
+            .conflicts_with_all([
+                "input", "infohash", "recover", "magnet", "lint", "tracker", "krpc",
+            ])
+            .help("Rebuild a .torrent file from files with ut_metadata data messages"),
+        Arg::new("expected-infohash")
+            .long("expected-infohash")
+            .requires("rebuild-metadata")
+            .value_parser(|hash: &str| {
+                ExpectedInfohash::from_hex(hash).ok_or("expected 40 or 64 hex digits")
+            })
+            .help("Infohash of the info dictionary rebuilt with --rebuild-metadata"),
+        Arg::new("magnet-uri")
+            .long("magnet-uri")
+            .requires("rebuild-metadata")
+            .value_parser(|uri: &str| uri.parse::<Magnet>().map_err(|err| err.to_string()))
+            .help("Magnet URI with the infohash and trackers for --rebuild-metadata"),
+    ]
+}
+
+/// It writes the magnet URI of the input `.torrent` file.
+fn write_magnet<R: io::BufRead>(
+    parser: &mut BencodeParser<R>,
+    mut output: Box<dyn Write>,
+    input_path: Option<&String>,
+    output_path: Option<&String>,
+    error_format: ErrorFormat,
+) {
+    let (torrent, infohash) = match Torrent::from_parser_with_infohash(parser) {
+        Ok(decoded) => decoded,
+        Err(e) => CliError::metainfo(&e, input_path).exit(error_format),
+    };
+
+    let magnet = Magnet::from_torrent(&torrent, &infohash);
+
+    if let Err(e) = writeln!(output, "{magnet}").and_then(|()| output.flush()) {
+        CliError::io(&e, output_path).exit(error_format);
+    }
+}
+
+/// It writes the problems found in the input `.torrent` file, one per line
+/// or as a JSON array, and exits with an error code if any of them is an
+/// error.
+fn write_lint_findings<R: io::BufRead>(
+    parser: &mut BencodeParser<R>,
+    mut output: Box<dyn Write>,
+    json: bool,
+    output_path: Option<&String>,
+    error_format: ErrorFormat,
+) {
+    let findings = lint::lint(parser);
+
+    let result = if json {
+        let findings: Vec<_> = findings
+            .iter()
+            .map(|finding| {
+                json!({
+                    "severity": finding.severity.as_str(),
+                    "path": finding.path,
+                    "message": finding.message,
+                })
+            })
+            .collect();
+
+        writeln!(output, "{}", serde_json::Value::Array(findings))
+    } else {
+        findings
+            .iter()
+            .try_for_each(|finding| writeln!(output, "{finding}"))
+    };
+
+    if let Err(e) = result.and_then(|()| output.flush()) {
+        CliError::io(&e, output_path).exit(error_format);
+    }
+
+    if lint::has_errors(&findings) {
+        std::process::exit(EXIT_CODE_INVALID_TORRENT);
+    }
+}
+
+/// It writes the JSON of the input tracker response and prints its status
+/// fields to stderr.
+fn write_tracker_response<R: io::BufRead>(
+    parser: &mut BencodeParser<R>,
+    mut output: Box<dyn Write>,
+    input_path: Option<&String>,
+    output_path: Option<&String>,
+    error_format: ErrorFormat,
+) {
+    let response = match value::from_parser(parser) {
+        Ok(response) => response,
+        Err(e) => CliError::value(&e, input_path).exit(error_format),
+    };
+
+    let result = writeln!(output, "{}", tracker::render(&response));
+
+    if let Err(e) = result.and_then(|()| output.flush()) {
+        CliError::io(&e, output_path).exit(error_format);
+    }
+
+    let status = tracker::Status::from_value(&response);
+
+    if let Some(failure_reason) = &status.failure_reason {
+        eprintln!("failure reason: {failure_reason}");
+    }
+    if let Some(warning_message) = &status.warning_message {
+        eprintln!("warning message: {warning_message}");
+    }
+    if let Some(interval) = status.interval {
+        eprintln!("interval: {interval}");
+    }
+    if let Some(min_interval) = status.min_interval {
+        eprintln!("min interval: {min_interval}");
+    }
+}
+
+/// It writes the JSON of the input DHT KRPC message.
+fn write_krpc_message<R: io::BufRead>(
+    parser: &mut BencodeParser<R>,
+    mut output: Box<dyn Write>,
+    input_path: Option<&String>,
+    output_path: Option<&String>,
+    error_format: ErrorFormat,
+) {
+    let message = match value::from_parser(parser) {
+        Ok(message) => message,
+        Err(e) => CliError::value(&e, input_path).exit(error_format),
+    };
+
+    let result = writeln!(output, "{}", krpc::render(&message));
+
+    if let Err(e) = result.and_then(|()| output.flush()) {
+        CliError::io(&e, output_path).exit(error_format);
+    }
+}
+
+/// It writes the bencoded payloads found in the input capture, one JSON
+/// object per line. The records found before an error are written too.
+fn write_capture_records(
+    input: Box<dyn Read>,
+    output: Box<dyn Write>,
+    input_path: Option<&String>,
+    output_path: Option<&String>,
+    error_format: ErrorFormat,
+) {
+    let mut output = io::BufWriter::new(output);
+
+    for record in pcap::extract(io::BufReader::new(input)) {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                if let Err(e) = output.flush() {
+                    CliError::io(&e, output_path).exit(error_format);
+                }
+
+                CliError::capture(&e, input_path).exit(error_format);
+            }
+        };
+
+        if let Err(e) = writeln!(output, "{}", record.to_json()) {
+            CliError::io(&e, output_path).exit(error_format);
+        }
+    }
+
+    if let Err(e) = output.flush() {
+        CliError::io(&e, output_path).exit(error_format);
+    }
+}
+
+/// It rebuilds a `.torrent` file from the `ut_metadata` messages in the
+/// files, and writes it.
+fn write_rebuilt_torrent(
+    matches: &ArgMatches,
+    mut output: Box<dyn Write>,
+    output_path: Option<&String>,
+    error_format: ErrorFormat,
+) {
+    let magnet = matches.get_one::<Magnet>("magnet-uri");
+
+    let Some(expected) = matches
+        .get_one::<ExpectedInfohash>("expected-infohash")
+        .copied()
+        .or_else(|| magnet.and_then(ExpectedInfohash::from_magnet))
+    else {
+        command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "--rebuild-metadata needs --expected-infohash or a --magnet-uri with an infohash",
+            )
+            .exit();
+    };
+
+    let paths: Vec<&String> = matches
+        .get_many::<String>("rebuild-metadata")
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let mut messages = Vec::with_capacity(paths.len());
+
+    for path in &paths {
+        match fs::read(path) {
+            Ok(message) => messages.push(message),
+            Err(e) => CliError::io(&e, Some(path)).exit(error_format),
+        }
+    }
+
+    let trackers = magnet
+        .map(|magnet| magnet.trackers.clone())
+        .unwrap_or_default();
+
+    let torrent = match metadata::rebuild(messages.iter().map(Vec::as_slice), &expected, &trackers)
+    {
+        Ok(torrent) => torrent,
+        Err(e) => CliError::metadata(&e, &paths).exit(error_format),
+    };
+
+    if let Err(e) = output.write_all(&torrent).and_then(|()| output.flush()) {
+        CliError::io(&e, output_path).exit(error_format);
+    }
+}
+
+fn run() {
+    let matches = command().get_matches();
+
+    let error_format = match matches
+        .get_one::<String>("error-format")
+        .map(String::as_str)
+    {
+        Some("json") => ErrorFormat::Json,
+        _ => ErrorFormat::Text,
+    };
+
+    let input_path = matches.get_one::<String>("input");
+    let output_path = matches.get_one::<String>("output");
 
     // Handle input stream (file or stdin)
-    let input: Box<dyn Read> = if let Some(input_path) = matches.get_one::<String>("input") {
+    let input: Box<dyn Read> = if let Some(input_path) = input_path {
         match File::open(input_path) {
             Ok(file) => Box::new(file),
-            Err(e) => {
-                eprintln!("Error: {e}");
-                std::process::exit(1);
-            }
+            Err(e) => CliError::io(&e, Some(input_path)).exit(error_format),
         }
     } else {
         Box::new(io::stdin())
     };
 
     // Handle output stream (file or stdout)
-    let mut output: Box<dyn Write> = if let Some(output_path) = matches.get_one::<String>("output")
-    {
+    let output: Box<dyn Write> = if let Some(output_path) = output_path {
         match File::create(output_path) {
             Ok(file) => Box::new(file),
-            Err(e) => {
-                eprintln!("Error: {e}");
-                std::process::exit(1);
-            }
+            Err(e) => CliError::io(&e, Some(output_path)).exit(error_format),
         }
     } else {
         Box::new(io::stdout())
     };
 
-    if let Err(e) = BencodeParser::new(input).write_bytes(&mut output) {
-        eprintln!("Error: {e}");
-        std::process::exit(1);
+    if matches.get_flag("pcap") {
+        write_capture_records(input, output, input_path, output_path, error_format);
+        return;
+    }
+
+    let error_handling = if matches.get_flag("recover") {
+        ErrorHandling::Recover
+    } else {
+        ErrorHandling::Stop
+    };
+
+    let mut parser = BencodeParser::builder(input)
+        .error_handling(error_handling)
+        .build();
+
+    if matches.get_flag("magnet") {
+        write_magnet(&mut parser, output, input_path, output_path, error_format);
+        return;
+    }
+
+    if matches.get_flag("lint") {
+        let json = matches.get_one::<String>("lint-format").map(String::as_str) == Some("json");
+
+        write_lint_findings(&mut parser, output, json, output_path, error_format);
+        return;
+    }
+
+    if matches.get_flag("tracker") {
+        write_tracker_response(&mut parser, output, input_path, output_path, error_format);
+        return;
+    }
+
+    if matches.contains_id("rebuild-metadata") {
+        write_rebuilt_torrent(&matches, output, output_path, error_format);
+        return;
+    }
+
+    if matches.get_flag("krpc") {
+        write_krpc_message(&mut parser, output, input_path, output_path, error_format);
+        return;
+    }
+
+    let output_failed = Rc::new(Cell::new(false));
+
+    let mut writer = parser.byte_writer(Output {
+        inner: output,
+        failed: Rc::clone(&output_failed),
+    });
+
+    let (result, infohashes) = if matches.get_flag("infohash") {
+        let output_key = matches
+            .get_one::<String>("infohash-key")
+            .expect("the infohash key has a default value");
+
+        let mut infohash_writer = InfohashWriter::new(writer).with_output_key(output_key);
+
+        let result = parser.write_to(&mut infohash_writer);
+        let infohashes = infohash_writer.infohashes().to_vec();
+
+        writer = infohash_writer.into_inner();
+
+        (result, infohashes)
+    } else {
+        (parser.write_to(&mut writer), Vec::new())
+    };
+
+    if let Err(e) = result {
+        if output_failed.get() {
+            CliError::output(&e, output_path).exit(error_format);
+        }
+
+        // The JSON before the error is written too. Errors writing it are
+        // ignored because the parser error is the one reported
+        let _unused = parser.finish(writer);
+
+        CliError::parser(&e, input_path)
+            .at_value(parser.value_path())
+            .exit(error_format);
+    }
+
+    // Errors writing the buffered output, like a full disk, are only
+    // reported when flushing
+    if let Err(e) = parser.finish(writer) {
+        CliError::output(&e, output_path).exit(error_format);
+    }
+
+    for infohash in &infohashes {
+        eprintln!("infohash v1: {}", infohash.v1_hex());
+        eprintln!("infohash v2: {}", infohash.v2_hex());
+        eprintln!("infohash v2 truncated: {}", infohash.v2_truncated_hex());
+    }
+
+    let diagnostics = parser.take_diagnostics();
+
+    for diagnostic in &diagnostics {
+        CliError::parser(diagnostic, input_path).print(error_format);
+    }
+
+    if let Some(first) = diagnostics.first() {
+        std::process::exit(CliError::parser(first, input_path).exit_code);
     }
 }
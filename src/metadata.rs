@@ -0,0 +1,468 @@
+//! Rebuilding `.torrent` files from `ut_metadata` pieces.
+//!
+//! Peers send the info dictionary of a torrent in 16 KiB pieces with the
+//! `ut_metadata` extension
+//! ([BEP 9](https://www.bittorrent.org/beps/bep_0009.html)). This module puts
+//! the pieces of the `data` messages back together, checks the infohash and
+//! builds a `.torrent` file with them:
+//!
+//! ```rust
+//! use torrust_bencode2json::{
+//!     infohash::Infohash,
+//!     metadata::{rebuild, ExpectedInfohash},
+//! };
+//!
+//! let info = b"d4:name1:ae";
+//! let message = [&b"d8:msg_typei1e5:piecei0e10:total_sizei11ee"[..], info].concat();
+//!
+//! let expected = ExpectedInfohash::V1(Infohash::of(info).v1);
+//!
+//! let torrent = rebuild([&message[..]], &expected, &["udp://t:1".to_string()]).unwrap();
+//!
+//! assert_eq!(torrent, b"d8:announce9:udp://t:14:infod4:name1:aee");
+//! ```
+//!
+//! Missing pieces, and pieces that don't fit (with the wrong length, out of
+//! range, or received twice with different data), are reported with their
+//! indices.
+use std::{collections::BTreeMap, fmt};
+
+use thiserror::Error;
+
+use crate::{
+    extension::{self, MetadataMessage, MetadataMessageType, METADATA_PIECE_LENGTH},
+    infohash::Infohash,
+    magnet::Magnet,
+    to_bencode,
+};
+
+/// The largest info dictionary it rebuilds. It limits the memory used with
+/// hostile `total_size` values.
+pub const MAX_INFO_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The infohash the rebuilt info dictionary must have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectedInfohash {
+    /// A v1 infohash, or a v2 infohash truncated to 20 bytes.
+    V1([u8; 20]),
+
+    /// A v2 infohash.
+    V2([u8; 32]),
+}
+
+impl ExpectedInfohash {
+    /// It returns the infohash in hex: 40 digits for v1 infohashes and 64
+    /// for v2 ones. It returns `None` for other values.
+    #[must_use]
+    pub fn from_hex(hash: &str) -> Option<Self> {
+        let bytes = hex::decode(hash).ok()?;
+
+        match bytes.len() {
+            20 => bytes.try_into().ok().map(Self::V1),
+            32 => bytes.try_into().ok().map(Self::V2),
+            _ => None,
+        }
+    }
+
+    /// It returns the infohash of a magnet URI, the v1 one if it has both.
+    #[must_use]
+    pub fn from_magnet(magnet: &Magnet) -> Option<Self> {
+        magnet.v1.map(Self::V1).or(magnet.v2.map(Self::V2))
+    }
+
+    /// It returns true if the infohash is the expected one.
+    #[must_use]
+    pub fn matches(&self, infohash: &Infohash) -> bool {
+        match self {
+            Self::V1(v1) => *v1 == infohash.v1 || *v1 == infohash.v2_truncated(),
+            Self::V2(v2) => *v2 == infohash.v2,
+        }
+    }
+}
+
+impl fmt::Display for ExpectedInfohash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V1(v1) => f.write_str(&hex::encode(v1)),
+            Self::V2(v2) => f.write_str(&hex::encode(v2)),
+        }
+    }
+}
+
+/// Errors that can occur while rebuilding the info dictionary.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A message is not a valid `ut_metadata` message.
+    #[error("Invalid ut_metadata message {index}: {source}")]
+    InvalidMessage {
+        /// The position of the message in the messages given.
+        index: usize,
+        source: extension::Error,
+    },
+
+    /// None of the `data` messages has the length of the info dictionary.
+    #[error("No data message has the total size of the info dictionary")]
+    MissingTotalSize,
+
+    /// The info dictionary is longer than [`MAX_INFO_SIZE`].
+    #[error("The info dictionary is too large: {0} bytes")]
+    TooLarge(u64),
+
+    /// There are missing pieces, or pieces that don't fit.
+    #[error("Missing pieces: {missing:?}, mismatched pieces: {mismatched:?}")]
+    Pieces {
+        missing: Vec<u64>,
+        mismatched: Vec<u64>,
+    },
+
+    /// The infohash of the info dictionary is not the expected one.
+    #[error("The infohash of the info dictionary is {actual}, expected {expected}")]
+    InfohashMismatch {
+        expected: ExpectedInfohash,
+        actual: String,
+    },
+}
+
+/// It puts the pieces of the `ut_metadata` messages back together, in piece
+/// order, and returns the info dictionary. Messages that are not `data`
+/// messages are ignored.
+///
+/// # Errors
+///
+/// Will return an error if a message is invalid, the total size is missing
+/// or too large, or there are missing or mismatched pieces.
+pub fn assemble<'a, I: IntoIterator<Item = &'a [u8]>>(messages: I) -> Result<Vec<u8>, Error> {
+    let mut total_size = None;
+    let mut received: Vec<MetadataMessage<'a>> = Vec::new();
+
+    for (index, message) in messages.into_iter().enumerate() {
+        let message = MetadataMessage::from_bytes(message)
+            .map_err(|source| Error::InvalidMessage { index, source })?;
+
+        if message.msg_type != MetadataMessageType::Data {
+            continue;
+        }
+
+        total_size = total_size.or(message.total_size);
+        received.push(message);
+    }
+
+    let total_size = total_size.ok_or(Error::MissingTotalSize)?;
+
+    if total_size > MAX_INFO_SIZE {
+        return Err(Error::TooLarge(total_size));
+    }
+
+    let piece_length = METADATA_PIECE_LENGTH as u64;
+    let num_pieces = total_size.div_ceil(piece_length);
+
+    let expected_length = |piece: u64| {
+        if piece + 1 == num_pieces {
+            total_size - piece * piece_length
+        } else {
+            piece_length
+        }
+    };
+
+    let mut pieces: BTreeMap<u64, &[u8]> = BTreeMap::new();
+    let mut mismatched = Vec::new();
+
+    for message in received {
+        let piece = message.piece;
+
+        let fits = piece < num_pieces
+            && message.data.len() as u64 == expected_length(piece)
+            && message.total_size.is_none_or(|size| size == total_size)
+            && pieces.get(&piece).is_none_or(|data| *data == message.data);
+
+        if fits {
+            pieces.insert(piece, message.data);
+        } else if !mismatched.contains(&piece) {
+            mismatched.push(piece);
+        }
+    }
+
+    mismatched.sort_unstable();
+
+    for piece in &mismatched {
+        pieces.remove(piece);
+    }
+
+    let missing: Vec<u64> = (0..num_pieces)
+        .filter(|piece| !pieces.contains_key(piece) && !mismatched.contains(piece))
+        .collect();
+
+    if !missing.is_empty() || !mismatched.is_empty() {
+        return Err(Error::Pieces {
+            missing,
+            mismatched,
+        });
+    }
+
+    Ok(pieces.into_values().flatten().copied().collect())
+}
+
+/// It checks that the info dictionary has the expected infohash, and
+/// returns its infohashes.
+///
+/// # Errors
+///
+/// Will return an error if the infohash is not the expected one.
+pub fn verify(info: &[u8], expected: &ExpectedInfohash) -> Result<Infohash, Error> {
+    let infohash = Infohash::of(info);
+
+    if !expected.matches(&infohash) {
+        return Err(Error::InfohashMismatch {
+            expected: *expected,
+            actual: match expected {
+                ExpectedInfohash::V1(_) => infohash.v1_hex(),
+                ExpectedInfohash::V2(_) => infohash.v2_hex(),
+            },
+        });
+    }
+
+    Ok(infohash)
+}
+
+/// It returns a `.torrent` file with the info dictionary and the trackers:
+/// the first one in `announce`, and all of them in `announce-list`, one per
+/// tier, when there are more than one.
+#[must_use]
+pub fn build_torrent(info: &[u8], trackers: &[String]) -> Vec<u8> {
+    let mut torrent = b"d".to_vec();
+
+    if let Some(tracker) = trackers.first() {
+        torrent.extend_from_slice(b"8:announce");
+        torrent.extend_from_slice(&to_bencode(tracker));
+    }
+
+    if trackers.len() > 1 {
+        torrent.extend_from_slice(b"13:announce-listl");
+
+        for tracker in trackers {
+            torrent.push(b'l');
+            torrent.extend_from_slice(&to_bencode(tracker));
+            torrent.push(b'e');
+        }
+
+        torrent.push(b'e');
+    }
+
+    torrent.extend_from_slice(b"4:info");
+    torrent.extend_from_slice(info);
+    torrent.push(b'e');
+
+    torrent
+}
+
+/// It puts the pieces of the `ut_metadata` messages back together, checks
+/// the infohash and returns a `.torrent` file with the trackers.
+///
+/// # Errors
+///
+/// Will return an error if the pieces can't be put back together (see
+/// [`assemble`]), or the infohash is not the expected one.
+pub fn rebuild<'a, I: IntoIterator<Item = &'a [u8]>>(
+    messages: I,
+    expected: &ExpectedInfohash,
+    trackers: &[String],
+) -> Result<Vec<u8>, Error> {
+    let info = assemble(messages)?;
+
+    verify(&info, expected)?;
+
+    Ok(build_torrent(&info, trackers))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::extension::METADATA_PIECE_LENGTH;
+
+    /// An info dictionary with three pieces, the last one shorter.
+    fn info() -> Vec<u8> {
+        let name = "a".repeat(2 * METADATA_PIECE_LENGTH + 100);
+
+        format!(
+            "d6:lengthi1e4:name{}:{name}12:piece lengthi16384e6:pieces20:{}e",
+            name.len(),
+            "p".repeat(20)
+        )
+        .into_bytes()
+    }
+
+    /// It returns the `data` message of a piece of the info dictionary.
+    fn data_message(info: &[u8], piece: usize) -> Vec<u8> {
+        let start = piece * METADATA_PIECE_LENGTH;
+        let end = (start + METADATA_PIECE_LENGTH).min(info.len());
+
+        [
+            format!(
+                "d8:msg_typei1e5:piecei{piece}e10:total_sizei{}ee",
+                info.len()
+            )
+            .as_bytes(),
+            &info[start..end],
+        ]
+        .concat()
+    }
+
+    mod it_should {
+        use crate::{
+            infohash::Infohash,
+            metadata::{
+                assemble, build_torrent, rebuild,
+                tests::{data_message, info},
+                ExpectedInfohash,
+            },
+            metainfo::Torrent,
+        };
+
+        #[test]
+        fn put_the_pieces_back_together_in_piece_order() {
+            let info = info();
+            let messages = [
+                data_message(&info, 2),
+                b"d8:msg_typei0e5:piecei0ee".to_vec(),
+                data_message(&info, 0),
+                data_message(&info, 1),
+                data_message(&info, 0),
+            ];
+
+            assert_eq!(assemble(messages.iter().map(Vec::as_slice)).unwrap(), info);
+        }
+
+        #[test]
+        fn rebuild_a_torrent_with_the_expected_infohash_and_the_trackers() {
+            let info = info();
+            let messages: Vec<_> = (0..3).map(|piece| data_message(&info, piece)).collect();
+
+            let expected = ExpectedInfohash::V1(Infohash::of(&info).v1);
+            let trackers = ["udp://t:1".to_string(), "http://u/announce".to_string()];
+
+            let torrent =
+                rebuild(messages.iter().map(Vec::as_slice), &expected, &trackers).unwrap();
+
+            let torrent = Torrent::from_bytes(&torrent).unwrap();
+
+            assert_eq!(torrent.announce.as_deref(), Some("udp://t:1"));
+            assert_eq!(
+                torrent.announce_list,
+                vec![
+                    vec!["udp://t:1".to_string()],
+                    vec!["http://u/announce".to_string()]
+                ]
+            );
+        }
+
+        #[test]
+        fn accept_v2_infohashes_and_truncated_v2_infohashes() {
+            let info = info();
+            let messages: Vec<_> = (0..3).map(|piece| data_message(&info, piece)).collect();
+            let infohash = Infohash::of(&info);
+
+            for expected in [
+                ExpectedInfohash::V2(infohash.v2),
+                ExpectedInfohash::V1(infohash.v2_truncated()),
+            ] {
+                assert!(rebuild(messages.iter().map(Vec::as_slice), &expected, &[]).is_ok());
+            }
+        }
+
+        #[test]
+        fn build_torrents_without_trackers() {
+            assert_eq!(build_torrent(b"de", &[]), b"d4:infodee");
+        }
+
+        #[test]
+        fn parse_expected_infohashes_in_hex() {
+            assert_eq!(
+                ExpectedInfohash::from_hex(&"ab".repeat(20)),
+                Some(ExpectedInfohash::V1([0xab; 20]))
+            );
+            assert_eq!(
+                ExpectedInfohash::from_hex(&"cd".repeat(32)),
+                Some(ExpectedInfohash::V2([0xcd; 32]))
+            );
+            assert_eq!(ExpectedInfohash::from_hex("abc"), None);
+        }
+    }
+
+    mod it_should_fail {
+        use crate::metadata::{
+            assemble, rebuild,
+            tests::{data_message, info},
+            Error, ExpectedInfohash,
+        };
+
+        #[test]
+        fn reporting_the_missing_pieces() {
+            let info = info();
+            let message = data_message(&info, 1);
+
+            assert!(matches!(
+                assemble([&message[..]]),
+                Err(Error::Pieces { missing, mismatched }) if missing == [0, 2] && mismatched.is_empty()
+            ));
+        }
+
+        #[test]
+        fn reporting_the_mismatched_pieces() {
+            let info = info();
+
+            let mut truncated = data_message(&info, 0);
+            truncated.pop();
+
+            let mut changed = data_message(&info, 1);
+            changed.push(b'x');
+            changed.remove(changed.len() - 2);
+
+            let messages = [
+                truncated,
+                data_message(&info, 1),
+                changed,
+                data_message(&info, 2),
+                b"d8:msg_typei1e5:piecei7e10:total_sizei1ee".to_vec(),
+            ];
+
+            assert!(matches!(
+                assemble(messages.iter().map(Vec::as_slice)),
+                Err(Error::Pieces { missing, mismatched }) if missing.is_empty() && mismatched == [0, 1, 7]
+            ));
+        }
+
+        #[test]
+        fn when_the_infohash_is_not_the_expected_one() {
+            let info = info();
+            let messages: Vec<_> = (0..3).map(|piece| data_message(&info, piece)).collect();
+
+            assert!(matches!(
+                rebuild(
+                    messages.iter().map(Vec::as_slice),
+                    &ExpectedInfohash::V1([0; 20]),
+                    &[]
+                ),
+                Err(Error::InfohashMismatch { .. })
+            ));
+        }
+
+        #[test]
+        fn when_a_message_is_invalid() {
+            assert!(matches!(
+                assemble([&b"d8:msg_typei1ee"[..]]),
+                Err(Error::InvalidMessage { index: 0, .. })
+            ));
+        }
+
+        #[test]
+        fn when_the_total_size_is_missing_or_too_large() {
+            assert!(matches!(
+                assemble([&b"d8:msg_typei1e5:piecei0ee"[..]]),
+                Err(Error::MissingTotalSize)
+            ));
+            assert!(matches!(
+                assemble([&b"d8:msg_typei1e5:piecei0e10:total_sizei1000000000000ee"[..]]),
+                Err(Error::TooLarge(1_000_000_000_000))
+            ));
+        }
+    }
+}
@@ -0,0 +1,940 @@
+//! Typed `.torrent` files.
+//!
+//! It decodes a `.torrent` file (a metainfo file) into a [`Torrent`], with the
+//! fields defined in:
+//!
+//! - [BEP 3](https://www.bittorrent.org/beps/bep_0003.html): announce URL and
+//!   info dictionary.
+//! - [BEP 12](https://www.bittorrent.org/beps/bep_0012.html): tiers of
+//!   announce URLs.
+//! - [BEP 19](https://www.bittorrent.org/beps/bep_0019.html): web seeds.
+//! - [BEP 27](https://www.bittorrent.org/beps/bep_0027.html): private flag.
+//! - [BEP 47](https://www.bittorrent.org/beps/bep_0047.html): padding files
+//!   and file attributes.
+//! - [BEP 52](https://www.bittorrent.org/beps/bep_0052.html): meta version
+//!   and file tree of v2 torrents.
+//!
+//! ```rust
+//! use torrust_bencode2json::metainfo::{Files, Torrent};
+//!
+//! let torrent = Torrent::from_bytes(
+//!     b"d8:announce9:udp://t:14:infod6:lengthi5e4:name5:a.txt12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee",
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(torrent.announce.as_deref(), Some("udp://t:1"));
+//! assert_eq!(torrent.info.name, "a.txt");
+//! assert_eq!(torrent.info.pieces, Some(vec![[b'a'; 20]]));
+//! assert!(matches!(torrent.info.files, Files::Single { length: 5, .. }));
+//! ```
+//!
+//! The file is parsed with the crate's parser, not converted into JSON.
+//! Strings like names and paths that are not valid UTF-8 are decoded with
+//! the replacement character `U+FFFD`.
+//!
+//! Missing required fields, and fields with the wrong type or an invalid
+//! value, give an [`Error`](enum@Error) with the path of the field, like
+//! `$.info.files[0].length`.
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read},
+};
+
+use thiserror::Error;
+
+use crate::{
+    infohash::{Infohash, InfohashWriter},
+    parsers::BencodeParser,
+    value::{self, Value, ValueBuilder},
+};
+
+/// The length of a SHA-1 hash.
+pub const SHA1_LENGTH: usize = 20;
+
+/// A SHA-1 hash, like the hash of a piece.
+pub type Sha1Hash = [u8; SHA1_LENGTH];
+
+/// Errors that can occur while decoding a `.torrent` file.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The file is not valid Bencode, or it doesn't have exactly one value.
+    #[error("{0}")]
+    Value(#[from] value::Error),
+
+    /// A required field is missing.
+    #[error("Missing required field {0}")]
+    MissingField(String),
+
+    /// A field has the wrong type.
+    #[error("Invalid type for field {path}: expected {expected}, found {found}")]
+    InvalidType {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    /// A field has an invalid value.
+    #[error("Invalid value for field {path}: {reason}")]
+    InvalidValue { path: String, reason: &'static str },
+}
+
+/// A `.torrent` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Torrent {
+    /// The tracker URL (`announce`).
+    pub announce: Option<String>,
+
+    /// Tiers of tracker URLs (`announce-list`, BEP 12).
+    pub announce_list: Vec<Vec<String>>,
+
+    pub info: Info,
+
+    /// Web seed URLs (`url-list`, BEP 19).
+    pub url_list: Vec<String>,
+
+    /// Creation time in seconds since the Unix epoch (`creation date`).
+    pub creation_date: Option<i64>,
+
+    /// The program that created the file (`created by`).
+    pub created_by: Option<String>,
+
+    pub comment: Option<String>,
+}
+
+/// The info dictionary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Info {
+    /// The suggested name of the file, or of the directory for multi-file
+    /// torrents.
+    pub name: String,
+
+    /// Number of bytes in each piece (`piece length`).
+    pub piece_length: u64,
+
+    /// The SHA-1 hash of each piece. v2-only torrents don't have it.
+    pub pieces: Option<Vec<Sha1Hash>>,
+
+    /// Peers should only be got from the trackers in the file (`private`,
+    /// BEP 27).
+    pub private: bool,
+
+    pub files: Files,
+
+    /// The version of the file format (`meta version`, BEP 52). Version 2
+    /// files don't need the v1 fields: without them, the files are the ones
+    /// in the `file tree`.
+    pub meta_version: Option<i64>,
+}
+
+/// The single-file or multi-file layout of the content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Files {
+    /// The content is one file, named after the torrent.
+    Single {
+        length: u64,
+        attributes: FileAttributes,
+    },
+
+    /// The content is a directory with these files.
+    Multiple(Vec<File>),
+}
+
+/// A file in a multi-file torrent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct File {
+    /// The path components, relative to the torrent directory.
+    pub path: Vec<String>,
+
+    pub length: u64,
+
+    pub attributes: FileAttributes,
+}
+
+/// File attributes (BEP 47).
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileAttributes {
+    /// The file is padding to align the next file with a piece (`p`).
+    pub padding: bool,
+
+    /// `x`
+    pub executable: bool,
+
+    /// `h`
+    pub hidden: bool,
+
+    /// The file is a symbolic link to `symlink_path` (`l`).
+    pub symlink: bool,
+
+    /// The target of the symbolic link (`symlink path`).
+    pub symlink_path: Option<Vec<String>>,
+
+    /// The SHA-1 hash of the file content (`sha1`).
+    pub sha1: Option<Sha1Hash>,
+}
+
+impl Torrent {
+    /// It decodes a `.torrent` file.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file is invalid Bencode or it's not a
+    /// valid `.torrent` file.
+    pub fn from_bytes(input: &[u8]) -> Result<Self, Error> {
+        Self::from_parser(&mut BencodeParser::from_buf_read(input))
+    }
+
+    /// It decodes a `.torrent` file read from a reader.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't read the file, the file is invalid
+    /// Bencode or it's not a valid `.torrent` file.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, Error> {
+        Self::from_parser(&mut BencodeParser::from_buf_read(BufReader::new(reader)))
+    }
+
+    /// It decodes a `.torrent` file parsed with a parser, so that the parser
+    /// options (like the limits) apply.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file is invalid Bencode or it's not a
+    /// valid `.torrent` file.
+    pub fn from_parser<R: BufRead>(parser: &mut BencodeParser<R>) -> Result<Self, Error> {
+        Self::from_value(&value::from_parser(parser)?)
+    }
+
+    /// It decodes a `.torrent` file and computes the infohashes of the info
+    /// dictionary in the same pass, from its exact bytes.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file is invalid Bencode or it's not a
+    /// valid `.torrent` file.
+    pub fn from_parser_with_infohash<R: BufRead>(
+        parser: &mut BencodeParser<R>,
+    ) -> Result<(Self, Infohash), Error> {
+        let mut writer = InfohashWriter::new(ValueBuilder::default());
+
+        let result = parser.write_to(&mut writer);
+
+        let infohash = writer.infohashes().first().copied();
+        let mut builder = writer.into_inner();
+
+        if let Some(err) = builder.take_error() {
+            return Err(err.into());
+        }
+
+        result.map_err(value::Error::from)?;
+
+        let torrent = Self::from_value(&builder.into_value()?)?;
+
+        // A decoded torrent always has the info dictionary hashed
+        let infohash = infohash.ok_or_else(|| Error::MissingField("$.info".to_string()))?;
+
+        Ok((torrent, infohash))
+    }
+
+    /// It decodes a `.torrent` file and computes the infohashes of the info
+    /// dictionary. See [`Torrent::from_parser_with_infohash`].
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file is invalid Bencode or it's not a
+    /// valid `.torrent` file.
+    pub fn from_bytes_with_infohash(input: &[u8]) -> Result<(Self, Infohash), Error> {
+        Self::from_parser_with_infohash(&mut BencodeParser::from_buf_read(input))
+    }
+
+    /// It decodes the dictionary of a `.torrent` file.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it's not a valid `.torrent` file.
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let root = Field::root(value);
+        let dict = root.dict()?;
+
+        Ok(Self {
+            announce: root
+                .optional(dict, "announce")
+                .map(|field| field.string())
+                .transpose()?,
+            announce_list: root
+                .optional(dict, "announce-list")
+                .map(|field| {
+                    field
+                        .list()?
+                        .into_iter()
+                        .map(|tier| tier.string_list())
+                        .collect()
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            info: Info::from_field(&root.required(dict, "info")?)?,
+            url_list: root
+                .optional(dict, "url-list")
+                .map(|field| match field.value {
+                    // A single URL can be a string instead of a list
+                    Value::String(_) => field.string().map(|url| vec![url]),
+                    _ => field.string_list(),
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            creation_date: root
+                .optional(dict, "creation date")
+                .map(|field| field.integer())
+                .transpose()?,
+            created_by: root
+                .optional(dict, "created by")
+                .map(|field| field.string())
+                .transpose()?,
+            comment: root
+                .optional(dict, "comment")
+                .map(|field| field.string())
+                .transpose()?,
+        })
+    }
+
+    /// It returns the total length of the content, including padding files,
+    /// or `None` if it doesn't fit in 64 bits.
+    #[must_use]
+    pub fn total_length(&self) -> Option<u64> {
+        self.info.total_length()
+    }
+}
+
+impl Info {
+    fn from_field(field: &Field<'_>) -> Result<Self, Error> {
+        let dict = field.dict()?;
+
+        let piece_length = field.required(dict, "piece length")?;
+
+        if piece_length.length()? == 0 {
+            return Err(piece_length.invalid("it should be greater than 0"));
+        }
+
+        let name = field.required(dict, "name")?.string()?;
+
+        let meta_version = field
+            .optional(dict, "meta version")
+            .map(|version| version.integer())
+            .transpose()?;
+
+        let is_v2 = meta_version == Some(2);
+
+        let pieces = match field.optional(dict, "pieces") {
+            Some(pieces) => {
+                let bytes = pieces.bytes()?;
+
+                if bytes.len() % SHA1_LENGTH != 0 {
+                    return Err(pieces.invalid("the length is not a multiple of 20"));
+                }
+
+                Some(
+                    bytes
+                        .chunks_exact(SHA1_LENGTH)
+                        .map(|hash| hash.try_into().expect("chunks should have 20 bytes"))
+                        .collect(),
+                )
+            }
+            None if is_v2 => None,
+            None => return Err(Error::MissingField(field.child("pieces").path)),
+        };
+
+        let files = match (
+            field.optional(dict, "length"),
+            field.optional(dict, "files"),
+        ) {
+            (Some(length), None) => Files::Single {
+                length: length.length()?,
+                attributes: FileAttributes::from_dict(field, dict)?,
+            },
+            (None, Some(files)) => {
+                let files = files
+                    .list()?
+                    .into_iter()
+                    .map(|file| File::from_field(&file))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Files::Multiple(files)
+            }
+            (Some(_), Some(_)) => {
+                return Err(field.invalid("it has both a length and a list of files"))
+            }
+            (None, None) if is_v2 => {
+                let mut files = Vec::new();
+                File::from_file_tree(
+                    &field.required(dict, "file tree")?,
+                    &mut Vec::new(),
+                    &mut files,
+                )?;
+
+                match files.as_slice() {
+                    // The tree of single-file torrents has one file named
+                    // after the torrent
+                    [file] if file.path == [name.as_str()] => Files::Single {
+                        length: file.length,
+                        attributes: file.attributes.clone(),
+                    },
+                    _ => Files::Multiple(files),
+                }
+            }
+            (None, None) => return Err(Error::MissingField(field.child("length").path)),
+        };
+
+        let private = match field.optional(dict, "private") {
+            None => false,
+            Some(private) => match private.integer()? {
+                0 => false,
+                1 => true,
+                _ => return Err(private.invalid("it should be 0 or 1")),
+            },
+        };
+
+        Ok(Self {
+            name,
+            piece_length: piece_length.length()?,
+            pieces,
+            private,
+            files,
+            meta_version,
+        })
+    }
+
+    /// It returns the total length of the content, including padding files,
+    /// or `None` if it doesn't fit in 64 bits.
+    #[must_use]
+    pub fn total_length(&self) -> Option<u64> {
+        match &self.files {
+            Files::Single { length, .. } => Some(*length),
+            Files::Multiple(files) => files
+                .iter()
+                .try_fold(0u64, |total, file| total.checked_add(file.length)),
+        }
+    }
+}
+
+impl File {
+    fn from_field(field: &Field<'_>) -> Result<Self, Error> {
+        let dict = field.dict()?;
+
+        let path = field.required(dict, "path")?;
+        let components = path.string_list()?;
+
+        if components.is_empty() {
+            return Err(path.invalid("it should have at least one component"));
+        }
+
+        Ok(Self {
+            path: components,
+            length: field.required(dict, "length")?.length()?,
+            attributes: FileAttributes::from_dict(field, dict)?,
+        })
+    }
+
+    /// It decodes the files in a v2 file tree (BEP 52), where the keys are
+    /// the path components, and files are under an empty key.
+    fn from_file_tree(
+        field: &Field<'_>,
+        path: &mut Vec<String>,
+        files: &mut Vec<File>,
+    ) -> Result<(), Error> {
+        for (component, entry) in field.entries()? {
+            if component.is_empty() {
+                if path.is_empty() {
+                    return Err(entry.invalid("the file should have a name"));
+                }
+
+                let dict = entry.dict()?;
+
+                files.push(Self {
+                    path: path.clone(),
+                    length: entry.required(dict, "length")?.length()?,
+                    attributes: FileAttributes::from_dict(&entry, dict)?,
+                });
+            } else {
+                path.push(String::from_utf8_lossy(component).into_owned());
+                Self::from_file_tree(&entry, path, files)?;
+                path.pop();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FileAttributes {
+    /// It decodes the attributes of the file dictionary (or of the info
+    /// dictionary for single-file torrents). Unknown attributes are ignored.
+    fn from_dict(field: &Field<'_>, dict: &BTreeMap<Vec<u8>, Value>) -> Result<Self, Error> {
+        let attr = field
+            .optional(dict, "attr")
+            .map(|attr| attr.bytes().map(<[u8]>::to_vec))
+            .transpose()?
+            .unwrap_or_default();
+
+        let sha1 = field
+            .optional(dict, "sha1")
+            .map(|sha1| {
+                sha1.bytes()?
+                    .try_into()
+                    .map_err(|_| sha1.invalid("it should have 20 bytes"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            padding: attr.contains(&b'p'),
+            executable: attr.contains(&b'x'),
+            hidden: attr.contains(&b'h'),
+            symlink: attr.contains(&b'l'),
+            symlink_path: field
+                .optional(dict, "symlink path")
+                .map(|path| path.string_list())
+                .transpose()?,
+            sha1,
+        })
+    }
+}
+
+/// A value in the file with its path, to give the path in errors.
+struct Field<'a> {
+    value: &'a Value,
+    path: String,
+}
+
+impl<'a> Field<'a> {
+    fn root(value: &'a Value) -> Self {
+        Self {
+            value,
+            path: "$".to_string(),
+        }
+    }
+
+    fn child(&self, key: &str) -> Field<'a> {
+        Field {
+            value: self.value,
+            path: format!("{}.{key}", self.path),
+        }
+    }
+
+    fn optional(&self, dict: &'a BTreeMap<Vec<u8>, Value>, key: &str) -> Option<Field<'a>> {
+        dict.get(key.as_bytes()).map(|value| Field {
+            value,
+            path: self.child(key).path,
+        })
+    }
+
+    fn required(&self, dict: &'a BTreeMap<Vec<u8>, Value>, key: &str) -> Result<Field<'a>, Error> {
+        self.optional(dict, key)
+            .ok_or_else(|| Error::MissingField(self.child(key).path))
+    }
+
+    fn invalid(&self, reason: &'static str) -> Error {
+        Error::InvalidValue {
+            path: self.path.clone(),
+            reason,
+        }
+    }
+
+    fn invalid_type(&self, expected: &'static str) -> Error {
+        Error::InvalidType {
+            path: self.path.clone(),
+            expected,
+            found: self.value.type_name(),
+        }
+    }
+
+    fn dict(&self) -> Result<&'a BTreeMap<Vec<u8>, Value>, Error> {
+        self.value
+            .as_dict()
+            .ok_or_else(|| self.invalid_type("dictionary"))
+    }
+
+    /// It returns the items of a dictionary, with the keys.
+    fn entries(&self) -> Result<Vec<(&'a [u8], Field<'a>)>, Error> {
+        Ok(self
+            .dict()?
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.as_slice(),
+                    Field {
+                        value,
+                        path: self.child(&String::from_utf8_lossy(key)).path,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    fn list(&self) -> Result<Vec<Field<'a>>, Error> {
+        let items = self
+            .value
+            .as_list()
+            .ok_or_else(|| self.invalid_type("list"))?;
+
+        Ok(items
+            .iter()
+            .enumerate()
+            .map(|(index, value)| Field {
+                value,
+                path: format!("{}[{index}]", self.path),
+            })
+            .collect())
+    }
+
+    fn integer(&self) -> Result<i64, Error> {
+        self.value
+            .as_integer()
+            .ok_or_else(|| self.invalid_type("integer"))
+    }
+
+    /// A length or a size, which can't be negative.
+    fn length(&self) -> Result<u64, Error> {
+        u64::try_from(self.integer()?).map_err(|_| self.invalid("it should not be negative"))
+    }
+
+    fn bytes(&self) -> Result<&'a [u8], Error> {
+        self.value
+            .as_bytes()
+            .ok_or_else(|| self.invalid_type("string"))
+    }
+
+    fn string(&self) -> Result<String, Error> {
+        Ok(String::from_utf8_lossy(self.bytes()?).into_owned())
+    }
+
+    fn string_list(&self) -> Result<Vec<String>, Error> {
+        self.list()?.iter().map(Field::string).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metainfo::Torrent;
+
+    /// It returns a valid single-file torrent with the extra fields in the
+    /// torrent and info dictionaries, which must be sorted.
+    fn torrent(extra: &str, extra_info: &str) -> Vec<u8> {
+        format!(
+            "d{extra}4:infod{extra_info}6:lengthi5e4:name5:a.txt12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee"
+        )
+        .into_bytes()
+    }
+
+    fn decode_error(input: &[u8]) -> String {
+        match Torrent::from_bytes(input) {
+            Err(err) => err.to_string(),
+            Ok(torrent) => panic!("unexpected torrent: {torrent:?}"),
+        }
+    }
+
+    mod it_should_decode {
+        use crate::{
+            infohash::Infohash,
+            metainfo::{tests::torrent, File, FileAttributes, Files, Info, Torrent},
+        };
+
+        #[test]
+        fn a_minimal_single_file_torrent() {
+            assert_eq!(
+                Torrent::from_bytes(&torrent("", "")).unwrap(),
+                Torrent {
+                    announce: None,
+                    announce_list: Vec::new(),
+                    info: Info {
+                        name: "a.txt".to_string(),
+                        piece_length: 16384,
+                        pieces: Some(vec![[b'a'; 20]]),
+                        private: false,
+                        files: Files::Single {
+                            length: 5,
+                            attributes: FileAttributes::default(),
+                        },
+                        meta_version: None,
+                    },
+                    url_list: Vec::new(),
+                    creation_date: None,
+                    created_by: None,
+                    comment: None,
+                }
+            );
+        }
+
+        #[test]
+        fn the_trackers_web_seeds_and_descriptive_fields() {
+            let torrent = Torrent::from_bytes(&torrent(
+                "8:announce9:udp://t:113:announce-listll9:udp://t:1el9:udp://u:1ee7:comment2:hi10:created by4:test13:creation datei1700000000e",
+                "",
+            ))
+            .unwrap();
+
+            assert_eq!(torrent.announce.as_deref(), Some("udp://t:1"));
+            assert_eq!(
+                torrent.announce_list,
+                vec![vec!["udp://t:1".to_string()], vec!["udp://u:1".to_string()]]
+            );
+            assert_eq!(torrent.comment.as_deref(), Some("hi"));
+            assert_eq!(torrent.created_by.as_deref(), Some("test"));
+            assert_eq!(torrent.creation_date, Some(1_700_000_000));
+        }
+
+        #[test]
+        fn web_seeds_as_a_list_or_a_single_url() {
+            let list =
+                Torrent::from_bytes(&torrent("8:url-listl8:http://a8:http://be", "")).unwrap();
+
+            assert_eq!(
+                list.url_list,
+                vec!["http://a".to_string(), "http://b".to_string()]
+            );
+
+            let single = Torrent::from_bytes(&torrent("8:url-list8:http://a", "")).unwrap();
+
+            assert_eq!(single.url_list, vec!["http://a".to_string()]);
+        }
+
+        #[test]
+        fn the_private_flag() {
+            let torrent = Torrent::from_bytes(&torrent("", "")).unwrap();
+            assert!(!torrent.info.private);
+
+            let private = Torrent::from_bytes(
+                b"d4:infod6:lengthi5e4:name5:a.txt12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa7:privatei1eee",
+            )
+            .unwrap();
+            assert!(private.info.private);
+        }
+
+        #[test]
+        fn the_pieces_split_into_hashes() {
+            let torrent = Torrent::from_bytes(
+                b"d4:infod6:lengthi5e4:name5:a.txt12:piece lengthi1e6:pieces40:aaaaaaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbbbbbee",
+            )
+            .unwrap();
+
+            assert_eq!(torrent.info.pieces, Some(vec![[b'a'; 20], [b'b'; 20]]));
+        }
+
+        #[test]
+        fn a_multi_file_torrent_with_padding_files_and_attributes() {
+            let torrent = Torrent::from_bytes(
+                b"d4:infod5:filesld6:lengthi3e4:pathl3:dir5:a.txteed4:attr1:p6:lengthi16381e4:pathl4:.pad5:16381eed4:attr2:lx6:lengthi0e4:pathl4:linke4:sha120:cccccccccccccccccccc12:symlink pathl5:a.txteee4:name4:root12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee",
+            )
+            .unwrap();
+
+            assert_eq!(
+                torrent.info.files,
+                Files::Multiple(vec![
+                    File {
+                        path: vec!["dir".to_string(), "a.txt".to_string()],
+                        length: 3,
+                        attributes: FileAttributes::default(),
+                    },
+                    File {
+                        path: vec![".pad".to_string(), "16381".to_string()],
+                        length: 16381,
+                        attributes: FileAttributes {
+                            padding: true,
+                            ..FileAttributes::default()
+                        },
+                    },
+                    File {
+                        path: vec!["link".to_string()],
+                        length: 0,
+                        attributes: FileAttributes {
+                            symlink: true,
+                            executable: true,
+                            symlink_path: Some(vec!["a.txt".to_string()]),
+                            sha1: Some([b'c'; 20]),
+                            ..FileAttributes::default()
+                        },
+                    },
+                ])
+            );
+            assert_eq!(torrent.total_length(), Some(16384));
+        }
+
+        #[test]
+        fn names_that_are_not_valid_utf8() {
+            let torrent = Torrent::from_bytes(
+                b"d4:infod6:lengthi5e4:name2:\xffa12:piece lengthi1e6:pieces0:ee",
+            )
+            .unwrap();
+
+            assert_eq!(torrent.info.name, "\u{fffd}a");
+        }
+
+        #[test]
+        fn the_meta_version_of_hybrid_torrents() {
+            let torrent = Torrent::from_bytes(&torrent("", "12:meta versioni2e")).unwrap();
+
+            assert_eq!(torrent.info.meta_version, Some(2));
+        }
+
+        #[test]
+        fn v2_only_single_file_torrents() {
+            let torrent = Torrent::from_bytes(b"d4:infod9:file treed5:a.txtd0:d6:lengthi5e11:pieces root32:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaeee12:meta versioni2e4:name5:a.txt12:piece lengthi16384eee").unwrap();
+
+            assert_eq!(torrent.info.pieces, None);
+            assert!(matches!(
+                torrent.info.files,
+                Files::Single { length: 5, .. }
+            ));
+        }
+
+        #[test]
+        fn v2_only_multi_file_torrents() {
+            let torrent = Torrent::from_bytes(b"d4:infod9:file treed3:dird1:ad0:d6:lengthi3eeee1:bd0:d4:attr1:x6:lengthi4eeee12:meta versioni2e4:name4:root12:piece lengthi16384eee").unwrap();
+
+            assert_eq!(
+                torrent.info.files,
+                Files::Multiple(vec![
+                    File {
+                        path: vec!["b".to_string()],
+                        length: 4,
+                        attributes: FileAttributes {
+                            executable: true,
+                            ..FileAttributes::default()
+                        },
+                    },
+                    File {
+                        path: vec!["dir".to_string(), "a".to_string()],
+                        length: 3,
+                        attributes: FileAttributes::default(),
+                    },
+                ])
+            );
+            assert_eq!(torrent.total_length(), Some(7));
+        }
+
+        #[test]
+        fn multi_file_torrents_with_a_total_length_that_does_not_fit_in_64_bits() {
+            let torrent = Torrent::from_bytes(b"d4:infod5:filesld6:lengthi9223372036854775807e4:pathl1:aeed6:lengthi9223372036854775807e4:pathl1:beed6:lengthi9223372036854775807e4:pathl1:ceee4:name1:d12:piece lengthi1e6:pieces0:ee").unwrap();
+
+            assert_eq!(torrent.total_length(), None);
+        }
+
+        #[test]
+        fn torrents_with_the_infohash_of_the_exact_info_bytes() {
+            // Keys are not sorted, so the info dictionary would not be
+            // re-encoded with the same bytes
+            let info = b"d4:name1:a6:lengthi1e12:piece lengthi1e6:pieces0:e";
+
+            let (torrent, infohash) =
+                Torrent::from_bytes_with_infohash(&[&b"d4:info"[..], info, b"e"].concat()).unwrap();
+
+            assert_eq!(torrent.info.name, "a");
+            assert_eq!(infohash, Infohash::of(info));
+        }
+
+        #[test]
+        fn torrents_read_from_a_reader() {
+            let torrent = Torrent::from_reader(&torrent("", "")[..]).unwrap();
+
+            assert_eq!(torrent.info.name, "a.txt");
+        }
+    }
+
+    mod it_should_fail_decoding {
+        use crate::metainfo::{
+            tests::{decode_error, torrent},
+            Error, Torrent,
+        };
+
+        #[test]
+        fn invalid_bencode() {
+            assert!(matches!(
+                Torrent::from_bytes(b"d4:info"),
+                Err(Error::Value(_))
+            ));
+        }
+
+        #[test]
+        fn a_file_that_is_not_a_dictionary() {
+            assert_eq!(
+                decode_error(b"le"),
+                "Invalid type for field $: expected dictionary, found list"
+            );
+        }
+
+        #[test]
+        fn a_torrent_without_info() {
+            assert_eq!(decode_error(b"de"), "Missing required field $.info");
+        }
+
+        #[test]
+        fn a_torrent_without_required_info_fields() {
+            assert_eq!(
+                decode_error(b"d4:infod6:lengthi5e12:piece lengthi1e6:pieces0:ee"),
+                "Missing required field $.info.name"
+            );
+            assert_eq!(
+                decode_error(b"d4:infod4:name1:a12:piece lengthi1e6:pieces0:ee"),
+                "Missing required field $.info.length"
+            );
+            assert_eq!(
+                decode_error(b"d4:infod6:lengthi5e4:name1:a12:piece lengthi1eee"),
+                "Missing required field $.info.pieces"
+            );
+            assert_eq!(
+                decode_error(b"d4:infod12:meta versioni2e4:name1:a12:piece lengthi1eee"),
+                "Missing required field $.info.file tree"
+            );
+        }
+
+        #[test]
+        fn mistyped_fields() {
+            assert_eq!(
+                decode_error(&torrent("8:announcei1e", "")),
+                "Invalid type for field $.announce: expected string, found integer"
+            );
+            assert_eq!(
+                decode_error(b"d4:infod5:filesld6:lengthi1e4:path1:aee4:name1:a12:piece lengthi1e6:pieces0:ee"),
+                "Invalid type for field $.info.files[0].path: expected list, found string"
+            );
+        }
+
+        #[test]
+        fn pieces_that_are_not_20_byte_hashes() {
+            assert_eq!(
+                decode_error(b"d4:infod6:lengthi5e4:name1:a12:piece lengthi1e6:pieces3:abcee"),
+                "Invalid value for field $.info.pieces: the length is not a multiple of 20"
+            );
+        }
+
+        #[test]
+        fn invalid_lengths() {
+            assert_eq!(
+                decode_error(b"d4:infod6:lengthi-5e4:name1:a12:piece lengthi1e6:pieces0:ee"),
+                "Invalid value for field $.info.length: it should not be negative"
+            );
+            assert_eq!(
+                decode_error(b"d4:infod6:lengthi5e4:name1:a12:piece lengthi0e6:pieces0:ee"),
+                "Invalid value for field $.info.piece length: it should be greater than 0"
+            );
+        }
+
+        #[test]
+        fn single_file_and_multi_file_layouts_at_the_same_time() {
+            assert_eq!(
+                decode_error(
+                    b"d4:infod5:filesle6:lengthi5e4:name1:a12:piece lengthi1e6:pieces0:ee"
+                ),
+                "Invalid value for field $.info: it has both a length and a list of files"
+            );
+        }
+
+        #[test]
+        fn an_invalid_private_flag() {
+            assert_eq!(
+                decode_error(
+                    b"d4:infod6:lengthi5e4:name1:a12:piece lengthi1e6:pieces0:7:privatei2eee"
+                ),
+                "Invalid value for field $.info.private: it should be 0 or 1"
+            );
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Async parser for [tokio](https://tokio.rs).
+//!
+//! It's only available with the `tokio` feature.
+//!
+//! [`AsyncBencodeParser`] converts from an [`AsyncRead`] to an [`AsyncWrite`]
+//! with the same JSON output and the same errors as [`BencodeParser`](super::BencodeParser),
+//! without blocking the runtime:
+//!
+//! ```rust
+//! use torrust_bencode2json::parsers::async_parser::AsyncBencodeParser;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let mut output = Vec::new();
+//!
+//! AsyncBencodeParser::new(&b"d4:spaml1:a1:bee"[..])
+//!     .write_bytes(&mut output)
+//!     .await
+//!     .unwrap();
+//!
+//! assert_eq!(output, br#"{"spam":["a","b"]}"#);
+//! # });
+//! ```
+//!
+//! Internally, it uses the same parser as the sync version on the bytes read
+//! so far. When a token is not complete, it reads more bytes and parses the
+//! token again.
+use std::cmp;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::{
+    error::Error,
+    incremental::{IncrementalParser, Step},
+    options::ParserOptions,
+};
+
+/// A Bencode to JSON parser for async inputs and outputs.
+pub struct AsyncBencodeParser<R> {
+    reader: R,
+    incremental: IncrementalParser,
+}
+
+impl<R: AsyncRead + Unpin> AsyncBencodeParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParserOptions::default())
+    }
+
+    /// It creates a parser with custom options.
+    ///
+    /// See [`ParserBuilder::build_async`] for a more convenient way to set
+    /// only some of the options.
+    ///
+    /// [`ParserBuilder::build_async`]: super::options::ParserBuilder::build_async
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
+        Self {
+            reader,
+            incremental: IncrementalParser::new(options),
+        }
+    }
+
+    /// It returns the parser options.
+    pub fn options(&self) -> &ParserOptions {
+        self.incremental.parser.options()
+    }
+
+    /// It returns the errors the parser recovered from.
+    pub fn diagnostics(&self) -> &[Error] {
+        self.incremental.parser.diagnostics()
+    }
+
+    /// It returns the errors the parser recovered from, leaving the list
+    /// empty.
+    pub fn take_diagnostics(&mut self) -> Vec<Error> {
+        self.incremental.parser.take_diagnostics()
+    }
+
+    /// It parses a bencoded value read from input and writes the corresponding
+    /// JSON UTF-8 string value as bytes to the output.
+    ///
+    /// The output is written in chunks of `output_buffer_size` bytes and
+    /// flushed at the end. When there is an error, the JSON written so far is
+    /// written too, like the sync parser does.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if:
+    ///
+    /// - It can't read from the input or write to the output.
+    /// - The input is invalid Bencode.
+    pub async fn write_bytes<W: AsyncWrite + Unpin>(&mut self, mut writer: W) -> Result<(), Error> {
+        let output_buffer_size = self.options().output_buffer_size;
+
+        loop {
+            let step = match self.incremental.parse_next_token() {
+                Ok(Step::NeedMoreInput) => {
+                    self.read_more().await?;
+                    continue;
+                }
+                Ok(step) => step,
+                Err(err) => {
+                    // Errors writing the partial output are ignored, like
+                    // when a `BufWriter` is dropped
+                    let _unused = writer.write_all(self.incremental.output.pending()).await;
+                    let _unused = writer.flush().await;
+                    return Err(err);
+                }
+            };
+
+            let ended = step == Step::Ended;
+
+            if ended || self.incremental.output.pending().len() >= output_buffer_size {
+                writer.write_all(self.incremental.output.pending()).await?;
+                self.incremental.output.mark_as_written();
+            }
+
+            if ended {
+                break;
+            }
+
+            self.incremental.compact();
+        }
+
+        writer.flush().await?;
+
+        Ok(())
+    }
+
+    /// It reads from the input the bytes needed to parse the pending token
+    /// again, and at least as many bytes as there are pending, so that long
+    /// tokens are not parsed again too many times.
+    async fn read_more(&mut self) -> Result<(), Error> {
+        let required_input = self.incremental.required_input();
+        let input = self.incremental.input_mut();
+
+        let target = cmp::max(
+            required_input,
+            cmp::max(input.pending().len() * 2, input.chunk_size),
+        );
+
+        while !input.ended && input.pending().len() < target {
+            input.bytes.reserve(target - input.pending().len());
+
+            if self.reader.read_buf(&mut input.bytes).await? == 0 {
+                input.ended = true;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use tokio::io::{AsyncRead, ReadBuf};
+
+    use crate::parsers::{
+        async_parser::AsyncBencodeParser,
+        options::{ErrorHandling, ParserBuilder, ParserOptions},
+        BencodeParser,
+    };
+
+    /// An async input that returns at most `chunk_size` bytes each time, and
+    /// is not ready every other time.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        chunk_size: usize,
+        ready: bool,
+    }
+
+    impl<'a> Trickle<'a> {
+        fn new(bytes: &'a [u8], chunk_size: usize) -> Self {
+            Self {
+                bytes,
+                chunk_size,
+                ready: false,
+            }
+        }
+    }
+
+    impl AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            self.ready = !self.ready;
+
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            let length = self.chunk_size.min(self.bytes.len()).min(buf.remaining());
+            let (chunk, rest) = self.bytes.split_at(length);
+
+            buf.put_slice(chunk);
+            self.bytes = rest;
+
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// The JSON output, the error message with its context, and the
+    /// recovered errors.
+    type Outcome = (String, Option<String>, Vec<String>);
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn parse_sync(input: &[u8], options: &ParserOptions) -> Outcome {
+        let mut parser = BencodeParser::with_options(input, options.clone());
+
+        let mut output = Vec::new();
+        let result = parser.write_bytes(&mut output);
+
+        (
+            String::from_utf8(output).unwrap(),
+            result.err().map(|err| err.to_string()),
+            parser
+                .diagnostics()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        )
+    }
+
+    fn parse_async<R: AsyncRead + Unpin>(input: R, options: &ParserOptions) -> Outcome {
+        let mut parser = AsyncBencodeParser::with_options(input, options.clone());
+
+        let mut output = Vec::new();
+        let result = block_on(parser.write_bytes(&mut output));
+
+        (
+            String::from_utf8(output).unwrap(),
+            result.err().map(|err| err.to_string()),
+            parser
+                .diagnostics()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_should_convert_from_an_async_input_to_an_async_output() {
+        let mut output = Vec::new();
+
+        AsyncBencodeParser::new(&b"d4:spaml1:ai42eee"[..])
+            .write_bytes(&mut output)
+            .await
+            .unwrap();
+
+        assert_eq!(output, br#"{"spam":["a",42]}"#);
+    }
+
+    #[tokio::test]
+    async fn it_should_be_built_with_the_parser_builder() {
+        let mut output = Vec::new();
+
+        let mut parser = ParserBuilder::new(&b"li1eixee"[..])
+            .error_handling(ErrorHandling::Recover)
+            .build_async();
+
+        parser.write_bytes(&mut output).await.unwrap();
+
+        assert_eq!(output, br#"[1,"<unreadable>"]"#);
+        assert_eq!(parser.diagnostics().len(), 1);
+    }
+
+    #[test]
+    fn it_should_parse_tokens_split_across_reads() {
+        let input = b"d4:spaml11:hello worldi-1234567890ee3:eggd0:lee";
+        let options = ParserOptions::default();
+
+        for chunk_size in 1..8 {
+            assert_eq!(
+                parse_async(Trickle::new(input, chunk_size), &options),
+                parse_sync(input, &options)
+            );
+        }
+    }
+
+    #[test]
+    fn it_should_write_the_output_in_chunks() {
+        let input = b"l1:a1:b1:c1:d1:ee";
+        let options = ParserOptions {
+            input_buffer_size: 1,
+            output_buffer_size: 4,
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(
+            parse_async(Trickle::new(input, 1), &options),
+            parse_sync(input, &options)
+        );
+    }
+
+    #[test]
+    fn it_should_return_the_same_errors_as_the_sync_parser() {
+        let input = b"d4:spaml1:ai00eee";
+        let options = ParserOptions::default();
+
+        let outcome = parse_async(Trickle::new(input, 2), &options);
+
+        assert_eq!(outcome, parse_sync(input, &options));
+        assert!(outcome.1.is_some());
+    }
+
+    mod it_should_give_the_same_output_and_errors_as_the_sync_parser {
+        use proptest::prelude::*;
+
+        use super::{parse_async, parse_sync, Trickle};
+        use crate::parsers::options::{ErrorHandling, Limits, ParserOptions};
+
+        const BENCODE_ALPHABET: &[u8] = b"ilde0123456789:-\nx";
+
+        proptest! {
+            #[test]
+            fn on_arbitrary_sequences_of_bencode_bytes(
+                input in proptest::collection::vec(proptest::sample::select(BENCODE_ALPHABET), 0..256),
+                chunk_size in 1..16usize,
+            ) {
+                let options = ParserOptions::default();
+
+                prop_assert_eq!(
+                    parse_async(Trickle::new(&input, chunk_size), &options),
+                    parse_sync(&input, &options)
+                );
+            }
+
+            #[test]
+            fn on_arbitrary_sequences_of_bencode_bytes_when_recovering_with_limits(
+                input in proptest::collection::vec(proptest::sample::select(BENCODE_ALPHABET), 0..256),
+                chunk_size in 1..16usize,
+            ) {
+                let options = ParserOptions {
+                    input_buffer_size: 4,
+                    output_buffer_size: 4,
+                    capture_window_size: 8,
+                    error_handling: ErrorHandling::Recover,
+                    limits: Limits {
+                        max_depth: Some(4),
+                        max_integer_length: Some(3),
+                        max_string_length: Some(5),
+                    },
+                    ..ParserOptions::default()
+                };
+
+                prop_assert_eq!(
+                    parse_async(Trickle::new(&input, chunk_size), &options),
+                    parse_sync(&input, &options)
+                );
+            }
+        }
+    }
+}
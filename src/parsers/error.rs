@@ -1,26 +1,39 @@
 //! Parser errors.
+//!
+//! Errors can be handled programmatically without matching every variant. The
+//! [`Error::kind`] method returns the [`ErrorKind`] (the category of the
+//! error), and [`Error::input_position`] and [`Error::output_position`] return
+//! the position in the input and output streams where the error occurred.
 use core::str;
 use std::{
     fmt::{self},
     io,
+    sync::Arc,
 };
 
 use thiserror::Error;
 
 use crate::rw;
 
-use super::BencodeType;
+use super::{stack, BencodeType};
 
 /// Errors that can occur while parsing a bencoded value.
-#[derive(Debug, Error)]
+///
+/// The enum is non-exhaustive because new validation modes can add new
+/// variants. Use [`Error::kind`] to handle categories of errors.
+///
+/// Errors are cheap to clone: the I/O error and the bytes captured for the
+/// contexts are reference-counted.
+#[derive(Debug, Error, Clone)]
+#[non_exhaustive]
 pub enum Error {
     /// I/O error.
     #[error("I/O error: {0}")]
-    Io(#[from] io::Error),
+    Io(Arc<io::Error>),
 
-    /// R/W error.
-    #[error("R/W error: {0}")]
-    Rw(#[from] rw::error::Error),
+    /// Formatting error writing to a `std::fmt::Write` output.
+    #[error("Formatting error: {0}")]
+    Fmt(fmt::Error),
 
     /// Read byte after peeking does match peeked byte.
     ///
@@ -53,6 +66,10 @@ pub enum Error {
     #[error("Unexpected end of input parsing integer; {0}; {1}")]
     UnexpectedEndOfInputParsingInteger(ReadContext, WriteContext),
 
+    /// The integer has more digits than the configured limit.
+    #[error("Integer is longer than the limit of {0} digits; {1}; {2}")]
+    MaxIntegerLengthExceeded(usize, ReadContext, WriteContext),
+
     /// Leading zeros in integers are not allowed, for example b'i00e'.
     #[error("Leading zeros in integers are not allowed, for example b'i00e'; {0}; {1}")]
     LeadingZerosInIntegersNotAllowed(ReadContext, WriteContext),
@@ -65,6 +82,16 @@ pub enum Error {
     #[error("Invalid string length byte, expected a digit; {0}; {1}")]
     InvalidStringLengthByte(ReadContext, WriteContext),
 
+    /// String length overflow.
+    ///
+    /// The string length does not fit in a `usize`.
+    #[error("String length overflow; {0}; {1}")]
+    StringLengthOverflow(ReadContext, WriteContext),
+
+    /// The string is longer than the configured limit.
+    #[error("String is longer than the limit of {0} bytes; {1}; {2}")]
+    MaxStringLengthExceeded(usize, ReadContext, WriteContext),
+
     /// Unexpected end of input parsing string length.
     ///
     /// The input ends before the string length ends.
@@ -77,6 +104,10 @@ pub enum Error {
     #[error("Unexpected end of input parsing string value; {0}; {1}")]
     UnexpectedEndOfInputParsingStringValue(ReadContext, WriteContext),
 
+    /// The parsed string value can't be serialized into a JSON string.
+    #[error("Can't serialize string value into JSON: {0}; {1}; {2}")]
+    StringJsonSerialization(Arc<serde_json::Error>, ReadContext, WriteContext),
+
     // Lists
     /// Unexpected end of input parsing list. Expecting first list item or list end.
     #[error(
@@ -119,10 +150,214 @@ pub enum Error {
         "Unexpected end of list or dict. No matching start for the list or dict end: {0}, {1}"
     )]
     NoMatchingStartForListOrDictEnd(ReadContext, WriteContext),
+
+    /// There are more nested lists and dictionaries than the configured limit.
+    #[error("Nesting depth is greater than the limit of {0}; {1}; {2}")]
+    MaxDepthExceeded(usize, ReadContext, WriteContext),
+
+    // Internal errors
+    /// Invalid stack state.
+    ///
+    /// The parser tried to read or mutate the stack in a way that is not
+    /// allowed. It should never happen. If it happens, it's a bug.
+    #[error("Invalid stack state: {0}; {1}; {2}")]
+    InvalidStackState(stack::Error, ReadContext, WriteContext),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(Arc::new(err))
+    }
+}
+
+impl From<rw::error::Error> for Error {
+    fn from(err: rw::error::Error) -> Self {
+        match err {
+            rw::error::Error::Io(err) => Self::from(err),
+            rw::error::Error::Fmt(err) => Self::Fmt(err),
+        }
+    }
+}
+
+/// It returns the read and write contexts of an error, as shared or mutable
+/// references depending on the reference to the error.
+macro_rules! contexts {
+    ($error:expr) => {
+        match $error {
+            Error::Io(_) | Error::Fmt(_) => None,
+            Error::ReadByteAfterPeekingDoesMatchPeekedByte(read_context, write_context)
+            | Error::UnrecognizedFirstBencodeValueByte(read_context, write_context)
+            | Error::UnexpectedByteParsingInteger(read_context, write_context)
+            | Error::UnexpectedEndOfInputParsingInteger(read_context, write_context)
+            | Error::LeadingZerosInIntegersNotAllowed(read_context, write_context)
+            | Error::InvalidStringLengthByte(read_context, write_context)
+            | Error::StringLengthOverflow(read_context, write_context)
+            | Error::MaxIntegerLengthExceeded(_, read_context, write_context)
+            | Error::MaxStringLengthExceeded(_, read_context, write_context)
+            | Error::MaxDepthExceeded(_, read_context, write_context)
+            | Error::UnexpectedEndOfInputParsingStringLength(read_context, write_context)
+            | Error::UnexpectedEndOfInputParsingStringValue(read_context, write_context)
+            | Error::UnexpectedEndOfInputExpectingFirstListItemOrEnd(read_context, write_context)
+            | Error::UnexpectedEndOfInputExpectingNextListItem(read_context, write_context)
+            | Error::UnexpectedEndOfInputExpectingFirstDictFieldOrEnd(
+                read_context,
+                write_context,
+            )
+            | Error::UnexpectedEndOfInputExpectingDictFieldValue(read_context, write_context)
+            | Error::UnexpectedEndOfInputExpectingDictFieldKeyOrEnd(read_context, write_context)
+            | Error::PrematureEndOfDict(read_context, write_context)
+            | Error::ExpectedStringForDictKeyGot(_, read_context, write_context)
+            | Error::NoMatchingStartForListOrDictEnd(read_context, write_context)
+            | Error::StringJsonSerialization(_, read_context, write_context)
+            | Error::InvalidStackState(_, read_context, write_context) => {
+                Some((read_context, write_context))
+            }
+        }
+    };
+}
+
+impl Error {
+    /// It returns the category of the error.
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io(_) | Error::Fmt(_) => ErrorKind::Io,
+            Error::UnexpectedEndOfInputParsingInteger(..)
+            | Error::UnexpectedEndOfInputParsingStringLength(..)
+            | Error::UnexpectedEndOfInputParsingStringValue(..)
+            | Error::UnexpectedEndOfInputExpectingFirstListItemOrEnd(..)
+            | Error::UnexpectedEndOfInputExpectingNextListItem(..)
+            | Error::UnexpectedEndOfInputExpectingFirstDictFieldOrEnd(..)
+            | Error::UnexpectedEndOfInputExpectingDictFieldValue(..)
+            | Error::UnexpectedEndOfInputExpectingDictFieldKeyOrEnd(..) => ErrorKind::UnexpectedEof,
+            Error::StringLengthOverflow(..)
+            | Error::MaxIntegerLengthExceeded(..)
+            | Error::MaxStringLengthExceeded(..)
+            | Error::MaxDepthExceeded(..) => ErrorKind::LimitExceeded,
+            Error::LeadingZerosInIntegersNotAllowed(..) => ErrorKind::Strictness,
+            Error::StringJsonSerialization(..) | Error::InvalidStackState(..) => {
+                ErrorKind::Internal
+            }
+            Error::ReadByteAfterPeekingDoesMatchPeekedByte(..)
+            | Error::UnrecognizedFirstBencodeValueByte(..)
+            | Error::UnexpectedByteParsingInteger(..)
+            | Error::InvalidStringLengthByte(..)
+            | Error::PrematureEndOfDict(..)
+            | Error::ExpectedStringForDictKeyGot(..)
+            | Error::NoMatchingStartForListOrDictEnd(..) => ErrorKind::Syntax,
+        }
+    }
+
+    /// It returns the reader context if the error was produced while parsing.
+    ///
+    /// I/O errors do not have context.
+    #[must_use]
+    pub fn read_context(&self) -> Option<&ReadContext> {
+        self.contexts().map(|(read_context, _)| read_context)
+    }
+
+    /// It returns the writer context if the error was produced while parsing.
+    ///
+    /// I/O errors do not have context.
+    #[must_use]
+    pub fn write_context(&self) -> Option<&WriteContext> {
+        self.contexts().map(|(_, write_context)| write_context)
+    }
+
+    /// It returns the position in the input where the error occurred, if
+    /// known.
+    #[must_use]
+    pub fn input_position(&self) -> Option<u64> {
+        self.read_context().map(|read_context| read_context.pos)
+    }
+
+    /// It returns the position in the output where the error occurred, if
+    /// known.
+    #[must_use]
+    pub fn output_position(&self) -> Option<u64> {
+        self.write_context().map(|write_context| write_context.pos)
+    }
+
+    /// It returns true if the input ended before the bencoded value was
+    /// complete.
+    #[must_use]
+    pub fn is_eof(&self) -> bool {
+        self.kind() == ErrorKind::UnexpectedEof
+    }
+
+    /// It returns true if the input is not valid bencode.
+    #[must_use]
+    pub fn is_syntax(&self) -> bool {
+        self.kind() == ErrorKind::Syntax
+    }
+
+    fn contexts(&self) -> Option<(&ReadContext, &WriteContext)> {
+        contexts!(self)
+    }
+
+    /// It returns the reader context to complete it after the error was
+    /// created, for example with bytes re-read from the input.
+    pub(crate) fn read_context_mut(&mut self) -> Option<&mut ReadContext> {
+        self.contexts_mut().map(|(read_context, _)| read_context)
+    }
+
+    fn contexts_mut(&mut self) -> Option<(&mut ReadContext, &mut WriteContext)> {
+        contexts!(self)
+    }
+}
+
+/// The category of an error.
+///
+/// The enum is non-exhaustive because new categories can be added in the
+/// future.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// It can't read from the input or write to the output.
+    Io,
+
+    /// The input ends before the bencoded value is complete.
+    UnexpectedEof,
+
+    /// The input is not valid bencode.
+    Syntax,
+
+    /// A configured limit was exceeded.
+    LimitExceeded,
+
+    /// The input is valid bencode but it's not in canonical form, for example
+    /// integers with leading zeros.
+    Strictness,
+
+    /// The parser reached an invalid internal state. It should never happen.
+    /// If it happens, it's a bug.
+    Internal,
+}
+
+impl ErrorKind {
+    /// It returns the short name of the error category, for example
+    /// `unexpected_eof`.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Io => "io",
+            ErrorKind::UnexpectedEof => "unexpected_eof",
+            ErrorKind::Syntax => "syntax",
+            ErrorKind::LimitExceeded => "limit_exceeded",
+            ErrorKind::Strictness => "strictness",
+            ErrorKind::Internal => "internal",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
 }
 
 /// The reader context when the error ocurred.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ReadContext {
     /// The read byte that caused the error if any.
     pub byte: Option<u8>,
@@ -131,7 +366,7 @@ pub struct ReadContext {
     pub pos: u64,
 
     /// The latest bytes read from input.
-    pub latest_bytes: Vec<u8>,
+    pub latest_bytes: Arc<[u8]>,
 }
 
 impl fmt::Display for ReadContext {
@@ -158,7 +393,7 @@ impl fmt::Display for ReadContext {
 }
 
 /// The writer context when the error ocurred.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WriteContext {
     /// The written byte that caused the error if any.
     pub byte: Option<u8>,
@@ -167,7 +402,7 @@ pub struct WriteContext {
     pub pos: u64,
 
     /// The latest bytes written to the output.
-    pub latest_bytes: Vec<u8>,
+    pub latest_bytes: Arc<[u8]>,
 }
 
 impl fmt::Display for WriteContext {
@@ -196,6 +431,91 @@ impl fmt::Display for WriteContext {
 #[cfg(test)]
 mod tests {
 
+    mod for_errors {
+        use std::{fmt, io};
+
+        use crate::{
+            parsers::error::{Error, ErrorKind},
+            rw, try_bencode_to_json,
+        };
+
+        #[test]
+        fn it_should_be_send_sync_and_static() {
+            fn assert_send_sync_static<T: Send + Sync + 'static>() {}
+
+            assert_send_sync_static::<Error>();
+        }
+
+        #[test]
+        fn it_should_be_cloneable() {
+            let err = try_bencode_to_json(b"i42").unwrap_err();
+
+            let cloned = err.clone();
+
+            assert_eq!(cloned.to_string(), err.to_string());
+        }
+
+        #[test]
+        fn it_should_return_the_error_kind() {
+            assert_eq!(
+                try_bencode_to_json(b"i42").unwrap_err().kind(),
+                ErrorKind::UnexpectedEof
+            );
+            assert_eq!(
+                try_bencode_to_json(b"a").unwrap_err().kind(),
+                ErrorKind::Syntax
+            );
+            assert_eq!(
+                try_bencode_to_json(b"i00e").unwrap_err().kind(),
+                ErrorKind::Strictness
+            );
+            assert_eq!(Error::from(io::Error::other("error")).kind(), ErrorKind::Io);
+        }
+
+        #[test]
+        fn it_should_return_the_input_and_output_positions() {
+            let err = try_bencode_to_json(b"li1ei42").unwrap_err();
+
+            assert_eq!(err.input_position(), Some(7));
+            assert_eq!(err.output_position(), Some(3));
+        }
+
+        #[test]
+        fn it_should_not_return_positions_for_io_errors() {
+            let err = Error::from(io::Error::other("error"));
+
+            assert_eq!(err.input_position(), None);
+            assert_eq!(err.output_position(), None);
+        }
+
+        #[test]
+        fn it_should_tell_if_the_input_ended_prematurely() {
+            assert!(try_bencode_to_json(b"l").unwrap_err().is_eof());
+            assert!(!try_bencode_to_json(b"a").unwrap_err().is_eof());
+        }
+
+        #[test]
+        fn it_should_tell_if_the_input_is_not_valid_bencode() {
+            assert!(try_bencode_to_json(b"a").unwrap_err().is_syntax());
+            assert!(!try_bencode_to_json(b"l").unwrap_err().is_syntax());
+        }
+
+        #[test]
+        fn it_should_not_wrap_io_errors_twice() {
+            let err = Error::from(rw::error::Error::Io(io::Error::other("error")));
+
+            assert!(matches!(err, Error::Io(_)));
+        }
+
+        #[test]
+        fn it_should_convert_formatting_errors() {
+            let err = Error::from(rw::error::Error::Fmt(fmt::Error));
+
+            assert!(matches!(err, Error::Fmt(_)));
+            assert_eq!(err.kind(), ErrorKind::Io);
+        }
+    }
+
     mod for_read_context {
         use crate::parsers::error::ReadContext;
 
@@ -204,7 +524,7 @@ mod tests {
             let read_context = ReadContext {
                 byte: Some(b'a'),
                 pos: 10,
-                latest_bytes: vec![b'a', b'b', b'c'],
+                latest_bytes: vec![b'a', b'b', b'c'].into(),
             };
 
             assert_eq!( read_context.to_string(),"read context: byte `97` (char: `a`), input pos 10, latest input bytes dump: [97, 98, 99] (UTF-8 string: `abc`)");
@@ -215,7 +535,7 @@ mod tests {
             let read_context = ReadContext {
                 byte: None,
                 pos: 10,
-                latest_bytes: vec![b'a', b'b', b'c'],
+                latest_bytes: vec![b'a', b'b', b'c'].into(),
             };
 
             assert_eq!(read_context.to_string(), "read context: input pos 10, latest input bytes dump: [97, 98, 99] (UTF-8 string: `abc`)");
@@ -226,7 +546,7 @@ mod tests {
             let read_context = ReadContext {
                 byte: None,
                 pos: 10,
-                latest_bytes: vec![b'\xFF', b'\xFE'],
+                latest_bytes: vec![b'\xFF', b'\xFE'].into(),
             };
 
             assert_eq!(
@@ -244,7 +564,7 @@ mod tests {
             let read_context = WriteContext {
                 byte: Some(b'a'),
                 pos: 10,
-                latest_bytes: vec![b'a', b'b', b'c'],
+                latest_bytes: vec![b'a', b'b', b'c'].into(),
             };
 
             assert_eq!( read_context.to_string(),"write context: byte `97` (char: `a`), output pos 10, latest output bytes dump: [97, 98, 99] (UTF-8 string: `abc`)");
@@ -255,7 +575,7 @@ mod tests {
             let read_context = WriteContext {
                 byte: None,
                 pos: 10,
-                latest_bytes: vec![b'a', b'b', b'c'],
+                latest_bytes: vec![b'a', b'b', b'c'].into(),
             };
 
             assert_eq!(read_context.to_string(), "write context: output pos 10, latest output bytes dump: [97, 98, 99] (UTF-8 string: `abc`)");
@@ -266,7 +586,7 @@ mod tests {
             let read_context = WriteContext {
                 byte: None,
                 pos: 10,
-                latest_bytes: vec![b'\xFF', b'\xFE'],
+                latest_bytes: vec![b'\xFF', b'\xFE'].into(),
             };
 
             assert_eq!(
@@ -0,0 +1,509 @@
+//! The parser for inputs that arrive in chunks.
+//!
+//! [`IncrementalParser`] runs the sync parser (and its state machine in
+//! [`stack`](super::stack)) on an in-memory buffer with the input received
+//! so far. Every token is parsed from a checkpoint. When the parser needs
+//! bytes that have not arrived yet, it goes back to the checkpoint and waits
+//! for more input to parse the token again, once the bytes that can complete
+//! it have arrived. Only the current token is kept in memory, plus the
+//! latest bytes to give context in errors.
+//!
+//! It's the base for the async parser and the push parser, so that they give
+//! the same output and errors as the sync parser.
+use std::{
+    cmp,
+    io::{self, BufRead, Read},
+};
+
+use super::{
+    error::Error,
+    options::ParserOptions,
+    stack::{Stack, State},
+    BencodeParser,
+};
+use crate::rw::{
+    byte_reader::{ByteReader, Checkpoint as ReaderCheckpoint},
+    error::Error as RwError,
+    writer::Writer,
+};
+
+/// The result of parsing one token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Step {
+    /// The token is not complete. The parser is where it was before it.
+    NeedMoreInput,
+
+    /// The token has been parsed.
+    Parsed,
+
+    /// A top-level value has been completed with the token, or with the end
+    /// of the input.
+    CompletedValue,
+
+    /// The input has ended and there is nothing else to parse.
+    Ended,
+}
+
+pub(super) struct IncrementalParser {
+    pub(super) parser: BencodeParser<InputBuffer>,
+    pub(super) output: OutputBuffer,
+
+    /// Number of pending input bytes needed before trying to parse the
+    /// current token again.
+    required_input: usize,
+
+    /// The integer or string length of the current token, when it has not
+    /// ended yet.
+    unfinished_number: Option<UnfinishedNumber>,
+
+    /// Input position where the current top-level value began.
+    value_start: u64,
+}
+
+impl IncrementalParser {
+    pub(super) fn new(options: ParserOptions) -> Self {
+        let capture_capacity = options
+            .capture_mode
+            .capture_capacity(options.capture_window_size);
+
+        let input = InputBuffer::new(options.input_buffer_size, capture_capacity);
+
+        let byte_reader = ByteReader::from_buf_read_with_capacity(capture_capacity, input);
+
+        let parser = BencodeParser::from_byte_reader(byte_reader, options);
+
+        Self {
+            output: OutputBuffer::new(parser.output_capture_capacity()),
+            parser,
+            required_input: 0,
+            unfinished_number: None,
+            value_start: 0,
+        }
+    }
+
+    pub(super) fn input(&self) -> &InputBuffer {
+        self.parser.byte_reader.get_ref()
+    }
+
+    pub(super) fn input_mut(&mut self) -> &mut InputBuffer {
+        self.parser.byte_reader.get_mut()
+    }
+
+    /// Number of input bytes parsed, including all the bytes of the latest
+    /// parsed token.
+    pub(super) fn input_position(&self) -> u64 {
+        self.parser.input_position()
+    }
+
+    /// Input position where the latest top-level value began.
+    pub(super) fn value_start(&self) -> u64 {
+        self.value_start
+    }
+
+    /// Number of pending input bytes needed before the current token can be
+    /// parsed.
+    #[cfg(any(test, feature = "tokio"))]
+    pub(super) fn required_input(&self) -> usize {
+        self.required_input
+    }
+
+    /// It parses the next token with the input received so far.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the input is invalid Bencode, like the sync
+    /// parser.
+    pub(super) fn parse_next_token(&mut self) -> Result<Step, Error> {
+        let input = self.input();
+
+        if !input.ended
+            && (input.pending().len() < self.required_input || self.is_number_unfinished())
+        {
+            return Ok(Step::NeedMoreInput);
+        }
+
+        let checkpoint = self.checkpoint();
+        let token_start = self.input_position();
+
+        match self.parser.parse_next_token(&mut self.output) {
+            Ok(ended) => {
+                self.required_input = 0;
+                self.unfinished_number = None;
+
+                // Skipped bytes, like ignored line breaks, don't begin values
+                if checkpoint.stack.depth() == 0
+                    && self.output.bytes.len() > checkpoint.output_length
+                {
+                    self.value_start = token_start;
+                }
+
+                // Values that were not closed when the input ended are
+                // completed when recovering from errors
+                let completed_value = self.output.bytes.len() > checkpoint.output_length
+                    && self.parser.stack.peek() == Ok(State::Initial);
+
+                if completed_value {
+                    Ok(Step::CompletedValue)
+                } else if ended {
+                    Ok(Step::Ended)
+                } else {
+                    Ok(Step::Parsed)
+                }
+            }
+            Err(Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => {
+                self.restore(checkpoint);
+                self.required_input = self.required_input_for_pending_token();
+                let first_byte = self.pending_token().next();
+                self.unfinished_number = first_byte.and_then(UnfinishedNumber::new);
+                self.is_number_unfinished();
+                Ok(Step::NeedMoreInput)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// It drops the input bytes that are not needed anymore.
+    pub(super) fn compact(&mut self) {
+        self.input_mut().compact();
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            reader: self.parser.byte_reader.checkpoint(),
+            input_position: self.input().position,
+            output_length: self.output.bytes.len(),
+            stack: self.parser.stack.clone(),
+            num_processed_tokens: self.parser.num_processed_tokens,
+            num_diagnostics: self.parser.diagnostics.len(),
+            unreadable_keys: self.parser.unreadable_keys,
+        }
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        self.input_mut().position = checkpoint.input_position;
+
+        let latest_bytes = self.input().latest_consumed_bytes().to_vec();
+        self.parser
+            .byte_reader
+            .restore(checkpoint.reader, &latest_bytes);
+
+        self.output.bytes.truncate(checkpoint.output_length);
+
+        self.parser.stack = checkpoint.stack;
+        self.parser.num_processed_tokens = checkpoint.num_processed_tokens;
+        self.parser.diagnostics.truncate(checkpoint.num_diagnostics);
+        self.parser.unreadable_keys = checkpoint.unreadable_keys;
+    }
+
+    /// The bytes received of the pending token, including the byte the
+    /// parser has already peeked.
+    fn pending_token(&self) -> impl Iterator<Item = u8> + '_ {
+        self.parser
+            .byte_reader
+            .peeked_byte()
+            .into_iter()
+            .chain(self.input().pending().iter().copied())
+    }
+
+    /// It returns true if the integer or string length of the pending token
+    /// has not ended yet with the bytes received. Only the bytes received
+    /// since the previous call are checked.
+    fn is_number_unfinished(&mut self) -> bool {
+        let Some(mut number) = self.unfinished_number else {
+            return false;
+        };
+
+        let max_integer_length = self.parser.options.limits.max_integer_length;
+
+        let unfinished = self
+            .pending_token()
+            .skip(number.checked)
+            .all(|byte| number.check(byte, max_integer_length));
+
+        self.unfinished_number = unfinished.then_some(number);
+
+        unfinished
+    }
+
+    /// Number of pending bytes needed to parse the pending token again.
+    ///
+    /// For strings whose length has already arrived it's the whole string,
+    /// so that long strings are not parsed again every time a few bytes
+    /// arrive. Otherwise, it's one more byte than the pending ones.
+    fn required_input_for_pending_token(&self) -> usize {
+        let pending = self.input().pending();
+
+        let one_more_byte = pending.len() + 1;
+
+        if self.parser.byte_reader.peeked_byte().is_some() {
+            return one_more_byte;
+        }
+
+        let Some(separator) = pending.iter().position(|byte| !byte.is_ascii_digit()) else {
+            return one_more_byte;
+        };
+
+        if separator == 0 || pending[separator] != b':' {
+            return one_more_byte;
+        }
+
+        let string_length = pending[..separator]
+            .iter()
+            .try_fold(0usize, |length, digit| {
+                length
+                    .checked_mul(10)?
+                    .checked_add(usize::from(digit - b'0'))
+            })
+            .and_then(|length| length.checked_add(separator + 1));
+
+        string_length.map_or(one_more_byte, |length| cmp::max(length, one_more_byte))
+    }
+}
+
+/// An integer or a string length that has not ended yet.
+///
+/// The bytes that arrive are checked one by one, so that the token is not
+/// parsed again from the beginning every time a few digits arrive. It's
+/// parsed again when the number ends, or when the parser would fail with the
+/// byte checked.
+#[derive(Debug, Clone, Copy)]
+struct UnfinishedNumber {
+    /// True for integers, false for string lengths.
+    is_integer: bool,
+
+    /// Number of bytes of the token checked.
+    checked: usize,
+
+    /// Number of digits checked.
+    digits: usize,
+
+    /// True if the only digit of the integer is a zero.
+    leading_zero: bool,
+
+    /// The string length so far.
+    string_length: usize,
+}
+
+impl UnfinishedNumber {
+    /// It returns the number that begins with the first byte of a token, or
+    /// `None` if the token is not an integer or a string.
+    fn new(first_byte: u8) -> Option<Self> {
+        let is_integer = match first_byte {
+            b'i' => true,
+            b'0'..=b'9' => false,
+            _ => return None,
+        };
+
+        Some(Self {
+            is_integer,
+            checked: 0,
+            digits: 0,
+            leading_zero: false,
+            string_length: 0,
+        })
+    }
+
+    /// It checks the next byte of the token. It returns false if the byte
+    /// ends the number, or if the parser would fail with it.
+    fn check(&mut self, byte: u8, max_integer_length: Option<usize>) -> bool {
+        let position = self.checked;
+        self.checked += 1;
+
+        if !self.is_integer {
+            let string_length = self
+                .string_length
+                .checked_mul(10)
+                .and_then(|length| length.checked_add(usize::from(byte.wrapping_sub(b'0'))));
+
+            return match string_length {
+                Some(length) if byte.is_ascii_digit() => {
+                    self.string_length = length;
+                    true
+                }
+                _ => false,
+            };
+        }
+
+        match byte {
+            b'i' => position == 0,
+            b'-' => position == 1,
+            b'0'..=b'9' if !self.leading_zero => {
+                self.leading_zero = self.digits == 0 && byte == b'0';
+                self.digits += 1;
+                max_integer_length.is_none_or(|max| self.digits <= max)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The state of the parser before a token, to parse it again when more input
+/// is available.
+struct Checkpoint {
+    reader: ReaderCheckpoint,
+    input_position: usize,
+    output_length: usize,
+    stack: Stack,
+    num_processed_tokens: u64,
+    num_diagnostics: usize,
+    unreadable_keys: u64,
+}
+
+/// The input bytes received that have not been parsed yet, plus the latest
+/// parsed ones to capture them again when going back to a checkpoint.
+///
+/// When all the bytes received have been read and the input has not ended,
+/// reading fails with [`io::ErrorKind::WouldBlock`].
+pub(super) struct InputBuffer {
+    pub(super) bytes: Vec<u8>,
+
+    /// Position of the next byte to read in `bytes`.
+    position: usize,
+
+    /// True when the input has ended.
+    pub(super) ended: bool,
+
+    /// Minimum number of bytes to drop when compacting the buffer. It's also
+    /// the minimum number of bytes to read each time from async inputs.
+    pub(super) chunk_size: usize,
+
+    /// Number of bytes already read that are kept.
+    history_size: usize,
+}
+
+impl InputBuffer {
+    fn new(chunk_size: usize, history_size: usize) -> Self {
+        Self {
+            bytes: Vec::new(),
+            position: 0,
+            ended: false,
+            chunk_size: cmp::max(chunk_size, 1),
+            history_size,
+        }
+    }
+
+    /// The bytes received that have not been read yet.
+    pub(super) fn pending(&self) -> &[u8] {
+        &self.bytes[self.position..]
+    }
+
+    /// The latest bytes read that are kept.
+    fn latest_consumed_bytes(&self) -> &[u8] {
+        &self.bytes[self.position.saturating_sub(self.history_size)..self.position]
+    }
+
+    /// It drops the bytes already read that don't have to be kept. It only
+    /// moves the bytes when there are many of them to drop.
+    fn compact(&mut self) {
+        let start = self.position.saturating_sub(self.history_size);
+
+        if start >= self.chunk_size {
+            self.bytes.drain(..start);
+            self.position -= start;
+        }
+    }
+}
+
+impl Read for InputBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+
+        let length = cmp::min(available.len(), buf.len());
+        buf[..length].copy_from_slice(&available[..length]);
+
+        self.consume(length);
+
+        Ok(length)
+    }
+}
+
+impl BufRead for InputBuffer {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position == self.bytes.len() && !self.ended {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+
+        Ok(self.pending())
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = cmp::min(self.position + amount, self.bytes.len());
+    }
+}
+
+/// The JSON output that has not been handed over yet, plus the latest bytes
+/// handed over to give context in errors.
+pub(super) struct OutputBuffer {
+    bytes: Vec<u8>,
+
+    /// Number of bytes at the beginning of `bytes` that have already been
+    /// handed over.
+    written: usize,
+
+    /// Number of bytes handed over and dropped from `bytes`.
+    dropped: u64,
+
+    /// Number of latest bytes to give context in errors.
+    capture_capacity: usize,
+}
+
+impl OutputBuffer {
+    pub(super) fn new(capture_capacity: usize) -> Self {
+        Self {
+            bytes: Vec::new(),
+            written: 0,
+            dropped: 0,
+            capture_capacity,
+        }
+    }
+
+    /// The bytes that have not been handed over yet.
+    pub(super) fn pending(&self) -> &[u8] {
+        &self.bytes[self.written..]
+    }
+
+    /// It hands over the first `amount` pending bytes. The bytes are dropped
+    /// when all of them have been handed over.
+    pub(super) fn consume(&mut self, amount: usize) {
+        self.written = cmp::min(self.written + amount, self.bytes.len());
+
+        if self.written == self.bytes.len() {
+            self.mark_as_written();
+        }
+    }
+
+    /// It drops the bytes handed over, except the ones kept to give context
+    /// in errors.
+    pub(super) fn mark_as_written(&mut self) {
+        let start = self.bytes.len().saturating_sub(self.capture_capacity);
+
+        self.bytes.drain(..start);
+        self.dropped += start as u64;
+        self.written = self.bytes.len();
+    }
+}
+
+impl Writer for OutputBuffer {
+    fn write_byte(&mut self, byte: u8) -> Result<(), RwError> {
+        self.bytes.push(byte);
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), RwError> {
+        self.bytes.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn write_str(&mut self, value: &str) -> Result<(), RwError> {
+        self.write_bytes(value.as_bytes())
+    }
+
+    fn output_byte_counter(&self) -> u64 {
+        self.dropped + self.bytes.len() as u64
+    }
+
+    fn captured_bytes(&self) -> Vec<u8> {
+        let start = self.bytes.len().saturating_sub(self.capture_capacity);
+        self.bytes[start..].to_vec()
+    }
+}
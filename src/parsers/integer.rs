@@ -1,13 +1,14 @@
 //! Bencoded integer parser.
 //!
 //! It reads bencoded bytes from the input and writes JSON bytes to the output.
-use std::io::{self, Read};
+use std::io::{self, BufRead};
 
 use crate::rw::{byte_reader::ByteReader, writer::Writer};
 
 use super::{
     error::{Error, ReadContext, WriteContext},
-    BENCODE_END_INTEGER,
+    options::ParserOptions,
+    scan, BENCODE_END_INTEGER,
 };
 
 /// The current state parsing the integer.
@@ -22,18 +23,45 @@ enum StateExpecting {
 
 /// It parses an integer bencoded value.
 ///
+/// The digits are written to the output when the whole integer has been
+/// parsed, following the integer policy in the parser options.
+///
 /// # Errors
 ///
-/// Will return an error if it can't read from the input or write to the
-/// output.
+/// Will return an error if:
 ///
-/// # Panics
+/// - It can't read from the input or write to the output.
+/// - The input is not a valid bencoded integer.
+/// - The integer is longer than the configured limit.
+/// - We reach the end of the input without completing the integer (without
+///   reaching the end of the integer `e`).
+pub fn parse<R: BufRead, W: Writer>(
+    reader: &mut ByteReader<R>,
+    writer: &mut W,
+    options: &ParserOptions,
+) -> Result<(), Error> {
+    parse_digits(reader, writer, options).map(|_digits| ())
+}
+
+/// It parses an integer bencoded value like [`parse`], and returns its
+/// digits as they are in the input, including the sign.
 ///
-/// Will panic if we reach the end of the input without completing the integer
-/// (without reaching the end of the integer `e`).
-pub fn parse<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &mut W) -> Result<(), Error> {
+/// # Errors
+///
+/// Will return an error in the same cases as [`parse`].
+pub(super) fn parse_digits<R: BufRead, W: Writer>(
+    reader: &mut ByteReader<R>,
+    writer: &mut W,
+    options: &ParserOptions,
+) -> Result<String, Error> {
+    if let Some(digits) = scan::integer(reader, options)? {
+        write_integer(&digits, writer, options)?;
+        return Ok(digits);
+    }
+
     let mut state = StateExpecting::Start;
     let mut first_digit_is_zero = false;
+    let mut digits = String::new();
 
     loop {
         let byte = next_byte(reader, writer)?;
@@ -47,11 +75,11 @@ pub fn parse<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &mut W) ->
             }
             StateExpecting::DigitOrSign => {
                 if char == '-' {
-                    writer.write_byte(byte)?;
+                    digits.push(char);
 
                     StateExpecting::DigitAfterSign
                 } else if char.is_ascii_digit() {
-                    writer.write_byte(byte)?;
+                    add_digit(&mut digits, char, reader, writer, options)?;
 
                     if char == '0' {
                         first_digit_is_zero = true;
@@ -63,19 +91,19 @@ pub fn parse<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &mut W) ->
                         ReadContext {
                             byte: Some(byte),
                             pos: reader.input_byte_counter(),
-                            latest_bytes: reader.captured_bytes(),
+                            latest_bytes: reader.captured_bytes().into(),
                         },
                         WriteContext {
                             byte: Some(byte),
                             pos: writer.output_byte_counter(),
-                            latest_bytes: writer.captured_bytes(),
+                            latest_bytes: writer.captured_bytes().into(),
                         },
                     ));
                 }
             }
             StateExpecting::DigitAfterSign => {
                 if char.is_ascii_digit() {
-                    writer.write_byte(byte)?;
+                    add_digit(&mut digits, char, reader, writer, options)?;
 
                     if char == '0' {
                         first_digit_is_zero = true;
@@ -87,49 +115,50 @@ pub fn parse<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &mut W) ->
                         ReadContext {
                             byte: Some(byte),
                             pos: reader.input_byte_counter(),
-                            latest_bytes: reader.captured_bytes(),
+                            latest_bytes: reader.captured_bytes().into(),
                         },
                         WriteContext {
                             byte: Some(byte),
                             pos: writer.output_byte_counter(),
-                            latest_bytes: writer.captured_bytes(),
+                            latest_bytes: writer.captured_bytes().into(),
                         },
                     ));
                 }
             }
             StateExpecting::DigitOrEnd => {
                 if char.is_ascii_digit() {
-                    writer.write_byte(byte)?;
-
                     if char == '0' && first_digit_is_zero {
                         return Err(Error::LeadingZerosInIntegersNotAllowed(
                             ReadContext {
                                 byte: Some(byte),
                                 pos: reader.input_byte_counter(),
-                                latest_bytes: reader.captured_bytes(),
+                                latest_bytes: reader.captured_bytes().into(),
                             },
                             WriteContext {
                                 byte: Some(byte),
                                 pos: writer.output_byte_counter(),
-                                latest_bytes: writer.captured_bytes(),
+                                latest_bytes: writer.captured_bytes().into(),
                             },
                         ));
                     }
 
+                    add_digit(&mut digits, char, reader, writer, options)?;
+
                     StateExpecting::DigitOrEnd
                 } else if byte == BENCODE_END_INTEGER {
-                    return Ok(());
+                    write_integer(&digits, writer, options)?;
+                    return Ok(digits);
                 } else {
                     return Err(Error::UnexpectedByteParsingInteger(
                         ReadContext {
                             byte: Some(byte),
                             pos: reader.input_byte_counter(),
-                            latest_bytes: reader.captured_bytes(),
+                            latest_bytes: reader.captured_bytes().into(),
                         },
                         WriteContext {
                             byte: Some(byte),
                             pos: writer.output_byte_counter(),
-                            latest_bytes: writer.captured_bytes(),
+                            latest_bytes: writer.captured_bytes().into(),
                         },
                     ));
                 }
@@ -138,12 +167,70 @@ pub fn parse<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &mut W) ->
     }
 }
 
+/// It adds a new digit to the integer.
+///
+/// # Errors
+///
+/// Will return an error if the integer is longer than the configured limit.
+fn add_digit<R: BufRead, W: Writer>(
+    digits: &mut String,
+    digit: char,
+    reader: &ByteReader<R>,
+    writer: &W,
+    options: &ParserOptions,
+) -> Result<(), Error> {
+    digits.push(digit);
+
+    if let Some(max_integer_length) = options.limits.max_integer_length {
+        let number_of_digits = digits.trim_start_matches('-').len();
+
+        if number_of_digits > max_integer_length {
+            return Err(Error::MaxIntegerLengthExceeded(
+                max_integer_length,
+                ReadContext {
+                    byte: Some(digit as u8),
+                    pos: reader.input_byte_counter(),
+                    latest_bytes: reader.captured_bytes().into(),
+                },
+                WriteContext {
+                    byte: None,
+                    pos: writer.output_byte_counter(),
+                    latest_bytes: writer.captured_bytes().into(),
+                },
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// It writes the parsed integer to the output following the integer policy.
+///
+/// # Errors
+///
+/// Will return an error if it can't write to the output.
+fn write_integer<W: Writer>(
+    digits: &str,
+    writer: &mut W,
+    options: &ParserOptions,
+) -> Result<(), Error> {
+    if options.integer_policy.quote(digits) {
+        writer.write_byte(b'"')?;
+        writer.write_str(digits)?;
+        writer.write_byte(b'"')?;
+    } else {
+        writer.write_str(digits)?;
+    }
+
+    Ok(())
+}
+
 /// It reads the next byte from the input.
 ///
 /// # Errors
 ///
 /// Will return an error if the end of input was reached.
-fn next_byte<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &W) -> Result<u8, Error> {
+fn next_byte<R: BufRead, W: Writer>(reader: &mut ByteReader<R>, writer: &W) -> Result<u8, Error> {
     match reader.read_byte() {
         Ok(byte) => Ok(byte),
         Err(err) => {
@@ -152,12 +239,12 @@ fn next_byte<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &W) -> Resu
                     ReadContext {
                         byte: None,
                         pos: reader.input_byte_counter(),
-                        latest_bytes: reader.captured_bytes(),
+                        latest_bytes: reader.captured_bytes().into(),
                     },
                     WriteContext {
                         byte: None,
                         pos: writer.output_byte_counter(),
-                        latest_bytes: writer.captured_bytes(),
+                        latest_bytes: writer.captured_bytes().into(),
                     },
                 ));
             }
@@ -169,7 +256,7 @@ fn next_byte<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &W) -> Resu
 #[cfg(test)]
 mod tests {
     use crate::{
-        parsers::{error::Error, integer::parse},
+        parsers::{error::Error, integer::parse, options::ParserOptions},
         rw::{byte_reader::ByteReader, string_writer::StringWriter},
     };
 
@@ -195,7 +282,7 @@ mod tests {
 
         let mut writer = StringWriter::new(output);
 
-        parse(&mut reader, &mut writer)
+        parse(&mut reader, &mut writer, &ParserOptions::default())
     }
 
     mod for_helpers {
@@ -239,6 +326,7 @@ mod tests {
             parsers::{
                 error::Error,
                 integer::{parse, tests::try_bencode_to_json},
+                options::ParserOptions,
             },
             rw::{byte_reader::ByteReader, string_writer::StringWriter},
         };
@@ -353,7 +441,7 @@ mod tests {
             let mut output = String::new();
             let mut writer = StringWriter::new(&mut output);
 
-            let result = parse(&mut reader, &mut writer);
+            let result = parse(&mut reader, &mut writer, &ParserOptions::default());
 
             assert!(matches!(result, Err(Error::Io(_))));
         }
@@ -0,0 +1,320 @@
+//! A reader of the JSON output.
+//!
+//! [`BencodeToJsonReader`] implements [`Read`], so the JSON output can be
+//! handed over to code that pulls bytes from a reader, like an HTTP body or a
+//! compressor, instead of writing it to a writer:
+//!
+//! ```rust
+//! use std::io::Read;
+//!
+//! use torrust_bencode2json::parsers::json_reader::BencodeToJsonReader;
+//!
+//! let mut json = String::new();
+//!
+//! BencodeToJsonReader::new(&b"d4:spaml1:a1:bee"[..])
+//!     .read_to_string(&mut json)
+//!     .unwrap();
+//!
+//! assert_eq!(json, r#"{"spam":["a","b"]}"#);
+//! ```
+//!
+//! The input is converted as the output is read, one token at a time, in the
+//! same thread. Only the JSON of the current token is kept in memory.
+use std::io::{self, BufReader, Read};
+
+use super::{error::Error, incremental::OutputBuffer, options::ParserOptions, BencodeParser};
+
+/// A reader of the JSON output for a bencoded input.
+///
+/// When the input is invalid Bencode, the JSON output before the error is
+/// read first, and then reading fails with an [`io::Error`] that wraps the
+/// parser [`Error`]. The parser error can be retrieved with
+/// [`io::Error::get_ref`] and downcasting it, or with
+/// [`BencodeToJsonReader::error`].
+pub struct BencodeToJsonReader<R: Read> {
+    parser: BencodeParser<BufReader<R>>,
+    output: OutputBuffer,
+
+    /// True when the input has been parsed, or parsing failed.
+    ended: bool,
+
+    error: Option<Error>,
+}
+
+impl<R: Read> BencodeToJsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParserOptions::default())
+    }
+
+    /// It creates a reader with custom parser options.
+    ///
+    /// See [`ParserBuilder::build_json_reader`] for a more convenient way to
+    /// set only some of the options.
+    ///
+    /// [`ParserBuilder::build_json_reader`]: super::options::ParserBuilder::build_json_reader
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
+        let parser = BencodeParser::with_options(reader, options);
+
+        Self {
+            output: OutputBuffer::new(parser.output_capture_capacity()),
+            parser,
+            ended: false,
+            error: None,
+        }
+    }
+
+    /// It returns the parser options.
+    #[must_use]
+    pub fn options(&self) -> &ParserOptions {
+        self.parser.options()
+    }
+
+    /// It returns the errors the parser recovered from.
+    #[must_use]
+    pub fn diagnostics(&self) -> &[Error] {
+        self.parser.diagnostics()
+    }
+
+    /// It returns the errors the parser recovered from, leaving the list
+    /// empty.
+    pub fn take_diagnostics(&mut self) -> Vec<Error> {
+        self.parser.take_diagnostics()
+    }
+
+    /// It returns the error that stopped the conversion, if any.
+    #[must_use]
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+}
+
+impl<R: Read> Read for BencodeToJsonReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output.pending().is_empty() && !self.ended {
+            match self.parser.parse_next_token(&mut self.output) {
+                Ok(ended) => self.ended = ended,
+                Err(err) => {
+                    self.ended = true;
+                    self.error = Some(err);
+                }
+            }
+        }
+
+        let pending = self.output.pending();
+
+        if pending.is_empty() {
+            return match &self.error {
+                Some(err) => Err(to_io_error(err)),
+                None => Ok(0),
+            };
+        }
+
+        let length = pending.len().min(buf.len());
+        buf[..length].copy_from_slice(&pending[..length]);
+
+        self.output.consume(length);
+
+        Ok(length)
+    }
+}
+
+/// It wraps a parser error into an I/O error. I/O errors keep their kind,
+/// and the rest are [`io::ErrorKind::InvalidData`].
+fn to_io_error(err: &Error) -> io::Error {
+    let kind = match err {
+        Error::Io(io_err) => io_err.kind(),
+        _ => io::ErrorKind::InvalidData,
+    };
+
+    io::Error::new(kind, err.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+
+    use crate::parsers::{error::Error, options::ParserOptions, BencodeParser};
+
+    use super::BencodeToJsonReader;
+
+    /// The JSON output and the error message.
+    type Outcome = (String, Option<String>);
+
+    fn parse(input: &[u8], options: &ParserOptions) -> Outcome {
+        let mut parser = BencodeParser::with_options(input, options.clone());
+
+        let mut output = Vec::new();
+        let result = parser.write_bytes(&mut output);
+
+        (
+            String::from_utf8(output).unwrap(),
+            result.err().map(|err| err.to_string()),
+        )
+    }
+
+    /// It reads the output into a buffer of `buffer_size` bytes each time.
+    fn read(input: &[u8], options: &ParserOptions, buffer_size: usize) -> Outcome {
+        let mut reader = BencodeToJsonReader::with_options(input, options.clone());
+
+        let mut output = Vec::new();
+        let mut buffer = vec![0; buffer_size];
+
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => return (String::from_utf8(output).unwrap(), None),
+                Ok(length) => output.extend_from_slice(&buffer[..length]),
+                Err(err) => {
+                    let parser_error = err.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+
+                    assert_eq!(
+                        Some(parser_error.to_string()),
+                        reader.error().map(ToString::to_string)
+                    );
+
+                    return (
+                        String::from_utf8(output).unwrap(),
+                        Some(parser_error.to_string()),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_read_the_json_output() {
+        let mut json = String::new();
+
+        BencodeToJsonReader::new(&b"d4:spaml1:ai42eee"[..])
+            .read_to_string(&mut json)
+            .unwrap();
+
+        assert_eq!(json, r#"{"spam":["a",42]}"#);
+    }
+
+    #[test]
+    fn it_should_be_read_in_small_chunks() {
+        let input = b"d4:spaml11:hello worldi-1234567890ee3:eggd0:lee";
+        let options = ParserOptions::default();
+
+        for buffer_size in 1..8 {
+            assert_eq!(read(input, &options, buffer_size), parse(input, &options));
+        }
+    }
+
+    #[test]
+    fn it_should_return_the_output_before_an_error_and_then_the_error() {
+        let input = b"l1:ai00ee";
+        let options = ParserOptions::default();
+
+        let outcome = read(input, &options, 1);
+
+        assert_eq!(outcome, parse(input, &options));
+        assert_eq!(outcome.0, r#"["a","#);
+        assert!(outcome.1.is_some());
+    }
+
+    #[test]
+    fn it_should_keep_returning_the_error() {
+        let mut reader = BencodeToJsonReader::new(&b"x"[..]);
+        let mut buffer = [0; 8];
+
+        assert_eq!(
+            reader.read(&mut buffer).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            reader.read(&mut buffer).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn it_should_keep_the_kind_of_input_errors() {
+        struct FailingReader;
+
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+            }
+        }
+
+        let mut reader = BencodeToJsonReader::new(FailingReader);
+
+        assert_eq!(
+            reader.read(&mut [0; 8]).unwrap_err().kind(),
+            io::ErrorKind::ConnectionReset
+        );
+    }
+
+    #[test]
+    fn it_should_pull_the_input_as_the_output_is_read() {
+        struct CountingReader<'a> {
+            bytes: &'a [u8],
+            read: usize,
+        }
+
+        impl Read for CountingReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let length = self.bytes.len().min(buf.len()).min(1);
+                buf[..length].copy_from_slice(&self.bytes[..length]);
+                self.bytes = &self.bytes[length..];
+                self.read += length;
+                Ok(length)
+            }
+        }
+
+        let input = CountingReader {
+            bytes: b"l1:a1:b1:c1:d1:ee",
+            read: 0,
+        };
+
+        let mut reader = BencodeToJsonReader::new(input);
+
+        let mut buffer = [0; 1];
+        reader.read_exact(&mut buffer).unwrap();
+
+        assert_eq!(&buffer, b"[");
+        assert!(reader.parser.byte_reader.get_ref().get_ref().read < 3);
+    }
+
+    mod it_should_give_the_same_output_and_errors_as_the_writer {
+        use proptest::prelude::*;
+
+        use super::{parse, read};
+        use crate::parsers::options::{ErrorHandling, Limits, ParserOptions};
+
+        const BENCODE_ALPHABET: &[u8] = b"ilde0123456789:-\nx";
+
+        proptest! {
+            #[test]
+            fn on_arbitrary_sequences_of_bencode_bytes(
+                input in proptest::collection::vec(proptest::sample::select(BENCODE_ALPHABET), 0..256),
+                buffer_size in 1..16usize,
+            ) {
+                let options = ParserOptions::default();
+
+                prop_assert_eq!(read(&input, &options, buffer_size), parse(&input, &options));
+            }
+
+            #[test]
+            fn on_arbitrary_sequences_of_bencode_bytes_when_recovering_with_limits(
+                input in proptest::collection::vec(proptest::sample::select(BENCODE_ALPHABET), 0..256),
+                buffer_size in 1..16usize,
+            ) {
+                let options = ParserOptions {
+                    input_buffer_size: 4,
+                    capture_window_size: 8,
+                    error_handling: ErrorHandling::Recover,
+                    limits: Limits {
+                        max_depth: Some(4),
+                        max_integer_length: Some(3),
+                        max_string_length: Some(5),
+                    },
+                    ..ParserOptions::default()
+                };
+
+                prop_assert_eq!(read(&input, &options, buffer_size), parse(&input, &options));
+            }
+        }
+    }
+}
@@ -3,19 +3,36 @@
 //!
 //! ``BencodeParser`` is the main parser. It is generic over the type of the
 //! input buffer.
+//!
+//! With the `tokio` feature, `async_parser::AsyncBencodeParser` converts
+//! from async inputs to async outputs.
+//!
+//! [`json_reader::BencodeToJsonReader`] converts the input as the JSON output
+//! is read.
+#[cfg(feature = "tokio")]
+pub mod async_parser;
 pub mod error;
+mod incremental;
 pub mod integer;
+pub mod json_reader;
+pub mod options;
+pub mod push;
+pub mod recovery;
+mod scan;
 pub mod stack;
 pub mod string;
+pub mod token;
 
 use std::{
     fmt::Write as FmtWrite,
-    io::{self, Read, Write as IoWrite},
+    io::{self, BufRead, BufReader, Read, Seek, Write as IoWrite},
 };
 
 use derive_more::derive::Display;
 use error::{ReadContext, WriteContext};
+use options::{CaptureMode, ErrorHandling, NewlineHandling, ParserBuilder, ParserOptions};
 use stack::{Stack, State};
+use token::{Token, TokenKind};
 
 use crate::rw::{
     byte_reader::ByteReader, byte_writer::ByteWriter, string_writer::StringWriter, writer::Writer,
@@ -28,7 +45,7 @@ const BENCODE_BEGIN_LIST: u8 = b'l';
 const BENCODE_BEGIN_DICT: u8 = b'd';
 const BENCODE_END_LIST_OR_DICT: u8 = b'e';
 
-#[derive(Debug, PartialEq, Display)]
+#[derive(Debug, PartialEq, Clone, Copy, Display)]
 pub enum BencodeType {
     Integer,
     String,
@@ -36,13 +53,69 @@ pub enum BencodeType {
     Dict,
 }
 
-pub struct BencodeParser<R: Read> {
+/// Statistics of a conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of JSON bytes written to the output.
+    pub bytes_written: u64,
+
+    /// Number of bencoded tokens processed.
+    pub tokens_processed: u64,
+}
+
+pub struct BencodeParser<R: BufRead> {
     byte_reader: ByteReader<R>,
     num_processed_tokens: u64,
     stack: Stack,
+    options: ParserOptions,
+    diagnostics: Vec<error::Error>,
+
+    /// The number of dictionary keys replaced with placeholders while
+    /// recovering, to number them.
+    unreadable_keys: u64,
+}
+
+impl<R: Read> BencodeParser<BufReader<R>> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParserOptions::default())
+    }
+
+    /// It creates a parser with custom options.
+    ///
+    /// See [`BencodeParser::builder`] for a more convenient way to set only
+    /// some of the options.
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
+        // An empty buffer would read nothing from the input
+        let byte_reader = ByteReader::with_capacity(
+            options.input_buffer_size.max(1),
+            options
+                .capture_mode
+                .capture_capacity(options.capture_window_size),
+            reader,
+        );
+
+        BencodeParser::from_byte_reader(byte_reader, options)
+    }
+
+    /// It returns a builder to configure the parser for the given input.
+    pub fn builder(reader: R) -> ParserBuilder<R> {
+        ParserBuilder::new(reader)
+    }
+}
+
+impl<R: BufRead + Seek> BencodeParser<R> {
+    /// It allows re-reading the latest input bytes when there is an error,
+    /// for the [`CaptureMode::Lazy`] capture mode.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't get the current input position.
+    pub fn enable_rereading(&mut self) -> Result<(), io::Error> {
+        self.byte_reader.enable_rereading()
+    }
 }
 
-impl<R: Read> BencodeParser<R> {
+impl<R: BufRead> BencodeParser<R> {
     const JSON_ARRAY_BEGIN: u8 = b'[';
     const JSON_ARRAY_ITEMS_SEPARATOR: u8 = b',';
     const JSON_ARRAY_END: u8 = b']';
@@ -52,46 +125,241 @@ impl<R: Read> BencodeParser<R> {
     const JSON_OBJ_FIELD_KEY_VALUE_SEPARATOR: u8 = b':';
     const JSON_OBJ_END: u8 = b'}';
 
-    pub fn new(reader: R) -> Self {
+    /// It creates a parser that reads directly from the buffer of an input
+    /// that is already buffered, like a slice (`&[u8]`) or a `BufReader`.
+    ///
+    /// It's faster than [`BencodeParser::new`] for those inputs because the
+    /// input is not copied into a second buffer.
+    pub fn from_buf_read(reader: R) -> Self {
+        Self::from_buf_read_with_options(reader, ParserOptions::default())
+    }
+
+    /// It creates a parser that reads directly from the buffer of an input
+    /// that is already buffered, with custom options.
+    ///
+    /// The `input_buffer_size` option is ignored because the input buffer is
+    /// not used.
+    pub fn from_buf_read_with_options(reader: R, options: ParserOptions) -> Self {
+        let byte_reader = ByteReader::from_buf_read_with_capacity(
+            options
+                .capture_mode
+                .capture_capacity(options.capture_window_size),
+            reader,
+        );
+
+        Self::from_byte_reader(byte_reader, options)
+    }
+
+    fn from_byte_reader(byte_reader: ByteReader<R>, options: ParserOptions) -> Self {
         BencodeParser {
-            byte_reader: ByteReader::new(reader),
+            byte_reader,
             num_processed_tokens: 1,
             stack: Stack::default(),
+            options,
+            diagnostics: Vec::new(),
+            unreadable_keys: 0,
         }
     }
 
+    /// It returns the parser options.
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    /// It returns the errors the parser recovered from.
+    ///
+    /// It's always empty unless the parser was built with
+    /// [`ErrorHandling::Recover`].
+    pub fn diagnostics(&self) -> &[error::Error] {
+        &self.diagnostics
+    }
+
+    /// It returns the errors the parser recovered from, leaving the list
+    /// empty.
+    pub fn take_diagnostics(&mut self) -> Vec<error::Error> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
     /// It parses a bencoded value read from input and writes the corresponding
     /// JSON UTF-8 string value to the output.
     ///
     /// # Errors
     ///
-    /// Will return an error if it can't read from the input or write to the
-    /// output.
-    ///
-    /// # Panics
+    /// Will return an error if:
     ///
-    /// Will panic if receives a byte that isn't a valid begin or end of a
-    /// bencoded type: integer, string, list or dictionary.
+    /// - It can't read from the input or write to the output.
+    /// - The input is invalid Bencode.
     pub fn write_str<W: FmtWrite>(&mut self, writer: W) -> Result<(), error::Error> {
-        let mut writer = StringWriter::new(writer);
+        let mut writer = StringWriter::with_capacity(self.output_capture_capacity(), writer);
         self.parse(&mut writer)
     }
 
     /// It parses a bencoded value read from input and writes the corresponding
     /// JSON UTF-8 string value as bytes to the output.
     ///
+    /// The output is buffered and flushed at the end. Use
+    /// [`BencodeParser::byte_writer`] and [`BencodeParser::finish`] to get the
+    /// output back with the conversion stats.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if:
+    ///
+    /// - It can't read from the input or write to the output.
+    /// - It can't flush the output.
+    /// - The input is invalid Bencode.
+    pub fn write_bytes<W: IoWrite>(&mut self, writer: W) -> Result<(), error::Error> {
+        let mut writer = self.byte_writer(writer);
+        self.parse(&mut writer)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// It returns a buffered writer for the output with the buffer size and
+    /// the capture window in the parser options, to use with
+    /// [`BencodeParser::write_to`] and [`BencodeParser::finish`]:
+    ///
+    /// ```rust
+    /// use torrust_bencode2json::parsers::BencodeParser;
+    ///
+    /// let mut parser = BencodeParser::new(&b"li1ei2ee"[..]);
+    ///
+    /// let mut writer = parser.byte_writer(Vec::new());
+    /// parser.write_to(&mut writer).unwrap();
+    /// let (output, stats) = parser.finish(writer).unwrap();
+    ///
+    /// assert_eq!(output, b"[1,2]");
+    /// assert_eq!(stats.bytes_written, 5);
+    /// assert_eq!(stats.tokens_processed, 4);
+    /// ```
+    pub fn byte_writer<W: IoWrite>(&self, writer: W) -> ByteWriter<W> {
+        ByteWriter::with_capacity(
+            self.options.output_buffer_size,
+            self.output_capture_capacity(),
+            writer,
+        )
+    }
+
+    /// It flushes the output and returns it with the conversion stats.
+    ///
     /// # Errors
     ///
-    /// Will return an error if it can't read from the input or write to the
+    /// Will return an error if it can't write the buffered bytes or flush the
     /// output.
+    pub fn finish<W: IoWrite>(&self, writer: ByteWriter<W>) -> Result<(W, Stats), error::Error> {
+        let stats = Stats {
+            bytes_written: writer.output_byte_counter(),
+            tokens_processed: self.num_processed_tokens(),
+        };
+
+        Ok((writer.finish()?, stats))
+    }
+
+    /// It returns the number of bencoded tokens processed so far.
+    ///
+    /// A token is an integer, a string, or the beginning or the end of a
+    /// list or dictionary.
+    #[must_use]
+    pub fn num_processed_tokens(&self) -> u64 {
+        // The counter starts at 1 to number the tokens in errors
+        self.num_processed_tokens - 1
+    }
+
+    /// It returns the JSON path of the latest value begun, like
+    /// `$.info.files[0]`. After an error, it's the path of the value where
+    /// the error was found.
+    #[must_use]
+    pub fn value_path(&self) -> String {
+        self.stack.value_path()
+    }
+
+    /// It parses a bencoded value read from input and writes the corresponding
+    /// JSON value to a custom [`Writer`].
     ///
-    /// # Panics
+    /// The output goes straight to the writer, without the buffer used by
+    /// [`BencodeParser::write_bytes`]. The writer's counter and captured
+    /// bytes are used to give context in errors.
     ///
-    /// Will panic if receives a byte that isn't a valid begin or end of a
-    /// bencoded type: integer, string, list or dictionary.
-    pub fn write_bytes<W: IoWrite>(&mut self, writer: W) -> Result<(), error::Error> {
-        let mut writer = ByteWriter::new(writer);
-        self.parse(&mut writer)
+    /// # Errors
+    ///
+    /// Will return an error if:
+    ///
+    /// - It can't read from the input or write to the output.
+    /// - The input is invalid Bencode.
+    pub fn write_to<W: Writer>(&mut self, writer: &mut W) -> Result<(), error::Error> {
+        self.parse(writer)
+    }
+
+    /// It parses only the next bencoded value and writes the corresponding
+    /// JSON value to a custom [`Writer`]. It stops right after the value, so
+    /// the bytes after it are not read. It returns the input position where
+    /// the value ends, or `None` if the input has no more values.
+    ///
+    /// It's useful for messages with a bencoded value followed by other data,
+    /// like the peer-wire extension messages. The data can be read from the
+    /// input returned by [`BencodeParser::into_inner`]:
+    ///
+    /// ```rust
+    /// use std::io::Read;
+    ///
+    /// use torrust_bencode2json::{parsers::BencodeParser, value::ValueBuilder};
+    ///
+    /// let mut parser = BencodeParser::from_buf_read(&b"d5:piecei0eeDATA"[..]);
+    ///
+    /// let end = parser.write_value_to(&mut ValueBuilder::default()).unwrap();
+    ///
+    /// let mut data = Vec::new();
+    /// parser.into_inner().read_to_end(&mut data).unwrap();
+    ///
+    /// assert_eq!(end, Some(12));
+    /// assert_eq!(data, b"DATA");
+    /// ```
+    ///
+    /// Line breaks before the value are skipped as usual, but line breaks
+    /// after it are left in the input.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if:
+    ///
+    /// - It can't read from the input or write to the output.
+    /// - The value is invalid Bencode.
+    pub fn write_value_to<W: Writer>(
+        &mut self,
+        writer: &mut W,
+    ) -> Result<Option<u64>, error::Error> {
+        let mut started = false;
+
+        loop {
+            if started && self.stack.depth() == 0 {
+                return Ok(Some(self.input_position()));
+            }
+
+            if !started && self.stack.depth() == 0 {
+                let ignored_newline = self.options.newline_handling == NewlineHandling::Ignore;
+
+                started = match Self::peek_byte(&mut self.byte_reader, writer)? {
+                    Some(b'\n') => !ignored_newline,
+                    Some(_) => true,
+                    None => false,
+                };
+            }
+
+            if self.parse_next_token(writer)? {
+                // The input ended. When recovering, a truncated value still
+                // counts as a value
+                return Ok(started.then(|| self.input_position()));
+            }
+        }
+    }
+
+    /// It returns the input, to read the bytes after the values parsed with
+    /// [`BencodeParser::write_value_to`].
+    ///
+    /// A byte peeked by the parser and not parsed yet, which only happens
+    /// after an error, is lost.
+    pub fn into_inner(self) -> R {
+        self.byte_reader.into_inner()
     }
 
     /// It parses a bencoded value read from input and writes the corresponding
@@ -104,56 +372,180 @@ impl<R: Read> BencodeParser<R> {
     /// - It can't read from the input or write to the output.
     /// - The input is invalid Bencode.
     fn parse<W: Writer>(&mut self, writer: &mut W) -> Result<(), error::Error> {
-        while let Some(peeked_byte) = Self::peek_byte(&mut self.byte_reader, writer)? {
-            match peeked_byte {
-                BENCODE_BEGIN_INTEGER => {
-                    self.begin_bencoded_value(BencodeType::Integer, writer)?;
-                    integer::parse(&mut self.byte_reader, writer)?;
-                }
-                b'0'..=b'9' => {
-                    self.begin_bencoded_value(BencodeType::String, writer)?;
-                    string::parse(&mut self.byte_reader, writer)?;
-                }
-                BENCODE_BEGIN_LIST => {
-                    let _byte = Self::read_peeked_byte(peeked_byte, &mut self.byte_reader, writer)?;
-                    self.begin_bencoded_value(BencodeType::List, writer)?;
-                    writer.write_byte(Self::JSON_ARRAY_BEGIN)?;
-                    self.stack.push(State::ExpectingFirstListItemOrEnd);
-                }
-                BENCODE_BEGIN_DICT => {
-                    let _byte = Self::read_peeked_byte(peeked_byte, &mut self.byte_reader, writer)?;
-                    self.begin_bencoded_value(BencodeType::Dict, writer)?;
-                    writer.write_byte(Self::JSON_OBJ_BEGIN)?;
-                    self.stack.push(State::ExpectingFirstDictFieldOrEnd);
-                }
-                BENCODE_END_LIST_OR_DICT => {
-                    let _byte = Self::read_peeked_byte(peeked_byte, &mut self.byte_reader, writer)?;
-                    self.end_list_or_dict(writer)?;
-                }
-                b'\n' => {
-                    // Ignore line breaks at the beginning, the end, or between values
-                    let _byte = Self::read_peeked_byte(peeked_byte, &mut self.byte_reader, writer)?;
-                }
-                _ => {
-                    return Err(error::Error::UnrecognizedFirstBencodeValueByte(
-                        ReadContext {
-                            byte: Some(peeked_byte),
-                            pos: self.byte_reader.input_byte_counter(),
-                            latest_bytes: self.byte_reader.captured_bytes(),
-                        },
-                        WriteContext {
-                            byte: Some(peeked_byte),
-                            pos: writer.output_byte_counter(),
-                            latest_bytes: writer.captured_bytes(),
-                        },
-                    ));
+        while !self.parse_next_token(writer)? {}
+
+        Ok(())
+    }
+
+    /// It parses the next bencoded token, or checks that all the lists and
+    /// dictionaries are closed if the input has ended. It returns true when
+    /// the input has ended.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if:
+    ///
+    /// - It can't read from the input or write to the output.
+    /// - The input is invalid Bencode.
+    fn parse_next_token<W: Writer>(&mut self, writer: &mut W) -> Result<bool, error::Error> {
+        let Some(peeked_byte) = Self::peek_byte(&mut self.byte_reader, writer)? else {
+            if let Err(err) = self.check_bad_end_stack_state(writer) {
+                self.handle_error(err, writer)?;
+            }
+
+            if self.options.error_handling == ErrorHandling::Recover {
+                self.finish_recovery(writer)?;
+            }
+
+            return Ok(true);
+        };
+
+        if let Err(err) = self.process_byte(peeked_byte, writer) {
+            self.handle_error(err, writer)?;
+        }
+
+        self.num_processed_tokens += 1;
+
+        Ok(false)
+    }
+
+    /// It processes the bencoded token that starts with the peeked byte.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if:
+    ///
+    /// - It can't read from the input or write to the output.
+    /// - The token is invalid Bencode.
+    fn process_byte<W: Writer>(
+        &mut self,
+        peeked_byte: u8,
+        writer: &mut W,
+    ) -> Result<(), error::Error> {
+        let input_start = self.input_position();
+
+        let digits;
+        let bytes;
+
+        let kind = match peeked_byte {
+            BENCODE_BEGIN_INTEGER => {
+                self.begin_bencoded_value(BencodeType::Integer, writer)?;
+                digits = integer::parse_digits(&mut self.byte_reader, writer, &self.options)?;
+                TokenKind::Integer(&digits)
+            }
+            b'0'..=b'9' => {
+                let is_key = matches!(
+                    self.stack.peek(),
+                    Ok(State::ExpectingFirstDictFieldOrEnd | State::ExpectingDictFieldKeyOrEnd)
+                );
+                self.begin_bencoded_value(BencodeType::String, writer)?;
+                bytes = string::parse_bytes(&mut self.byte_reader, writer, &self.options)?;
+                if is_key {
+                    self.stack.set_key(&bytes);
                 }
+                TokenKind::String(&bytes)
+            }
+            BENCODE_BEGIN_LIST => {
+                let _byte = Self::read_peeked_byte(peeked_byte, &mut self.byte_reader, writer)?;
+                self.begin_bencoded_value(BencodeType::List, writer)?;
+                self.check_max_depth(writer)?;
+                writer.write_byte(Self::JSON_ARRAY_BEGIN)?;
+                self.stack.push(State::ExpectingFirstListItemOrEnd);
+                TokenKind::BeginList
+            }
+            BENCODE_BEGIN_DICT => {
+                let _byte = Self::read_peeked_byte(peeked_byte, &mut self.byte_reader, writer)?;
+                self.begin_bencoded_value(BencodeType::Dict, writer)?;
+                self.check_max_depth(writer)?;
+                writer.write_byte(Self::JSON_OBJ_BEGIN)?;
+                self.stack.push(State::ExpectingFirstDictFieldOrEnd);
+                TokenKind::BeginDict
+            }
+            BENCODE_END_LIST_OR_DICT => {
+                let _byte = Self::read_peeked_byte(peeked_byte, &mut self.byte_reader, writer)?;
+                self.end_list_or_dict(writer)?;
+                TokenKind::End
+            }
+            b'\n' if self.options.newline_handling == NewlineHandling::Ignore => {
+                // Ignore line breaks at the beginning, the end, or between values
+                let _byte = Self::read_peeked_byte(peeked_byte, &mut self.byte_reader, writer)?;
+                return Ok(());
+            }
+            _ => {
+                return Err(error::Error::UnrecognizedFirstBencodeValueByte(
+                    ReadContext {
+                        byte: Some(peeked_byte),
+                        pos: self.byte_reader.input_byte_counter(),
+                        latest_bytes: self.byte_reader.captured_bytes().into(),
+                    },
+                    WriteContext {
+                        byte: Some(peeked_byte),
+                        pos: writer.output_byte_counter(),
+                        latest_bytes: writer.captured_bytes().into(),
+                    },
+                ));
             }
+        };
+
+        writer.token(&Token {
+            kind,
+            input_start,
+            input_end: self.input_position(),
+        })?;
 
-            self.num_processed_tokens += 1;
+        Ok(())
+    }
+
+    /// Number of input bytes parsed, not counting the peeked byte.
+    fn input_position(&self) -> u64 {
+        self.byte_reader.input_byte_counter() - u64::from(self.byte_reader.peeked_byte().is_some())
+    }
+
+    /// It stops the parsing or recovers from the error depending on the
+    /// [`ErrorHandling`] option.
+    ///
+    /// # Errors
+    ///
+    /// Will return the error if the parser is not in recovery mode or the
+    /// error can't be recovered.
+    fn handle_error<W: Writer>(
+        &mut self,
+        err: error::Error,
+        writer: &mut W,
+    ) -> Result<(), error::Error> {
+        let err = self.reread_latest_input_bytes(err);
+
+        match self.options.error_handling {
+            ErrorHandling::Stop => Err(err),
+            ErrorHandling::Recover => self.recover(err, writer),
+        }
+    }
+
+    /// It fills the latest input bytes of the error re-reading them from the
+    /// input, when the capture mode is [`CaptureMode::Lazy`] and the input is
+    /// seekable.
+    fn reread_latest_input_bytes(&mut self, mut err: error::Error) -> error::Error {
+        if self.options.capture_mode != CaptureMode::Lazy {
+            return err;
+        }
+
+        if let Some(read_context) = err.read_context_mut() {
+            if let Some(latest_bytes) = self
+                .byte_reader
+                .reread_bytes_before(read_context.pos, self.options.capture_window_size)
+            {
+                read_context.latest_bytes = latest_bytes.into();
+            }
         }
 
-        self.check_bad_end_stack_state(writer)
+        err
+    }
+
+    /// The capacity of the buffer that captures the latest output bytes.
+    fn output_capture_capacity(&self) -> usize {
+        self.options
+            .capture_mode
+            .capture_capacity(self.options.capture_window_size)
     }
 
     /// It reads the next byte from the input consuming it. It returns `None` if
@@ -179,12 +571,12 @@ impl<R: Read> BencodeParser<R> {
                     ReadContext {
                         byte: Some(byte),
                         pos: reader.input_byte_counter(),
-                        latest_bytes: reader.captured_bytes(),
+                        latest_bytes: reader.captured_bytes().into(),
                     },
                     WriteContext {
                         byte: Some(byte),
                         pos: writer.output_byte_counter(),
-                        latest_bytes: writer.captured_bytes(),
+                        latest_bytes: writer.captured_bytes().into(),
                     },
                 ))
             }
@@ -231,10 +623,19 @@ impl<R: Read> BencodeParser<R> {
         bencode_type: BencodeType,
         writer: &mut W,
     ) -> Result<(), error::Error> {
-        match self.stack.peek() {
+        let state = self
+            .stack
+            .peek()
+            .map_err(|err| self.invalid_stack_state(err, writer))?;
+
+        self.stack.begin_value();
+
+        match state {
             State::Initial => {}
             State::ExpectingFirstListItemOrEnd => {
-                self.stack.swap_top(State::ExpectingNextListItem);
+                self.stack
+                    .swap_top(State::ExpectingNextListItem)
+                    .map_err(|err| self.invalid_stack_state(err, writer))?;
             }
             State::ExpectingNextListItem => {
                 writer.write_byte(Self::JSON_ARRAY_ITEMS_SEPARATOR)?;
@@ -246,22 +647,26 @@ impl<R: Read> BencodeParser<R> {
                         ReadContext {
                             byte: None,
                             pos: self.byte_reader.input_byte_counter(),
-                            latest_bytes: self.byte_reader.captured_bytes(),
+                            latest_bytes: self.byte_reader.captured_bytes().into(),
                         },
                         WriteContext {
                             byte: None,
                             pos: writer.output_byte_counter(),
-                            latest_bytes: writer.captured_bytes(),
+                            latest_bytes: writer.captured_bytes().into(),
                         },
                     ));
                 }
 
-                self.stack.swap_top(State::ExpectingDictFieldValue);
+                self.stack
+                    .swap_top(State::ExpectingDictFieldValue)
+                    .map_err(|err| self.invalid_stack_state(err, writer))?;
             }
             State::ExpectingDictFieldValue => {
                 writer.write_byte(Self::JSON_OBJ_FIELD_KEY_VALUE_SEPARATOR)?;
 
-                self.stack.swap_top(State::ExpectingDictFieldKeyOrEnd);
+                self.stack
+                    .swap_top(State::ExpectingDictFieldKeyOrEnd)
+                    .map_err(|err| self.invalid_stack_state(err, writer))?;
             }
             State::ExpectingDictFieldKeyOrEnd => {
                 if bencode_type != BencodeType::String {
@@ -270,19 +675,21 @@ impl<R: Read> BencodeParser<R> {
                         ReadContext {
                             byte: None,
                             pos: self.byte_reader.input_byte_counter(),
-                            latest_bytes: self.byte_reader.captured_bytes(),
+                            latest_bytes: self.byte_reader.captured_bytes().into(),
                         },
                         WriteContext {
                             byte: None,
                             pos: writer.output_byte_counter(),
-                            latest_bytes: writer.captured_bytes(),
+                            latest_bytes: writer.captured_bytes().into(),
                         },
                     ));
                 }
 
                 writer.write_byte(Self::JSON_OBJ_FIELDS_SEPARATOR)?;
 
-                self.stack.swap_top(State::ExpectingDictFieldValue);
+                self.stack
+                    .swap_top(State::ExpectingDictFieldValue)
+                    .map_err(|err| self.invalid_stack_state(err, writer))?;
             }
         }
 
@@ -298,26 +705,35 @@ impl<R: Read> BencodeParser<R> {
     ///
     /// Will return an error if the writer can't write to the output.
     pub fn end_list_or_dict<W: Writer>(&mut self, writer: &mut W) -> Result<(), error::Error> {
-        match self.stack.peek() {
+        let state = self
+            .stack
+            .peek()
+            .map_err(|err| self.invalid_stack_state(err, writer))?;
+
+        match state {
             State::ExpectingFirstListItemOrEnd | State::ExpectingNextListItem => {
                 writer.write_byte(Self::JSON_ARRAY_END)?;
-                self.stack.pop();
+                self.stack
+                    .pop()
+                    .map_err(|err| self.invalid_stack_state(err, writer))?;
             }
             State::ExpectingFirstDictFieldOrEnd | State::ExpectingDictFieldKeyOrEnd => {
                 writer.write_byte(Self::JSON_OBJ_END)?;
-                self.stack.pop();
+                self.stack
+                    .pop()
+                    .map_err(|err| self.invalid_stack_state(err, writer))?;
             }
             State::ExpectingDictFieldValue => {
                 return Err(error::Error::PrematureEndOfDict(
                     ReadContext {
                         byte: None,
                         pos: self.byte_reader.input_byte_counter(),
-                        latest_bytes: self.byte_reader.captured_bytes(),
+                        latest_bytes: self.byte_reader.captured_bytes().into(),
                     },
                     WriteContext {
                         byte: None,
                         pos: writer.output_byte_counter(),
-                        latest_bytes: writer.captured_bytes(),
+                        latest_bytes: writer.captured_bytes().into(),
                     },
                 ))
             }
@@ -326,12 +742,12 @@ impl<R: Read> BencodeParser<R> {
                     ReadContext {
                         byte: None,
                         pos: self.byte_reader.input_byte_counter(),
-                        latest_bytes: self.byte_reader.captured_bytes(),
+                        latest_bytes: self.byte_reader.captured_bytes().into(),
                     },
                     WriteContext {
                         byte: None,
                         pos: writer.output_byte_counter(),
-                        latest_bytes: writer.captured_bytes(),
+                        latest_bytes: writer.captured_bytes().into(),
                     },
                 ))
             }
@@ -348,19 +764,24 @@ impl<R: Read> BencodeParser<R> {
     ///
     /// Will return an error if the stack state is not correct.
     fn check_bad_end_stack_state<W: Writer>(&self, writer: &W) -> Result<(), error::Error> {
-        match self.stack.peek() {
+        let state = self
+            .stack
+            .peek()
+            .map_err(|err| self.invalid_stack_state(err, writer))?;
+
+        match state {
             State::Initial => Ok(()),
             State::ExpectingFirstListItemOrEnd => Err(
                 error::Error::UnexpectedEndOfInputExpectingFirstListItemOrEnd(
                     ReadContext {
                         byte: None,
                         pos: self.byte_reader.input_byte_counter(),
-                        latest_bytes: self.byte_reader.captured_bytes(),
+                        latest_bytes: self.byte_reader.captured_bytes().into(),
                     },
                     WriteContext {
                         byte: None,
                         pos: writer.output_byte_counter(),
-                        latest_bytes: writer.captured_bytes(),
+                        latest_bytes: writer.captured_bytes().into(),
                     },
                 ),
             ),
@@ -369,12 +790,12 @@ impl<R: Read> BencodeParser<R> {
                     ReadContext {
                         byte: None,
                         pos: self.byte_reader.input_byte_counter(),
-                        latest_bytes: self.byte_reader.captured_bytes(),
+                        latest_bytes: self.byte_reader.captured_bytes().into(),
                     },
                     WriteContext {
                         byte: None,
                         pos: writer.output_byte_counter(),
-                        latest_bytes: writer.captured_bytes(),
+                        latest_bytes: writer.captured_bytes().into(),
                     },
                 ))
             }
@@ -383,12 +804,12 @@ impl<R: Read> BencodeParser<R> {
                     ReadContext {
                         byte: None,
                         pos: self.byte_reader.input_byte_counter(),
-                        latest_bytes: self.byte_reader.captured_bytes(),
+                        latest_bytes: self.byte_reader.captured_bytes().into(),
                     },
                     WriteContext {
                         byte: None,
                         pos: writer.output_byte_counter(),
-                        latest_bytes: writer.captured_bytes(),
+                        latest_bytes: writer.captured_bytes().into(),
                     },
                 ),
             ),
@@ -397,12 +818,12 @@ impl<R: Read> BencodeParser<R> {
                     ReadContext {
                         byte: None,
                         pos: self.byte_reader.input_byte_counter(),
-                        latest_bytes: self.byte_reader.captured_bytes(),
+                        latest_bytes: self.byte_reader.captured_bytes().into(),
                     },
                     WriteContext {
                         byte: None,
                         pos: writer.output_byte_counter(),
-                        latest_bytes: writer.captured_bytes(),
+                        latest_bytes: writer.captured_bytes().into(),
                     },
                 ))
             }
@@ -411,17 +832,62 @@ impl<R: Read> BencodeParser<R> {
                     ReadContext {
                         byte: None,
                         pos: self.byte_reader.input_byte_counter(),
-                        latest_bytes: self.byte_reader.captured_bytes(),
+                        latest_bytes: self.byte_reader.captured_bytes().into(),
                     },
                     WriteContext {
                         byte: None,
                         pos: writer.output_byte_counter(),
-                        latest_bytes: writer.captured_bytes(),
+                        latest_bytes: writer.captured_bytes().into(),
                     },
                 ),
             ),
         }
     }
+
+    /// It checks that a new nested list or dictionary does not exceed the
+    /// maximum depth.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the maximum depth is reached.
+    fn check_max_depth<W: Writer>(&self, writer: &W) -> Result<(), error::Error> {
+        if let Some(max_depth) = self.options.limits.max_depth {
+            if self.stack.depth() >= max_depth {
+                return Err(error::Error::MaxDepthExceeded(
+                    max_depth,
+                    ReadContext {
+                        byte: None,
+                        pos: self.byte_reader.input_byte_counter(),
+                        latest_bytes: self.byte_reader.captured_bytes().into(),
+                    },
+                    WriteContext {
+                        byte: None,
+                        pos: writer.output_byte_counter(),
+                        latest_bytes: writer.captured_bytes().into(),
+                    },
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// It builds the error returned when the stack can't be read or mutated.
+    fn invalid_stack_state<W: Writer>(&self, err: stack::Error, writer: &W) -> error::Error {
+        error::Error::InvalidStackState(
+            err,
+            ReadContext {
+                byte: None,
+                pos: self.byte_reader.input_byte_counter(),
+                latest_bytes: self.byte_reader.captured_bytes().into(),
+            },
+            WriteContext {
+                byte: None,
+                pos: writer.output_byte_counter(),
+                latest_bytes: writer.captured_bytes().into(),
+            },
+        )
+    }
 }
 
 #[cfg(test)]
@@ -459,58 +925,508 @@ mod tests {
 
             assert_eq!(output, "0".to_string());
         }
-    }
 
-    #[test]
-    fn it_should_allow_reading_from_an_empty_input() {
-        struct EmptyReader;
+        mod to_any_type_implementing_the_writer_trait {
+            use crate::{
+                parsers::{error::Error, BencodeParser},
+                rw::{error::Error as RwError, writer::Writer},
+            };
 
-        impl Read for EmptyReader {
-            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
-                Err(io::Error::new(
-                    io::ErrorKind::UnexpectedEof,
-                    "Unexpected EOF",
-                ))
+            /// A writer that keeps at most `max_length` bytes.
+            struct LimitedWriter {
+                bytes: Vec<u8>,
+                max_length: usize,
             }
-        }
 
-        let mut output = String::new();
+            impl LimitedWriter {
+                fn new(max_length: usize) -> Self {
+                    Self {
+                        bytes: Vec::new(),
+                        max_length,
+                    }
+                }
+            }
 
-        let mut parser = BencodeParser::new(EmptyReader);
+            impl Writer for LimitedWriter {
+                fn write_byte(&mut self, byte: u8) -> Result<(), RwError> {
+                    self.write_bytes(&[byte])
+                }
 
-        parser.write_str(&mut output).unwrap();
+                fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), RwError> {
+                    if self.bytes.len() + bytes.len() > self.max_length {
+                        return Err(RwError::Io(std::io::Error::other("output too long")));
+                    }
 
-        assert_eq!(output, "");
-    }
+                    self.bytes.extend_from_slice(bytes);
 
-    mod it_should_allow_special_bencode_cases {
+                    Ok(())
+                }
 
-        use crate::{parsers::BencodeParser, test::bencode_to_json_unchecked};
+                fn write_str(&mut self, value: &str) -> Result<(), RwError> {
+                    self.write_bytes(value.as_bytes())
+                }
 
-        #[test]
-        fn an_empty_input() {
-            let mut output = String::new();
+                fn output_byte_counter(&self) -> u64 {
+                    self.bytes.len() as u64
+                }
 
-            let mut parser = BencodeParser::new(&b""[..]);
+                fn captured_bytes(&self) -> Vec<u8> {
+                    self.bytes.clone()
+                }
+            }
 
-            parser
-                .write_str(&mut output)
-                .expect("Bencode to JSON conversion failed");
+            #[test]
+            fn writing_the_json_to_the_writer() {
+                let mut writer = LimitedWriter::new(64);
 
-            assert_eq!(output, String::new());
-        }
+                BencodeParser::new(&b"d4:spaml1:ai42eee"[..])
+                    .write_to(&mut writer)
+                    .unwrap();
 
-        #[test]
-        fn line_breaks_at_the_beginning_of_the_input_stream() {
-            assert_eq!(bencode_to_json_unchecked(b"\ni0e"), "0".to_string());
-        }
+                assert_eq!(writer.bytes, br#"{"spam":["a",42]}"#);
+            }
 
-        #[test]
-        fn line_breaks_at_the_end_of_the_input_stream() {
-            assert_eq!(bencode_to_json_unchecked(b"i0e\n"), "0".to_string());
+            #[test]
+            fn returning_the_writer_errors_with_its_position_and_captured_bytes() {
+                let mut writer = LimitedWriter::new(4);
+
+                let err = BencodeParser::new(&b"l4:spame"[..])
+                    .write_to(&mut writer)
+                    .unwrap_err();
+
+                assert!(matches!(err, Error::Io(_)), "{err}");
+                assert_eq!(writer.bytes, b"[");
+            }
         }
+    }
 
-        #[test]
+    mod it_should_finish_the_output {
+        use std::io::{self, Write};
+
+        use crate::parsers::{error::Error, BencodeParser, Stats};
+
+        /// An output that fails to flush.
+        #[derive(Debug)]
+        struct UnflushableOutput(Vec<u8>);
+
+        impl Write for UnflushableOutput {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+            }
+        }
+
+        #[test]
+        fn returning_the_output_with_the_bytes_written_and_the_tokens_processed() {
+            let mut parser = BencodeParser::new(&b"d4:spaml1:ai42eee"[..]);
+
+            let mut writer = parser.byte_writer(Vec::new());
+            parser.write_to(&mut writer).unwrap();
+            let (output, stats) = parser.finish(writer).unwrap();
+
+            assert_eq!(output, br#"{"spam":["a",42]}"#);
+            assert_eq!(
+                stats,
+                Stats {
+                    bytes_written: 17,
+                    tokens_processed: 7,
+                }
+            );
+        }
+
+        #[test]
+        fn reporting_flush_errors() {
+            let mut parser = BencodeParser::new(&b"i42e"[..]);
+
+            let mut writer = parser.byte_writer(UnflushableOutput(Vec::new()));
+            parser.write_to(&mut writer).unwrap();
+
+            let err = parser.finish(writer).unwrap_err();
+
+            assert!(matches!(&err, Error::Io(err) if err.kind() == io::ErrorKind::StorageFull));
+        }
+
+        #[test]
+        fn reporting_flush_errors_when_writing_bytes() {
+            let mut parser = BencodeParser::new(&b"i42e"[..]);
+
+            let err = parser
+                .write_bytes(UnflushableOutput(Vec::new()))
+                .unwrap_err();
+
+            assert!(matches!(&err, Error::Io(err) if err.kind() == io::ErrorKind::StorageFull));
+        }
+    }
+
+    #[test]
+    fn it_should_allow_reading_from_an_empty_input() {
+        struct EmptyReader;
+
+        impl Read for EmptyReader {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Unexpected EOF",
+                ))
+            }
+        }
+
+        let mut output = String::new();
+
+        let mut parser = BencodeParser::new(EmptyReader);
+
+        parser.write_str(&mut output).unwrap();
+
+        assert_eq!(output, "");
+    }
+
+    mod it_should_allow_reading_directly_from_buffered_inputs {
+        use std::io::BufReader;
+
+        use proptest::prelude::*;
+
+        use crate::parsers::{error::Error, options::IntegerPolicy, BencodeParser};
+
+        #[test]
+        fn from_slices() {
+            let mut output = String::new();
+
+            let mut parser = BencodeParser::from_buf_read(&b"d4:spamli1eee"[..]);
+
+            parser
+                .write_str(&mut output)
+                .expect("Bencode to JSON conversion failed");
+
+            assert_eq!(output, r#"{"spam":[1]}"#);
+        }
+
+        #[test]
+        fn from_any_type_implementing_buf_read() {
+            let mut output = String::new();
+
+            // A tiny buffer forces strings to be read in many chunks
+            let input = BufReader::with_capacity(3, &b"l11:hello worlde"[..]);
+
+            let mut parser = BencodeParser::from_buf_read(input);
+
+            parser
+                .write_str(&mut output)
+                .expect("Bencode to JSON conversion failed");
+
+            assert_eq!(output, r#"["hello world"]"#);
+        }
+
+        #[test]
+        fn with_custom_options() {
+            let mut output = String::new();
+
+            let mut parser = BencodeParser::builder(&b"i42e"[..])
+                .integer_policy(IntegerPolicy::String)
+                .build_from_buf_read();
+
+            parser
+                .write_str(&mut output)
+                .expect("Bencode to JSON conversion failed");
+
+            assert_eq!(output, r#""42""#);
+        }
+
+        /// The JSON output, or the input and output positions of the error.
+        type Outcome = Result<String, (Option<u64>, Option<u64>)>;
+
+        fn outcome(result: Result<(), Error>, output: String) -> Outcome {
+            result
+                .map(|()| output)
+                .map_err(|err| (err.input_position(), err.output_position()))
+        }
+
+        /// It parses the input with both readers.
+        fn parse_both_readers(input: &[u8]) -> (Outcome, Outcome) {
+            let mut buffered_output = String::new();
+            let buffered = BencodeParser::new(input).write_str(&mut buffered_output);
+
+            let mut direct_output = String::new();
+            let direct = BencodeParser::from_buf_read(input).write_str(&mut direct_output);
+
+            (
+                outcome(buffered, buffered_output),
+                outcome(direct, direct_output),
+            )
+        }
+
+        proptest! {
+            #[test]
+            fn with_the_same_output_and_errors_as_the_buffered_reader(
+                input in proptest::collection::vec(proptest::sample::select(&b"ilde0123456789:-x"[..]), 0..256)
+            ) {
+                let (buffered, direct) = parse_both_readers(&input);
+                prop_assert_eq!(buffered, direct);
+            }
+        }
+    }
+
+    mod it_should_parse_one_value_at_a_time {
+        use std::io::{BufReader, Read};
+
+        use crate::{
+            parsers::{options::ScanMode, BencodeParser},
+            rw::string_writer::StringWriter,
+        };
+
+        /// It parses the first value with both scan modes and returns the
+        /// JSON, the end position and the bytes after the value.
+        fn parse_first_value(input: &[u8]) -> (String, Option<u64>, Vec<u8>) {
+            let outcomes = [ScanMode::Scalar, ScanMode::Vectorized].map(|scan_mode| {
+                let mut output = String::new();
+
+                let mut parser = BencodeParser::builder(input)
+                    .scan_mode(scan_mode)
+                    .build_from_buf_read();
+
+                let end = parser
+                    .write_value_to(&mut StringWriter::new(&mut output))
+                    .unwrap();
+
+                let mut rest = Vec::new();
+                parser.into_inner().read_to_end(&mut rest).unwrap();
+
+                (output, end, rest)
+            });
+
+            assert_eq!(outcomes[0], outcomes[1]);
+
+            outcomes[0].clone()
+        }
+
+        #[test]
+        fn stopping_after_the_value_of_each_type() {
+            assert_eq!(
+                parse_first_value(b"i42eRAW"),
+                ("42".to_string(), Some(4), b"RAW".to_vec())
+            );
+            assert_eq!(
+                parse_first_value(b"4:spamRAW"),
+                (r#""spam""#.to_string(), Some(6), b"RAW".to_vec())
+            );
+            assert_eq!(
+                parse_first_value(b"li1eeRAW"),
+                ("[1]".to_string(), Some(5), b"RAW".to_vec())
+            );
+            assert_eq!(
+                parse_first_value(b"d1:ai1ee\xff\x00"),
+                (r#"{"a":1}"#.to_string(), Some(8), b"\xff\x00".to_vec())
+            );
+        }
+
+        #[test]
+        fn skipping_line_breaks_only_before_the_value() {
+            assert_eq!(
+                parse_first_value(b"\ni1e\n"),
+                ("1".to_string(), Some(4), b"\n".to_vec())
+            );
+        }
+
+        #[test]
+        fn returning_none_when_there_are_no_more_values() {
+            assert_eq!(parse_first_value(b""), (String::new(), None, Vec::new()));
+            assert_eq!(parse_first_value(b"\n"), (String::new(), None, Vec::new()));
+        }
+
+        #[test]
+        fn parsing_values_sent_back_to_back() {
+            let mut parser = BencodeParser::from_buf_read(&b"i1e4:spamle"[..]);
+
+            let mut ends = Vec::new();
+            let mut output = String::new();
+
+            while let Some(end) = parser
+                .write_value_to(&mut StringWriter::new(&mut output))
+                .unwrap()
+            {
+                ends.push(end);
+                output.push(' ');
+            }
+
+            assert_eq!(ends, vec![3, 9, 11]);
+            assert_eq!(output, r#"1 "spam" [] "#);
+        }
+
+        #[test]
+        fn leaving_the_rest_of_the_input_in_the_parser_buffer() {
+            let input = BufReader::with_capacity(4, &b"d1:ai1eetrailing"[..]);
+
+            let mut parser = BencodeParser::new(input);
+
+            let end = parser
+                .write_value_to(&mut StringWriter::new(String::new()))
+                .unwrap();
+
+            let mut rest = String::new();
+            parser.into_inner().read_to_string(&mut rest).unwrap();
+
+            assert_eq!(end, Some(8));
+            assert_eq!(rest, "trailing");
+        }
+
+        #[test]
+        fn failing_when_the_value_is_truncated() {
+            let mut parser = BencodeParser::from_buf_read(&b"d1:a"[..]);
+
+            assert!(parser
+                .write_value_to(&mut StringWriter::new(String::new()))
+                .is_err());
+        }
+    }
+
+    mod it_should_scan_integers_and_string_lengths_in_bulk {
+        use std::io::{BufRead, BufReader};
+
+        use proptest::prelude::*;
+
+        use crate::parsers::{
+            options::{ErrorHandling, Limits, ParserBuilder, ScanMode},
+            BencodeParser,
+        };
+
+        /// The JSON output, or the error message with its context, plus the
+        /// recovered errors.
+        type Outcome = (Result<String, String>, Vec<String>);
+
+        fn parse<R: BufRead>(builder: ParserBuilder<R>, scan_mode: ScanMode) -> Outcome {
+            let mut parser = builder.scan_mode(scan_mode).build_from_buf_read();
+
+            let mut output = String::new();
+
+            let result = parser
+                .write_str(&mut output)
+                .map(|()| output)
+                .map_err(|err| err.to_string());
+
+            let diagnostics = parser
+                .diagnostics()
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+
+            (result, diagnostics)
+        }
+
+        /// It parses the input with both scan modes and returns both outcomes.
+        fn parse_both_ways<R: BufRead, F: Fn() -> ParserBuilder<R>>(
+            builder: F,
+        ) -> (Outcome, Outcome) {
+            (
+                parse(builder(), ScanMode::Scalar),
+                parse(builder(), ScanMode::Vectorized),
+            )
+        }
+
+        #[test]
+        fn integers_and_string_lengths_in_the_input_buffer() {
+            let (scalar, vectorized) =
+                parse_both_ways(|| BencodeParser::builder(&b"d4:spami-42e3:eggi1234567890ee"[..]));
+
+            assert_eq!(vectorized, scalar);
+            assert_eq!(
+                vectorized.0,
+                Ok(r#"{"spam":-42,"egg":1234567890}"#.to_string())
+            );
+        }
+
+        #[test]
+        fn integers_with_leading_zeros_using_the_scalar_path() {
+            for input in [&b"i01e"[..], b"i00e", b"i-00e", b"i0100e", b"i-0e"] {
+                let (scalar, vectorized) = parse_both_ways(|| BencodeParser::builder(input));
+
+                assert_eq!(vectorized, scalar);
+            }
+        }
+
+        #[test]
+        fn tokens_split_across_the_input_buffer() {
+            let (scalar, vectorized) = parse_both_ways(|| {
+                ParserBuilder::new(BufReader::with_capacity(
+                    3,
+                    &b"li1234567ei-9e11:hello worlde"[..],
+                ))
+            });
+
+            assert_eq!(vectorized, scalar);
+            assert_eq!(
+                vectorized.0,
+                Ok(r#"[1234567,-9,"hello world"]"#.to_string())
+            );
+        }
+
+        proptest! {
+            #[test]
+            fn with_the_same_output_and_errors_as_the_scalar_path(
+                input in proptest::collection::vec(proptest::sample::select(&b"ilde0123456789:-x"[..]), 0..256)
+            ) {
+                let (scalar, vectorized) = parse_both_ways(|| BencodeParser::builder(&input[..]));
+                prop_assert_eq!(vectorized, scalar);
+            }
+
+            #[test]
+            fn with_the_same_output_and_errors_as_the_scalar_path_with_small_input_buffers(
+                input in proptest::collection::vec(proptest::sample::select(&b"ilde0123456789:-x"[..]), 0..256),
+                buffer_size in 1..16usize,
+            ) {
+                let (scalar, vectorized) = parse_both_ways(|| {
+                    ParserBuilder::new(BufReader::with_capacity(buffer_size, &input[..]))
+                });
+                prop_assert_eq!(vectorized, scalar);
+            }
+
+            #[test]
+            fn with_the_same_output_and_errors_as_the_scalar_path_when_recovering_with_limits(
+                input in proptest::collection::vec(proptest::sample::select(&b"ilde0123456789:-x"[..]), 0..256)
+            ) {
+                let (scalar, vectorized) = parse_both_ways(|| {
+                    BencodeParser::builder(&input[..])
+                        .error_handling(ErrorHandling::Recover)
+                        .limits(Limits {
+                            max_integer_length: Some(3),
+                            max_string_length: Some(5),
+                            ..Limits::default()
+                        })
+                });
+                prop_assert_eq!(vectorized, scalar);
+            }
+        }
+    }
+
+    mod it_should_allow_special_bencode_cases {
+
+        use crate::{parsers::BencodeParser, test::bencode_to_json_unchecked};
+
+        #[test]
+        fn an_empty_input() {
+            let mut output = String::new();
+
+            let mut parser = BencodeParser::new(&b""[..]);
+
+            parser
+                .write_str(&mut output)
+                .expect("Bencode to JSON conversion failed");
+
+            assert_eq!(output, String::new());
+        }
+
+        #[test]
+        fn line_breaks_at_the_beginning_of_the_input_stream() {
+            assert_eq!(bencode_to_json_unchecked(b"\ni0e"), "0".to_string());
+        }
+
+        #[test]
+        fn line_breaks_at_the_end_of_the_input_stream() {
+            assert_eq!(bencode_to_json_unchecked(b"i0e\n"), "0".to_string());
+        }
+
+        #[test]
         fn line_breaks_between_bencoded_values() {
             assert_eq!(
                 bencode_to_json_unchecked(b"li0e\ni1ee"),
@@ -519,6 +1435,535 @@ mod tests {
         }
     }
 
+    mod it_should_allow_configuring {
+        use std::io::BufRead;
+
+        use crate::parsers::{
+            error::Error,
+            options::{BinaryPolicy, IntegerPolicy, Limits, NewlineHandling},
+            BencodeParser,
+        };
+
+        fn parse<R: BufRead>(parser: &mut BencodeParser<R>) -> Result<String, Error> {
+            let mut output = String::new();
+            parser.write_str(&mut output)?;
+            Ok(output)
+        }
+
+        #[test]
+        fn rejecting_line_breaks() {
+            let mut parser = BencodeParser::builder(&b"li0e\ni1ee"[..])
+                .newline_handling(NewlineHandling::Reject)
+                .build();
+
+            assert!(matches!(
+                parse(&mut parser),
+                Err(Error::UnrecognizedFirstBencodeValueByte { .. })
+            ));
+        }
+
+        #[test]
+        fn writing_integers_as_strings() {
+            let mut parser = BencodeParser::builder(&b"li-1ei42ee"[..])
+                .integer_policy(IntegerPolicy::String)
+                .build();
+
+            assert_eq!(parse(&mut parser).unwrap(), r#"["-1","42"]"#);
+        }
+
+        #[test]
+        fn writing_only_unsafe_integers_as_strings() {
+            let mut parser = BencodeParser::builder(&b"li42ei9007199254740992ee"[..])
+                .integer_policy(IntegerPolicy::StringIfUnsafe)
+                .build();
+
+            assert_eq!(parse(&mut parser).unwrap(), r#"[42,"9007199254740992"]"#);
+        }
+
+        #[test]
+        fn writing_non_utf8_strings_as_plain_hex() {
+            let mut parser = BencodeParser::builder(&b"2:\xFF\xFE"[..])
+                .binary_policy(BinaryPolicy::Hex)
+                .build();
+
+            assert_eq!(parse(&mut parser).unwrap(), r#""fffe""#);
+        }
+
+        #[test]
+        fn writing_non_utf8_strings_replacing_invalid_sequences() {
+            let mut parser = BencodeParser::builder(&b"3:a\xFFb"[..])
+                .binary_policy(BinaryPolicy::Lossy)
+                .build();
+
+            assert_eq!(parse(&mut parser).unwrap(), "\"a\u{FFFD}b\"");
+        }
+
+        #[test]
+        fn the_capture_window_size() {
+            let mut parser = BencodeParser::builder(&b"i42"[..])
+                .capture_window_size(2)
+                .build();
+
+            let err = parse(&mut parser).unwrap_err();
+
+            assert_eq!(&*err.read_context().unwrap().latest_bytes, b"42");
+        }
+
+        mod the_capture_mode {
+            use std::io::Cursor;
+
+            use crate::parsers::{options::CaptureMode, BencodeParser};
+
+            use super::parse;
+
+            #[test]
+            fn capturing_nothing() {
+                let mut parser = BencodeParser::builder(&b"li1ei42"[..])
+                    .capture_mode(CaptureMode::Off)
+                    .build();
+
+                let err = parse(&mut parser).unwrap_err();
+
+                assert!(err.read_context().unwrap().latest_bytes.is_empty());
+                assert!(err.write_context().unwrap().latest_bytes.is_empty());
+
+                // Errors still have the positions
+                assert_eq!(err.input_position(), Some(7));
+                assert_eq!(err.output_position(), Some(3));
+            }
+
+            #[test]
+            fn capturing_nothing_reading_directly_from_buffered_inputs() {
+                let mut parser = BencodeParser::builder(&b"li1ei42"[..])
+                    .capture_mode(CaptureMode::Off)
+                    .build_from_buf_read();
+
+                let err = parse(&mut parser).unwrap_err();
+
+                assert!(err.read_context().unwrap().latest_bytes.is_empty());
+            }
+
+            #[test]
+            fn capturing_the_latest_bytes_in_a_ring_buffer() {
+                let mut parser = BencodeParser::builder(&b"li1ei42"[..])
+                    .capture_mode(CaptureMode::RingBuffer)
+                    .capture_window_size(4)
+                    .build();
+
+                let err = parse(&mut parser).unwrap_err();
+
+                assert_eq!(&*err.read_context().unwrap().latest_bytes, b"ei42");
+                assert_eq!(&*err.write_context().unwrap().latest_bytes, b"[1,");
+            }
+
+            #[test]
+            fn rereading_the_latest_input_bytes_from_seekable_inputs() {
+                let mut parser = BencodeParser::builder(Cursor::new(b"li1ei42".to_vec()))
+                    .capture_mode(CaptureMode::Lazy)
+                    .capture_window_size(4)
+                    .build_seekable()
+                    .unwrap();
+
+                let err = parse(&mut parser).unwrap_err();
+
+                assert_eq!(&*err.read_context().unwrap().latest_bytes, b"ei42");
+                assert!(err.write_context().unwrap().latest_bytes.is_empty());
+            }
+
+            #[test]
+            fn rereading_the_latest_input_bytes_for_each_recovered_error() {
+                let mut output = String::new();
+
+                let mut parser = BencodeParser::builder(Cursor::new(b"li00ei3ee".to_vec()))
+                    .capture_mode(CaptureMode::Lazy)
+                    .error_handling(crate::parsers::options::ErrorHandling::Recover)
+                    .build_seekable()
+                    .unwrap();
+
+                parser.write_str(&mut output).unwrap();
+
+                assert_eq!(output, r#"["<unreadable>",3]"#);
+                assert_eq!(
+                    &*parser.diagnostics()[0].read_context().unwrap().latest_bytes,
+                    b"li00"
+                );
+            }
+
+            #[test]
+            fn not_rereading_when_the_input_is_not_seekable() {
+                let mut parser = BencodeParser::builder(&b"li1ei42"[..])
+                    .capture_mode(CaptureMode::Lazy)
+                    .build();
+
+                let err = parse(&mut parser).unwrap_err();
+
+                assert!(err.read_context().unwrap().latest_bytes.is_empty());
+            }
+        }
+
+        #[test]
+        fn the_input_and_output_buffer_sizes() {
+            let mut output = Vec::new();
+
+            let mut parser = BencodeParser::builder(&b"d3:fool4:spamee"[..])
+                .input_buffer_size(1)
+                .output_buffer_size(1)
+                .build();
+
+            parser.write_bytes(&mut output).unwrap();
+
+            assert_eq!(output, br#"{"foo":["spam"]}"#);
+        }
+
+        #[test]
+        fn the_path_of_the_value_with_the_error() {
+            let mut parser = BencodeParser::new(&b"d4:infod5:filesld6:lengthi1e4:pathl1:ai0x"[..]);
+
+            parser.write_bytes(Vec::new()).unwrap_err();
+
+            assert_eq!(parser.value_path(), "$.info.files[0].path[1]");
+        }
+
+        #[test]
+        fn an_empty_input_buffer_as_a_one_byte_buffer() {
+            let options = crate::parsers::options::ParserOptions {
+                input_buffer_size: 0,
+                ..Default::default()
+            };
+
+            let mut output = Vec::new();
+
+            let mut parser = BencodeParser::with_options(&b"li42ee"[..], options);
+
+            parser.write_bytes(&mut output).unwrap();
+
+            assert_eq!(output, b"[42]");
+        }
+
+        mod limits {
+            use crate::parsers::{error::Error, options::Limits, BencodeParser};
+
+            use super::parse;
+
+            #[test]
+            fn failing_when_the_nesting_depth_exceeds_the_limit() {
+                let limits = Limits {
+                    max_depth: Some(2),
+                    ..Limits::default()
+                };
+
+                let mut parser = BencodeParser::builder(&b"llee"[..]).limits(limits).build();
+                assert_eq!(parse(&mut parser).unwrap(), "[[]]");
+
+                let mut parser = BencodeParser::builder(&b"llleee"[..])
+                    .limits(limits)
+                    .build();
+                assert!(matches!(
+                    parse(&mut parser),
+                    Err(Error::MaxDepthExceeded(2, _, _))
+                ));
+
+                let mut parser = BencodeParser::builder(&b"ld1:adeee"[..])
+                    .limits(limits)
+                    .build();
+                assert!(matches!(
+                    parse(&mut parser),
+                    Err(Error::MaxDepthExceeded(2, _, _))
+                ));
+            }
+
+            #[test]
+            fn failing_when_a_string_is_longer_than_the_limit() {
+                let limits = Limits {
+                    max_string_length: Some(4),
+                    ..Limits::default()
+                };
+
+                let mut parser = BencodeParser::builder(&b"4:spam"[..])
+                    .limits(limits)
+                    .build();
+                assert_eq!(parse(&mut parser).unwrap(), r#""spam""#);
+
+                let mut parser = BencodeParser::builder(&b"5:spams"[..])
+                    .limits(limits)
+                    .build();
+                assert!(matches!(
+                    parse(&mut parser),
+                    Err(Error::MaxStringLengthExceeded(4, _, _))
+                ));
+            }
+
+            #[test]
+            fn failing_when_an_integer_is_longer_than_the_limit() {
+                let limits = Limits {
+                    max_integer_length: Some(2),
+                    ..Limits::default()
+                };
+
+                let mut parser = BencodeParser::builder(&b"i-42e"[..]).limits(limits).build();
+                assert_eq!(parse(&mut parser).unwrap(), "-42");
+
+                let mut parser = BencodeParser::builder(&b"i420e"[..]).limits(limits).build();
+                assert!(matches!(
+                    parse(&mut parser),
+                    Err(Error::MaxIntegerLengthExceeded(2, _, _))
+                ));
+            }
+        }
+
+        #[test]
+        fn all_the_options_at_once() {
+            let mut parser = BencodeParser::builder(&b"d3:fooi1e3:bar2:\xFF\xFEe\n"[..])
+                .capture_window_size(16)
+                .input_buffer_size(16)
+                .output_buffer_size(16)
+                .newline_handling(NewlineHandling::Ignore)
+                .integer_policy(IntegerPolicy::String)
+                .binary_policy(BinaryPolicy::Hex)
+                .limits(Limits {
+                    max_depth: Some(1),
+                    max_string_length: Some(3),
+                    max_integer_length: Some(3),
+                })
+                .build();
+
+            assert_eq!(parse(&mut parser).unwrap(), r#"{"foo":"1","bar":"fffe"}"#);
+        }
+    }
+
+    mod it_should_recover_from_errors {
+        use crate::parsers::{
+            error::{Error, ErrorKind},
+            options::{ErrorHandling, Limits},
+            BencodeParser,
+        };
+
+        /// It returns the JSON output and the kinds of the recovered errors.
+        fn parse_recovering(input: &[u8]) -> (String, Vec<ErrorKind>) {
+            let mut output = String::new();
+
+            let mut parser = BencodeParser::builder(input)
+                .error_handling(ErrorHandling::Recover)
+                .build();
+
+            parser
+                .write_str(&mut output)
+                .expect("Bencode to JSON conversion failed");
+
+            let kinds = parser.diagnostics().iter().map(Error::kind).collect();
+
+            (output, kinds)
+        }
+
+        #[test]
+        fn not_reporting_anything_for_valid_inputs() {
+            assert_eq!(
+                parse_recovering(b"d3:fooli1e4:spamee"),
+                (r#"{"foo":[1,"spam"]}"#.to_string(), vec![])
+            );
+        }
+
+        #[test]
+        fn reporting_all_the_errors_in_one_pass() {
+            assert_eq!(
+                parse_recovering(b"li00eixei3ee"),
+                (
+                    r#"["<unreadable>","<unreadable>",3]"#.to_string(),
+                    vec![ErrorKind::Strictness, ErrorKind::Syntax]
+                )
+            );
+        }
+
+        #[test]
+        fn replacing_integers_with_leading_zeros() {
+            assert_eq!(
+                parse_recovering(b"li1ei00ei3ee"),
+                (
+                    r#"[1,"<unreadable>",3]"#.to_string(),
+                    vec![ErrorKind::Strictness]
+                )
+            );
+        }
+
+        #[test]
+        fn replacing_integers_ending_with_the_beginning_of_another_value() {
+            assert_eq!(
+                parse_recovering(b"li1li2eee"),
+                (
+                    r#"["<unreadable>",[2]]"#.to_string(),
+                    vec![ErrorKind::Syntax]
+                )
+            );
+        }
+
+        #[test]
+        fn replacing_strings_with_invalid_length_bytes() {
+            assert_eq!(
+                parse_recovering(b"l4a:spami1ee"),
+                (
+                    r#"["<unreadable>",1]"#.to_string(),
+                    vec![ErrorKind::Syntax, ErrorKind::Syntax]
+                )
+            );
+        }
+
+        #[test]
+        fn skipping_a_sequence_of_unrecognized_bytes_reporting_it_once() {
+            assert_eq!(
+                parse_recovering(b"lxyzi1ee"),
+                ("[1]".to_string(), vec![ErrorKind::Syntax])
+            );
+        }
+
+        #[test]
+        fn skipping_unmatched_list_or_dict_ends() {
+            assert_eq!(
+                parse_recovering(b"i1ee"),
+                ("1".to_string(), vec![ErrorKind::Syntax])
+            );
+        }
+
+        #[test]
+        fn replacing_missing_dict_field_values() {
+            assert_eq!(
+                parse_recovering(b"d3:fooe"),
+                (
+                    r#"{"foo":"<unreadable>"}"#.to_string(),
+                    vec![ErrorKind::Syntax]
+                )
+            );
+        }
+
+        #[test]
+        fn replacing_dict_keys_that_are_not_strings() {
+            assert_eq!(
+                parse_recovering(b"di42e3:bare"),
+                (
+                    r#"{"<unreadable-1>":"bar"}"#.to_string(),
+                    vec![ErrorKind::Syntax]
+                )
+            );
+            assert_eq!(
+                parse_recovering(b"d3:fooi1eli2ee3:bare"),
+                (
+                    r#"{"foo":1,"<unreadable-1>":"bar"}"#.to_string(),
+                    vec![ErrorKind::Syntax]
+                )
+            );
+        }
+
+        #[test]
+        fn numbering_the_replaced_dict_keys() {
+            assert_eq!(
+                parse_recovering(b"di1e1:ai2ee"),
+                (
+                    r#"{"<unreadable-1>":"a","<unreadable-2>":"<unreadable>"}"#.to_string(),
+                    vec![ErrorKind::Syntax, ErrorKind::Syntax, ErrorKind::Syntax]
+                )
+            );
+        }
+
+        #[test]
+        fn replacing_strings_with_overflowed_lengths() {
+            assert_eq!(
+                parse_recovering(b"li1e20000000000000000000:abi2ee"),
+                (
+                    r#"[1,"<unreadable>",2]"#.to_string(),
+                    vec![ErrorKind::LimitExceeded]
+                )
+            );
+        }
+
+        #[test]
+        fn writing_a_placeholder_for_inputs_without_readable_values() {
+            assert_eq!(
+                parse_recovering(b"ee"),
+                (
+                    r#""<unreadable>""#.to_string(),
+                    vec![ErrorKind::Syntax, ErrorKind::Syntax]
+                )
+            );
+            assert_eq!(
+                parse_recovering(b"xyz"),
+                (r#""<unreadable>""#.to_string(), vec![ErrorKind::Syntax])
+            );
+        }
+
+        #[test]
+        fn closing_lists_and_dicts_when_the_input_ends() {
+            assert_eq!(
+                parse_recovering(b"d3:fooli1e"),
+                (r#"{"foo":[1]}"#.to_string(), vec![ErrorKind::UnexpectedEof])
+            );
+            assert_eq!(
+                parse_recovering(b"d3:foo"),
+                (
+                    r#"{"foo":"<unreadable>"}"#.to_string(),
+                    vec![ErrorKind::UnexpectedEof]
+                )
+            );
+        }
+
+        #[test]
+        fn reporting_a_truncated_value_only_once() {
+            assert_eq!(
+                parse_recovering(b"d3:fooli1ei2"),
+                (
+                    r#"{"foo":[1,"<unreadable>"]}"#.to_string(),
+                    vec![ErrorKind::UnexpectedEof]
+                )
+            );
+        }
+
+        #[test]
+        fn but_not_from_exceeded_limits() {
+            let mut output = String::new();
+
+            let mut parser = BencodeParser::builder(&b"li1ellee"[..])
+                .error_handling(ErrorHandling::Recover)
+                .limits(Limits {
+                    max_depth: Some(1),
+                    ..Limits::default()
+                })
+                .build();
+
+            let result = parser.write_str(&mut output);
+
+            assert!(matches!(result, Err(Error::MaxDepthExceeded(1, _, _))));
+        }
+
+        #[test]
+        fn only_when_enabled() {
+            let mut output = String::new();
+
+            let mut parser = BencodeParser::new(&b"li00ee"[..]);
+
+            let result = parser.write_str(&mut output);
+
+            assert!(matches!(
+                result,
+                Err(Error::LeadingZerosInIntegersNotAllowed(..))
+            ));
+            assert!(parser.diagnostics().is_empty());
+        }
+
+        #[test]
+        fn allowing_to_take_the_diagnostics() {
+            let mut output = String::new();
+
+            let mut parser = BencodeParser::builder(&b"i00e"[..])
+                .error_handling(ErrorHandling::Recover)
+                .build();
+
+            parser
+                .write_str(&mut output)
+                .expect("Bencode to JSON conversion failed");
+
+            assert_eq!(parser.take_diagnostics().len(), 1);
+            assert!(parser.diagnostics().is_empty());
+        }
+    }
+
     mod it_should_fail {
         use std::io::{self, Read};
 
@@ -574,6 +2019,63 @@ mod tests {
         }
     }
 
+    mod it_should_never_panic {
+        use proptest::prelude::*;
+
+        use crate::parsers::{options::ErrorHandling, BencodeParser};
+
+        /// Bytes that are meaningful for the parser, plus some that are not,
+        /// so that the generated inputs reach the nested states more often
+        /// than fully random bytes.
+        const BENCODE_ALPHABET: &[u8] = b"ilde0123456789:-\nax\xff";
+
+        fn parse_both_ways(input: &[u8]) {
+            let mut output = String::new();
+            let _result = BencodeParser::new(input).write_str(&mut output);
+
+            let mut output = Vec::new();
+            let _result = BencodeParser::new(input).write_bytes(&mut output);
+        }
+
+        /// In recovery mode only I/O errors and exceeded limits stop the
+        /// parser, and there are none of them here.
+        fn parse_recovering(input: &[u8]) {
+            let mut output = String::new();
+
+            BencodeParser::builder(input)
+                .error_handling(ErrorHandling::Recover)
+                .build()
+                .write_str(&mut output)
+                .expect("recoverable errors should not stop the parser");
+        }
+
+        proptest! {
+            #[test]
+            fn on_arbitrary_bytes(input in proptest::collection::vec(any::<u8>(), 0..512)) {
+                parse_both_ways(&input);
+            }
+
+            #[test]
+            fn on_arbitrary_bytes_when_recovering(input in proptest::collection::vec(any::<u8>(), 0..512)) {
+                parse_recovering(&input);
+            }
+
+            #[test]
+            fn on_arbitrary_sequences_of_bencode_bytes_when_recovering(
+                input in proptest::collection::vec(proptest::sample::select(BENCODE_ALPHABET), 0..512)
+            ) {
+                parse_recovering(&input);
+            }
+
+            #[test]
+            fn on_arbitrary_sequences_of_bencode_bytes(
+                input in proptest::collection::vec(proptest::sample::select(BENCODE_ALPHABET), 0..512)
+            ) {
+                parse_both_ways(&input);
+            }
+        }
+    }
+
     mod integers {
         use crate::test::bencode_to_json_unchecked;
 
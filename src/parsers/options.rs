@@ -0,0 +1,469 @@
+//! Parser configuration.
+//!
+//! All the parser settings are grouped in [`ParserOptions`]. The easiest way
+//! to build a parser with custom settings is the [`ParserBuilder`], returned
+//! by [`BencodeParser::builder`]:
+//!
+//! ```rust
+//! use torrust_bencode2json::parsers::{
+//!     options::{IntegerPolicy, Limits},
+//!     BencodeParser,
+//! };
+//!
+//! let mut output = String::new();
+//!
+//! let mut parser = BencodeParser::builder(&b"li42ee"[..])
+//!     .integer_policy(IntegerPolicy::String)
+//!     .limits(Limits {
+//!         max_depth: Some(32),
+//!         ..Limits::default()
+//!     })
+//!     .build();
+//!
+//! parser.write_str(&mut output).unwrap();
+//!
+//! assert_eq!(output, r#"["42"]"#);
+//! ```
+use std::io::{self, BufRead, BufReader, Read, Seek};
+
+#[cfg(feature = "tokio")]
+use tokio::io::AsyncRead;
+
+#[cfg(feature = "tokio")]
+use super::async_parser::AsyncBencodeParser;
+use super::{json_reader::BencodeToJsonReader, BencodeParser};
+
+/// The default number of bytes captured from the input and the output to
+/// give context in errors.
+pub const DEFAULT_CAPTURE_WINDOW_SIZE: usize = 1024;
+
+/// The default capacity of the input and output buffers.
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// The largest integer that can be represented exactly in a JSON number by
+/// most JSON parsers (`2^53 - 1`, `Number.MAX_SAFE_INTEGER` in JavaScript).
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// All the parser settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Number of latest bytes read from the input and written to the output
+    /// that are kept to give context in errors. It must be at least 1.
+    pub capture_window_size: usize,
+
+    /// How the latest bytes are kept to give context in errors.
+    pub capture_mode: CaptureMode,
+
+    /// Capacity of the buffer used to read from the input. It must be at
+    /// least 1.
+    pub input_buffer_size: usize,
+
+    /// Capacity of the buffer used to write to the output. It's only used
+    /// when writing bytes (`std::io::Write`).
+    pub output_buffer_size: usize,
+
+    /// What to do with line breaks between bencoded values.
+    pub newline_handling: NewlineHandling,
+
+    /// How to write integers.
+    pub integer_policy: IntegerPolicy,
+
+    /// How to write strings that are not valid UTF-8.
+    pub binary_policy: BinaryPolicy,
+
+    /// Limits to protect against hostile inputs.
+    pub limits: Limits,
+
+    /// What to do when the input is not valid Bencode.
+    pub error_handling: ErrorHandling,
+
+    /// How integers and string lengths are scanned.
+    pub scan_mode: ScanMode,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            capture_window_size: DEFAULT_CAPTURE_WINDOW_SIZE,
+            capture_mode: CaptureMode::default(),
+            input_buffer_size: DEFAULT_BUFFER_SIZE,
+            output_buffer_size: DEFAULT_BUFFER_SIZE,
+            newline_handling: NewlineHandling::default(),
+            integer_policy: IntegerPolicy::default(),
+            binary_policy: BinaryPolicy::default(),
+            limits: Limits::default(),
+            error_handling: ErrorHandling::default(),
+            scan_mode: ScanMode::default(),
+        }
+    }
+}
+
+/// How the latest bytes read from the input and written to the output are
+/// kept to give context in errors (`latest_bytes` in
+/// [`ReadContext`](super::error::ReadContext) and
+/// [`WriteContext`](super::error::WriteContext)).
+///
+/// Errors always include the input and output positions and the byte that
+/// caused the error, whatever the mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureMode {
+    /// Nothing is captured. It's the fastest mode. The latest bytes in
+    /// errors are empty.
+    Off,
+
+    /// The latest `capture_window_size` bytes are copied into a ring buffer
+    /// while reading and writing.
+    #[default]
+    RingBuffer,
+
+    /// Nothing is captured while parsing. When there is an error, the latest
+    /// `capture_window_size` input bytes are re-read from the input. It only
+    /// works for parsers built with [`ParserBuilder::build_seekable`]; for
+    /// other parsers it's like `Off`. The latest output bytes are not
+    /// available.
+    Lazy,
+}
+
+impl CaptureMode {
+    /// It returns the capacity of the buffers used to capture the input and
+    /// output bytes while parsing. It's 0 when nothing is captured.
+    #[must_use]
+    pub fn capture_capacity(self, capture_window_size: usize) -> usize {
+        match self {
+            CaptureMode::Off | CaptureMode::Lazy => 0,
+            CaptureMode::RingBuffer => capture_window_size,
+        }
+    }
+}
+
+/// What to do with line breaks (`\n`) at the beginning, the end, or between
+/// bencoded values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NewlineHandling {
+    /// Line breaks are ignored.
+    #[default]
+    Ignore,
+
+    /// Line breaks are not allowed. They are treated like any other
+    /// unexpected byte.
+    Reject,
+}
+
+/// How bencoded integers are written to the JSON output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegerPolicy {
+    /// Integers are written as JSON numbers with all their digits, even if
+    /// they don't fit in a 64-bit integer.
+    #[default]
+    Number,
+
+    /// Integers are written as JSON strings, for example `"42"`.
+    String,
+
+    /// Integers are written as JSON numbers when they can be represented
+    /// exactly by most JSON parsers (between `-(2^53 - 1)` and `2^53 - 1`),
+    /// and as JSON strings otherwise.
+    StringIfUnsafe,
+}
+
+impl IntegerPolicy {
+    /// It returns true if the integer has to be written as a JSON string.
+    ///
+    /// The `digits` are the integer digits including the sign, for example
+    /// `-42`.
+    #[must_use]
+    pub fn quote(self, digits: &str) -> bool {
+        match self {
+            IntegerPolicy::Number => false,
+            IntegerPolicy::String => true,
+            IntegerPolicy::StringIfUnsafe => digits
+                .parse::<i64>()
+                .map_or(true, |number| number.unsigned_abs() > MAX_SAFE_INTEGER),
+        }
+    }
+}
+
+/// How bencoded strings that are not valid UTF-8 are written to the JSON
+/// output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BinaryPolicy {
+    /// Bytes are written in hexadecimal between tags, for example
+    /// `"<hex>fffe</hex>"`.
+    #[default]
+    TaggedHex,
+
+    /// Bytes are written in hexadecimal without tags, for example `"fffe"`.
+    Hex,
+
+    /// Invalid UTF-8 sequences are replaced with the replacement character
+    /// `U+FFFD`.
+    Lossy,
+}
+
+/// Limits to protect against hostile inputs.
+///
+/// All limits are disabled (`None`) by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Maximum number of nested lists and dictionaries.
+    pub max_depth: Option<usize>,
+
+    /// Maximum length of a bencoded string in bytes.
+    pub max_string_length: Option<usize>,
+
+    /// Maximum number of digits of a bencoded integer, without the sign.
+    pub max_integer_length: Option<usize>,
+}
+
+/// What to do when the input is not valid Bencode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorHandling {
+    /// The parser stops and returns the first error.
+    #[default]
+    Stop,
+
+    /// The parser records the error, skips the invalid bytes and goes on
+    /// with the next plausible value. Unreadable values are written as
+    /// [`UNREADABLE_VALUE`](super::recovery::UNREADABLE_VALUE) so that the
+    /// output is still valid JSON. The recorded errors are returned by
+    /// [`BencodeParser::diagnostics`].
+    ///
+    /// I/O errors and exceeded limits are never recovered.
+    Recover,
+}
+
+/// How integers and string lengths are scanned.
+///
+/// Both modes produce the same output and the same errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanMode {
+    /// When the whole token is already in the input buffer, the terminator
+    /// (`e` or `:`) is searched with `memchr` and the digits are validated
+    /// eight at a time. Otherwise, or if the token is not valid, it falls
+    /// back to the scalar path.
+    #[default]
+    Vectorized,
+
+    /// The tokens are always read byte by byte.
+    Scalar,
+}
+
+/// A builder for [`BencodeParser`].
+///
+/// Options that are not set keep their default values.
+#[allow(clippy::module_name_repetitions)]
+pub struct ParserBuilder<R> {
+    reader: R,
+    options: ParserOptions,
+}
+
+impl<R> ParserBuilder<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            options: ParserOptions::default(),
+        }
+    }
+
+    /// It replaces all the options. It's useful to share the same options
+    /// between parsers.
+    #[must_use]
+    pub fn options(mut self, options: ParserOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Number of latest bytes kept to give context in errors. Values lower
+    /// than 1 are treated as 1.
+    #[must_use]
+    pub fn capture_window_size(mut self, size: usize) -> Self {
+        self.options.capture_window_size = size.max(1);
+        self
+    }
+
+    /// How the latest bytes are kept to give context in errors.
+    #[must_use]
+    pub fn capture_mode(mut self, capture_mode: CaptureMode) -> Self {
+        self.options.capture_mode = capture_mode;
+        self
+    }
+
+    /// Capacity of the buffer used to read from the input. Values lower than
+    /// 1 are treated as 1.
+    #[must_use]
+    pub fn input_buffer_size(mut self, size: usize) -> Self {
+        self.options.input_buffer_size = size.max(1);
+        self
+    }
+
+    /// Capacity of the buffer used to write to the output.
+    #[must_use]
+    pub fn output_buffer_size(mut self, size: usize) -> Self {
+        self.options.output_buffer_size = size;
+        self
+    }
+
+    /// What to do with line breaks between bencoded values.
+    #[must_use]
+    pub fn newline_handling(mut self, newline_handling: NewlineHandling) -> Self {
+        self.options.newline_handling = newline_handling;
+        self
+    }
+
+    /// How to write integers.
+    #[must_use]
+    pub fn integer_policy(mut self, integer_policy: IntegerPolicy) -> Self {
+        self.options.integer_policy = integer_policy;
+        self
+    }
+
+    /// How to write strings that are not valid UTF-8.
+    #[must_use]
+    pub fn binary_policy(mut self, binary_policy: BinaryPolicy) -> Self {
+        self.options.binary_policy = binary_policy;
+        self
+    }
+
+    /// Limits to protect against hostile inputs.
+    #[must_use]
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.options.limits = limits;
+        self
+    }
+
+    /// What to do when the input is not valid Bencode.
+    #[must_use]
+    pub fn error_handling(mut self, error_handling: ErrorHandling) -> Self {
+        self.options.error_handling = error_handling;
+        self
+    }
+
+    /// How integers and string lengths are scanned.
+    #[must_use]
+    pub fn scan_mode(mut self, scan_mode: ScanMode) -> Self {
+        self.options.scan_mode = scan_mode;
+        self
+    }
+
+    /// It builds an async parser for tokio inputs. It's only available with
+    /// the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub fn build_async(self) -> AsyncBencodeParser<R>
+    where
+        R: AsyncRead + Unpin,
+    {
+        AsyncBencodeParser::with_options(self.reader, self.options)
+    }
+
+    /// It builds the parser.
+    pub fn build(self) -> BencodeParser<BufReader<R>>
+    where
+        R: Read,
+    {
+        BencodeParser::with_options(self.reader, self.options)
+    }
+
+    /// It builds a reader of the JSON output. See [`BencodeToJsonReader`].
+    pub fn build_json_reader(self) -> BencodeToJsonReader<R>
+    where
+        R: Read,
+    {
+        BencodeToJsonReader::with_options(self.reader, self.options)
+    }
+
+    /// It builds a parser that reads directly from the buffer of an input
+    /// that is already buffered, like a slice (`&[u8]`). See
+    /// [`BencodeParser::from_buf_read`].
+    pub fn build_from_buf_read(self) -> BencodeParser<R>
+    where
+        R: BufRead,
+    {
+        BencodeParser::from_buf_read_with_options(self.reader, self.options)
+    }
+
+    /// It builds a parser for a seekable input, like a file. With
+    /// [`CaptureMode::Lazy`] the latest input bytes are re-read from the input
+    /// when there is an error.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't get the current input position.
+    pub fn build_seekable(self) -> Result<BencodeParser<BufReader<R>>, io::Error>
+    where
+        R: Read + Seek,
+    {
+        let mut parser = BencodeParser::with_options(self.reader, self.options);
+
+        parser.enable_rereading()?;
+
+        Ok(parser)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    mod the_integer_policy {
+        use crate::parsers::options::IntegerPolicy;
+
+        #[test]
+        fn number_should_never_quote_integers() {
+            assert!(!IntegerPolicy::Number.quote("42"));
+            assert!(!IntegerPolicy::Number.quote("92233720368547758071"));
+        }
+
+        #[test]
+        fn string_should_always_quote_integers() {
+            assert!(IntegerPolicy::String.quote("42"));
+        }
+
+        #[test]
+        fn string_if_unsafe_should_only_quote_integers_outside_the_safe_range() {
+            assert!(!IntegerPolicy::StringIfUnsafe.quote("9007199254740991"));
+            assert!(!IntegerPolicy::StringIfUnsafe.quote("-9007199254740991"));
+            assert!(IntegerPolicy::StringIfUnsafe.quote("9007199254740992"));
+            assert!(IntegerPolicy::StringIfUnsafe.quote("-9007199254740992"));
+            assert!(IntegerPolicy::StringIfUnsafe.quote("92233720368547758071"));
+            assert!(IntegerPolicy::StringIfUnsafe.quote(&i64::MIN.to_string()));
+        }
+    }
+
+    mod the_builder {
+        use crate::parsers::options::{IntegerPolicy, ParserBuilder, ParserOptions};
+
+        #[test]
+        fn should_use_the_default_options() {
+            let parser = ParserBuilder::new(&b""[..]).build();
+
+            assert_eq!(parser.options(), &ParserOptions::default());
+        }
+
+        #[test]
+        fn should_allow_replacing_all_the_options() {
+            let options = ParserOptions {
+                integer_policy: IntegerPolicy::String,
+                ..ParserOptions::default()
+            };
+
+            let parser = ParserBuilder::new(&b""[..])
+                .options(options.clone())
+                .build();
+
+            assert_eq!(parser.options(), &options);
+        }
+
+        #[test]
+        fn should_not_allow_a_capture_window_smaller_than_one_byte() {
+            let parser = ParserBuilder::new(&b""[..]).capture_window_size(0).build();
+
+            assert_eq!(parser.options().capture_window_size, 1);
+        }
+
+        #[test]
+        fn should_not_allow_an_input_buffer_smaller_than_one_byte() {
+            let parser = ParserBuilder::new(&b""[..]).input_buffer_size(0).build();
+
+            assert_eq!(parser.options().input_buffer_size, 1);
+        }
+    }
+}
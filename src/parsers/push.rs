@@ -0,0 +1,443 @@
+//! Push parser.
+//!
+//! [`PushParser`] is for inputs that arrive in chunks that can't be read with
+//! a blocking `Read`, like UDP datagrams or socket events. The chunks are fed
+//! to the parser as they arrive, and it returns the JSON as soon as every
+//! token is complete, and the top-level values that have been completed:
+//!
+//! ```rust
+//! use torrust_bencode2json::parsers::push::PushParser;
+//!
+//! let mut parser = PushParser::new();
+//!
+//! let progress = parser.feed(b"d4:spaml1:a").unwrap();
+//!
+//! assert_eq!(progress.json, r#"{"spam":["a""#);
+//! assert!(progress.completed_values.is_empty());
+//!
+//! let progress = parser.feed(b"i42eee").unwrap();
+//!
+//! assert_eq!(progress.json, ",42]}");
+//! assert_eq!(progress.completed_values.len(), 1);
+//! assert_eq!(progress.completed_values[0].input_end, 17);
+//!
+//! parser.finish().unwrap();
+//! ```
+//!
+//! The output and errors are the same as the ones of the sync parser. The
+//! parser keeps the state between chunks: the [`stack`](super::stack) of
+//! open lists and dictionaries, and the bytes of the token that is not
+//! complete yet. Once the length of a string has arrived, the string is not
+//! parsed again until all its bytes have arrived. Integers and string
+//! lengths are not parsed again until the byte that ends them has arrived.
+use super::{
+    error::Error,
+    incremental::{IncrementalParser, Step},
+    options::ParserOptions,
+};
+
+/// A Bencode to JSON parser that is fed with chunks of input.
+pub struct PushParser {
+    incremental: IncrementalParser,
+}
+
+/// What the parser could do with the bytes fed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// The JSON for the tokens completed with the bytes fed. It can end in
+    /// the middle of a value, but not in the middle of a token.
+    pub json: String,
+
+    /// The top-level values completed with the bytes fed, in order.
+    pub completed_values: Vec<CompletedValue>,
+}
+
+/// A top-level value that has been completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompletedValue {
+    /// Number of bytes fed to the parser, since it was created, before the
+    /// beginning of the value.
+    pub input_start: u64,
+
+    /// Number of bytes fed to the parser, since it was created, up to the
+    /// end of the value.
+    pub input_end: u64,
+
+    /// Position of the end of the value in [`Progress::json`].
+    pub json_end: usize,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PushParser {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_options(ParserOptions::default())
+    }
+
+    /// It creates a parser with custom options.
+    ///
+    /// The `input_buffer_size` option is the minimum number of parsed bytes
+    /// that are dropped at once from the internal buffer.
+    #[must_use]
+    pub fn with_options(options: ParserOptions) -> Self {
+        Self {
+            incremental: IncrementalParser::new(options),
+        }
+    }
+
+    /// It returns the parser options.
+    #[must_use]
+    pub fn options(&self) -> &ParserOptions {
+        self.incremental.parser.options()
+    }
+
+    /// It returns the errors the parser recovered from.
+    #[must_use]
+    pub fn diagnostics(&self) -> &[Error] {
+        self.incremental.parser.diagnostics()
+    }
+
+    /// It returns the errors the parser recovered from, leaving the list
+    /// empty.
+    pub fn take_diagnostics(&mut self) -> Vec<Error> {
+        self.incremental.parser.take_diagnostics()
+    }
+
+    /// It returns true if some bytes fed are part of a value that has not
+    /// been completed yet.
+    #[must_use]
+    pub fn is_within_value(&self) -> bool {
+        !self.incremental.input().pending().is_empty()
+            || self.incremental.parser.byte_reader.peeked_byte().is_some()
+            || self.incremental.parser.stack.depth() > 0
+    }
+
+    /// It parses the next chunk of input.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the input is invalid Bencode. The parser can't
+    /// be used after an error, and the JSON of the tokens parsed with the
+    /// same chunk before the error is not returned.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Progress, Error> {
+        self.incremental.input_mut().bytes.extend_from_slice(bytes);
+
+        self.parse()
+    }
+
+    /// It tells the parser that the input has ended, and parses the rest of
+    /// the input.
+    ///
+    /// No more chunks should be fed after it.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the input is invalid Bencode, for example, if
+    /// it ends in the middle of a value.
+    pub fn finish(&mut self) -> Result<Progress, Error> {
+        self.incremental.input_mut().ended = true;
+
+        self.parse()
+    }
+
+    /// It parses all the complete tokens.
+    fn parse(&mut self) -> Result<Progress, Error> {
+        let mut progress = Progress::default();
+
+        loop {
+            match self.incremental.parse_next_token()? {
+                Step::NeedMoreInput | Step::Ended => break,
+                Step::Parsed => {}
+                Step::CompletedValue => progress.completed_values.push(CompletedValue {
+                    input_start: self.incremental.value_start(),
+                    input_end: self.incremental.input_position(),
+                    json_end: self.incremental.output.pending().len(),
+                }),
+            }
+        }
+
+        // The JSON is always valid UTF-8 at the end of a token
+        progress.json = String::from_utf8_lossy(self.incremental.output.pending()).into_owned();
+
+        self.incremental.output.mark_as_written();
+        self.incremental.compact();
+
+        Ok(progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should {
+        use std::time::{Duration, Instant};
+
+        use crate::parsers::{
+            options::{ErrorHandling, NewlineHandling, ParserOptions},
+            push::{CompletedValue, Progress, PushParser},
+        };
+
+        #[test]
+        fn emit_the_json_of_every_complete_token() {
+            let mut parser = PushParser::new();
+
+            assert_eq!(parser.feed(b"l4:sp").unwrap().json, "[");
+            assert_eq!(parser.feed(b"am").unwrap().json, r#""spam""#);
+            assert_eq!(parser.feed(b"i4").unwrap().json, "");
+            assert_eq!(parser.feed(b"2").unwrap().json, "");
+            assert_eq!(parser.feed(b"e").unwrap().json, ",42");
+            assert_eq!(parser.feed(b"e").unwrap().json, "]");
+        }
+
+        #[test]
+        fn report_when_a_top_level_value_is_completed() {
+            let mut parser = PushParser::new();
+
+            let progress = parser.feed(b"li1ee").unwrap();
+
+            assert_eq!(
+                progress.completed_values,
+                vec![CompletedValue {
+                    input_start: 0,
+                    input_end: 5,
+                    json_end: 3
+                }]
+            );
+            assert!(!parser.is_within_value());
+        }
+
+        #[test]
+        fn report_many_top_level_values_in_one_chunk() {
+            let mut parser = PushParser::new();
+
+            let progress = parser.feed(b"i1e4:spamle").unwrap();
+
+            assert_eq!(progress.json, r#"1"spam"[]"#);
+            assert_eq!(
+                progress.completed_values,
+                vec![
+                    CompletedValue {
+                        input_start: 0,
+                        input_end: 3,
+                        json_end: 1
+                    },
+                    CompletedValue {
+                        input_start: 3,
+                        input_end: 9,
+                        json_end: 7
+                    },
+                    CompletedValue {
+                        input_start: 9,
+                        input_end: 11,
+                        json_end: 9
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn not_include_ignored_line_breaks_in_values() {
+            let mut parser = PushParser::with_options(ParserOptions {
+                newline_handling: NewlineHandling::Ignore,
+                ..ParserOptions::default()
+            });
+
+            let progress = parser.feed(b"\n\nli1ee\n").unwrap();
+
+            assert_eq!(progress.completed_values[0].input_start, 2);
+            assert_eq!(progress.completed_values[0].input_end, 7);
+        }
+
+        #[test]
+        fn know_when_it_is_within_a_value() {
+            let mut parser = PushParser::new();
+
+            parser.feed(b"l").unwrap();
+            assert!(parser.is_within_value());
+
+            parser.feed(b"e3:a").unwrap();
+            assert!(parser.is_within_value());
+
+            parser.feed(b"bc").unwrap();
+            assert!(!parser.is_within_value());
+        }
+
+        #[test]
+        fn fail_when_the_input_ends_within_a_value() {
+            let mut parser = PushParser::new();
+
+            parser.feed(b"li1e").unwrap();
+
+            assert!(parser.finish().is_err());
+        }
+
+        #[test]
+        fn complete_values_closed_when_the_input_ends_when_recovering() {
+            let mut parser = PushParser::with_options(ParserOptions {
+                error_handling: ErrorHandling::Recover,
+                ..ParserOptions::default()
+            });
+
+            parser.feed(b"li1e").unwrap();
+
+            let progress = parser.finish().unwrap();
+
+            assert_eq!(progress.json, "]");
+            assert_eq!(progress.completed_values.len(), 1);
+            assert_eq!(parser.diagnostics().len(), 1);
+        }
+
+        #[test]
+        fn not_parse_long_strings_again_until_all_the_bytes_have_arrived() {
+            let mut parser = PushParser::new();
+
+            parser.feed(b"10000:").unwrap();
+
+            for _ in 0..9999 {
+                assert_eq!(parser.feed(b"a").unwrap(), Progress::default());
+                assert_eq!(parser.incremental.required_input(), 10006);
+            }
+
+            assert_eq!(parser.feed(b"a").unwrap().json.len(), 10002);
+        }
+
+        #[test]
+        fn not_parse_long_integers_again_every_time_a_digit_arrives() {
+            let started = Instant::now();
+
+            let mut parser = PushParser::new();
+
+            parser.feed(b"i1").unwrap();
+
+            for _ in 0..100_000 {
+                assert_eq!(parser.feed(b"0").unwrap(), Progress::default());
+            }
+
+            assert_eq!(parser.feed(b"e").unwrap().json.len(), 100_001);
+            assert!(started.elapsed() < Duration::from_secs(5));
+        }
+    }
+
+    mod it_should_give_the_same_output_and_errors_as_the_sync_parser {
+        use proptest::prelude::*;
+
+        use crate::parsers::{
+            options::{ErrorHandling, Limits, ParserOptions},
+            push::PushParser,
+            BencodeParser,
+        };
+
+        const BENCODE_ALPHABET: &[u8] = b"ilde0123456789:-\nx";
+
+        /// The JSON output, the error message with its context, and the
+        /// recovered errors.
+        type Outcome = (String, Option<String>, Vec<String>);
+
+        fn parse_sync(input: &[u8], options: &ParserOptions) -> Outcome {
+            let mut parser = BencodeParser::with_options(input, options.clone());
+
+            let mut output = Vec::new();
+            let result = parser.write_bytes(&mut output);
+
+            (
+                String::from_utf8(output).unwrap(),
+                result.err().map(|err| err.to_string()),
+                parser
+                    .diagnostics()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            )
+        }
+
+        fn parse_in_chunks(input: &[u8], chunk_size: usize, options: &ParserOptions) -> Outcome {
+            let mut parser = PushParser::with_options(options.clone());
+
+            let mut output = String::new();
+
+            let mut result = Ok(());
+
+            for chunk in input.chunks(chunk_size) {
+                match parser.feed(chunk) {
+                    Ok(progress) => output.push_str(&progress.json),
+                    Err(err) => {
+                        result = Err(err);
+                        break;
+                    }
+                }
+            }
+
+            if result.is_ok() {
+                result = parser
+                    .finish()
+                    .map(|progress| output.push_str(&progress.json));
+            }
+
+            (
+                output,
+                result.err().map(|err| err.to_string()),
+                parser
+                    .diagnostics()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            )
+        }
+
+        proptest! {
+            #[test]
+            fn on_arbitrary_sequences_of_bencode_bytes(
+                input in proptest::collection::vec(proptest::sample::select(BENCODE_ALPHABET), 0..256),
+                chunk_size in 1..16usize,
+            ) {
+                let options = ParserOptions::default();
+
+                let (sync_output, sync_error, sync_diagnostics) = parse_sync(&input, &options);
+                let (output, error, diagnostics) = parse_in_chunks(&input, chunk_size, &options);
+
+                // The JSON of the tokens before the error in the same chunk
+                // is not returned
+                prop_assert!(sync_output.starts_with(&output));
+                prop_assert_eq!(error.clone(), sync_error);
+                prop_assert_eq!(diagnostics, sync_diagnostics);
+
+                if error.is_none() {
+                    prop_assert_eq!(output, sync_output);
+                }
+            }
+
+            #[test]
+            fn on_arbitrary_sequences_of_bencode_bytes_when_recovering_with_limits(
+                input in proptest::collection::vec(proptest::sample::select(BENCODE_ALPHABET), 0..256),
+                chunk_size in 1..16usize,
+            ) {
+                let options = ParserOptions {
+                    input_buffer_size: 4,
+                    capture_window_size: 8,
+                    error_handling: ErrorHandling::Recover,
+                    limits: Limits {
+                        max_depth: Some(4),
+                        max_integer_length: Some(3),
+                        max_string_length: Some(5),
+                    },
+                    ..ParserOptions::default()
+                };
+
+                let (sync_output, sync_error, sync_diagnostics) = parse_sync(&input, &options);
+                let (output, error, diagnostics) = parse_in_chunks(&input, chunk_size, &options);
+
+                prop_assert!(sync_output.starts_with(&output));
+                prop_assert_eq!(error.clone(), sync_error);
+                prop_assert_eq!(diagnostics, sync_diagnostics);
+
+                if error.is_none() {
+                    prop_assert_eq!(output, sync_output);
+                }
+            }
+        }
+    }
+}
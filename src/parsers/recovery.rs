@@ -0,0 +1,355 @@
+//! Error recovery.
+//!
+//! When the parser is built with [`ErrorHandling::Recover`](super::options::ErrorHandling::Recover)
+//! it does not stop at the first error. It records the error, skips the
+//! invalid bytes until the next plausible value boundary and goes on.
+//!
+//! Values that can't be read are replaced with the JSON string
+//! [`UNREADABLE_VALUE`], dictionary keys that are not strings with numbered
+//! placeholders (`"<unreadable-1>"`, `"<unreadable-2>"`, ...) so that the
+//! keys are unique, and lists or dictionaries that are not closed when the
+//! input ends are closed, so the output is always valid JSON. An input with
+//! only unreadable bytes is written as [`UNREADABLE_VALUE`].
+//!
+//! For example, `li1ei00ei3ee` is written as `[1,"<unreadable>",3]`.
+//!
+//! I/O errors and exceeded limits are never recovered. String lengths that
+//! overflow are malformed lengths, not exceeded limits, so they are
+//! recovered.
+use std::io::{self, BufRead};
+
+use super::{
+    error::{Error, ErrorKind},
+    integer,
+    stack::State,
+    string, BencodeParser, BencodeType, NewlineHandling, BENCODE_BEGIN_DICT, BENCODE_BEGIN_INTEGER,
+    BENCODE_BEGIN_LIST, BENCODE_END_INTEGER, BENCODE_END_LIST_OR_DICT,
+};
+use crate::rw::{error::Error as RwError, writer::Writer};
+
+/// The placeholder written instead of the values that can't be read.
+pub const UNREADABLE_VALUE: &str = "<unreadable>";
+
+/// The placeholder as a JSON string.
+const JSON_UNREADABLE_VALUE: &str = "\"<unreadable>\"";
+
+impl<R: BufRead> BencodeParser<R> {
+    /// It records the error and leaves the parser ready to go on with the
+    /// next plausible value.
+    ///
+    /// # Errors
+    ///
+    /// Will return the error if it can't be recovered, or a new error if it
+    /// can't read from the input or write to the output while recovering.
+    pub(super) fn recover<W: Writer>(&mut self, err: Error, writer: &mut W) -> Result<(), Error> {
+        if !matches!(
+            err.kind(),
+            ErrorKind::Syntax | ErrorKind::UnexpectedEof | ErrorKind::Strictness
+        ) && !matches!(err, Error::StringLengthOverflow(..))
+        {
+            return Err(err);
+        }
+
+        match &err {
+            Error::UnexpectedByteParsingInteger(read_context, _) => {
+                writer.write_str(JSON_UNREADABLE_VALUE)?;
+
+                match read_context.byte {
+                    // The integer ended with the unexpected byte, for example `ie`
+                    None | Some(BENCODE_END_INTEGER) => {}
+                    Some(byte) if self.is_plausible_value_boundary(byte) => {
+                        self.byte_reader.unread_byte(byte);
+                    }
+                    Some(_) => self.skip_past(BENCODE_END_INTEGER)?,
+                }
+            }
+            Error::LeadingZerosInIntegersNotAllowed(..) => {
+                writer.write_str(JSON_UNREADABLE_VALUE)?;
+                self.skip_past(BENCODE_END_INTEGER)?;
+            }
+            Error::InvalidStringLengthByte(read_context, _) => {
+                writer.write_str(JSON_UNREADABLE_VALUE)?;
+
+                if let Some(byte) = read_context.byte {
+                    if self.is_plausible_value_boundary(byte) {
+                        self.byte_reader.unread_byte(byte);
+                    }
+                }
+            }
+            Error::StringLengthOverflow(..) => {
+                writer.write_str(JSON_UNREADABLE_VALUE)?;
+                self.skip_string_with_overflowed_length()?;
+            }
+            Error::UnexpectedEndOfInputParsingInteger(..)
+            | Error::UnexpectedEndOfInputParsingStringLength(..)
+            | Error::UnexpectedEndOfInputParsingStringValue(..) => {
+                writer.write_str(JSON_UNREADABLE_VALUE)?;
+            }
+            Error::UnrecognizedFirstBencodeValueByte(..) => {
+                self.skip_unrecognized_bytes()?;
+            }
+            Error::NoMatchingStartForListOrDictEnd(..) => {
+                // The unmatched end byte has already been consumed
+            }
+            Error::PrematureEndOfDict(..) => {
+                writer.write_byte(Self::JSON_OBJ_FIELD_KEY_VALUE_SEPARATOR)?;
+                writer.write_str(JSON_UNREADABLE_VALUE)?;
+                writer.write_byte(Self::JSON_OBJ_END)?;
+                self.stack
+                    .pop()
+                    .map_err(|err| self.invalid_stack_state(err, writer))?;
+            }
+            Error::ExpectedStringForDictKeyGot(bencode_type, ..) => {
+                self.replace_dict_key(*bencode_type, writer)?;
+            }
+            Error::UnexpectedEndOfInputExpectingFirstListItemOrEnd(..)
+            | Error::UnexpectedEndOfInputExpectingNextListItem(..)
+            | Error::UnexpectedEndOfInputExpectingFirstDictFieldOrEnd(..)
+            | Error::UnexpectedEndOfInputExpectingDictFieldValue(..)
+            | Error::UnexpectedEndOfInputExpectingDictFieldKeyOrEnd(..) => {
+                self.close_open_lists_and_dicts(writer)?;
+
+                // The truncated value has already been reported
+                if self.diagnostics.last().is_some_and(Error::is_eof) {
+                    return Ok(());
+                }
+            }
+            _ => return Err(err),
+        }
+
+        self.diagnostics.push(err);
+
+        Ok(())
+    }
+
+    /// It writes a placeholder when the input had only unreadable bytes, so
+    /// that the output is still a JSON value.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't write to the output.
+    pub(super) fn finish_recovery<W: Writer>(&mut self, writer: &mut W) -> Result<(), Error> {
+        if writer.output_byte_counter() == 0 && !self.diagnostics.is_empty() {
+            writer.write_str(JSON_UNREADABLE_VALUE)?;
+        }
+
+        Ok(())
+    }
+
+    /// It returns true if the byte can start a new token.
+    fn is_plausible_value_boundary(&self, byte: u8) -> bool {
+        match byte {
+            BENCODE_BEGIN_INTEGER
+            | BENCODE_BEGIN_LIST
+            | BENCODE_BEGIN_DICT
+            | BENCODE_END_LIST_OR_DICT
+            | b'0'..=b'9' => true,
+            b'\n' => self.options.newline_handling == NewlineHandling::Ignore,
+            _ => false,
+        }
+    }
+
+    /// It writes a placeholder for a dictionary key that is not a string and
+    /// skips the key value. The next value is used as the field value.
+    fn replace_dict_key<W: Writer>(
+        &mut self,
+        bencode_type: BencodeType,
+        writer: &mut W,
+    ) -> Result<(), Error> {
+        let state = self
+            .stack
+            .peek()
+            .map_err(|err| self.invalid_stack_state(err, writer))?;
+
+        if state == State::ExpectingDictFieldKeyOrEnd {
+            writer.write_byte(Self::JSON_OBJ_FIELDS_SEPARATOR)?;
+        }
+
+        self.unreadable_keys += 1;
+        let key = format!("<unreadable-{}>", self.unreadable_keys);
+        writer.write_str(&format!("\"{key}\""))?;
+        self.stack.set_key(key.as_bytes());
+
+        self.stack
+            .swap_top(State::ExpectingDictFieldValue)
+            .map_err(|err| self.invalid_stack_state(err, writer))?;
+
+        let skipped = match bencode_type {
+            BencodeType::Integer => {
+                integer::parse(&mut self.byte_reader, &mut Discard, &self.options)
+            }
+            BencodeType::String => Ok(()),
+            // The first byte has already been consumed
+            BencodeType::List | BencodeType::Dict => self.skip_list_or_dict(),
+        };
+
+        match skipped {
+            Err(err) if err.kind() == ErrorKind::Io || err.kind() == ErrorKind::LimitExceeded => {
+                Err(err)
+            }
+            // Errors inside the skipped key are not reported
+            _ => Ok(()),
+        }
+    }
+
+    /// It consumes the rest of a list or dictionary whose first byte has
+    /// already been read, without writing anything.
+    ///
+    /// Only the nesting level is tracked, so it uses constant memory apart
+    /// from the strings.
+    fn skip_list_or_dict(&mut self) -> Result<(), Error> {
+        let mut depth: usize = 1;
+
+        while let Some(byte) = self.peek_next_byte()? {
+            match byte {
+                BENCODE_BEGIN_INTEGER => {
+                    integer::parse(&mut self.byte_reader, &mut Discard, &self.options)?;
+                }
+                b'0'..=b'9' => {
+                    string::parse(&mut self.byte_reader, &mut Discard, &self.options)?;
+                }
+                BENCODE_BEGIN_LIST | BENCODE_BEGIN_DICT => {
+                    self.read_next_byte()?;
+                    depth += 1;
+                }
+                BENCODE_END_LIST_OR_DICT => {
+                    self.read_next_byte()?;
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {
+                    self.read_next_byte()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// It consumes the rest of a string whose length overflowed: the
+    /// remaining digits, the `:` and, since the length is unknown, the bytes
+    /// until the next plausible value boundary.
+    fn skip_string_with_overflowed_length(&mut self) -> Result<(), Error> {
+        while let Some(byte) = self.peek_next_byte()? {
+            if !byte.is_ascii_digit() {
+                break;
+            }
+            self.read_next_byte()?;
+        }
+
+        if self.peek_next_byte()? == Some(b':') {
+            self.read_next_byte()?;
+
+            while let Some(byte) = self.peek_next_byte()? {
+                if self.is_plausible_value_boundary(byte) {
+                    break;
+                }
+                self.read_next_byte()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// It consumes bytes until it finds the given byte (included) or the
+    /// input ends.
+    fn skip_past(&mut self, end: u8) -> Result<(), Error> {
+        while let Some(byte) = self.read_next_byte()? {
+            if byte == end {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// It consumes the peeked unrecognized byte and the following ones until
+    /// the next plausible value boundary.
+    fn skip_unrecognized_bytes(&mut self) -> Result<(), Error> {
+        self.read_next_byte()?;
+
+        while let Some(byte) = self.peek_next_byte()? {
+            if self.is_plausible_value_boundary(byte) {
+                break;
+            }
+            self.read_next_byte()?;
+        }
+
+        Ok(())
+    }
+
+    /// It closes all the lists and dictionaries that are still open when the
+    /// input ends.
+    fn close_open_lists_and_dicts<W: Writer>(&mut self, writer: &mut W) -> Result<(), Error> {
+        loop {
+            let state = self
+                .stack
+                .peek()
+                .map_err(|err| self.invalid_stack_state(err, writer))?;
+
+            match state {
+                State::Initial => return Ok(()),
+                State::ExpectingFirstListItemOrEnd | State::ExpectingNextListItem => {
+                    writer.write_byte(Self::JSON_ARRAY_END)?;
+                }
+                State::ExpectingFirstDictFieldOrEnd | State::ExpectingDictFieldKeyOrEnd => {
+                    writer.write_byte(Self::JSON_OBJ_END)?;
+                }
+                State::ExpectingDictFieldValue => {
+                    writer.write_byte(Self::JSON_OBJ_FIELD_KEY_VALUE_SEPARATOR)?;
+                    writer.write_str(JSON_UNREADABLE_VALUE)?;
+                    writer.write_byte(Self::JSON_OBJ_END)?;
+                }
+            }
+
+            self.stack
+                .pop()
+                .map_err(|err| self.invalid_stack_state(err, writer))?;
+        }
+    }
+
+    /// It reads the next byte. It returns `None` if the input has ended.
+    fn read_next_byte(&mut self) -> Result<Option<u8>, Error> {
+        match self.byte_reader.read_byte() {
+            Ok(byte) => Ok(Some(byte)),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// It peeks the next byte. It returns `None` if the input has ended.
+    fn peek_next_byte(&mut self) -> Result<Option<u8>, Error> {
+        match self.byte_reader.peek_byte() {
+            Ok(byte) => Ok(Some(byte)),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// A writer that discards everything. It's used to skip values.
+struct Discard;
+
+impl Writer for Discard {
+    fn write_byte(&mut self, _byte: u8) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, _bytes: &[u8]) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn write_str(&mut self, _value: &str) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn output_byte_counter(&self) -> u64 {
+        0
+    }
+
+    fn captured_bytes(&self) -> Vec<u8> {
+        Vec::new()
+    }
+}
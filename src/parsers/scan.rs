@@ -0,0 +1,198 @@
+//! Fast scanning of integers and string lengths.
+//!
+//! When a whole token is already in the input buffer, the terminator (`e`
+//! for integers and `:` for string lengths) is searched with `memchr` and the
+//! digits are validated eight at a time (SWAR). The token is then consumed
+//! at once instead of byte by byte.
+//!
+//! The fast path only accepts tokens the scalar parsers accept too. For
+//! anything else (the token continues after the buffer, invalid bytes,
+//! leading zeros, too many digits, overflows) it does not consume anything
+//! and the caller falls back to the scalar parser, so errors are always
+//! reported by the same code, at the same positions.
+use std::io::BufRead;
+
+use crate::rw::byte_reader::ByteReader;
+
+use super::{
+    error::Error,
+    options::{ParserOptions, ScanMode},
+    BENCODE_BEGIN_INTEGER, BENCODE_END_INTEGER,
+};
+
+const END_OF_STRING_LENGTH_BYTE: u8 = b':';
+
+/// It scans a bencoded integer whose first byte (`i`) has been peeked.
+///
+/// It returns the integer (with the sign), or `None` if the scalar parser
+/// must be used. In that case nothing has been consumed.
+///
+/// # Errors
+///
+/// Will return an error if it can't read from the input.
+pub fn integer<R: BufRead>(
+    reader: &mut ByteReader<R>,
+    options: &ParserOptions,
+) -> Result<Option<String>, Error> {
+    if options.scan_mode != ScanMode::Vectorized
+        || reader.peeked_byte() != Some(BENCODE_BEGIN_INTEGER)
+    {
+        return Ok(None);
+    }
+
+    let buffered = reader.buffered_bytes()?;
+
+    let Some(end) = memchr::memchr(BENCODE_END_INTEGER, buffered) else {
+        return Ok(None);
+    };
+
+    let token = &buffered[..end];
+    let digits = token.strip_prefix(b"-").unwrap_or(token);
+
+    // The scalar parser rejects some integers with leading zeros and accepts
+    // others (`i01e`), so they are left to it.
+    if digits.is_empty() || !all_digits(digits) || (digits[0] == b'0' && digits.len() > 1) {
+        return Ok(None);
+    }
+
+    if options
+        .limits
+        .max_integer_length
+        .is_some_and(|max_integer_length| digits.len() > max_integer_length)
+    {
+        return Ok(None);
+    }
+
+    // Only ASCII digits and the sign
+    let integer = token.iter().map(|byte| *byte as char).collect();
+
+    // The peeked `i`, the digits and the `e`
+    reader.read_byte()?;
+    reader.consume_buffered_bytes(end + 1)?;
+
+    Ok(Some(integer))
+}
+
+/// It scans the length of a bencoded string whose first digit has been
+/// peeked, including the `:` separator.
+///
+/// It returns the length, or `None` if the scalar parser must be used. In
+/// that case nothing has been consumed.
+///
+/// # Errors
+///
+/// Will return an error if it can't read from the input.
+pub fn string_length<R: BufRead>(
+    reader: &mut ByteReader<R>,
+    options: &ParserOptions,
+) -> Result<Option<usize>, Error> {
+    if options.scan_mode != ScanMode::Vectorized {
+        return Ok(None);
+    }
+
+    let Some(first_digit) = reader.peeked_byte().filter(u8::is_ascii_digit) else {
+        return Ok(None);
+    };
+
+    let buffered = reader.buffered_bytes()?;
+
+    let Some(end) = memchr::memchr(END_OF_STRING_LENGTH_BYTE, buffered) else {
+        return Ok(None);
+    };
+
+    let digits = &buffered[..end];
+
+    if !all_digits(digits) {
+        return Ok(None);
+    }
+
+    let Some(length) =
+        std::iter::once(&first_digit)
+            .chain(digits)
+            .try_fold(0usize, |length, digit| {
+                length
+                    .checked_mul(10)?
+                    .checked_add(usize::from(digit - b'0'))
+            })
+    else {
+        return Ok(None);
+    };
+
+    // The peeked first digit, the rest of the digits and the `:`
+    reader.read_byte()?;
+    reader.consume_buffered_bytes(end + 1)?;
+
+    Ok(Some(length))
+}
+
+/// It returns true if all the bytes are ASCII digits (`0..9`).
+///
+/// The bytes are checked in words of eight: a byte is a digit if its high
+/// nibble is `3` and it's still `3` after adding `6` (`0x30..=0x39`).
+#[must_use]
+pub fn all_digits(bytes: &[u8]) -> bool {
+    const HIGH_NIBBLES: u64 = 0xF0F0_F0F0_F0F0_F0F0;
+    const SIX: u64 = 0x0606_0606_0606_0606;
+    const THREES: u64 = 0x3333_3333_3333_3333;
+
+    let mut words = bytes.chunks_exact(8);
+
+    for word in words.by_ref() {
+        let mut le_bytes = [0; 8];
+        le_bytes.copy_from_slice(word);
+        let word = u64::from_le_bytes(le_bytes);
+
+        // A carry can only come from a byte that is not a digit, and then
+        // the word is rejected anyway.
+        if (word & HIGH_NIBBLES) | ((word.wrapping_add(SIX) & HIGH_NIBBLES) >> 4) != THREES {
+            return false;
+        }
+    }
+
+    words.remainder().iter().all(u8::is_ascii_digit)
+}
+
+#[cfg(test)]
+mod tests {
+    mod for_validating_digits {
+        use crate::parsers::scan::all_digits;
+
+        #[test]
+        fn it_should_accept_only_ascii_digits() {
+            assert!(all_digits(b""));
+            assert!(all_digits(b"0123456789"));
+            assert!(all_digits(b"9999999999999999"));
+
+            assert!(!all_digits(b"0123456/"));
+            assert!(!all_digits(b"0123456:"));
+            assert!(!all_digits(b"a1234567"));
+            assert!(!all_digits(b"12345678-"));
+        }
+
+        #[test]
+        fn it_should_reject_bytes_whose_carry_would_change_the_next_byte() {
+            assert!(!all_digits(&[
+                0xFF, b'2', b'3', b'4', b'5', b'6', b'7', b'8'
+            ]));
+            assert!(!all_digits(&[
+                b'1', b'2', b'3', b'4', b'5', b'6', b'7', 0xFA
+            ]));
+        }
+
+        #[test]
+        fn it_should_give_the_same_result_as_checking_byte_by_byte() {
+            for byte in 0..=u8::MAX {
+                for position in 0..9 {
+                    let mut bytes = *b"123456789";
+                    bytes[position] = byte;
+
+                    assert_eq!(
+                        all_digits(&bytes),
+                        bytes.iter().all(u8::is_ascii_digit),
+                        "byte {byte:#04x} at {position}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,16 +1,39 @@
 //! The stack used by the Bencoded to JSON converter to keep track of the
 //! current parsing state.
-use std::fmt::Display;
+use std::fmt::{Display, Write};
+
+use thiserror::Error;
 
 /// Stack containing states for nested Bencoded values.
 ///
 /// The stack has an immutable initial state.
 ///
-/// > NOTICE!: It's not allowed to pop or change the initial state.
-#[derive(Debug)]
+/// > NOTICE!: It's not allowed to pop or change the initial state. Trying to
+/// > do it returns an error.
+#[derive(Debug, Clone)]
 pub(crate) struct Stack {
     /// The stack of states.
     states: Vec<State>,
+
+    /// The position of the current value in each nested list or
+    /// dictionary, to build its JSON path. There is one segment for each
+    /// state but the initial one.
+    path: Vec<PathSegment>,
+
+    /// The key of the current field in the dictionary of each segment. The
+    /// buffers are kept when the segments are popped and reused for the
+    /// next keys at the same depth, so keys are not allocated one by one.
+    keys: Vec<Vec<u8>>,
+}
+
+/// The position of the current value in a list or a dictionary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PathSegment {
+    /// Number of items begun in the list.
+    List(u64),
+
+    /// Whether the key of the current field has been parsed.
+    Dict { has_key: bool },
 }
 
 /// States while parsing list or dictionaries.
@@ -69,7 +92,11 @@ impl Display for State {
 impl Default for Stack {
     fn default() -> Self {
         let states = vec![State::Initial];
-        Self { states }
+        Self {
+            states,
+            path: Vec::new(),
+            keys: Vec::new(),
+        }
     }
 }
 
@@ -90,60 +117,166 @@ impl Display for Stack {
     }
 }
 
+/// Errors that can occur while reading or mutating the stack.
+///
+/// They should never happen because the parser checks the state before
+/// mutating the stack. If they happen, it's a bug in the parser.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The stack is empty.
+    #[error("empty stack")]
+    EmptyStack,
+
+    /// The initial state can't be popped or swapped.
+    #[error("trying to mutate immutable initial state. It can't be popped or swapped")]
+    ImmutableInitialState,
+}
+
 impl Stack {
     /// It adds a new state to the stack.
     pub fn push(&mut self, state: State) {
+        let segment = match state {
+            State::ExpectingFirstDictFieldOrEnd
+            | State::ExpectingDictFieldValue
+            | State::ExpectingDictFieldKeyOrEnd => PathSegment::Dict { has_key: false },
+            _ => PathSegment::List(0),
+        };
+
         self.states.push(state);
+        self.path.push(segment);
     }
 
     /// It returns and consumes the stack top.
     ///
     /// It doesn't allow popping the initial state.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Will panic is the stack state is the initial state.
-    pub fn pop(&mut self) {
-        self.guard_immutable_initial_state();
+    /// Will return an error if the stack state is the initial state.
+    pub fn pop(&mut self) -> Result<(), Error> {
+        self.guard_immutable_initial_state()?;
         self.states.pop();
+        self.path.pop();
+        Ok(())
     }
 
     /// It swaps the stack top with the new state.
     ///
     /// It doesn't allow swapping the initial state.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Will panic is the stack state is the initial state.
-    pub fn swap_top(&mut self, new_state: State) {
-        self.guard_immutable_initial_state();
+    /// Will return an error if the stack state is the initial state.
+    pub fn swap_top(&mut self, new_state: State) -> Result<(), Error> {
+        self.guard_immutable_initial_state()?;
         self.states.pop();
-        self.push(new_state);
+        self.states.push(new_state);
+        Ok(())
     }
 
     /// It returns the top element on the stack without consuming it.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Will panic is the stack is empty. The stack is never empty because it's
-    /// not allowed to pop or change the initial state.
-    #[must_use]
-    pub fn peek(&self) -> State {
+    /// Will return an error if the stack is empty. The stack is never empty
+    /// because it's not allowed to pop or change the initial state.
+    pub fn peek(&self) -> Result<State, Error> {
         match self.states.last() {
-            Some(top) => top.clone(),
-            None => panic!("empty stack!"),
+            Some(top) => Ok(top.clone()),
+            None => Err(Error::EmptyStack),
         }
     }
 
-    /// Prevent from mutating the initial state.
-    fn guard_immutable_initial_state(&self) {
-        if let Some(top) = self.states.last() {
-            if *top != State::Initial {
-                return;
+    /// It returns the number of nested lists and dictionaries. It's zero when
+    /// the stack only contains the initial state.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.states.len().saturating_sub(1)
+    }
+
+    /// It moves the path to a new value in the current list, or to a new key
+    /// in the current dictionary.
+    pub fn begin_value(&mut self) {
+        let expecting_key = matches!(
+            self.states.last(),
+            Some(State::ExpectingFirstDictFieldOrEnd | State::ExpectingDictFieldKeyOrEnd)
+        );
+
+        match self.path.last_mut() {
+            Some(PathSegment::List(items)) => *items += 1,
+            Some(PathSegment::Dict { has_key }) if expecting_key => *has_key = false,
+            _ => {}
+        }
+    }
+
+    /// It sets the key of the current field in the current dictionary.
+    pub fn set_key(&mut self, key: &[u8]) {
+        let index = self.path.len().saturating_sub(1);
+
+        if let Some(PathSegment::Dict { has_key }) = self.path.last_mut() {
+            *has_key = true;
+
+            if self.keys.len() <= index {
+                self.keys.resize_with(index + 1, Vec::new);
             }
-        };
 
-        panic!("trying to mutate immutable initial state. It can't be popped or swapped!")
+            self.keys[index].clear();
+            self.keys[index].extend_from_slice(key);
+        }
+    }
+
+    /// It returns the JSON path of the current value, like
+    /// `$.info.files[0]`. Keys that are not valid UTF-8 are written lossily,
+    /// and keys with characters of the path syntax are written in brackets
+    /// as JSON strings, like `$["a.b"]`.
+    #[must_use]
+    pub fn value_path(&self) -> String {
+        let mut path = String::from("$");
+
+        for (index, segment) in self.path.iter().enumerate() {
+            match segment {
+                PathSegment::List(0) | PathSegment::Dict { has_key: false } => {}
+                PathSegment::List(items) => {
+                    write!(path, "[{}]", items - 1).expect("writing to a string should not fail");
+                }
+                PathSegment::Dict { has_key: true } => {
+                    // The key is set along with the flag
+                    if let Some(key) = self.keys.get(index) {
+                        write_key(&mut path, key);
+                    }
+                }
+            }
+        }
+
+        path
+    }
+
+    /// Prevent from mutating the initial state.
+    fn guard_immutable_initial_state(&self) -> Result<(), Error> {
+        match self.states.last() {
+            Some(State::Initial) => Err(Error::ImmutableInitialState),
+            Some(_) => Ok(()),
+            None => Err(Error::EmptyStack),
+        }
+    }
+}
+
+/// It writes a key of the JSON path, in brackets when it's empty or it has
+/// characters that would make the path ambiguous.
+fn write_key(path: &mut String, key: &[u8]) {
+    let key = String::from_utf8_lossy(key);
+
+    let needs_brackets = key.is_empty()
+        || key
+            .chars()
+            .any(|char| matches!(char, '.' | '[' | ']' | '"' | '\\') || char.is_control());
+
+    if needs_brackets {
+        write!(path, "[{}]", serde_json::Value::from(key.as_ref()))
+            .expect("writing to a string should not fail");
+    } else {
+        path.push('.');
+        path.push_str(&key);
     }
 }
 
@@ -165,11 +298,11 @@ mod tests {
 
     mod the_stack {
         mod it_should {
-            use crate::parsers::stack::{Stack, State};
+            use crate::parsers::stack::{Error, Stack, State};
 
             #[test]
             fn have_an_initial_state() {
-                assert_eq!(Stack::default().peek(), State::Initial);
+                assert_eq!(Stack::default().peek(), Ok(State::Initial));
             }
 
             #[test]
@@ -178,18 +311,16 @@ mod tests {
 
                 let _ = stack.peek();
 
-                assert_eq!(stack.peek(), State::Initial);
+                assert_eq!(stack.peek(), Ok(State::Initial));
             }
 
             #[test]
-            #[should_panic(expected = "empty stack!")]
-            fn panic_peeking_the_top_element_if_the_stack_is_empty() {
+            fn fail_peeking_the_top_element_if_the_stack_is_empty() {
                 let mut stack = Stack::default();
 
                 stack.states.clear();
-                let _ = stack.peek();
 
-                assert_eq!(stack.peek(), State::Initial);
+                assert_eq!(stack.peek(), Err(Error::EmptyStack));
             }
 
             #[test]
@@ -198,7 +329,7 @@ mod tests {
 
                 stack.push(State::ExpectingDictFieldKeyOrEnd);
 
-                assert_eq!(stack.peek(), State::ExpectingDictFieldKeyOrEnd);
+                assert_eq!(stack.peek(), Ok(State::ExpectingDictFieldKeyOrEnd));
             }
 
             #[test]
@@ -206,15 +337,14 @@ mod tests {
                 let mut stack = Stack::default();
 
                 stack.push(State::ExpectingDictFieldKeyOrEnd);
-                stack.pop();
+                stack.pop().unwrap();
 
-                assert_eq!(stack.peek(), State::Initial);
+                assert_eq!(stack.peek(), Ok(State::Initial));
             }
 
             #[test]
-            #[should_panic(expected = "trying to mutate")]
             fn not_allow_popping_the_initial_state() {
-                Stack::default().pop();
+                assert_eq!(Stack::default().pop(), Err(Error::ImmutableInitialState));
             }
 
             #[test]
@@ -222,15 +352,91 @@ mod tests {
                 let mut stack = Stack::default();
 
                 stack.push(State::ExpectingDictFieldKeyOrEnd);
-                stack.swap_top(State::ExpectingDictFieldValue);
+                stack.swap_top(State::ExpectingDictFieldValue).unwrap();
 
-                assert_eq!(stack.peek(), State::ExpectingDictFieldValue);
+                assert_eq!(stack.peek(), Ok(State::ExpectingDictFieldValue));
             }
 
             #[test]
-            #[should_panic(expected = "trying to mutate")]
             fn not_allow_swapping_the_initial_state() {
-                Stack::default().swap_top(State::Initial);
+                assert_eq!(
+                    Stack::default().swap_top(State::Initial),
+                    Err(Error::ImmutableInitialState)
+                );
+            }
+
+            #[test]
+            fn return_the_number_of_nested_states() {
+                let mut stack = Stack::default();
+
+                assert_eq!(stack.depth(), 0);
+
+                stack.push(State::ExpectingFirstListItemOrEnd);
+
+                assert_eq!(stack.depth(), 1);
+            }
+
+            #[test]
+            fn return_the_path_of_the_current_value() {
+                let mut stack = Stack::default();
+
+                assert_eq!(stack.value_path(), "$");
+
+                stack.push(State::ExpectingFirstDictFieldOrEnd);
+                stack.begin_value();
+                stack.set_key(b"files");
+                stack.swap_top(State::ExpectingDictFieldValue).unwrap();
+
+                stack.begin_value();
+                stack.push(State::ExpectingFirstListItemOrEnd);
+
+                assert_eq!(stack.value_path(), "$.files");
+
+                stack.begin_value();
+                stack.begin_value();
+
+                assert_eq!(stack.value_path(), "$.files[1]");
+
+                stack.pop().unwrap();
+
+                assert_eq!(stack.value_path(), "$.files");
+            }
+
+            #[test]
+            fn write_keys_with_characters_of_the_path_syntax_in_brackets() {
+                let mut stack = Stack::default();
+
+                stack.push(State::ExpectingFirstDictFieldOrEnd);
+                stack.begin_value();
+                stack.set_key(b"a.b[\"c\"]");
+                stack.swap_top(State::ExpectingDictFieldValue).unwrap();
+
+                stack.begin_value();
+                stack.push(State::ExpectingFirstDictFieldOrEnd);
+                stack.begin_value();
+                stack.set_key(b"");
+
+                assert_eq!(stack.value_path(), r#"$["a.b[\"c\"]"][""]"#);
+            }
+
+            #[test]
+            fn reuse_the_key_buffers_of_closed_dictionaries() {
+                let mut stack = Stack::default();
+
+                stack.push(State::ExpectingFirstDictFieldOrEnd);
+                stack.begin_value();
+                stack.set_key(b"long key");
+                stack.pop().unwrap();
+
+                stack.push(State::ExpectingFirstDictFieldOrEnd);
+
+                assert_eq!(stack.value_path(), "$");
+
+                stack.begin_value();
+                stack.set_key(b"a");
+
+                assert_eq!(stack.value_path(), "$.a");
+                assert!(stack.keys[0].capacity() >= b"long key".len());
             }
 
             mod be_displayed_with_single_letter_abbreviations_for_states {
@@ -1,9 +1,15 @@
 //! Bencoded string parser.
 //!
 //! It reads bencoded bytes from the input and writes JSON bytes to the output.
-use std::io::{self, Read};
+use std::{
+    io::{self, BufRead},
+    sync::Arc,
+};
 
-use crate::rw::{byte_reader::ByteReader, writer::Writer};
+use crate::{
+    encoder,
+    rw::{byte_reader::ByteReader, writer::Writer},
+};
 
 /* todo: Optimize UTF-8 conversion. Try to convert to string partially and stop
     converting if we reach a point when input is not valid UTF-8 anymore. This
@@ -13,21 +19,42 @@ use crate::rw::{byte_reader::ByteReader, writer::Writer};
 
 use core::str;
 
-use super::error::{Error, ReadContext, WriteContext};
+use super::{
+    error::{Error, ReadContext, WriteContext},
+    options::{BinaryPolicy, ParserOptions},
+    scan,
+};
 
 /// It parses a string bencoded value.
 ///
 /// # Errors
 ///
-/// Will return an error if it can't read from the input or write to the
-/// output.
+/// Will return an error if:
 ///
-/// # Panics
+/// - It can't read from the input or write to the output.
+/// - The string length is not valid.
+/// - The string is longer than the configured limit.
+/// - We reach the end of the input without completing the string.
+pub fn parse<R: BufRead, W: Writer>(
+    reader: &mut ByteReader<R>,
+    writer: &mut W,
+    options: &ParserOptions,
+) -> Result<(), Error> {
+    parse_bytes(reader, writer, options).map(|_bytes| ())
+}
+
+/// It parses a string bencoded value like [`parse`], and returns its bytes.
+///
+/// # Errors
 ///
-/// Will panic if we reach the end of the input without completing the string.
-pub fn parse<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &mut W) -> Result<(), Error> {
+/// Will return an error in the same cases as [`parse`].
+pub(super) fn parse_bytes<R: BufRead, W: Writer>(
+    reader: &mut ByteReader<R>,
+    writer: &mut W,
+    options: &ParserOptions,
+) -> Result<Vec<u8>, Error> {
     let mut string_parser = StringParser::default();
-    string_parser.parse(reader, writer)
+    string_parser.parse(reader, writer, options)
 }
 
 /// Strings bencode format have two parts: `length:value`.
@@ -42,38 +69,81 @@ struct StringParser {
 }
 
 impl StringParser {
-    fn parse<R: Read, W: Writer>(
+    fn parse<R: BufRead, W: Writer>(
         &mut self,
         reader: &mut ByteReader<R>,
         writer: &mut W,
-    ) -> Result<(), Error> {
-        let mut length = Length::default();
+        options: &ParserOptions,
+    ) -> Result<Vec<u8>, Error> {
+        let length = if let Some(length) = scan::string_length(reader, options)? {
+            length
+        } else {
+            let mut length = Length::default();
+            length.parse(reader, writer)?;
+            length.number
+        };
 
-        length.parse(reader, writer)?;
+        if let Some(max_string_length) = options.limits.max_string_length {
+            if length > max_string_length {
+                return Err(Error::MaxStringLengthExceeded(
+                    max_string_length,
+                    ReadContext {
+                        byte: None,
+                        pos: reader.input_byte_counter(),
+                        latest_bytes: reader.captured_bytes().into(),
+                    },
+                    WriteContext {
+                        byte: None,
+                        pos: writer.output_byte_counter(),
+                        latest_bytes: writer.captured_bytes().into(),
+                    },
+                ));
+            }
+        }
 
-        let mut value = Value::new(length.number);
+        let mut value = Value::new(length);
 
         value.parse(reader, writer)?;
 
-        self.parsed_value = value.utf8();
+        self.parsed_value = value.utf8(options.binary_policy);
 
-        writer.write_str(&self.json())?;
+        let json = self.json().map_err(|err| {
+            Error::StringJsonSerialization(
+                Arc::new(err),
+                ReadContext {
+                    byte: None,
+                    pos: reader.input_byte_counter(),
+                    latest_bytes: reader.captured_bytes().into(),
+                },
+                WriteContext {
+                    byte: None,
+                    pos: writer.output_byte_counter(),
+                    latest_bytes: writer.captured_bytes().into(),
+                },
+            )
+        })?;
 
-        Ok(())
+        writer.write_str(&json)?;
+
+        Ok(value.bytes)
     }
 
     /// It returns the final parsed value as string.
     ///
-    /// If the string contains non UTF-8 bytes it returns the hexadecimal list
-    /// of bytes in in the format '<hex>fa fb</hex>'  
+    /// If the string contains non UTF-8 bytes it returns the bytes following
+    /// the binary policy. By default, the hexadecimal list of bytes in the
+    /// format '<hex>fafb</hex>'.
     fn parsed_value(&self) -> String {
         self.parsed_value.clone()
     }
 
     /// It serializes the parsed value into JSON.
-    #[must_use]
-    fn json(&self) -> String {
-        serde_json::to_string(&self.parsed_value()).unwrap()
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the value can't be serialized.
+    fn json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&self.parsed_value())
     }
 }
 
@@ -89,7 +159,7 @@ struct Length {
 impl Length {
     const END_OF_STRING_LENGTH_BYTE: u8 = b':';
 
-    fn parse<R: Read, W: Writer>(
+    fn parse<R: BufRead, W: Writer>(
         &mut self,
         reader: &mut ByteReader<R>,
         writer: &W,
@@ -115,7 +185,10 @@ impl Length {
     /// # Errors
     ///
     /// Will return an error if the end of input was reached.
-    fn next_byte<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &W) -> Result<u8, Error> {
+    fn next_byte<R: BufRead, W: Writer>(
+        reader: &mut ByteReader<R>,
+        writer: &W,
+    ) -> Result<u8, Error> {
         match reader.read_byte() {
             Ok(byte) => Ok(byte),
             Err(err) => {
@@ -124,12 +197,12 @@ impl Length {
                         ReadContext {
                             byte: None,
                             pos: reader.input_byte_counter(),
-                            latest_bytes: reader.captured_bytes(),
+                            latest_bytes: reader.captured_bytes().into(),
                         },
                         WriteContext {
                             byte: None,
                             pos: writer.output_byte_counter(),
-                            latest_bytes: writer.captured_bytes(),
+                            latest_bytes: writer.captured_bytes().into(),
                         },
                     ));
                 }
@@ -142,8 +215,11 @@ impl Length {
     ///
     /// # Errors
     ///
-    /// Will return an error if the byte is not a digit (0..9).
-    fn add_byte<R: Read, W: Writer>(
+    /// Will return an error if:
+    ///
+    /// - The byte is not a digit (0..9).
+    /// - The length does not fit in a `usize`.
+    fn add_byte<R: BufRead, W: Writer>(
         &mut self,
         byte: u8,
         reader: &mut ByteReader<R>,
@@ -154,19 +230,35 @@ impl Length {
                 ReadContext {
                     byte: Some(byte),
                     pos: reader.input_byte_counter(),
-                    latest_bytes: reader.captured_bytes(),
+                    latest_bytes: reader.captured_bytes().into(),
                 },
                 WriteContext {
                     byte: Some(byte),
                     pos: writer.output_byte_counter(),
-                    latest_bytes: writer.captured_bytes(),
+                    latest_bytes: writer.captured_bytes().into(),
                 },
             ));
         }
 
         self.bytes.push(byte);
 
-        self.add_digit_to_length(Self::byte_to_digit(byte));
+        if self
+            .add_digit_to_length(Self::byte_to_digit(byte))
+            .is_none()
+        {
+            return Err(Error::StringLengthOverflow(
+                ReadContext {
+                    byte: Some(byte),
+                    pos: reader.input_byte_counter(),
+                    latest_bytes: reader.captured_bytes().into(),
+                },
+                WriteContext {
+                    byte: Some(byte),
+                    pos: writer.output_byte_counter(),
+                    latest_bytes: writer.captured_bytes().into(),
+                },
+            ));
+        }
 
         Ok(())
     }
@@ -177,8 +269,11 @@ impl Length {
     }
 
     /// It adds the new digit to the number.
-    fn add_digit_to_length(&mut self, digit: usize) {
-        self.number = (self.number * 10) + digit;
+    ///
+    /// It returns `None` if the new number overflows.
+    fn add_digit_to_length(&mut self, digit: usize) -> Option<usize> {
+        self.number = self.number.checked_mul(10)?.checked_add(digit)?;
+        Some(self.number)
     }
 }
 
@@ -186,7 +281,6 @@ impl Length {
 struct Value {
     length: usize,
     bytes: Vec<u8>,
-    bytes_counter: usize,
 }
 
 impl Value {
@@ -194,42 +288,35 @@ impl Value {
         Self {
             length,
             bytes: vec![],
-            bytes_counter: 0,
         }
     }
 
-    fn parse<R: Read, W: Writer>(
+    /// It reads all the string bytes at once, copying them in chunks from the
+    /// input buffer.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't read from the input or the end of
+    /// input was reached.
+    fn parse<R: BufRead, W: Writer>(
         &mut self,
         reader: &mut ByteReader<R>,
         writer: &W,
     ) -> Result<(), Error> {
-        for _i in 1..=self.length {
-            self.add_byte(Self::next_byte(reader, writer)?);
-        }
-
-        Ok(())
-    }
-
-    /// It reads the next byte from the input.
-    ///
-    /// # Errors
-    ///
-    /// Will return an error if the end of input was reached.
-    fn next_byte<R: Read, W: Writer>(reader: &mut ByteReader<R>, writer: &W) -> Result<u8, Error> {
-        match reader.read_byte() {
-            Ok(byte) => Ok(byte),
+        match reader.read_bytes(self.length, &mut self.bytes) {
+            Ok(()) => Ok(()),
             Err(err) => {
                 if err.kind() == io::ErrorKind::UnexpectedEof {
                     return Err(Error::UnexpectedEndOfInputParsingStringValue(
                         ReadContext {
                             byte: None,
                             pos: reader.input_byte_counter(),
-                            latest_bytes: reader.captured_bytes(),
+                            latest_bytes: reader.captured_bytes().into(),
                         },
                         WriteContext {
                             byte: None,
                             pos: writer.output_byte_counter(),
-                            latest_bytes: writer.captured_bytes(),
+                            latest_bytes: writer.captured_bytes().into(),
                         },
                     ));
                 }
@@ -238,20 +325,28 @@ impl Value {
         }
     }
 
-    fn add_byte(&mut self, byte: u8) {
-        self.bytes.push(byte);
-        self.bytes_counter += 1;
-    }
-
-    fn utf8(&self) -> String {
+    fn utf8(&self, binary_policy: BinaryPolicy) -> String {
         match str::from_utf8(&self.bytes) {
+            Ok(string)
+                if binary_policy == BinaryPolicy::TaggedHex
+                    && encoder::decode_tagged_hex(string).is_some() =>
+            {
+                // Text that looks like bytes in the tagged format is written
+                // in the format too, so that it's not encoded back as bytes
+                Self::bytes_to_hex(&self.bytes)
+            }
             Ok(string) => {
                 // String only contains valid UTF-8 chars -> print it as it's
                 string.to_owned()
             }
             Err(_) => {
-                // String contains non valid UTF-8 chars -> print it as hex bytes
-                Self::bytes_to_hex(&self.bytes)
+                // String contains non valid UTF-8 chars -> print it following
+                // the binary policy
+                match binary_policy {
+                    BinaryPolicy::TaggedHex => Self::bytes_to_hex(&self.bytes),
+                    BinaryPolicy::Hex => hex::encode(&self.bytes),
+                    BinaryPolicy::Lossy => String::from_utf8_lossy(&self.bytes).into_owned(),
+                }
             }
         }
     }
@@ -264,7 +359,7 @@ impl Value {
 #[cfg(test)]
 mod tests {
     use crate::{
-        parsers::error::Error,
+        parsers::{error::Error, options::ParserOptions},
         rw::{byte_reader::ByteReader, string_writer::StringWriter},
     };
 
@@ -292,7 +387,7 @@ mod tests {
 
         let mut writer = StringWriter::new(output);
 
-        parse(&mut reader, &mut writer)
+        parse(&mut reader, &mut writer, &ParserOptions::default())
     }
 
     mod for_helpers {
@@ -424,6 +519,7 @@ mod tests {
         use crate::{
             parsers::{
                 error::Error,
+                options::ParserOptions,
                 string::{parse, tests::try_bencode_to_json},
             },
             rw::{byte_reader::ByteReader, string_writer::StringWriter},
@@ -462,6 +558,15 @@ mod tests {
             assert!(matches!(result, Err(Error::InvalidStringLengthByte { .. })));
         }
 
+        #[test]
+        fn the_string_length_overflows() {
+            let string_with_huge_length = format!("{}0:", usize::MAX);
+
+            let result = try_bencode_to_json(string_with_huge_length.as_bytes());
+
+            assert!(matches!(result, Err(Error::StringLengthOverflow { .. })));
+        }
+
         /// Fake reader that fails after reading a certain number of bytes
         struct FaultyReader {
             /// The bytes the reader will return
@@ -517,7 +622,7 @@ mod tests {
             let mut output = String::new();
             let mut writer = StringWriter::new(&mut output);
 
-            let result = parse(&mut reader, &mut writer);
+            let result = parse(&mut reader, &mut writer, &ParserOptions::default());
 
             assert!(matches!(result, Err(Error::Io(_))));
         }
@@ -529,7 +634,7 @@ mod tests {
             let mut output = String::new();
             let mut writer = StringWriter::new(&mut output);
 
-            let result = parse(&mut reader, &mut writer);
+            let result = parse(&mut reader, &mut writer, &ParserOptions::default());
 
             assert!(matches!(result, Err(Error::Io(_))));
         }
@@ -0,0 +1,197 @@
+//! Bencoded tokens reported to writers.
+//!
+//! Besides the JSON output, the parser reports every token it parses to the
+//! writer with [`Writer::token`](crate::rw::writer::Writer::token). Writers
+//! that need the bencoded values, instead of their JSON, can use them. For
+//! example, to build an in-memory representation of the values (see
+//! [`value`](crate::value)).
+//!
+//! Tokens are only reported for valid input. When the parser recovers from
+//! an error, the token with the error is not reported.
+
+/// A bencoded token and its position in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind<'a>,
+
+    /// Number of input bytes before the token.
+    pub input_start: u64,
+
+    /// Number of input bytes up to the end of the token.
+    pub input_end: u64,
+}
+
+/// The kinds of bencoded tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind<'a> {
+    /// An integer with its digits as they are in the input, including the
+    /// sign, for example `-42`.
+    Integer(&'a str),
+
+    /// A string with its bytes.
+    String(&'a [u8]),
+
+    /// The beginning of a list (`l`).
+    BeginList,
+
+    /// The beginning of a dictionary (`d`).
+    BeginDict,
+
+    /// The end of a list or a dictionary (`e`).
+    End,
+}
+
+impl Token<'_> {
+    /// It returns the token as it is in the input.
+    ///
+    /// String lengths with leading zeros, which the parser accepts, keep
+    /// them, so the bytes are always the same as in the input.
+    #[must_use]
+    pub fn to_raw_bytes(&self) -> Vec<u8> {
+        let mut raw = Vec::new();
+        self.write_raw_bytes(|chunk| raw.extend_from_slice(chunk));
+        raw
+    }
+
+    /// It passes the token as it is in the input to `write`, in chunks,
+    /// without allocating. The bytes are the ones of
+    /// [`Token::to_raw_bytes`].
+    pub fn write_raw_bytes(&self, mut write: impl FnMut(&[u8])) {
+        match self.kind {
+            TokenKind::Integer(digits) => {
+                write(b"i");
+                write(digits.as_bytes());
+                write(b"e");
+            }
+            TokenKind::String(bytes) => {
+                let mut length_digits = [0; 20];
+                let length_digits = decimal_digits(bytes.len(), &mut length_digits);
+
+                let length_width = usize::try_from(self.input_end - self.input_start)
+                    .map_or(0, |token_length| {
+                        token_length.saturating_sub(bytes.len() + 1)
+                    });
+
+                let mut leading_zeros = length_width.saturating_sub(length_digits.len());
+
+                while leading_zeros > 0 {
+                    let zeros = leading_zeros.min(ZEROS.len());
+                    write(&ZEROS[..zeros]);
+                    leading_zeros -= zeros;
+                }
+
+                write(length_digits);
+                write(b":");
+                write(bytes);
+            }
+            TokenKind::BeginList => write(b"l"),
+            TokenKind::BeginDict => write(b"d"),
+            TokenKind::End => write(b"e"),
+        }
+    }
+}
+
+const ZEROS: &[u8] = b"0000000000000000";
+
+/// It writes the decimal digits of a number at the end of the buffer, and
+/// returns them.
+fn decimal_digits(mut number: usize, buffer: &mut [u8; 20]) -> &[u8] {
+    let mut start = buffer.len();
+
+    loop {
+        start -= 1;
+        buffer[start] = b"0123456789"[number % 10];
+        number /= 10;
+
+        if number == 0 {
+            return &buffer[start..];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should {
+        use crate::{
+            parsers::{
+                token::{Token, TokenKind},
+                BencodeParser,
+            },
+            rw::{error::Error, writer::Writer},
+        };
+
+        /// It only keeps the tokens and their raw bytes.
+        #[derive(Default)]
+        struct TokenCollector {
+            tokens: Vec<(String, u64, u64)>,
+            raw: Vec<u8>,
+        }
+
+        impl Writer for TokenCollector {
+            fn write_byte(&mut self, _byte: u8) -> Result<(), Error> {
+                Ok(())
+            }
+
+            fn write_str(&mut self, _value: &str) -> Result<(), Error> {
+                Ok(())
+            }
+
+            fn output_byte_counter(&self) -> u64 {
+                0
+            }
+
+            fn captured_bytes(&self) -> Vec<u8> {
+                Vec::new()
+            }
+
+            fn token(&mut self, token: &Token<'_>) -> Result<(), Error> {
+                self.tokens.push((
+                    format!("{:?}", token.kind),
+                    token.input_start,
+                    token.input_end,
+                ));
+                self.raw.extend_from_slice(&token.to_raw_bytes());
+                Ok(())
+            }
+        }
+
+        fn tokens(input: &[u8]) -> TokenCollector {
+            let mut collector = TokenCollector::default();
+
+            BencodeParser::new(input).write_to(&mut collector).unwrap();
+
+            collector
+        }
+
+        #[test]
+        fn report_every_token_with_its_input_position() {
+            assert_eq!(
+                tokens(b"d4:spaml1:ai-42eee").tokens,
+                vec![
+                    (format!("{:?}", TokenKind::BeginDict), 0, 1),
+                    (format!("{:?}", TokenKind::String(b"spam")), 1, 7),
+                    (format!("{:?}", TokenKind::BeginList), 7, 8),
+                    (format!("{:?}", TokenKind::String(b"a")), 8, 11),
+                    (format!("{:?}", TokenKind::Integer("-42")), 11, 16),
+                    (format!("{:?}", TokenKind::End), 16, 17),
+                    (format!("{:?}", TokenKind::End), 17, 18),
+                ]
+            );
+        }
+
+        #[test]
+        fn not_report_ignored_line_breaks() {
+            assert_eq!(
+                tokens(b"\ni1e\n").tokens,
+                vec![(format!("{:?}", TokenKind::Integer("1")), 1, 4)]
+            );
+        }
+
+        #[test]
+        fn give_the_raw_bytes_of_the_tokens_as_they_are_in_the_input() {
+            let input = b"d4:spaml004:\xff\xfe\xfd\xfci-0ei01eee";
+
+            assert_eq!(tokens(input).raw, input);
+        }
+    }
+}
@@ -0,0 +1,593 @@
+//! Reader for `.pcap` and `.pcapng` capture files.
+//!
+//! It reads the packets one by one, without loading the whole file:
+//!
+//! - [pcap](https://www.ietf.org/archive/id/draft-ietf-opsawg-pcap-04.html):
+//!   microsecond and nanosecond timestamps, in both byte orders.
+//! - [pcapng](https://www.ietf.org/archive/id/draft-ietf-opsawg-pcapng-02.html):
+//!   enhanced, simple and obsolete packet blocks, several sections and
+//!   interfaces, and the `if_tsresol` timestamp resolution.
+use std::io::{self, Read};
+
+use super::{Error, Timestamp};
+
+/// The largest block or packet it reads. It limits the memory used with
+/// corrupted files.
+const MAX_BLOCK_LENGTH: u32 = 64 * 1024 * 1024;
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+
+const PCAPNG_SECTION_HEADER_BLOCK: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+const PCAPNG_OBSOLETE_PACKET_BLOCK: u32 = 2;
+const PCAPNG_SIMPLE_PACKET_BLOCK: u32 = 3;
+const PCAPNG_ENHANCED_PACKET_BLOCK: u32 = 6;
+
+/// The `if_tsresol` option of interface description blocks.
+const PCAPNG_OPTION_TIMESTAMP_RESOLUTION: u16 = 9;
+const PCAPNG_OPTION_END: u16 = 0;
+
+/// A captured packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    /// When the packet was captured.
+    pub timestamp: Timestamp,
+
+    /// The link-layer header type, like 1 for Ethernet.
+    pub link_type: u32,
+
+    /// The captured bytes, from the link-layer header.
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    fn u16(self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+
+        match self {
+            Self::Little => u16::from_le_bytes(bytes),
+            Self::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    fn u32(self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+
+        match self {
+            Self::Little => u32::from_le_bytes(bytes),
+            Self::Big => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+/// An interface of a pcapng section.
+#[derive(Debug, Clone, Copy)]
+struct Interface {
+    link_type: u32,
+    ticks_per_second: u64,
+}
+
+#[derive(Debug)]
+enum Format {
+    Pcap {
+        byte_order: ByteOrder,
+        ticks_per_second: u64,
+        link_type: u32,
+    },
+    Pcapng {
+        byte_order: ByteOrder,
+        interfaces: Vec<Interface>,
+    },
+}
+
+/// A reader of the packets in a capture file.
+///
+/// The format is detected from the first bytes.
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    format: Option<Format>,
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            format: None,
+        }
+    }
+
+    /// It returns the next packet, or `None` at the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't read from the input, the format is
+    /// not pcap or pcapng, or the file is corrupted or truncated.
+    pub fn next_packet(&mut self) -> Result<Option<Packet>, Error> {
+        if self.format.is_none() {
+            let mut magic = [0; 4];
+
+            if !read_or_eof(&mut self.reader, &mut magic)? {
+                return Ok(None);
+            }
+
+            self.format = Some(self.read_file_header(magic)?);
+        }
+
+        match self.format {
+            Some(Format::Pcap { .. }) => self.next_pcap_packet(),
+            _ => self.next_pcapng_packet(),
+        }
+    }
+
+    fn read_file_header(&mut self, magic: [u8; 4]) -> Result<Format, Error> {
+        let little_endian = u32::from_le_bytes(magic);
+
+        if little_endian == PCAPNG_SECTION_HEADER_BLOCK {
+            let byte_order = self.read_section_header()?;
+
+            return Ok(Format::Pcapng {
+                byte_order,
+                interfaces: Vec::new(),
+            });
+        }
+
+        let (byte_order, nanos) = match (little_endian, u32::from_be_bytes(magic)) {
+            (PCAP_MAGIC_MICROS, _) => (ByteOrder::Little, false),
+            (PCAP_MAGIC_NANOS, _) => (ByteOrder::Little, true),
+            (_, PCAP_MAGIC_MICROS) => (ByteOrder::Big, false),
+            (_, PCAP_MAGIC_NANOS) => (ByteOrder::Big, true),
+            _ => return Err(Error::UnknownFormat(u32::from_be_bytes(magic))),
+        };
+
+        // Version, time zone, timestamp accuracy, snapshot length and link
+        // type
+        let mut header = [0; 20];
+        read_all(&mut self.reader, &mut header)?;
+
+        Ok(Format::Pcap {
+            byte_order,
+            ticks_per_second: if nanos { 1_000_000_000 } else { 1_000_000 },
+            // The upper bits have the FCS length
+            link_type: byte_order.u32(&header[16..]) & 0xffff,
+        })
+    }
+
+    fn next_pcap_packet(&mut self) -> Result<Option<Packet>, Error> {
+        let Some(Format::Pcap {
+            byte_order,
+            ticks_per_second,
+            link_type,
+        }) = self.format
+        else {
+            return Ok(None);
+        };
+
+        let mut header = [0; 16];
+
+        if !read_or_eof(&mut self.reader, &mut header)? {
+            return Ok(None);
+        }
+
+        let seconds = byte_order.u32(&header[0..]);
+        let fraction = byte_order.u32(&header[4..]);
+        let captured_length = byte_order.u32(&header[8..]);
+
+        if captured_length > MAX_BLOCK_LENGTH {
+            return Err(Error::Invalid("the packet is too large"));
+        }
+
+        let mut data = vec![0; captured_length as usize];
+        read_all(&mut self.reader, &mut data)?;
+
+        Ok(Some(Packet {
+            timestamp: Timestamp::from_ticks(
+                u64::from(seconds) * ticks_per_second + u64::from(fraction),
+                ticks_per_second,
+            ),
+            link_type,
+            data,
+        }))
+    }
+
+    /// It reads the rest of a section header block, after the block type,
+    /// and returns the byte order of the section.
+    fn read_section_header(&mut self) -> Result<ByteOrder, Error> {
+        let mut header = [0; 8];
+        read_all(&mut self.reader, &mut header)?;
+
+        let byte_order = match u32::from_le_bytes([header[4], header[5], header[6], header[7]]) {
+            PCAPNG_BYTE_ORDER_MAGIC => ByteOrder::Little,
+            _ if u32::from_be_bytes([header[4], header[5], header[6], header[7]])
+                == PCAPNG_BYTE_ORDER_MAGIC =>
+            {
+                ByteOrder::Big
+            }
+            _ => return Err(Error::Invalid("unknown byte order in the section header")),
+        };
+
+        let total_length = byte_order.u32(&header[0..]);
+
+        // The type, the length and the byte-order magic are already read
+        let body_length = checked_body_length(total_length)?
+            .checked_sub(4)
+            .ok_or(Error::Invalid("the section header block is too short"))?;
+
+        self.skip(body_length)?;
+
+        Ok(byte_order)
+    }
+
+    fn next_pcapng_packet(&mut self) -> Result<Option<Packet>, Error> {
+        loop {
+            let Some(Format::Pcapng { byte_order, .. }) = self.format else {
+                return Ok(None);
+            };
+
+            let mut header = [0; 8];
+
+            if !read_or_eof(&mut self.reader, &mut header)? {
+                return Ok(None);
+            }
+
+            let block_type = byte_order.u32(&header[0..]);
+
+            if block_type == PCAPNG_SECTION_HEADER_BLOCK {
+                // A new section, maybe with another byte order. The length
+                // is read again with it
+                let mut rest = [0; 4];
+                read_all(&mut self.reader, &mut rest)?;
+
+                let byte_order = self.read_new_section_header(&header[4..], rest)?;
+
+                self.format = Some(Format::Pcapng {
+                    byte_order,
+                    interfaces: Vec::new(),
+                });
+
+                continue;
+            }
+
+            let body_length = checked_body_length(byte_order.u32(&header[4..]))?;
+
+            let mut body = vec![0; body_length as usize];
+            read_all(&mut self.reader, &mut body)?;
+
+            if let Some(packet) = self.decode_pcapng_block(block_type, &body)? {
+                return Ok(Some(packet));
+            }
+        }
+    }
+
+    fn read_new_section_header(
+        &mut self,
+        length: &[u8],
+        byte_order_magic: [u8; 4],
+    ) -> Result<ByteOrder, Error> {
+        let byte_order = if u32::from_le_bytes(byte_order_magic) == PCAPNG_BYTE_ORDER_MAGIC {
+            ByteOrder::Little
+        } else if u32::from_be_bytes(byte_order_magic) == PCAPNG_BYTE_ORDER_MAGIC {
+            ByteOrder::Big
+        } else {
+            return Err(Error::Invalid("unknown byte order in the section header"));
+        };
+
+        let body_length = checked_body_length(byte_order.u32(length))?
+            .checked_sub(4)
+            .ok_or(Error::Invalid("the section header block is too short"))?;
+
+        self.skip(body_length)?;
+
+        Ok(byte_order)
+    }
+
+    /// It decodes a pcapng block body. It returns the packet of packet
+    /// blocks, and `None` for other blocks.
+    fn decode_pcapng_block(
+        &mut self,
+        block_type: u32,
+        body: &[u8],
+    ) -> Result<Option<Packet>, Error> {
+        let Some(Format::Pcapng {
+            byte_order,
+            interfaces,
+        }) = &mut self.format
+        else {
+            return Ok(None);
+        };
+
+        let byte_order = *byte_order;
+        let truncated = || Error::Invalid("the block is shorter than its fields");
+
+        let (interface_id, timestamp_ticks, captured_length, data_start) = match block_type {
+            PCAPNG_INTERFACE_DESCRIPTION_BLOCK => {
+                if body.len() < 8 {
+                    return Err(truncated());
+                }
+
+                interfaces.push(Interface {
+                    link_type: u32::from(byte_order.u16(body)),
+                    ticks_per_second: timestamp_resolution(byte_order, &body[8..]),
+                });
+
+                return Ok(None);
+            }
+            PCAPNG_ENHANCED_PACKET_BLOCK => {
+                if body.len() < 20 {
+                    return Err(truncated());
+                }
+
+                (
+                    byte_order.u32(body),
+                    u64::from(byte_order.u32(&body[4..])) << 32
+                        | u64::from(byte_order.u32(&body[8..])),
+                    byte_order.u32(&body[12..]) as usize,
+                    20,
+                )
+            }
+            PCAPNG_OBSOLETE_PACKET_BLOCK => {
+                if body.len() < 20 {
+                    return Err(truncated());
+                }
+
+                (
+                    u32::from(byte_order.u16(body)),
+                    u64::from(byte_order.u32(&body[4..])) << 32
+                        | u64::from(byte_order.u32(&body[8..])),
+                    byte_order.u32(&body[12..]) as usize,
+                    20,
+                )
+            }
+            PCAPNG_SIMPLE_PACKET_BLOCK => {
+                if body.len() < 4 {
+                    return Err(truncated());
+                }
+
+                // The captured length is the original length, unless the
+                // packet was cut to fit the block
+                let original_length = byte_order.u32(body) as usize;
+
+                (0, 0, original_length.min(body.len() - 4), 4)
+            }
+            _ => return Ok(None),
+        };
+
+        let interface = interfaces
+            .get(interface_id as usize)
+            .ok_or(Error::Invalid("the packet refers to an unknown interface"))?;
+
+        let data = body
+            .get(data_start..data_start + captured_length)
+            .ok_or_else(truncated)?;
+
+        Ok(Some(Packet {
+            timestamp: Timestamp::from_ticks(timestamp_ticks, interface.ticks_per_second),
+            link_type: interface.link_type,
+            data: data.to_vec(),
+        }))
+    }
+
+    fn skip(&mut self, length: u32) -> Result<(), Error> {
+        let skipped = io::copy(
+            &mut (&mut self.reader).take(u64::from(length)),
+            &mut io::sink(),
+        )?;
+
+        if skipped < u64::from(length) {
+            return Err(Error::Truncated);
+        }
+
+        Ok(())
+    }
+}
+
+/// It returns the length of a pcapng block body from the block total
+/// length, which includes the type and the length at the beginning and the
+/// length again at the end.
+fn checked_body_length(total_length: u32) -> Result<u32, Error> {
+    if total_length > MAX_BLOCK_LENGTH {
+        return Err(Error::Invalid("the block is too large"));
+    }
+
+    if !total_length.is_multiple_of(4) {
+        return Err(Error::Invalid("the block length is not a multiple of 4"));
+    }
+
+    // The trailing length is read as part of the body and ignored
+    total_length
+        .checked_sub(8)
+        .ok_or(Error::Invalid("the block is too short"))
+}
+
+/// It returns the timestamp ticks per second in the options of an interface
+/// description block: microseconds, unless there is an `if_tsresol` option.
+fn timestamp_resolution(byte_order: ByteOrder, mut options: &[u8]) -> u64 {
+    while options.len() >= 4 {
+        let code = byte_order.u16(options);
+        let length = usize::from(byte_order.u16(&options[2..]));
+
+        if code == PCAPNG_OPTION_END {
+            break;
+        }
+
+        let Some(value) = options.get(4..4 + length) else {
+            break;
+        };
+
+        if code == PCAPNG_OPTION_TIMESTAMP_RESOLUTION && length == 1 {
+            let exponent = u32::from(value[0] & 0x7f);
+
+            let ticks = if value[0] & 0x80 == 0 {
+                10u64.checked_pow(exponent)
+            } else {
+                2u64.checked_pow(exponent)
+            };
+
+            return ticks.unwrap_or(1_000_000);
+        }
+
+        // Option values are padded to 32 bits
+        options = options
+            .get(4 + length.next_multiple_of(4)..)
+            .unwrap_or_default();
+    }
+
+    1_000_000
+}
+
+/// It fills the buffer. It returns false if the input has ended before the
+/// first byte.
+fn read_or_eof<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<bool, Error> {
+    let mut filled = 0;
+
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(Error::Truncated),
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(true)
+}
+
+/// It fills the buffer. The input can't end before.
+fn read_all<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<(), Error> {
+    if buffer.is_empty() || read_or_eof(reader, buffer)? {
+        Ok(())
+    } else {
+        Err(Error::Truncated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should_read {
+        use crate::pcap::{
+            capture::{CaptureReader, Packet},
+            Error, Timestamp,
+        };
+
+        fn pcap_header(magic: [u8; 4], link_type: u32) -> Vec<u8> {
+            let mut header = magic.to_vec();
+            header.extend_from_slice(&[2, 0, 4, 0]);
+            header.extend_from_slice(&[0; 8]);
+            header.extend_from_slice(&65535u32.to_le_bytes());
+            header.extend_from_slice(&link_type.to_le_bytes());
+            header
+        }
+
+        fn read_all(input: &[u8]) -> Result<Vec<Packet>, Error> {
+            let mut reader = CaptureReader::new(input);
+            let mut packets = Vec::new();
+
+            while let Some(packet) = reader.next_packet()? {
+                packets.push(packet);
+            }
+
+            Ok(packets)
+        }
+
+        #[test]
+        fn pcap_files_with_microsecond_timestamps() {
+            let mut input = pcap_header([0xd4, 0xc3, 0xb2, 0xa1], 1);
+            input.extend_from_slice(&10u32.to_le_bytes());
+            input.extend_from_slice(&250_000u32.to_le_bytes());
+            input.extend_from_slice(&3u32.to_le_bytes());
+            input.extend_from_slice(&3u32.to_le_bytes());
+            input.extend_from_slice(b"abc");
+
+            assert_eq!(
+                read_all(&input).unwrap(),
+                vec![Packet {
+                    timestamp: Timestamp {
+                        seconds: 10,
+                        nanos: 250_000_000
+                    },
+                    link_type: 1,
+                    data: b"abc".to_vec(),
+                }]
+            );
+        }
+
+        #[test]
+        fn pcapng_files_with_a_custom_timestamp_resolution() {
+            let mut input = Vec::new();
+
+            // Section header block
+            input.extend_from_slice(&0x0a0d_0d0au32.to_le_bytes());
+            input.extend_from_slice(&28u32.to_le_bytes());
+            input.extend_from_slice(&0x1a2b_3c4du32.to_le_bytes());
+            input.extend_from_slice(&[1, 0, 0, 0]);
+            input.extend_from_slice(&u64::MAX.to_le_bytes());
+            input.extend_from_slice(&28u32.to_le_bytes());
+
+            // Interface description block with nanosecond timestamps
+            input.extend_from_slice(&1u32.to_le_bytes());
+            input.extend_from_slice(&32u32.to_le_bytes());
+            input.extend_from_slice(&101u16.to_le_bytes());
+            input.extend_from_slice(&[0; 6]);
+            input.extend_from_slice(&[9, 0, 1, 0, 9, 0, 0, 0]);
+            input.extend_from_slice(&[0; 4]);
+            input.extend_from_slice(&32u32.to_le_bytes());
+
+            // Enhanced packet block
+            let ticks = 5_000_000_007u64.to_be_bytes();
+            input.extend_from_slice(&6u32.to_le_bytes());
+            input.extend_from_slice(&36u32.to_le_bytes());
+            input.extend_from_slice(&0u32.to_le_bytes());
+            input.extend(ticks[..4].iter().rev());
+            input.extend(ticks[4..].iter().rev());
+            input.extend_from_slice(&3u32.to_le_bytes());
+            input.extend_from_slice(&3u32.to_le_bytes());
+            input.extend_from_slice(b"abc\0");
+            input.extend_from_slice(&36u32.to_le_bytes());
+
+            assert_eq!(
+                read_all(&input).unwrap(),
+                vec![Packet {
+                    timestamp: Timestamp {
+                        seconds: 5,
+                        nanos: 7
+                    },
+                    link_type: 101,
+                    data: b"abc".to_vec(),
+                }]
+            );
+        }
+
+        #[test]
+        fn empty_files() {
+            assert_eq!(read_all(b"").unwrap(), vec![]);
+        }
+    }
+
+    mod it_should_fail {
+        use crate::pcap::{capture::CaptureReader, Error};
+
+        #[test]
+        fn when_the_format_is_unknown() {
+            assert!(matches!(
+                CaptureReader::new(&b"GIF89a"[..]).next_packet(),
+                Err(Error::UnknownFormat(0x4749_4638))
+            ));
+        }
+
+        #[test]
+        fn when_the_file_is_truncated() {
+            assert!(matches!(
+                CaptureReader::new(&[0xd4, 0xc3, 0xb2, 0xa1, 2, 0][..]).next_packet(),
+                Err(Error::Truncated)
+            ));
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Bencoded payloads in packet captures.
+//!
+//! It reads `.pcap` and `.pcapng` files and extracts the bencoded payloads of
+//! the protocols used by torrent clients:
+//!
+//! - DHT KRPC messages in UDP datagrams
+//!   ([BEP 5](https://www.bittorrent.org/beps/bep_0005.html)), rendered like
+//!   [`krpc::render`].
+//! - HTTP tracker responses in TCP streams, rendered like
+//!   [`tracker::render`](crate::tracker::render). Chunked and
+//!   `Content-Length` bodies are supported. Compressed bodies are skipped.
+//! - Extension messages in peer-wire TCP streams
+//!   ([BEP 10](https://www.bittorrent.org/beps/bep_0010.html)): the extension
+//!   handshake, `ut_metadata` and `ut_pex` messages. The extension IDs are
+//!   taken from the handshake of the receiver.
+//!
+//! TCP segments are reassembled per direction, handling retransmissions and
+//! segments out of order. IPv4 and IPv6 over Ethernet (with VLAN tags),
+//! Linux cooked captures, loopback and raw IP captures are supported. IP
+//! fragments and uTP streams are ignored.
+//!
+//! ```rust,no_run
+//! use std::fs::File;
+//!
+//! use torrust_bencode2json::pcap;
+//!
+//! let capture = File::open("dht.pcapng").unwrap();
+//!
+//! for record in pcap::extract(capture) {
+//!     println!("{}", record.unwrap().to_json());
+//! }
+//! ```
+//!
+//! The payloads are parsed with a maximum nesting depth of [`MAX_DEPTH`].
+mod capture;
+mod packet;
+mod stream;
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, Read},
+    net::SocketAddr,
+};
+
+use serde_json::json;
+use thiserror::Error;
+
+use crate::{
+    krpc,
+    parsers::{options::Limits, BencodeParser},
+    value::{Value, ValueBuilder},
+};
+
+use self::{
+    capture::CaptureReader,
+    packet::{Segment, TransportSegment},
+    stream::Reassembler,
+};
+
+/// Maximum number of nested lists and dictionaries in the payloads.
+pub const MAX_DEPTH: usize = 64;
+
+/// Errors that can occur while reading a capture.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// I/O error reading the capture.
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    /// The file is not a pcap or pcapng capture.
+    #[error("Unknown capture format with magic number {0:#010x}")]
+    UnknownFormat(u32),
+
+    /// The capture is corrupted.
+    #[error("Invalid capture: {0}")]
+    Invalid(&'static str),
+
+    /// The capture ends in the middle of a packet or a block.
+    #[error("The capture ends in the middle of a packet")]
+    Truncated,
+}
+
+/// When a packet was captured, since the Unix epoch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    pub seconds: u64,
+    pub nanos: u32,
+}
+
+impl Timestamp {
+    /// It returns the timestamp of a number of ticks of the given
+    /// resolution.
+    fn from_ticks(ticks: u64, ticks_per_second: u64) -> Self {
+        let ticks_per_second = ticks_per_second.max(1);
+        let fraction =
+            u128::from(ticks % ticks_per_second) * 1_000_000_000 / u128::from(ticks_per_second);
+
+        Self {
+            seconds: ticks / ticks_per_second,
+            // It's always less than a second
+            nanos: u32::try_from(fraction).unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:09}", self.seconds, self.nanos)
+    }
+}
+
+/// The transport protocol of a flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+impl Transport {
+    /// It returns the name of the protocol, like `tcp`.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tcp => "tcp",
+            Self::Udp => "udp",
+        }
+    }
+}
+
+/// The 5-tuple of the packets with a payload, in the direction the payload
+/// was sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Flow {
+    pub transport: Transport,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+}
+
+/// The kind of a payload found in a capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    /// A DHT KRPC message.
+    Krpc,
+
+    /// The body of an HTTP tracker response.
+    TrackerResponse,
+
+    /// A peer-wire extension handshake.
+    ExtensionHandshake,
+
+    /// A `ut_metadata` extension message.
+    UtMetadata,
+
+    /// A `ut_pex` extension message.
+    UtPex,
+
+    /// A message of another extension, or of an extension whose ID is not
+    /// in the handshake.
+    Extension,
+}
+
+impl RecordKind {
+    /// It returns the name of the kind, like `krpc`.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Krpc => "krpc",
+            Self::TrackerResponse => "tracker_response",
+            Self::ExtensionHandshake => "extension_handshake",
+            Self::UtMetadata => "ut_metadata",
+            Self::UtPex => "ut_pex",
+            Self::Extension => "extension",
+        }
+    }
+}
+
+impl fmt::Display for RecordKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A bencoded payload found in a capture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// The timestamp of the packet that completed the payload.
+    pub timestamp: Timestamp,
+
+    pub flow: Flow,
+
+    pub kind: RecordKind,
+
+    /// The decoded payload.
+    pub json: serde_json::Value,
+}
+
+impl Record {
+    /// It returns the JSON of the record, with the timestamp as a string
+    /// with nanoseconds, so that it doesn't lose precision.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "timestamp": self.timestamp.to_string(),
+            "flow": {
+                "protocol": self.flow.transport.as_str(),
+                "src_ip": self.flow.src.ip().to_string(),
+                "src_port": self.flow.src.port(),
+                "dst_ip": self.flow.dst.ip().to_string(),
+                "dst_port": self.flow.dst.port(),
+            },
+            "kind": self.kind.as_str(),
+            "json": self.json,
+        })
+    }
+}
+
+/// An iterator over the payloads found in a capture, in the order they are
+/// completed.
+///
+/// It stops after the first error.
+pub struct Extractor<R: Read> {
+    capture: CaptureReader<R>,
+    streams: Reassembler,
+    records: VecDeque<Record>,
+    finished: bool,
+}
+
+impl<R: Read> Extractor<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            capture: CaptureReader::new(reader),
+            streams: Reassembler::default(),
+            records: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    fn process(&mut self, timestamp: Timestamp, segment: &Segment<'_>) {
+        match &segment.transport {
+            TransportSegment::Udp { payload } => {
+                if let Some(json) = decode_krpc(payload) {
+                    self.records.push_back(Record {
+                        timestamp,
+                        flow: Flow {
+                            transport: Transport::Udp,
+                            src: segment.src,
+                            dst: segment.dst,
+                        },
+                        kind: RecordKind::Krpc,
+                        json,
+                    });
+                }
+            }
+            TransportSegment::Tcp(tcp) => {
+                self.records.extend(
+                    self.streams
+                        .process(timestamp, segment.src, segment.dst, tcp),
+                );
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for Extractor<R> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.records.pop_front() {
+                return Some(Ok(record));
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            match self.capture.next_packet() {
+                Ok(Some(packet)) => {
+                    if let Some(segment) = packet::decode(packet.link_type, &packet.data) {
+                        self.process(packet.timestamp, &segment);
+                    }
+                }
+                Ok(None) => {
+                    self.finished = true;
+                    self.records.extend(self.streams.finish());
+                }
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// It returns an iterator over the payloads found in a capture.
+pub fn extract<R: Read>(reader: R) -> Extractor<R> {
+    Extractor::new(reader)
+}
+
+/// It returns the rendered KRPC message in a UDP payload, or `None` if the
+/// payload is not a KRPC message.
+fn decode_krpc(payload: &[u8]) -> Option<serde_json::Value> {
+    if payload.first() != Some(&b'd') {
+        return None;
+    }
+
+    let (message, end) = decode_prefix(payload)?;
+
+    if end != payload.len() {
+        return None;
+    }
+
+    krpc::Kind::of(&message)?;
+
+    Some(krpc::render(&message))
+}
+
+/// It builds the value at the beginning of a payload with the maximum
+/// depth, and returns it with the position where it ends. It returns `None`
+/// if the payload doesn't start with a valid value.
+fn decode_prefix(payload: &[u8]) -> Option<(Value, usize)> {
+    let mut parser = BencodeParser::builder(payload)
+        .limits(Limits {
+            max_depth: Some(MAX_DEPTH),
+            ..Limits::default()
+        })
+        .build_from_buf_read();
+
+    let mut builder = ValueBuilder::default();
+
+    let end = builder.parse_value(&mut parser).ok()??;
+
+    Some((builder.into_value().ok()?, usize::try_from(end).ok()?))
+}
+
+/// It builds the value in a payload with the maximum depth. It returns
+/// `None` if the payload is not exactly one valid value.
+fn decode_exact(payload: &[u8]) -> Option<Value> {
+    match decode_prefix(payload)? {
+        (value, end) if end == payload.len() => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should {
+        use crate::pcap::{decode_krpc, Timestamp};
+
+        #[test]
+        fn convert_ticks_to_timestamps() {
+            assert_eq!(
+                Timestamp::from_ticks(1_500_000, 1_000_000),
+                Timestamp {
+                    seconds: 1,
+                    nanos: 500_000_000
+                }
+            );
+            assert_eq!(
+                Timestamp::from_ticks(3, 4),
+                Timestamp {
+                    seconds: 0,
+                    nanos: 750_000_000
+                }
+            );
+        }
+
+        #[test]
+        fn format_timestamps_with_nanoseconds() {
+            assert_eq!(
+                Timestamp {
+                    seconds: 1,
+                    nanos: 5
+                }
+                .to_string(),
+                "1.000000005"
+            );
+        }
+
+        #[test]
+        fn only_decode_udp_payloads_that_are_krpc_messages() {
+            assert!(decode_krpc(b"d1:y1:qe").is_some());
+            assert!(decode_krpc(b"d1:y1:qeXX").is_none());
+            assert!(decode_krpc(b"d4:spam4:eggse").is_none());
+            assert!(decode_krpc(b"\x00\x00\x04\x17\x27\x10\x19\x80").is_none());
+        }
+
+        #[test]
+        fn ignore_payloads_nested_too_deep() {
+            let mut input = b"d1:y1:q1:a".to_vec();
+            input.extend(std::iter::repeat_n(b'l', 100));
+            input.extend(std::iter::repeat_n(b'e', 101));
+
+            assert!(decode_krpc(&input).is_none());
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//! Decoding of the link, network and transport headers of captured packets.
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+const IP_PROTOCOL_TCP: u8 = 6;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+const IPV6_HOP_BY_HOP_OPTIONS: u8 = 0;
+const IPV6_ROUTING: u8 = 43;
+const IPV6_FRAGMENT: u8 = 44;
+const IPV6_DESTINATION_OPTIONS: u8 = 60;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+
+/// A UDP datagram or a TCP segment.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Segment<'a> {
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub transport: TransportSegment<'a>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TransportSegment<'a> {
+    Udp { payload: &'a [u8] },
+    Tcp(TcpSegment<'a>),
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TcpSegment<'a> {
+    pub sequence: u32,
+    pub syn: bool,
+    pub fin: bool,
+    pub rst: bool,
+    pub payload: &'a [u8],
+}
+
+/// It returns the UDP datagram or TCP segment in a captured packet, or
+/// `None` for other packets, unsupported link types, IP fragments and
+/// truncated packets.
+pub fn decode(link_type: u32, data: &[u8]) -> Option<Segment<'_>> {
+    match link_type {
+        LINKTYPE_NULL | LINKTYPE_LOOP => decode_ip(data.get(4..)?),
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => decode_ip(data),
+        LINKTYPE_ETHERNET => decode_ethernet(data),
+        LINKTYPE_LINUX_SLL => decode_ethertype(read_u16(data, 14)?, data.get(16..)?),
+        LINKTYPE_LINUX_SLL2 => decode_ethertype(read_u16(data, 0)?, data.get(20..)?),
+        _ => None,
+    }
+}
+
+fn decode_ethernet(data: &[u8]) -> Option<Segment<'_>> {
+    let mut offset = 12;
+
+    loop {
+        let ethertype = read_u16(data, offset)?;
+
+        if ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+            offset += 4;
+        } else {
+            return decode_ethertype(ethertype, data.get(offset + 2..)?);
+        }
+    }
+}
+
+fn decode_ethertype(ethertype: u16, data: &[u8]) -> Option<Segment<'_>> {
+    match ethertype {
+        ETHERTYPE_IPV4 => decode_ipv4(data),
+        ETHERTYPE_IPV6 => decode_ipv6(data),
+        _ => None,
+    }
+}
+
+/// It decodes an IP packet whose version is only in the IP header.
+fn decode_ip(data: &[u8]) -> Option<Segment<'_>> {
+    match data.first()? >> 4 {
+        4 => decode_ipv4(data),
+        6 => decode_ipv6(data),
+        _ => None,
+    }
+}
+
+fn decode_ipv4(data: &[u8]) -> Option<Segment<'_>> {
+    let header_length = usize::from(data.first()? & 0x0f) * 4;
+    let total_length = usize::from(read_u16(data, 2)?);
+    let fragment = read_u16(data, 6)?;
+
+    // More fragments, or not the first one
+    if fragment & 0x3fff != 0 || header_length < 20 {
+        return None;
+    }
+
+    // Ethernet frames can have padding after the packet
+    let packet = data.get(..total_length.min(data.len()))?;
+
+    decode_transport(
+        packet[9],
+        IpAddr::V4(Ipv4Addr::from(read_array::<4>(packet, 12)?)),
+        IpAddr::V4(Ipv4Addr::from(read_array::<4>(packet, 16)?)),
+        packet.get(header_length..)?,
+    )
+}
+
+fn decode_ipv6(data: &[u8]) -> Option<Segment<'_>> {
+    let payload_length = usize::from(read_u16(data, 4)?);
+    let src = IpAddr::V6(Ipv6Addr::from(read_array::<16>(data, 8)?));
+    let dst = IpAddr::V6(Ipv6Addr::from(read_array::<16>(data, 24)?));
+
+    let mut next_header = data[6];
+    let end = data.len().min(40 + payload_length);
+    let mut payload = data.get(40..end)?;
+
+    loop {
+        match next_header {
+            IPV6_HOP_BY_HOP_OPTIONS | IPV6_ROUTING | IPV6_DESTINATION_OPTIONS => {
+                let length = (usize::from(*payload.get(1)?) + 1) * 8;
+
+                next_header = payload[0];
+                payload = payload.get(length..)?;
+            }
+            IPV6_FRAGMENT => {
+                // Only atomic fragments, with offset 0 and no more fragments
+                if read_u16(payload, 2)? & 0xfff9 != 0 {
+                    return None;
+                }
+
+                next_header = payload[0];
+                payload = payload.get(8..)?;
+            }
+            protocol => return decode_transport(protocol, src, dst, payload),
+        }
+    }
+}
+
+fn decode_transport(protocol: u8, src: IpAddr, dst: IpAddr, data: &[u8]) -> Option<Segment<'_>> {
+    let src = SocketAddr::new(src, read_u16(data, 0)?);
+    let dst = SocketAddr::new(dst, read_u16(data, 2)?);
+
+    let transport = match protocol {
+        IP_PROTOCOL_UDP => {
+            let length = usize::from(read_u16(data, 4)?);
+
+            TransportSegment::Udp {
+                payload: data.get(8..length.min(data.len()))?,
+            }
+        }
+        IP_PROTOCOL_TCP => {
+            let header_length = usize::from(data.get(12)? >> 4) * 4;
+            let flags = *data.get(13)?;
+
+            TransportSegment::Tcp(TcpSegment {
+                sequence: u32::from_be_bytes(read_array::<4>(data, 4)?),
+                syn: flags & TCP_SYN != 0,
+                fin: flags & TCP_FIN != 0,
+                rst: flags & TCP_RST != 0,
+                payload: data.get(header_length.max(20)..)?,
+            })
+        }
+        _ => return None,
+    };
+
+    Some(Segment {
+        src,
+        dst,
+        transport,
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    read_array::<2>(data, offset).map(u16::from_be_bytes)
+}
+
+fn read_array<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    data.get(offset..offset + N)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should_decode {
+        use std::net::SocketAddr;
+
+        use crate::pcap::packet::{decode, Segment, TcpSegment, TransportSegment};
+
+        fn ipv4_udp(payload: &[u8]) -> Vec<u8> {
+            let total_length = u16::try_from(28 + payload.len()).unwrap();
+
+            let mut packet = vec![0x45, 0];
+            packet.extend_from_slice(&total_length.to_be_bytes());
+            packet.extend_from_slice(&[0, 0, 0, 0, 64, 17, 0, 0]);
+            packet.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+            packet.extend_from_slice(&6881u16.to_be_bytes());
+            packet.extend_from_slice(&6882u16.to_be_bytes());
+            packet.extend_from_slice(&(total_length - 20).to_be_bytes());
+            packet.extend_from_slice(&[0, 0]);
+            packet.extend_from_slice(payload);
+            packet
+        }
+
+        fn udp_segment(payload: &[u8]) -> Segment<'_> {
+            Segment {
+                src: "10.0.0.1:6881".parse().unwrap(),
+                dst: "10.0.0.2:6882".parse().unwrap(),
+                transport: TransportSegment::Udp { payload },
+            }
+        }
+
+        #[test]
+        fn udp_datagrams_in_ethernet_frames_with_vlan_tags_and_padding() {
+            let mut frame = vec![0; 12];
+            frame.extend_from_slice(&[0x81, 0x00, 0, 1, 0x08, 0x00]);
+            frame.extend_from_slice(&ipv4_udp(b"abc"));
+            frame.extend_from_slice(&[0; 8]);
+
+            assert_eq!(decode(1, &frame), Some(udp_segment(b"abc")));
+        }
+
+        #[test]
+        fn udp_datagrams_in_linux_cooked_captures() {
+            let mut frame = vec![0; 14];
+            frame.extend_from_slice(&[0x08, 0x00]);
+            frame.extend_from_slice(&ipv4_udp(b"abc"));
+
+            assert_eq!(decode(113, &frame), Some(udp_segment(b"abc")));
+        }
+
+        #[test]
+        fn tcp_segments_in_ipv6_packets_with_extension_headers() {
+            let mut packet = vec![0x60, 0, 0, 0, 0, 31, 0, 64];
+            packet.extend_from_slice(&[0; 15]);
+            packet.push(1);
+            packet.extend_from_slice(&[0; 15]);
+            packet.push(2);
+
+            // Hop-by-hop options
+            packet.extend_from_slice(&[6, 0, 0, 0, 0, 0, 0, 0]);
+
+            // TCP header with the SYN and FIN flags
+            packet.extend_from_slice(&80u16.to_be_bytes());
+            packet.extend_from_slice(&1234u16.to_be_bytes());
+            packet.extend_from_slice(&7u32.to_be_bytes());
+            packet.extend_from_slice(&[0, 0, 0, 0, 0x50, 0x03, 0, 0, 0, 0, 0, 0]);
+            packet.extend_from_slice(b"abc");
+
+            assert_eq!(
+                decode(101, &packet),
+                Some(Segment {
+                    src: "[::1]:80".parse::<SocketAddr>().unwrap(),
+                    dst: "[::2]:1234".parse::<SocketAddr>().unwrap(),
+                    transport: TransportSegment::Tcp(TcpSegment {
+                        sequence: 7,
+                        syn: true,
+                        fin: true,
+                        rst: false,
+                        payload: b"abc",
+                    }),
+                })
+            );
+        }
+    }
+
+    mod it_should_ignore {
+        use crate::pcap::packet::decode;
+
+        #[test]
+        fn ipv4_fragments() {
+            let mut packet = vec![0x45, 0, 0, 28, 0, 0, 0x20, 0, 64, 17, 0, 0];
+            packet.extend_from_slice(&[0; 16]);
+
+            assert_eq!(decode(101, &packet), None);
+        }
+
+        #[test]
+        fn unsupported_link_types() {
+            assert_eq!(decode(105, &[0; 64]), None);
+        }
+
+        #[test]
+        fn truncated_packets() {
+            assert_eq!(decode(1, &[0; 13]), None);
+            assert_eq!(decode(101, &[0x45, 0, 0, 28]), None);
+        }
+    }
+}
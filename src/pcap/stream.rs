@@ -0,0 +1,833 @@
+//! TCP stream reassembly, and the HTTP and peer-wire protocols on the
+//! streams.
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+};
+
+use serde_json::json;
+
+use super::{
+    decode_exact, decode_prefix, packet::TcpSegment, Flow, Record, RecordKind, Timestamp, Transport,
+};
+use crate::{
+    extension::{render_handshake, Handshake, MetadataMessage, Pex, UT_METADATA, UT_PEX},
+    tracker,
+};
+
+/// Maximum number of bytes buffered per direction, including the segments
+/// out of order. Streams above it are ignored.
+const MAX_BUFFERED_LENGTH: usize = 16 * 1024 * 1024;
+
+/// Maximum length of the headers of an HTTP response.
+const MAX_HTTP_HEADERS_LENGTH: usize = 64 * 1024;
+
+/// The peer-wire handshake: the protocol name with its length, the reserved
+/// bytes, the infohash and the peer ID.
+const PEER_WIRE_HANDSHAKE_LENGTH: usize = 68;
+const PEER_WIRE_PROTOCOL: &[u8] = b"\x13BitTorrent protocol";
+
+/// The ID of the extended messages of BEP 10.
+const EXTENDED_MESSAGE_ID: u8 = 20;
+const EXTENSION_HANDSHAKE_ID: u8 = 0;
+
+const HTTP_RESPONSE: &[u8] = b"HTTP/";
+const HTTP_HEADERS_END: &[u8] = b"\r\n\r\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    /// Not enough bytes to know the protocol.
+    Unknown,
+
+    Http,
+
+    /// A peer-wire stream, before or after the handshake.
+    PeerWire {
+        handshake_done: bool,
+    },
+
+    /// Another protocol, or a stream that can't be followed. The data is
+    /// discarded.
+    Ignored,
+}
+
+/// One direction of a TCP connection.
+#[derive(Debug)]
+struct Stream {
+    /// The sequence number of the next byte in order.
+    next_sequence: Option<u32>,
+
+    /// The bytes in order not consumed yet.
+    buffer: Vec<u8>,
+
+    /// The segments after a gap, by sequence number.
+    pending: BTreeMap<u32, Vec<u8>>,
+
+    /// The number of bytes in the pending segments.
+    pending_length: usize,
+
+    protocol: Protocol,
+
+    /// The timestamp of the last segment.
+    timestamp: Timestamp,
+}
+
+impl Stream {
+    fn new(timestamp: Timestamp) -> Self {
+        Self {
+            next_sequence: None,
+            buffer: Vec::new(),
+            pending: BTreeMap::new(),
+            pending_length: 0,
+            protocol: Protocol::Unknown,
+            timestamp,
+        }
+    }
+
+    /// It adds the payload of a segment to the stream, in order.
+    fn push(&mut self, sequence: u32, payload: &[u8]) {
+        if payload.is_empty() || self.protocol == Protocol::Ignored {
+            return;
+        }
+
+        let next_sequence = *self.next_sequence.get_or_insert(sequence);
+
+        if sequence_distance(sequence, next_sequence) > 0 {
+            self.pending_length += payload.len();
+
+            // A retransmitted segment replaces the previous one
+            if let Some(previous) = self.pending.insert(sequence, payload.to_vec()) {
+                self.pending_length -= previous.len();
+            }
+        } else {
+            self.append(sequence, payload);
+
+            // The gaps the segment filled
+            while let Some(sequence) = self.next_pending() {
+                let payload = self.pending.remove(&sequence).unwrap_or_default();
+
+                self.pending_length -= payload.len();
+                self.append(sequence, &payload);
+            }
+        }
+
+        if self.buffer.len() + self.pending_length > MAX_BUFFERED_LENGTH {
+            self.ignore();
+        }
+    }
+
+    /// It appends the bytes of a segment after the next sequence number,
+    /// skipping the retransmitted bytes.
+    fn append(&mut self, sequence: u32, payload: &[u8]) {
+        let Some(next_sequence) = self.next_sequence else {
+            return;
+        };
+
+        let retransmitted =
+            usize::try_from(-sequence_distance(sequence, next_sequence)).unwrap_or(usize::MAX);
+
+        if let Some(new_bytes) = payload.get(retransmitted..) {
+            // Segments are much smaller than 4 GiB
+            let length = u32::try_from(new_bytes.len()).unwrap_or(u32::MAX);
+
+            self.buffer.extend_from_slice(new_bytes);
+            self.next_sequence = Some(next_sequence.wrapping_add(length));
+        }
+    }
+
+    /// It returns a pending segment that starts before the next sequence
+    /// number.
+    fn next_pending(&mut self) -> Option<u32> {
+        let next_sequence = self.next_sequence?;
+
+        let (sequence, _) = self
+            .pending
+            .iter()
+            .find(|(sequence, _)| sequence_distance(**sequence, next_sequence) <= 0)?;
+
+        Some(*sequence)
+    }
+
+    fn ignore(&mut self) {
+        self.protocol = Protocol::Ignored;
+        self.buffer = Vec::new();
+        self.pending = BTreeMap::new();
+        self.pending_length = 0;
+    }
+
+    fn consume(&mut self, length: usize) {
+        self.buffer.drain(..length);
+    }
+}
+
+/// It returns how far a sequence number is after another one, with
+/// wrap-around.
+fn sequence_distance(sequence: u32, other: u32) -> i64 {
+    i64::from(sequence.wrapping_sub(other).cast_signed())
+}
+
+/// The TCP streams of a capture.
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    /// The streams by source and destination.
+    streams: HashMap<(SocketAddr, SocketAddr), Stream>,
+
+    /// The extension IDs in the extension handshakes, by the address of the
+    /// peer that sent the handshake and the address of the receiver.
+    extensions: HashMap<(SocketAddr, SocketAddr), Handshake>,
+}
+
+impl Reassembler {
+    /// It processes a TCP segment and returns the payloads it completes.
+    pub fn process(
+        &mut self,
+        timestamp: Timestamp,
+        src: SocketAddr,
+        dst: SocketAddr,
+        segment: &TcpSegment<'_>,
+    ) -> Vec<Record> {
+        if segment.rst {
+            self.streams.remove(&(src, dst));
+            self.streams.remove(&(dst, src));
+
+            return Vec::new();
+        }
+
+        let stream = self
+            .streams
+            .entry((src, dst))
+            .or_insert_with(|| Stream::new(timestamp));
+
+        stream.timestamp = timestamp;
+
+        if segment.syn {
+            // A new connection, maybe reusing the addresses
+            *stream = Stream::new(timestamp);
+            stream.next_sequence = Some(segment.sequence.wrapping_add(1));
+        } else {
+            stream.push(segment.sequence, segment.payload);
+        }
+
+        let mut records = Vec::new();
+
+        self.read_stream(src, dst, false, &mut records);
+
+        if segment.fin {
+            self.read_stream(src, dst, true, &mut records);
+            self.streams.remove(&(src, dst));
+        }
+
+        records
+    }
+
+    /// It returns the payloads of the streams that are not closed at the end
+    /// of the capture.
+    pub fn finish(&mut self) -> Vec<Record> {
+        let mut records = Vec::new();
+
+        let mut keys: Vec<_> = self.streams.keys().copied().collect();
+        keys.sort_by_key(|key| self.streams[key].timestamp);
+
+        for (src, dst) in keys {
+            self.read_stream(src, dst, true, &mut records);
+        }
+
+        self.streams.clear();
+
+        records
+    }
+
+    /// It reads the complete payloads in a stream. At the end of the stream,
+    /// HTTP bodies without a length end with the stream.
+    fn read_stream(
+        &mut self,
+        src: SocketAddr,
+        dst: SocketAddr,
+        end: bool,
+        records: &mut Vec<Record>,
+    ) {
+        let Some(stream) = self.streams.get_mut(&(src, dst)) else {
+            return;
+        };
+
+        let flow = Flow {
+            transport: Transport::Tcp,
+            src,
+            dst,
+        };
+
+        loop {
+            let payload = match stream.protocol {
+                Protocol::Unknown => {
+                    stream.protocol = classify(&stream.buffer);
+
+                    if stream.protocol == Protocol::Unknown {
+                        return;
+                    }
+
+                    continue;
+                }
+                Protocol::Ignored => {
+                    stream.ignore();
+                    return;
+                }
+                Protocol::Http => match read_http_response(&stream.buffer, end) {
+                    HttpResponse::Incomplete => return,
+                    HttpResponse::Invalid => {
+                        stream.ignore();
+                        return;
+                    }
+                    HttpResponse::Complete { body, length } => {
+                        stream.consume(length);
+
+                        // Keep-alive connections can have more responses
+                        stream.protocol = Protocol::Unknown;
+
+                        body.and_then(|body| decode_tracker_response(&body))
+                    }
+                },
+                Protocol::PeerWire {
+                    handshake_done: false,
+                } => {
+                    if stream.buffer.len() < PEER_WIRE_HANDSHAKE_LENGTH {
+                        return;
+                    }
+
+                    stream.consume(PEER_WIRE_HANDSHAKE_LENGTH);
+                    stream.protocol = Protocol::PeerWire {
+                        handshake_done: true,
+                    };
+
+                    continue;
+                }
+                Protocol::PeerWire {
+                    handshake_done: true,
+                } => {
+                    let Some(length) = read_length_prefix(&stream.buffer) else {
+                        return;
+                    };
+
+                    if length > MAX_BUFFERED_LENGTH {
+                        stream.ignore();
+                        return;
+                    }
+
+                    let Some(message) = stream.buffer.get(4..4 + length) else {
+                        return;
+                    };
+
+                    let payload = match message {
+                        [EXTENDED_MESSAGE_ID, extension_id, payload @ ..] => {
+                            decode_extension_message(
+                                &mut self.extensions,
+                                src,
+                                dst,
+                                *extension_id,
+                                payload,
+                            )
+                        }
+                        _ => None,
+                    };
+
+                    stream.consume(4 + length);
+
+                    payload
+                }
+            };
+
+            if let Some((kind, json)) = payload {
+                records.push(Record {
+                    timestamp: stream.timestamp,
+                    flow,
+                    kind,
+                    json,
+                });
+            }
+        }
+    }
+}
+
+/// It returns the protocol of a stream from its first bytes.
+fn classify(buffer: &[u8]) -> Protocol {
+    for (prefix, protocol) in [
+        (HTTP_RESPONSE, Protocol::Http),
+        (
+            PEER_WIRE_PROTOCOL,
+            Protocol::PeerWire {
+                handshake_done: false,
+            },
+        ),
+    ] {
+        let length = buffer.len().min(prefix.len());
+
+        if buffer[..length] == prefix[..length] {
+            return if length == prefix.len() {
+                protocol
+            } else {
+                Protocol::Unknown
+            };
+        }
+    }
+
+    Protocol::Ignored
+}
+
+fn read_length_prefix(buffer: &[u8]) -> Option<usize> {
+    let length = u32::from_be_bytes(buffer.get(..4)?.try_into().ok()?);
+
+    usize::try_from(length).ok()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum HttpResponse {
+    Incomplete,
+    Invalid,
+
+    /// A response with its length, and the body unless it's compressed.
+    Complete {
+        body: Option<Vec<u8>>,
+        length: usize,
+    },
+}
+
+/// It reads an HTTP response at the beginning of the buffer.
+fn read_http_response(buffer: &[u8], end: bool) -> HttpResponse {
+    let Some(headers_end) = find(buffer, HTTP_HEADERS_END) else {
+        return if buffer.len() > MAX_HTTP_HEADERS_LENGTH {
+            HttpResponse::Invalid
+        } else {
+            HttpResponse::Incomplete
+        };
+    };
+
+    let Ok(headers) = std::str::from_utf8(&buffer[..headers_end]) else {
+        return HttpResponse::Invalid;
+    };
+
+    let mut lines = headers.split("\r\n");
+
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse::<u16>().ok());
+
+    let Some(status) = status else {
+        return HttpResponse::Invalid;
+    };
+
+    let mut content_length = None;
+    let mut chunked = false;
+    let mut compressed = false;
+
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let value = value.trim();
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => match value.parse::<usize>() {
+                Ok(length) => content_length = Some(length),
+                Err(_) => return HttpResponse::Invalid,
+            },
+            "transfer-encoding" => chunked = value.to_ascii_lowercase().ends_with("chunked"),
+            "content-encoding" => compressed = !value.eq_ignore_ascii_case("identity"),
+            _ => {}
+        }
+    }
+
+    let body_start = headers_end + HTTP_HEADERS_END.len();
+    let input = &buffer[body_start..];
+
+    let (body, body_length) = if (100..200).contains(&status) || status == 204 || status == 304 {
+        (Vec::new(), 0)
+    } else if chunked {
+        match read_chunked_body(input) {
+            HttpBody::Incomplete => return HttpResponse::Incomplete,
+            HttpBody::Invalid => return HttpResponse::Invalid,
+            HttpBody::Complete { body, length } => (body, length),
+        }
+    } else if let Some(length) = content_length {
+        match input.get(..length) {
+            Some(body) => (body.to_vec(), length),
+            None if length > MAX_BUFFERED_LENGTH => return HttpResponse::Invalid,
+            None => return HttpResponse::Incomplete,
+        }
+    } else if end {
+        // The body ends with the connection
+        (input.to_vec(), input.len())
+    } else {
+        return HttpResponse::Incomplete;
+    };
+
+    HttpResponse::Complete {
+        body: (!compressed).then_some(body),
+        length: body_start + body_length,
+    }
+}
+
+enum HttpBody {
+    Incomplete,
+    Invalid,
+    Complete { body: Vec<u8>, length: usize },
+}
+
+/// It reads a body with the chunked transfer encoding.
+fn read_chunked_body(input: &[u8]) -> HttpBody {
+    let mut body = Vec::new();
+    let mut position = 0;
+
+    loop {
+        let Some(line_length) = find(&input[position..], b"\r\n") else {
+            return HttpBody::Incomplete;
+        };
+
+        let line = &input[position..position + line_length];
+
+        // Chunk extensions after `;` are ignored
+        let size = line.split(|byte| *byte == b';').next().unwrap_or_default();
+
+        let Some(size) = std::str::from_utf8(size)
+            .ok()
+            .and_then(|size| usize::from_str_radix(size.trim(), 16).ok())
+        else {
+            return HttpBody::Invalid;
+        };
+
+        if size > MAX_BUFFERED_LENGTH {
+            return HttpBody::Invalid;
+        }
+
+        position += line_length + 2;
+
+        if size == 0 {
+            // The trailer fields end with an empty line
+            return match find(&input[position..], b"\r\n") {
+                Some(0) => HttpBody::Complete {
+                    body,
+                    length: position + 2,
+                },
+                Some(_) => match find(&input[position..], HTTP_HEADERS_END) {
+                    Some(trailers_length) => HttpBody::Complete {
+                        body,
+                        length: position + trailers_length + HTTP_HEADERS_END.len(),
+                    },
+                    None => HttpBody::Incomplete,
+                },
+                None => HttpBody::Incomplete,
+            };
+        }
+
+        let Some(chunk) = input.get(position..position + size) else {
+            return HttpBody::Incomplete;
+        };
+
+        body.extend_from_slice(chunk);
+        position += size;
+
+        match input.get(position..position + 2) {
+            Some(b"\r\n") => position += 2,
+            Some(_) => return HttpBody::Invalid,
+            None => return HttpBody::Incomplete,
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// It returns the rendered tracker response in an HTTP body, or `None` if
+/// the body is not a bencoded dictionary.
+fn decode_tracker_response(body: &[u8]) -> Option<(RecordKind, serde_json::Value)> {
+    if body.first() != Some(&b'd') {
+        return None;
+    }
+
+    let response = decode_exact(body)?;
+
+    Some((RecordKind::TrackerResponse, tracker::render(&response)))
+}
+
+/// It decodes the payload of an extended message sent from `src` to `dst`.
+/// The extension handshakes are stored in `extensions`, to find the
+/// extension of the messages sent to the peer.
+fn decode_extension_message(
+    extensions: &mut HashMap<(SocketAddr, SocketAddr), Handshake>,
+    src: SocketAddr,
+    dst: SocketAddr,
+    extension_id: u8,
+    payload: &[u8],
+) -> Option<(RecordKind, serde_json::Value)> {
+    // It also checks the depth before the extension decoders build the
+    // values
+    let (value, end) = decode_prefix(payload)?;
+
+    if extension_id == EXTENSION_HANDSHAKE_ID {
+        if end != payload.len() {
+            return None;
+        }
+
+        if let Ok(handshake) = Handshake::from_value(&value) {
+            extensions.insert((src, dst), handshake);
+        }
+
+        return Some((RecordKind::ExtensionHandshake, render_handshake(&value)));
+    }
+
+    // The IDs of the messages sent to a peer are the ones in its handshake
+    let name = extensions.get(&(dst, src)).and_then(|handshake| {
+        handshake
+            .extensions
+            .iter()
+            .find(|(_, id)| **id == i64::from(extension_id))
+            .map(|(name, _)| name.as_str())
+    });
+
+    match name {
+        Some(UT_METADATA) => {
+            if let Ok(message) = MetadataMessage::from_bytes(payload) {
+                return Some((RecordKind::UtMetadata, message.to_json()));
+            }
+        }
+        Some(UT_PEX) => {
+            if let Ok(pex) = Pex::from_bytes(payload) {
+                return Some((RecordKind::UtPex, pex.to_json()));
+            }
+        }
+        _ => {}
+    }
+
+    Some((
+        RecordKind::Extension,
+        json!({
+            "extension_id": extension_id,
+            "name": name,
+            "value": value.to_json(),
+            "trailing_length": payload.len() - end,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should {
+        use std::net::SocketAddr;
+
+        use serde_json::json;
+
+        use crate::pcap::{
+            packet::TcpSegment,
+            stream::{read_http_response, HttpResponse, Reassembler, Stream},
+            RecordKind, Timestamp,
+        };
+
+        fn server() -> SocketAddr {
+            "10.0.0.1:80".parse().unwrap()
+        }
+
+        fn client() -> SocketAddr {
+            "10.0.0.2:50000".parse().unwrap()
+        }
+
+        fn segment(sequence: u32, payload: &[u8]) -> TcpSegment<'_> {
+            TcpSegment {
+                sequence,
+                payload,
+                ..TcpSegment::default()
+            }
+        }
+
+        fn process(
+            reassembler: &mut Reassembler,
+            segments: &[TcpSegment<'_>],
+        ) -> Vec<serde_json::Value> {
+            segments
+                .iter()
+                .flat_map(|segment| {
+                    reassembler.process(Timestamp::default(), server(), client(), segment)
+                })
+                .map(|record| record.json)
+                .collect()
+        }
+
+        const RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 18\r\n\r\nd8:intervali1800ee";
+
+        #[test]
+        fn reassemble_segments_out_of_order_and_retransmitted() {
+            let mut reassembler = Reassembler::default();
+
+            let records = process(
+                &mut reassembler,
+                &[
+                    TcpSegment {
+                        sequence: u32::MAX,
+                        syn: true,
+                        ..TcpSegment::default()
+                    },
+                    segment(20, &RESPONSE[20..]),
+                    segment(0, &RESPONSE[..10]),
+                    segment(5, &RESPONSE[5..25]),
+                ],
+            );
+
+            assert_eq!(records, vec![json!({"interval": 1800})]);
+        }
+
+        #[test]
+        fn keep_the_length_of_the_pending_segments() {
+            let mut stream = Stream::new(Timestamp::default());
+
+            stream.push(0, b"a");
+            stream.push(5, b"xyz");
+            stream.push(5, b"xyzw");
+            stream.push(3, b"bb");
+
+            assert_eq!(stream.pending_length, 6);
+
+            stream.push(1, b"cc");
+
+            assert_eq!(stream.pending_length, 0);
+            assert_eq!(stream.buffer, b"accbbxyzw");
+        }
+
+        #[test]
+        fn read_several_responses_in_a_keep_alive_connection() {
+            let mut reassembler = Reassembler::default();
+
+            let input = [RESPONSE, RESPONSE].concat();
+
+            assert_eq!(process(&mut reassembler, &[segment(1, &input)]).len(), 2);
+        }
+
+        #[test]
+        fn read_bodies_that_end_with_the_connection() {
+            let mut reassembler = Reassembler::default();
+
+            let records = process(
+                &mut reassembler,
+                &[
+                    segment(1, b"HTTP/1.0 200 OK\r\n\r\nd8:intervali60ee"),
+                    TcpSegment {
+                        sequence: 35,
+                        fin: true,
+                        ..TcpSegment::default()
+                    },
+                ],
+            );
+
+            assert_eq!(records, vec![json!({"interval": 60})]);
+        }
+
+        #[test]
+        fn read_chunked_bodies() {
+            let input = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nd8:in\r\nb;ext=1\r\ntervali60ee\r\n0\r\n\r\nHTTP";
+
+            assert_eq!(
+                read_http_response(input, false),
+                HttpResponse::Complete {
+                    body: Some(b"d8:intervali60ee".to_vec()),
+                    length: input.len() - 4
+                }
+            );
+        }
+
+        #[test]
+        fn skip_compressed_bodies() {
+            let input = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 2\r\n\r\nde";
+
+            assert_eq!(
+                read_http_response(input, false),
+                HttpResponse::Complete {
+                    body: None,
+                    length: input.len()
+                }
+            );
+        }
+
+        #[test]
+        fn wait_for_incomplete_bodies() {
+            assert_eq!(
+                read_http_response(&RESPONSE[..RESPONSE.len() - 1], false),
+                HttpResponse::Incomplete
+            );
+        }
+
+        #[test]
+        fn decode_extension_messages_with_the_ids_of_the_receiver_handshake() {
+            let mut reassembler = Reassembler::default();
+
+            let mut handshake = b"\x13BitTorrent protocol".to_vec();
+            handshake.extend_from_slice(&[0; 48]);
+
+            let extended = |id: u8, payload: &[u8]| {
+                let mut message = u32::try_from(payload.len() + 2)
+                    .unwrap()
+                    .to_be_bytes()
+                    .to_vec();
+                message.extend_from_slice(&[20, id]);
+                message.extend_from_slice(payload);
+                message
+            };
+
+            // The client sends its handshake with the ID of ut_metadata
+            let client_messages =
+                [handshake.clone(), extended(0, b"d1:md11:ut_metadatai3eee")].concat();
+
+            let records = reassembler.process(
+                Timestamp::default(),
+                client(),
+                server(),
+                &segment(1, &client_messages),
+            );
+
+            assert_eq!(records[0].kind, RecordKind::ExtensionHandshake);
+
+            // The server uses it to send a piece, after a keep-alive and a
+            // message of another type
+            let server_messages = [
+                handshake,
+                vec![0, 0, 0, 0],
+                vec![0, 0, 0, 1, 2],
+                extended(3, b"d8:msg_typei1e5:piecei0e10:total_sizei4eeDATA"),
+                extended(9, b"i1eXY"),
+            ]
+            .concat();
+
+            let records: Vec<_> = reassembler
+                .process(
+                    Timestamp::default(),
+                    server(),
+                    client(),
+                    &segment(1, &server_messages),
+                )
+                .into_iter()
+                .map(|record| (record.kind, record.json))
+                .collect();
+
+            assert_eq!(
+                records,
+                vec![
+                    (
+                        RecordKind::UtMetadata,
+                        json!({"msg_type": "data", "piece": 0, "total_size": 4, "data_length": 4})
+                    ),
+                    (
+                        RecordKind::Extension,
+                        json!({"extension_id": 9, "name": null, "value": 1, "trailing_length": 2})
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn ignore_other_protocols() {
+            let mut reassembler = Reassembler::default();
+
+            assert!(process(&mut reassembler, &[segment(1, b"GET / HTTP/1.1\r\n\r\n")]).is_empty());
+        }
+    }
+}
@@ -1,19 +1,26 @@
 //! A reader that reads bytes from an input.
 //!
-//! The input is any type that implements the `std::io::Read` trait.
+//! The input is any type that implements the `std::io::BufRead` trait. Inputs
+//! that only implement `std::io::Read` are wrapped in a `BufReader`. Slices
+//! (`&[u8]`) and already buffered inputs are read directly, without copying
+//! them into a second buffer.
+use std::io::BufRead;
 use std::io::BufReader;
 use std::io::Error;
+use std::io::ErrorKind;
 use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
 
-use ringbuffer::AllocRingBuffer;
-use ringbuffer::RingBuffer;
+use super::capture::Capture;
+use crate::parsers::options::{DEFAULT_BUFFER_SIZE, DEFAULT_CAPTURE_WINDOW_SIZE};
 
 /// A reader that reads bytes from an input.
 ///
 /// It's wrapper of a basic reader with extra functionality.
-pub struct ByteReader<R: Read> {
+pub struct ByteReader<R: BufRead> {
     /// It's a buffered reader.
-    reader: BufReader<R>,
+    reader: R,
 
     /// Number of bytes read from the input.
     input_byte_counter: u64,
@@ -25,17 +32,68 @@ pub struct ByteReader<R: Read> {
     last_byte: Option<u8>,
 
     /// A buffer to capture the latest bytes read from the input.
-    captured_bytes: AllocRingBuffer<u8>,
+    captured_bytes: Capture<u8>,
+
+    /// Position of the input when the reader was created. Only known for
+    /// seekable inputs.
+    start_position: u64,
+
+    /// A function to re-read bytes from the input, between two absolute
+    /// positions. Only available for seekable inputs.
+    reread: Option<Reread<R>>,
+}
+
+/// The state of a [`ByteReader`] at some point, to go back to it with
+/// [`ByteReader::restore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    input_byte_counter: u64,
+    peeked_byte: Option<u8>,
+    last_byte: Option<u8>,
 }
 
-impl<R: Read> ByteReader<R> {
+/// A function that re-reads the bytes between two absolute positions of a
+/// seekable input, leaving the input where it was.
+type Reread<R> = fn(&mut R, u64, u64) -> Result<Vec<u8>, Error>;
+
+impl<R: Read> ByteReader<BufReader<R>> {
     pub fn new(reader: R) -> Self {
+        Self::with_capacity(DEFAULT_BUFFER_SIZE, DEFAULT_CAPTURE_WINDOW_SIZE, reader)
+    }
+
+    /// It creates a new reader with the given capacities for the input buffer
+    /// and for the buffer that captures the latest bytes read.
+    ///
+    /// A capture capacity of 0 disables the capture.
+    pub fn with_capacity(buffer_capacity: usize, capture_capacity: usize, reader: R) -> Self {
+        ByteReader::from_buf_read_with_capacity(
+            capture_capacity,
+            BufReader::with_capacity(buffer_capacity, reader),
+        )
+    }
+}
+
+impl<R: BufRead> ByteReader<R> {
+    /// It creates a new reader that reads directly from the buffer of the
+    /// input.
+    pub fn from_buf_read(reader: R) -> Self {
+        Self::from_buf_read_with_capacity(DEFAULT_CAPTURE_WINDOW_SIZE, reader)
+    }
+
+    /// It creates a new reader that reads directly from the buffer of the
+    /// input, with the given capacity for the buffer that captures the latest
+    /// bytes read.
+    ///
+    /// A capture capacity of 0 disables the capture.
+    pub fn from_buf_read_with_capacity(capture_capacity: usize, reader: R) -> Self {
         Self {
-            reader: BufReader::new(reader),
+            reader,
             input_byte_counter: 0,
             peeked_byte: None,
             last_byte: None,
-            captured_bytes: AllocRingBuffer::new(1024),
+            captured_bytes: Capture::with_capacity(capture_capacity),
+            start_position: 0,
+            reread: None,
         }
     }
 
@@ -49,20 +107,153 @@ impl<R: Read> ByteReader<R> {
             return Ok(byte);
         }
 
-        let mut byte = [0; 1];
+        let byte = match Self::fill_buf(&mut self.reader)?.first() {
+            Some(byte) => *byte,
+            None => return Err(ErrorKind::UnexpectedEof.into()),
+        };
 
-        self.reader.read_exact(&mut byte)?;
+        self.reader.consume(1);
 
         self.input_byte_counter += 1;
 
-        let byte = byte[0];
-
         self.last_byte = Some(byte);
         self.captured_bytes.push(byte);
 
         Ok(byte)
     }
 
+    /// It reads exactly `length` bytes from the input and appends them to
+    /// `bytes`.
+    ///
+    /// The bytes are copied in chunks directly from the input buffer. If the
+    /// input ends before, the bytes read so far are appended and consumed.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't read all the bytes from the input.
+    pub fn read_bytes(&mut self, length: usize, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        let mut remaining = length;
+
+        if remaining > 0 {
+            if let Some(byte) = self.peeked_byte.take() {
+                bytes.push(byte);
+                remaining -= 1;
+            }
+        }
+
+        while remaining > 0 {
+            let available = Self::fill_buf(&mut self.reader)?;
+
+            if available.is_empty() {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+
+            let chunk = &available[..available.len().min(remaining)];
+
+            bytes.extend_from_slice(chunk);
+            self.captured_bytes.extend_from_slice(chunk);
+            self.last_byte = chunk.last().copied();
+
+            let chunk_length = chunk.len();
+
+            self.reader.consume(chunk_length);
+            self.input_byte_counter += chunk_length as u64;
+            remaining -= chunk_length;
+        }
+
+        Ok(())
+    }
+
+    /// It returns the bytes available in the input buffer without consuming
+    /// them, filling the buffer if it's empty. The peeked byte is not
+    /// included. It's empty when the input has ended.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't read from the input.
+    pub fn buffered_bytes(&mut self) -> Result<&[u8], Error> {
+        Self::fill_buf(&mut self.reader)
+    }
+
+    /// It consumes the first `amount` bytes returned by
+    /// [`ByteReader::buffered_bytes`], as if they had been read one by one.
+    /// It returns the number of bytes consumed, which is lower than `amount`
+    /// when there are fewer bytes in the input buffer.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't read from the input.
+    pub fn consume_buffered_bytes(&mut self, amount: usize) -> Result<usize, Error> {
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        let available = Self::fill_buf(&mut self.reader)?;
+
+        let chunk = &available[..amount.min(available.len())];
+        let consumed = chunk.len();
+
+        if consumed == 0 {
+            return Ok(0);
+        }
+
+        self.captured_bytes.extend_from_slice(chunk);
+        self.last_byte = chunk.last().copied();
+
+        self.reader.consume(consumed);
+        self.input_byte_counter += consumed as u64;
+
+        Ok(consumed)
+    }
+
+    /// It returns the current state of the reader.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            input_byte_counter: self.input_byte_counter,
+            peeked_byte: self.peeked_byte,
+            last_byte: self.last_byte,
+        }
+    }
+
+    /// It goes back to a previous state of the reader.
+    ///
+    /// The caller must move the underlying input back to where it was at the
+    /// checkpoint, and pass the bytes read before it (at least the capture
+    /// capacity, if there are so many) to capture them again.
+    pub fn restore(&mut self, checkpoint: Checkpoint, latest_bytes: &[u8]) {
+        self.input_byte_counter = checkpoint.input_byte_counter;
+        self.peeked_byte = checkpoint.peeked_byte;
+        self.last_byte = checkpoint.last_byte;
+
+        self.captured_bytes.clear();
+        self.captured_bytes.extend_from_slice(latest_bytes);
+    }
+
+    /// It returns a reference to the underlying input.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// It returns a mutable reference to the underlying input.
+    ///
+    /// Reading from it directly would leave the counter and the captured
+    /// bytes out of sync.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// It returns the underlying input.
+    ///
+    /// The peeked byte, if any, is not put back into the input.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns the byte that has been peeked and not read yet, if any.
+    pub fn peeked_byte(&self) -> Option<u8> {
+        self.peeked_byte
+    }
+
     /// Peeks at the next byte in the input without consuming it.
     ///
     /// # Errors
@@ -80,6 +271,12 @@ impl<R: Read> ByteReader<R> {
         Ok(byte)
     }
 
+    /// It puts back the last byte read, so that the next read or peek returns
+    /// it again. Only one byte can be put back.
+    pub fn unread_byte(&mut self, byte: u8) {
+        self.peeked_byte = Some(byte);
+    }
+
     /// Returns the number of bytes that have been read from the input.
     pub fn input_byte_counter(&self) -> u64 {
         self.input_byte_counter
@@ -94,6 +291,63 @@ impl<R: Read> ByteReader<R> {
     pub fn last_byte(&self) -> Option<u8> {
         self.last_byte
     }
+
+    /// It re-reads from the input up to `window` bytes before the position
+    /// `pos` (the number of bytes read when they were needed).
+    ///
+    /// It's an alternative to capturing the bytes while reading. It returns
+    /// `None` if the input is not seekable or the bytes can't be re-read.
+    pub fn reread_bytes_before(&mut self, pos: u64, window: usize) -> Option<Vec<u8>> {
+        let reread = self.reread?;
+
+        let end = self.start_position + pos;
+        let start = end.saturating_sub(window as u64).max(self.start_position);
+
+        reread(&mut self.reader, start, end).ok()
+    }
+
+    /// It returns the bytes available in the input buffer, filling it if it's
+    /// empty. It retries when the read is interrupted.
+    fn fill_buf(reader: &mut R) -> Result<&[u8], Error> {
+        loop {
+            match reader.fill_buf() {
+                Ok(_) => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        reader.fill_buf()
+    }
+}
+
+impl<R: BufRead + Seek> ByteReader<R> {
+    /// It allows re-reading the latest bytes from the input on demand with
+    /// [`ByteReader::reread_bytes_before`], instead of capturing them while
+    /// reading.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't get the current input position.
+    pub fn enable_rereading(&mut self) -> Result<(), Error> {
+        self.start_position = self.reader.stream_position()? - self.input_byte_counter;
+        self.reread = Some(Self::reread);
+        Ok(())
+    }
+
+    fn reread(reader: &mut R, start: u64, end: u64) -> Result<Vec<u8>, Error> {
+        let current = reader.stream_position()?;
+
+        reader.seek(SeekFrom::Start(start))?;
+
+        let length = usize::try_from(end - start).map_err(Error::other)?;
+        let mut bytes = vec![0; length];
+        let result = reader.read_exact(&mut bytes);
+
+        reader.seek(SeekFrom::Start(current))?;
+
+        result.map(|()| bytes)
+    }
 }
 
 #[cfg(test)]
@@ -144,6 +398,222 @@ mod tests {
 
             assert_eq!(byte_reader.last_byte(), Some(b'e'));
         }
+
+        #[test]
+        fn it_should_allow_putting_back_the_last_byte_read() {
+            let input = vec![b'l', b'e'];
+
+            let mut byte_reader = ByteReader::new(input.as_slice());
+
+            let byte = byte_reader.read_byte().unwrap();
+            byte_reader.unread_byte(byte);
+
+            assert_eq!(byte_reader.read_byte().unwrap(), b'l');
+            assert_eq!(byte_reader.read_byte().unwrap(), b'e');
+            assert_eq!(byte_reader.input_byte_counter(), 2);
+        }
+    }
+
+    mod for_reading_directly_from_a_buffered_input {
+        use crate::rw::byte_reader::ByteReader;
+
+        #[test]
+        fn it_should_read_from_a_slice() {
+            let mut byte_reader = ByteReader::from_buf_read(&b"le"[..]);
+
+            assert_eq!(byte_reader.read_byte().unwrap(), b'l');
+            assert_eq!(byte_reader.read_byte().unwrap(), b'e');
+            assert!(byte_reader.read_byte().is_err());
+        }
+
+        #[test]
+        fn it_should_read_from_any_type_implementing_buf_read() {
+            let input = std::io::Cursor::new(b"le".to_vec());
+
+            let mut byte_reader = ByteReader::from_buf_read(input);
+
+            assert_eq!(byte_reader.read_byte().unwrap(), b'l');
+            assert_eq!(byte_reader.read_byte().unwrap(), b'e');
+        }
+    }
+
+    mod for_reading_many_bytes {
+        use std::io::{BufReader, ErrorKind};
+
+        use crate::rw::byte_reader::ByteReader;
+
+        #[test]
+        fn it_should_read_the_given_number_of_bytes() {
+            let mut byte_reader = ByteReader::new(&b"spam"[..]);
+
+            let mut bytes = vec![];
+            byte_reader.read_bytes(3, &mut bytes).unwrap();
+
+            assert_eq!(bytes, b"spa");
+            assert_eq!(byte_reader.read_byte().unwrap(), b'm');
+        }
+
+        #[test]
+        fn it_should_read_across_the_input_buffer_boundaries() {
+            let input = vec![b'a'; 100];
+
+            let mut byte_reader =
+                ByteReader::from_buf_read(BufReader::with_capacity(7, input.as_slice()));
+
+            let mut bytes = vec![];
+            byte_reader.read_bytes(100, &mut bytes).unwrap();
+
+            assert_eq!(bytes, input);
+        }
+
+        #[test]
+        fn it_should_use_the_peeked_byte_first() {
+            let mut byte_reader = ByteReader::new(&b"spam"[..]);
+
+            byte_reader.peek_byte().unwrap();
+
+            let mut bytes = vec![];
+            byte_reader.read_bytes(4, &mut bytes).unwrap();
+
+            assert_eq!(bytes, b"spam");
+            assert_eq!(byte_reader.input_byte_counter(), 4);
+        }
+
+        #[test]
+        fn it_should_update_the_counter_the_last_byte_and_the_captured_bytes() {
+            let mut byte_reader = ByteReader::with_capacity(1024, 2, &b"spam"[..]);
+
+            let mut bytes = vec![];
+            byte_reader.read_bytes(4, &mut bytes).unwrap();
+
+            assert_eq!(byte_reader.input_byte_counter(), 4);
+            assert_eq!(byte_reader.last_byte(), Some(b'm'));
+            assert_eq!(byte_reader.captured_bytes(), b"am");
+        }
+
+        #[test]
+        fn it_should_fail_when_the_input_ends_before_reading_all_the_bytes() {
+            let mut byte_reader = ByteReader::new(&b"spa"[..]);
+
+            let mut bytes = vec![];
+            let err = byte_reader.read_bytes(4, &mut bytes).unwrap_err();
+
+            assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+            assert_eq!(bytes, b"spa");
+            assert_eq!(byte_reader.input_byte_counter(), 3);
+        }
+    }
+
+    mod for_accessing_the_input_buffer {
+        use std::io::BufReader;
+
+        use crate::rw::byte_reader::ByteReader;
+
+        #[test]
+        fn it_should_return_the_buffered_bytes_without_consuming_them() {
+            let mut byte_reader = ByteReader::new(&b"spam"[..]);
+
+            assert_eq!(byte_reader.buffered_bytes().unwrap(), b"spam");
+            assert_eq!(byte_reader.input_byte_counter(), 0);
+            assert_eq!(byte_reader.read_byte().unwrap(), b's');
+        }
+
+        #[test]
+        fn it_should_not_include_the_peeked_byte() {
+            let mut byte_reader = ByteReader::new(&b"spam"[..]);
+
+            byte_reader.peek_byte().unwrap();
+
+            assert_eq!(byte_reader.peeked_byte(), Some(b's'));
+            assert_eq!(byte_reader.buffered_bytes().unwrap(), b"pam");
+        }
+
+        #[test]
+        fn it_should_return_only_the_bytes_in_the_input_buffer() {
+            let mut byte_reader =
+                ByteReader::from_buf_read(BufReader::with_capacity(2, &b"spam"[..]));
+
+            assert_eq!(byte_reader.buffered_bytes().unwrap(), b"sp");
+        }
+
+        #[test]
+        fn it_should_return_no_bytes_when_the_input_has_ended() {
+            let mut byte_reader = ByteReader::new(&b""[..]);
+
+            assert!(byte_reader.buffered_bytes().unwrap().is_empty());
+        }
+
+        #[test]
+        fn it_should_consume_buffered_bytes_as_if_they_were_read_one_by_one() {
+            let mut byte_reader = ByteReader::new(&b"spam"[..]);
+
+            byte_reader.buffered_bytes().unwrap();
+
+            assert_eq!(byte_reader.consume_buffered_bytes(3).unwrap(), 3);
+
+            assert_eq!(byte_reader.input_byte_counter(), 3);
+            assert_eq!(byte_reader.last_byte(), Some(b'a'));
+            assert_eq!(byte_reader.captured_bytes(), b"spa");
+            assert_eq!(byte_reader.read_byte().unwrap(), b'm');
+        }
+
+        #[test]
+        fn it_should_consume_only_the_buffered_bytes_when_asked_for_more() {
+            let mut byte_reader = ByteReader::new(&b"spam"[..]);
+
+            assert_eq!(byte_reader.consume_buffered_bytes(10).unwrap(), 4);
+            assert_eq!(byte_reader.input_byte_counter(), 4);
+            assert_eq!(byte_reader.consume_buffered_bytes(1).unwrap(), 0);
+            assert_eq!(byte_reader.last_byte(), Some(b'm'));
+        }
+    }
+
+    mod for_rereading {
+        use std::io::{BufReader, Cursor};
+
+        use crate::rw::byte_reader::ByteReader;
+
+        #[test]
+        fn it_should_reread_the_latest_bytes_from_a_seekable_input() {
+            let mut byte_reader = ByteReader::with_capacity(1024, 0, Cursor::new(b"spam".to_vec()));
+            byte_reader.enable_rereading().unwrap();
+
+            let mut bytes = vec![];
+            byte_reader.read_bytes(3, &mut bytes).unwrap();
+
+            assert!(byte_reader.captured_bytes().is_empty());
+            assert_eq!(byte_reader.reread_bytes_before(3, 2), Some(b"pa".to_vec()));
+            assert_eq!(
+                byte_reader.reread_bytes_before(3, 10),
+                Some(b"spa".to_vec())
+            );
+
+            // The reader goes on where it was
+            assert_eq!(byte_reader.read_byte().unwrap(), b'm');
+        }
+
+        #[test]
+        fn it_should_take_into_account_where_the_input_was_when_the_reader_was_created() {
+            let mut input = Cursor::new(b"xxspam".to_vec());
+            input.set_position(2);
+
+            let mut byte_reader = ByteReader::from_buf_read_with_capacity(0, BufReader::new(input));
+            byte_reader.enable_rereading().unwrap();
+
+            byte_reader.read_byte().unwrap();
+            byte_reader.read_byte().unwrap();
+
+            assert_eq!(byte_reader.reread_bytes_before(2, 10), Some(b"sp".to_vec()));
+        }
+
+        #[test]
+        fn it_should_not_reread_if_it_was_not_enabled() {
+            let mut byte_reader = ByteReader::new(&b"spam"[..]);
+
+            byte_reader.read_byte().unwrap();
+
+            assert_eq!(byte_reader.reread_bytes_before(1, 10), None);
+        }
     }
 
     mod for_peeking {
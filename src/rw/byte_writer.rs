@@ -5,10 +5,8 @@ use core::str;
 use std::io::BufWriter;
 use std::io::Write;
 
-use ringbuffer::AllocRingBuffer;
-use ringbuffer::RingBuffer;
-
-use super::{error::Error, writer::Writer};
+use super::{capture::Capture, error::Error, writer::Writer};
+use crate::parsers::options::{DEFAULT_BUFFER_SIZE, DEFAULT_CAPTURE_WINDOW_SIZE};
 
 /// A writer that writes to an output implementing `std::io::Write`.
 ///
@@ -24,16 +22,24 @@ pub struct ByteWriter<W: Write> {
     last_byte: Option<u8>,
 
     /// A buffer to capture the latest bytes written to the output.
-    captured_bytes: AllocRingBuffer<u8>,
+    captured_bytes: Capture<u8>,
 }
 
 impl<W: Write> ByteWriter<W> {
     pub fn new(writer: W) -> Self {
+        Self::with_capacity(DEFAULT_BUFFER_SIZE, DEFAULT_CAPTURE_WINDOW_SIZE, writer)
+    }
+
+    /// It creates a new writer with the given capacities for the output
+    /// buffer and for the buffer that captures the latest bytes written.
+    ///
+    /// A capture capacity of 0 disables the capture.
+    pub fn with_capacity(buffer_capacity: usize, capture_capacity: usize, writer: W) -> Self {
         Self {
             output_byte_counter: 0,
-            writer: BufWriter::new(writer),
+            writer: BufWriter::with_capacity(buffer_capacity, writer),
             last_byte: None,
-            captured_bytes: AllocRingBuffer::new(1024),
+            captured_bytes: Capture::with_capacity(capture_capacity),
         }
     }
 
@@ -51,6 +57,26 @@ impl<W: Write> ByteWriter<W> {
     pub fn last_byte(&self) -> Option<u8> {
         self.last_byte
     }
+
+    /// It writes the buffered bytes to the output, flushes it and returns it.
+    ///
+    /// Dropping the writer also writes the buffered bytes, but errors are
+    /// ignored then.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't write the buffered bytes or flush the
+    /// output.
+    pub fn finish(self) -> Result<W, Error> {
+        let mut writer = self
+            .writer
+            .into_inner()
+            .map_err(std::io::IntoInnerError::into_error)?;
+
+        writer.flush()?;
+
+        Ok(writer)
+    }
 }
 
 impl<W: Write> Writer for ByteWriter<W> {
@@ -68,14 +94,26 @@ impl<W: Write> Writer for ByteWriter<W> {
         Ok(())
     }
 
-    fn write_str(&mut self, value: &str) -> Result<(), Error> {
-        for byte in value.bytes() {
-            self.write_byte(byte)?;
-        }
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let Some(last_byte) = bytes.last() else {
+            return Ok(());
+        };
+
+        self.writer.write_all(bytes)?;
+
+        self.output_byte_counter += bytes.len() as u64;
+
+        self.last_byte = Some(*last_byte);
+
+        self.captured_bytes.extend_from_slice(bytes);
 
         Ok(())
     }
 
+    fn write_str(&mut self, value: &str) -> Result<(), Error> {
+        self.write_bytes(value.as_bytes())
+    }
+
     fn output_byte_counter(&self) -> u64 {
         self.output_byte_counter
     }
@@ -88,6 +126,19 @@ impl<W: Write> Writer for ByteWriter<W> {
 #[cfg(test)]
 mod tests {
 
+    mod for_finishing {
+        use crate::rw::{byte_writer::ByteWriter, writer::Writer};
+
+        #[test]
+        fn it_should_write_the_buffered_bytes_and_return_the_output() {
+            let mut byte_writer = ByteWriter::new(Vec::new());
+
+            byte_writer.write_bytes(b"[1]").unwrap();
+
+            assert_eq!(byte_writer.finish().unwrap(), b"[1]");
+        }
+    }
+
     mod for_writing {
         use crate::rw::{byte_writer::ByteWriter, writer::Writer};
 
@@ -140,6 +191,49 @@ mod tests {
         }
     }
 
+    mod for_writing_many_bytes {
+        use crate::rw::{byte_writer::ByteWriter, writer::Writer};
+
+        #[test]
+        fn it_should_write_all_the_bytes_to_the_output() {
+            let mut output = Vec::new();
+
+            let mut byte_writer = ByteWriter::new(&mut output);
+
+            byte_writer.write_bytes(b"spam").unwrap();
+
+            drop(byte_writer);
+
+            assert_eq!(output, b"spam");
+        }
+
+        #[test]
+        fn it_should_update_the_counter_the_last_byte_and_the_captured_bytes() {
+            let mut output = Vec::new();
+
+            let mut byte_writer = ByteWriter::with_capacity(1024, 2, &mut output);
+
+            byte_writer.write_bytes(b"spam").unwrap();
+
+            assert_eq!(byte_writer.output_byte_counter(), 4);
+            assert_eq!(byte_writer.last_byte(), Some(b'm'));
+            assert_eq!(byte_writer.captured_bytes(), b"am");
+        }
+
+        #[test]
+        fn it_should_not_change_anything_when_there_are_no_bytes() {
+            let mut output = Vec::new();
+
+            let mut byte_writer = ByteWriter::new(&mut output);
+
+            byte_writer.write_byte(b'l').unwrap();
+            byte_writer.write_bytes(b"").unwrap();
+
+            assert_eq!(byte_writer.output_byte_counter(), 1);
+            assert_eq!(byte_writer.last_byte(), Some(b'l'));
+        }
+    }
+
     mod for_capturing {
 
         use crate::rw::{byte_writer::ByteWriter, writer::Writer};
@@ -0,0 +1,120 @@
+//! Capture of the latest bytes read or written, to give context in errors.
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+
+/// The latest items (bytes or chars) read or written.
+///
+/// Capturing can be disabled to avoid its cost on the happy path.
+pub(super) enum Capture<T> {
+    /// Nothing is captured.
+    Off,
+
+    /// The latest items are kept in a ring buffer.
+    RingBuffer(AllocRingBuffer<T>),
+}
+
+impl<T> Capture<T> {
+    /// It creates a capture that keeps the latest `capacity` items. A
+    /// capacity of 0 disables the capture.
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        if capacity == 0 {
+            Self::Off
+        } else {
+            Self::RingBuffer(AllocRingBuffer::new(capacity))
+        }
+    }
+
+    /// It removes all the captured items.
+    pub(super) fn clear(&mut self) {
+        if let Self::RingBuffer(captured) = self {
+            captured.clear();
+        }
+    }
+
+    pub(super) fn push(&mut self, item: T) {
+        if let Self::RingBuffer(captured) = self {
+            captured.push(item);
+        }
+    }
+}
+
+impl<T: Clone> Capture<T> {
+    /// It returns a copy of the captured items. It's empty when the capture
+    /// is disabled.
+    pub(super) fn to_vec(&self) -> Vec<T> {
+        match self {
+            Self::Off => Vec::new(),
+            Self::RingBuffer(captured) => captured.to_vec(),
+        }
+    }
+}
+
+impl Capture<u8> {
+    /// It adds a chunk of bytes in one go. Only the latest bytes that fit in
+    /// the buffer are copied.
+    pub(super) fn extend_from_slice(&mut self, bytes: &[u8]) {
+        if let Self::RingBuffer(captured) = self {
+            let skip = bytes.len().saturating_sub(captured.capacity());
+            captured.extend(bytes[skip..].iter().copied());
+        }
+    }
+}
+
+impl Capture<char> {
+    /// It adds the chars of a string in one go. Only the latest chars that
+    /// fit in the buffer are copied.
+    pub(super) fn extend_from_str(&mut self, value: &str) {
+        if let Self::RingBuffer(captured) = self {
+            let start = value
+                .char_indices()
+                .rev()
+                .nth(captured.capacity() - 1)
+                .map_or(0, |(index, _)| index);
+
+            captured.extend(value[start..].chars());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rw::capture::Capture;
+
+    #[test]
+    fn it_should_capture_all_the_bytes_when_they_fit() {
+        let mut captured = Capture::with_capacity(4);
+
+        captured.extend_from_slice(b"ab");
+        captured.extend_from_slice(b"cd");
+
+        assert_eq!(captured.to_vec(), b"abcd");
+    }
+
+    #[test]
+    fn it_should_capture_only_the_latest_bytes_when_they_do_not_fit() {
+        let mut captured = Capture::with_capacity(4);
+
+        captured.extend_from_slice(b"ab");
+        captured.extend_from_slice(b"cdefgh");
+
+        assert_eq!(captured.to_vec(), b"efgh");
+    }
+
+    #[test]
+    fn it_should_capture_only_the_latest_chars_when_they_do_not_fit() {
+        let mut captured = Capture::with_capacity(3);
+
+        captured.extend_from_str("añbçd");
+
+        assert_eq!(captured.to_vec(), vec!['b', 'ç', 'd']);
+    }
+
+    #[test]
+    fn it_should_not_capture_anything_when_the_capacity_is_zero() {
+        let mut captured = Capture::with_capacity(0);
+
+        captured.push(b'a');
+        captured.extend_from_slice(b"bc");
+
+        assert!(captured.to_vec().is_empty());
+    }
+}
@@ -1,6 +1,7 @@
 //! Read and write operations.
 pub mod byte_reader;
 pub mod byte_writer;
+mod capture;
 pub mod error;
 pub mod string_writer;
 pub mod writer;
@@ -2,9 +2,8 @@
 use core::str;
 use std::fmt::Write;
 
-use ringbuffer::{AllocRingBuffer, RingBuffer};
-
-use super::{error::Error, writer::Writer};
+use super::{capture::Capture, error::Error, writer::Writer};
+use crate::parsers::options::DEFAULT_CAPTURE_WINDOW_SIZE;
 
 /// A writer that writes to an output implementing `std::fmt::Write`.
 ///
@@ -20,17 +19,25 @@ pub struct StringWriter<W: Write> {
     last_char: Option<char>,
 
     /// A buffer to capture the latest bytes written to the output.
-    captured_chars: AllocRingBuffer<char>,
+    captured_chars: Capture<char>,
 }
 
 impl<W: Write> StringWriter<W> {
     pub fn new(writer: W) -> Self {
+        Self::with_capacity(DEFAULT_CAPTURE_WINDOW_SIZE, writer)
+    }
+
+    /// It creates a new writer with the given capacity for the buffer that
+    /// captures the latest chars written.
+    ///
+    /// A capture capacity of 0 disables the capture.
+    pub fn with_capacity(capture_capacity: usize, writer: W) -> Self {
         Self {
             writer,
             output_byte_counter: 0,
 
             last_char: None,
-            captured_chars: AllocRingBuffer::new(1024),
+            captured_chars: Capture::with_capacity(capture_capacity),
         }
     }
 
@@ -70,12 +77,24 @@ impl<W: Write> Writer for StringWriter<W> {
 
         self.output_byte_counter += value.len() as u64;
 
-        if let Some(last_char) = value.chars().last() {
+        if let Some(last_char) = value.chars().next_back() {
             self.last_char = Some(last_char);
         }
 
-        for c in value.chars() {
-            self.captured_chars.push(c);
+        self.captured_chars.extend_from_str(value);
+
+        Ok(())
+    }
+
+    /// It writes the bytes as a string when they are valid UTF-8. Otherwise,
+    /// like `write_byte`, each byte is written as one char.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        if let Ok(value) = str::from_utf8(bytes) {
+            return self.write_str(value);
+        }
+
+        for byte in bytes {
+            self.write_byte(*byte)?;
         }
 
         Ok(())
@@ -151,6 +170,34 @@ mod tests {
         }
     }
 
+    mod for_writing_many_bytes {
+        use crate::rw::{string_writer::StringWriter, writer::Writer};
+
+        #[test]
+        fn it_should_write_utf8_bytes_as_a_string() {
+            let mut output = String::new();
+
+            let mut string_writer = StringWriter::new(&mut output);
+
+            string_writer.write_bytes("añb".as_bytes()).unwrap();
+
+            assert_eq!(string_writer.output_byte_counter(), 4);
+            assert_eq!(string_writer.last_byte(), Some('b'));
+            assert_eq!(output, "añb");
+        }
+
+        #[test]
+        fn it_should_write_each_byte_as_a_char_when_the_bytes_are_not_utf8() {
+            let mut output = String::new();
+
+            let mut string_writer = StringWriter::new(&mut output);
+
+            string_writer.write_bytes(&[b'a', 0xff]).unwrap();
+
+            assert_eq!(output, "a\u{ff}");
+        }
+    }
+
     mod for_capturing {
 
         use crate::rw::{string_writer::StringWriter, writer::Writer};
@@ -15,7 +15,55 @@
 */
 
 use super::error::Error;
+use crate::parsers::token::Token;
 
+/// An output for the JSON generated by the parser.
+///
+/// The crate implements it for `std::io::Write` outputs
+/// ([`ByteWriter`](super::byte_writer::ByteWriter)) and `std::fmt::Write`
+/// outputs ([`StringWriter`](super::string_writer::StringWriter)). Custom
+/// writers can be passed to [`BencodeParser::write_to`](crate::parsers::BencodeParser::write_to):
+///
+/// ```rust
+/// use torrust_bencode2json::{
+///     parsers::BencodeParser,
+///     rw::{error::Error, writer::Writer},
+/// };
+///
+/// /// It only counts the bytes.
+/// #[derive(Default)]
+/// struct CountingWriter {
+///     count: u64,
+/// }
+///
+/// impl Writer for CountingWriter {
+///     fn write_byte(&mut self, _byte: u8) -> Result<(), Error> {
+///         self.count += 1;
+///         Ok(())
+///     }
+///
+///     fn write_str(&mut self, value: &str) -> Result<(), Error> {
+///         self.count += value.len() as u64;
+///         Ok(())
+///     }
+///
+///     fn output_byte_counter(&self) -> u64 {
+///         self.count
+///     }
+///
+///     fn captured_bytes(&self) -> Vec<u8> {
+///         Vec::new()
+///     }
+/// }
+///
+/// let mut writer = CountingWriter::default();
+///
+/// BencodeParser::new(&b"d4:spaml1:a1:bee"[..])
+///     .write_to(&mut writer)
+///     .unwrap();
+///
+/// assert_eq!(writer.count, br#"{"spam":["a","b"]}"#.len() as u64);
+/// ```
 pub trait Writer {
     /// It writes one byte to the output.
     ///
@@ -24,6 +72,22 @@ pub trait Writer {
     /// Will return an error if it can't write the byte.
     fn write_byte(&mut self, byte: u8) -> Result<(), Error>;
 
+    /// It writes many bytes to the output in one call.
+    ///
+    /// The default implementation writes the bytes one by one. Writers should
+    /// override it when they can write all of them at once.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if it can't write the bytes.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        for byte in bytes {
+            self.write_byte(*byte)?;
+        }
+
+        Ok(())
+    }
+
     /// It writes a string to the output.
     ///
     /// # Errors
@@ -37,4 +101,17 @@ pub trait Writer {
     /// It returns a copy of the latest bytes that have been written to the
     /// output.
     fn captured_bytes(&self) -> Vec<u8>;
+
+    /// It receives every bencoded token parsed, after its JSON has been
+    /// written. See [`token`](crate::parsers::token).
+    ///
+    /// The default implementation ignores the tokens.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the writer can't handle the token.
+    fn token(&mut self, token: &Token<'_>) -> Result<(), Error> {
+        let _ = token;
+        Ok(())
+    }
 }
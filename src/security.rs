@@ -0,0 +1,788 @@
+//! Safety checks for untrusted `.torrent` files.
+//!
+//! It looks for torrents designed to attack the programs that use them:
+//!
+//! - File paths with `..` or `.` components, path separators, absolute
+//!   paths, NUL bytes or names reserved on Windows, like `CON`. They are
+//!   checked in `name` and in the paths of the files.
+//! - Negative file lengths or lengths that overflow a 64-bit integer when
+//!   they are added up.
+//! - Piece hashes, present or implied by the total length and the piece
+//!   length, above a maximum size.
+//! - Lists and dictionaries nested too deep, or with too many items.
+//!
+//! The checks run on the tokens reported by the parser, without building the
+//! values, so memory is bounded by the limits. Parsing stops when the
+//! nesting is too deep.
+//!
+//! ```rust
+//! use torrust_bencode2json::security::{check_bytes, ThreatKind};
+//!
+//! let threats = check_bytes(b"d4:infod5:filesld6:lengthi1e4:pathl2:..6:passwdeee4:name1:a12:piece lengthi1e6:pieces20:aaaaaaaaaaaaaaaaaaaaee");
+//!
+//! assert_eq!(threats[0].kind, ThreatKind::PathTraversal);
+//! assert_eq!(threats[0].path, "$.info.files[0].path[0]");
+//! ```
+use std::{
+    fmt::{self, Write},
+    io::{self, BufRead, Read},
+};
+
+use crate::{
+    metainfo::SHA1_LENGTH,
+    parsers::{
+        error::ErrorKind,
+        options::Limits,
+        token::{Token, TokenKind},
+        BencodeParser,
+    },
+    rw::{error::Error as RwError, writer::Writer},
+};
+
+/// File names reserved on Windows, with or without an extension.
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The limits of the checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecurityLimits {
+    /// Maximum number of nested lists and dictionaries.
+    pub max_depth: usize,
+
+    /// Maximum number of items in a list, or entries in a dictionary.
+    pub max_items: u64,
+
+    /// Maximum size of the piece hashes, in bytes. It's also the maximum
+    /// length of strings when the checks build the parser.
+    pub max_piece_hashes_size: u64,
+}
+
+impl Default for SecurityLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 32,
+            max_items: 1_000_000,
+            // 8 million pieces
+            max_piece_hashes_size: 160 * 1024 * 1024,
+        }
+    }
+}
+
+/// The kinds of threats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreatKind {
+    /// A path component that is `..` or `.`, or contains a path separator.
+    PathTraversal,
+
+    /// A path component that is an absolute path, like `/etc` or `C:`.
+    AbsolutePath,
+
+    /// A path component with a NUL byte.
+    NulByte,
+
+    /// A path component that is a name reserved on Windows.
+    ReservedName,
+
+    /// A negative file length, or file lengths that overflow when added up.
+    LengthOverflow,
+
+    /// Piece hashes, present or implied, above the maximum size.
+    TooManyPieces,
+
+    /// Lists and dictionaries nested above the maximum depth.
+    TooDeep,
+
+    /// A list or dictionary with more items than the maximum, or a value
+    /// above the parser limits.
+    TooLarge,
+
+    /// The input is not valid Bencode.
+    InvalidBencode,
+}
+
+impl ThreatKind {
+    /// It returns the short name of the threat, for example
+    /// `path_traversal`.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThreatKind::PathTraversal => "path_traversal",
+            ThreatKind::AbsolutePath => "absolute_path",
+            ThreatKind::NulByte => "nul_byte",
+            ThreatKind::ReservedName => "reserved_name",
+            ThreatKind::LengthOverflow => "length_overflow",
+            ThreatKind::TooManyPieces => "too_many_pieces",
+            ThreatKind::TooDeep => "too_deep",
+            ThreatKind::TooLarge => "too_large",
+            ThreatKind::InvalidBencode => "invalid_bencode",
+        }
+    }
+}
+
+impl fmt::Display for ThreatKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A threat found in a `.torrent` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Threat {
+    pub kind: ThreatKind,
+
+    /// The path of the value, like `$.info.files[0].path[1]`.
+    pub path: String,
+
+    pub message: String,
+}
+
+impl fmt::Display for Threat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.kind, self.path, self.message)
+    }
+}
+
+/// It checks a `.torrent` file with the default limits.
+#[must_use]
+pub fn check_bytes(input: &[u8]) -> Vec<Threat> {
+    check_reader(input, &SecurityLimits::default())
+}
+
+/// It checks a `.torrent` file read from a reader.
+///
+/// The parser is built with limits for the depth and for the length of
+/// strings and integers, so that it doesn't need more memory either.
+pub fn check_reader<R: Read>(reader: R, limits: &SecurityLimits) -> Vec<Threat> {
+    let mut parser = BencodeParser::builder(reader)
+        .limits(Limits {
+            max_depth: Some(limits.max_depth + 1),
+            max_string_length: Some(
+                usize::try_from(limits.max_piece_hashes_size).unwrap_or(usize::MAX),
+            ),
+            // One more digit than 64-bit integers, to report overflows
+            max_integer_length: Some(20),
+        })
+        .build();
+
+    check(&mut parser, limits)
+}
+
+/// It checks a `.torrent` file parsed with a parser.
+///
+/// The parser should stop at the first error (the default). Its limits
+/// bound the memory used for each string.
+pub fn check<R: BufRead>(parser: &mut BencodeParser<R>, limits: &SecurityLimits) -> Vec<Threat> {
+    let mut writer = SecurityWriter::new(*limits);
+
+    let result = parser.write_to(&mut writer);
+
+    if let Err(err) = result {
+        if !writer.aborted {
+            let kind = match err.kind() {
+                ErrorKind::LimitExceeded => ThreatKind::TooLarge,
+                _ => ThreatKind::InvalidBencode,
+            };
+
+            let path = writer.path();
+            writer.report(kind, path, err.to_string());
+        }
+    }
+
+    writer.threats
+}
+
+/// A [`Writer`] that ignores the JSON output and checks the tokens.
+struct SecurityWriter {
+    limits: SecurityLimits,
+
+    /// The lists and dictionaries that have not been closed yet.
+    frames: Vec<Frame>,
+
+    /// The sum of the file lengths, if they are valid so far.
+    total_length: Option<u64>,
+
+    piece_length: Option<u64>,
+
+    /// True when the parser has been stopped.
+    aborted: bool,
+
+    threats: Vec<Threat>,
+}
+
+enum Frame {
+    List {
+        items: u64,
+    },
+    Dict {
+        /// The key of the current value, or the previous key when the next
+        /// item is a key.
+        key: Option<Vec<u8>>,
+        expecting_key: bool,
+        items: u64,
+    },
+}
+
+/// A segment of a path pattern: a dictionary key or any list index.
+enum Segment {
+    Key(&'static [u8]),
+    AnyIndex,
+}
+
+const NAME: [Segment; 2] = [Segment::Key(b"info"), Segment::Key(b"name")];
+const NAME_UTF8: [Segment; 2] = [Segment::Key(b"info"), Segment::Key(b"name.utf-8")];
+const FILE_PATH: [Segment; 5] = [
+    Segment::Key(b"info"),
+    Segment::Key(b"files"),
+    Segment::AnyIndex,
+    Segment::Key(b"path"),
+    Segment::AnyIndex,
+];
+const FILE_PATH_UTF8: [Segment; 5] = [
+    Segment::Key(b"info"),
+    Segment::Key(b"files"),
+    Segment::AnyIndex,
+    Segment::Key(b"path.utf-8"),
+    Segment::AnyIndex,
+];
+const FILE_LENGTH: [Segment; 4] = [
+    Segment::Key(b"info"),
+    Segment::Key(b"files"),
+    Segment::AnyIndex,
+    Segment::Key(b"length"),
+];
+const LENGTH: [Segment; 2] = [Segment::Key(b"info"), Segment::Key(b"length")];
+const PIECE_LENGTH: [Segment; 2] = [Segment::Key(b"info"), Segment::Key(b"piece length")];
+const PIECES: [Segment; 2] = [Segment::Key(b"info"), Segment::Key(b"pieces")];
+const INFO: [Segment; 1] = [Segment::Key(b"info")];
+
+impl SecurityWriter {
+    fn new(limits: SecurityLimits) -> Self {
+        Self {
+            limits,
+            frames: Vec::new(),
+            total_length: Some(0),
+            piece_length: None,
+            aborted: false,
+            threats: Vec::new(),
+        }
+    }
+
+    /// It returns the path of the current value.
+    fn path(&self) -> String {
+        self.path_at_depth(self.frames.len())
+    }
+
+    /// It returns the path of the current list or dictionary.
+    fn container_path(&self) -> String {
+        self.path_at_depth(self.frames.len().saturating_sub(1))
+    }
+
+    /// It returns the path of the current value at the depth.
+    fn path_at_depth(&self, depth: usize) -> String {
+        let mut path = String::from("$");
+
+        for frame in &self.frames[..depth] {
+            match frame {
+                Frame::List { items } => {
+                    write!(path, "[{items}]").expect("writing to a string should not fail");
+                }
+                Frame::Dict { key: Some(key), .. } => {
+                    path.push('.');
+                    path.push_str(&String::from_utf8_lossy(key));
+                }
+                Frame::Dict { key: None, .. } => {}
+            }
+        }
+
+        path
+    }
+
+    /// True when the current value is at the path of the pattern.
+    fn is_at(&self, pattern: &[Segment]) -> bool {
+        self.frames.len() == pattern.len()
+            && self
+                .frames
+                .iter()
+                .zip(pattern)
+                .all(|(frame, segment)| match (frame, segment) {
+                    (Frame::List { .. }, Segment::AnyIndex) => true,
+                    (
+                        Frame::Dict {
+                            key: Some(key),
+                            expecting_key: false,
+                            ..
+                        },
+                        Segment::Key(expected),
+                    ) => key == expected,
+                    _ => false,
+                })
+    }
+
+    fn report(&mut self, kind: ThreatKind, path: String, message: impl Into<String>) {
+        self.threats.push(Threat {
+            kind,
+            path,
+            message: message.into(),
+        });
+    }
+
+    /// It counts an item of the current list or dictionary.
+    fn end_item(&mut self) {
+        let max_items = self.limits.max_items;
+
+        let exceeded = match self.frames.last_mut() {
+            Some(Frame::List { items }) => {
+                *items += 1;
+                *items == max_items + 1
+            }
+            Some(Frame::Dict {
+                expecting_key,
+                items,
+                ..
+            }) => {
+                *expecting_key = true;
+                *items += 1;
+                *items == max_items + 1
+            }
+            None => false,
+        };
+
+        if exceeded {
+            let path = self.container_path();
+
+            self.report(
+                ThreatKind::TooLarge,
+                path,
+                format!("more than {max_items} items"),
+            );
+        }
+    }
+
+    fn check_path_component(&mut self, component: &[u8]) {
+        let problem = if component.contains(&0) {
+            Some((ThreatKind::NulByte, "NUL byte in a path component"))
+        } else if component.starts_with(b"/")
+            || component.starts_with(b"\\")
+            || (component.len() >= 2 && component[0].is_ascii_alphabetic() && component[1] == b':')
+        {
+            Some((ThreatKind::AbsolutePath, "absolute path"))
+        } else if component == b".." || component == b"." {
+            Some((ThreatKind::PathTraversal, "relative path component"))
+        } else if component.contains(&b'/') || component.contains(&b'\\') {
+            Some((
+                ThreatKind::PathTraversal,
+                "path separator in a path component",
+            ))
+        } else if is_windows_reserved_name(component) {
+            Some((ThreatKind::ReservedName, "file name reserved on Windows"))
+        } else {
+            None
+        };
+
+        if let Some((kind, message)) = problem {
+            let path = self.path();
+            self.report(kind, path, message);
+        }
+    }
+
+    fn add_file_length(&mut self, digits: &str) {
+        let message = match digits.parse::<i64>() {
+            Err(_) => "the length doesn't fit in a 64-bit integer",
+            Ok(length) if length < 0 => "negative length",
+            Ok(length) => {
+                // The sum is only reported the first time it overflows
+                let Some(total_length) = self.total_length else {
+                    return;
+                };
+
+                match total_length
+                    .checked_add(length.unsigned_abs())
+                    .filter(|total_length| i64::try_from(*total_length).is_ok())
+                {
+                    Some(total_length) => {
+                        self.total_length = Some(total_length);
+                        return;
+                    }
+                    None => "the sum of the file lengths overflows",
+                }
+            }
+        };
+
+        self.total_length = None;
+
+        let path = self.path();
+        self.report(ThreatKind::LengthOverflow, path, message);
+    }
+
+    fn has_reported(&self, kind: ThreatKind) -> bool {
+        self.threats.iter().any(|threat| threat.kind == kind)
+    }
+
+    fn check_pieces_size(&mut self, size: u64) {
+        if size > self.limits.max_piece_hashes_size {
+            self.report(
+                ThreatKind::TooManyPieces,
+                "$.info.pieces".to_string(),
+                format!(
+                    "{size} bytes of piece hashes, more than {}",
+                    self.limits.max_piece_hashes_size
+                ),
+            );
+        }
+    }
+
+    /// It checks the piece hashes implied by the total length and the piece
+    /// length, at the end of the info dictionary.
+    fn check_implied_pieces(&mut self) {
+        let (Some(total_length), Some(piece_length)) = (self.total_length, self.piece_length)
+        else {
+            return;
+        };
+
+        if piece_length == 0 {
+            if total_length > 0 {
+                self.report(
+                    ThreatKind::TooManyPieces,
+                    "$.info.piece length".to_string(),
+                    "a piece length of 0 implies infinite pieces",
+                );
+            }
+            return;
+        }
+
+        let pieces = total_length.div_ceil(piece_length);
+
+        if !self.has_reported(ThreatKind::TooManyPieces) {
+            self.check_pieces_size(pieces.saturating_mul(SHA1_LENGTH as u64));
+        }
+    }
+
+    fn abort(&mut self) -> RwError {
+        self.aborted = true;
+
+        RwError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the safety checks stopped the parser",
+        ))
+    }
+}
+
+fn is_windows_reserved_name(component: &[u8]) -> bool {
+    let stem = component
+        .split(|byte| *byte == b'.')
+        .next()
+        .unwrap_or_default();
+
+    WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|name| stem.eq_ignore_ascii_case(name.as_bytes()))
+}
+
+impl Writer for SecurityWriter {
+    fn write_byte(&mut self, _byte: u8) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, _bytes: &[u8]) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn write_str(&mut self, _value: &str) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn output_byte_counter(&self) -> u64 {
+        0
+    }
+
+    fn captured_bytes(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn token(&mut self, token: &Token<'_>) -> Result<(), RwError> {
+        let is_key = matches!(
+            self.frames.last(),
+            Some(Frame::Dict {
+                expecting_key: true,
+                ..
+            })
+        );
+
+        match token.kind {
+            TokenKind::String(key) if is_key => {
+                if let Some(Frame::Dict {
+                    key: current,
+                    expecting_key,
+                    ..
+                }) = self.frames.last_mut()
+                {
+                    *current = Some(key.to_vec());
+                    *expecting_key = false;
+                }
+            }
+            TokenKind::BeginList | TokenKind::BeginDict => {
+                if self.frames.len() >= self.limits.max_depth {
+                    let path = self.path();
+                    self.report(
+                        ThreatKind::TooDeep,
+                        path,
+                        format!("more than {} nested values", self.limits.max_depth),
+                    );
+
+                    return Err(self.abort());
+                }
+
+                self.frames.push(if token.kind == TokenKind::BeginList {
+                    Frame::List { items: 0 }
+                } else {
+                    Frame::Dict {
+                        key: None,
+                        expecting_key: true,
+                        items: 0,
+                    }
+                });
+            }
+            TokenKind::End => {
+                self.frames.pop();
+
+                if self.is_at(&INFO) {
+                    self.check_implied_pieces();
+                }
+
+                self.end_item();
+            }
+            TokenKind::String(bytes) => {
+                if self.is_at(&NAME)
+                    || self.is_at(&NAME_UTF8)
+                    || self.is_at(&FILE_PATH)
+                    || self.is_at(&FILE_PATH_UTF8)
+                {
+                    self.check_path_component(bytes);
+                } else if self.is_at(&PIECES) {
+                    self.check_pieces_size(bytes.len() as u64);
+                }
+
+                self.end_item();
+            }
+            TokenKind::Integer(digits) => {
+                if self.is_at(&FILE_LENGTH) || self.is_at(&LENGTH) {
+                    self.add_file_length(digits);
+                } else if self.is_at(&PIECE_LENGTH) {
+                    self.piece_length = digits.parse().ok();
+                }
+
+                self.end_item();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::security::{check_reader, SecurityLimits, ThreatKind};
+
+    /// It returns the kind and path of the threats.
+    fn threats(input: &[u8], limits: &SecurityLimits) -> Vec<(ThreatKind, String)> {
+        check_reader(input, limits)
+            .into_iter()
+            .map(|threat| (threat.kind, threat.path))
+            .collect()
+    }
+
+    /// It returns a multi-file torrent with the files.
+    fn with_files(files: &str) -> Vec<u8> {
+        format!("d4:infod5:filesl{files}e4:name1:a12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee")
+            .into_bytes()
+    }
+
+    fn file_with_path(path: &str) -> Vec<u8> {
+        with_files(&format!("d6:lengthi1e4:pathl{path}ee"))
+    }
+
+    mod it_should_not_report {
+        use crate::security::{
+            check_bytes,
+            tests::{file_with_path, with_files},
+        };
+
+        #[test]
+        fn safe_torrents() {
+            assert_eq!(check_bytes(&file_with_path("3:dir5:a.txt")), vec![]);
+            assert_eq!(
+                check_bytes(&with_files("d6:lengthi1e4:pathl7:consoleee")),
+                vec![]
+            );
+        }
+    }
+
+    mod it_should_report {
+        use crate::security::{
+            check_bytes,
+            tests::{file_with_path, threats, with_files},
+            SecurityLimits, ThreatKind,
+        };
+
+        fn path_threat(path: &str) -> ThreatKind {
+            let threats = check_bytes(&file_with_path(path));
+
+            assert_eq!(threats.len(), 1, "{threats:?}");
+            assert_eq!(threats[0].path, "$.info.files[0].path[0]");
+
+            threats[0].kind
+        }
+
+        #[test]
+        fn path_traversal() {
+            assert_eq!(path_threat("2:..6:passwd"), ThreatKind::PathTraversal);
+            assert_eq!(path_threat("1:."), ThreatKind::PathTraversal);
+            assert_eq!(path_threat("5:../ab"), ThreatKind::PathTraversal);
+            assert_eq!(path_threat("4:a\\.."), ThreatKind::PathTraversal);
+        }
+
+        #[test]
+        fn absolute_paths() {
+            assert_eq!(path_threat("4:/etc"), ThreatKind::AbsolutePath);
+            assert_eq!(path_threat("3:C:x"), ThreatKind::AbsolutePath);
+        }
+
+        #[test]
+        fn nul_bytes() {
+            assert_eq!(path_threat("3:a\0b"), ThreatKind::NulByte);
+        }
+
+        #[test]
+        fn names_reserved_on_windows() {
+            assert_eq!(path_threat("3:CON"), ThreatKind::ReservedName);
+            assert_eq!(path_threat("8:lpt1.txt"), ThreatKind::ReservedName);
+        }
+
+        #[test]
+        fn unsafe_torrent_names() {
+            let threats = check_bytes(
+                b"d4:infod6:lengthi1e4:name2:..12:piece lengthi1e6:pieces20:aaaaaaaaaaaaaaaaaaaaee",
+            );
+
+            assert_eq!(threats[0].kind, ThreatKind::PathTraversal);
+            assert_eq!(threats[0].path, "$.info.name");
+        }
+
+        #[test]
+        fn file_lengths_that_overflow_when_added_up() {
+            assert_eq!(
+                threats(
+                    &with_files(
+                        "d6:lengthi9223372036854775807e4:pathl1:aeed6:lengthi1e4:pathl1:beed6:lengthi1e4:pathl1:cee"
+                    ),
+                    &SecurityLimits::default()
+                ),
+                vec![(ThreatKind::LengthOverflow, "$.info.files[1].length".to_string())]
+            );
+        }
+
+        #[test]
+        fn negative_and_too_long_lengths() {
+            assert_eq!(
+                threats(
+                    &with_files(
+                        "d6:lengthi-1e4:pathl1:aeed6:lengthi99999999999999999999e4:pathl1:bee"
+                    ),
+                    &SecurityLimits::default()
+                ),
+                vec![
+                    (
+                        ThreatKind::LengthOverflow,
+                        "$.info.files[0].length".to_string()
+                    ),
+                    (
+                        ThreatKind::LengthOverflow,
+                        "$.info.files[1].length".to_string()
+                    )
+                ]
+            );
+        }
+
+        #[test]
+        fn piece_counts_implying_too_much_hash_data() {
+            // 1 PiB in 16 KiB pieces needs 1.25 TiB of hashes
+            assert_eq!(
+                threats(
+                    b"d4:infod6:lengthi1125899906842624e4:name1:a12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee",
+                    &SecurityLimits::default()
+                ),
+                vec![(ThreatKind::TooManyPieces, "$.info.pieces".to_string())]
+            );
+        }
+
+        #[test]
+        fn pieces_above_the_maximum_size() {
+            let limits = SecurityLimits {
+                max_piece_hashes_size: 40,
+                ..SecurityLimits::default()
+            };
+
+            assert_eq!(
+                threats(
+                    b"d4:infod6:lengthi1e4:name1:a12:piece lengthi1e6:pieces40:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaee",
+                    &limits
+                ),
+                vec![]
+            );
+            assert_eq!(
+                threats(
+                    b"d4:infod6:lengthi1e4:name1:a12:piece lengthi1e6:pieces60:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaee",
+                    &limits
+                ),
+                vec![(ThreatKind::TooLarge, "$.info.pieces".to_string())]
+            );
+        }
+
+        #[test]
+        fn a_piece_length_of_zero() {
+            assert_eq!(
+                threats(
+                    b"d4:infod6:lengthi1e4:name1:a12:piece lengthi0e6:pieces0:ee",
+                    &SecurityLimits::default()
+                ),
+                vec![(ThreatKind::TooManyPieces, "$.info.piece length".to_string())]
+            );
+        }
+
+        #[test]
+        fn deep_nesting_and_stop_parsing() {
+            let limits = SecurityLimits {
+                max_depth: 3,
+                ..SecurityLimits::default()
+            };
+
+            assert_eq!(
+                threats(b"d1:alllli1eeeee", &limits),
+                vec![(ThreatKind::TooDeep, "$.a[0][0]".to_string())]
+            );
+        }
+
+        #[test]
+        fn lists_with_too_many_items() {
+            let limits = SecurityLimits {
+                max_items: 2,
+                ..SecurityLimits::default()
+            };
+
+            assert_eq!(
+                threats(b"d1:ali1ei2ei3ei4eee", &limits),
+                vec![(ThreatKind::TooLarge, "$.a".to_string())]
+            );
+        }
+
+        #[test]
+        fn invalid_bencode() {
+            assert_eq!(
+                threats(b"d4:infod4:name", &SecurityLimits::default()),
+                vec![(ThreatKind::InvalidBencode, "$.info.name".to_string())]
+            );
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Tracker responses.
+//!
+//! It renders the responses of HTTP trackers as JSON, decoding the fields
+//! that are binary in bencode:
+//!
+//! - `peers` and `peers6` in the compact format
+//!   ([BEP 23](https://www.bittorrent.org/beps/bep_0023.html) and
+//!   [BEP 7](https://www.bittorrent.org/beps/bep_0007.html)) are written as
+//!   lists of `"ip:port"` strings. Peers in the dictionary format
+//!   ([BEP 3](https://www.bittorrent.org/beps/bep_0003.html)) are written as
+//!   they are.
+//! - The keys of the `files` dictionary of scrape responses
+//!   ([BEP 48](https://www.bittorrent.org/beps/bep_0048.html)), which are
+//!   infohashes, are written in hex.
+//!
+//! ```rust
+//! use torrust_bencode2json::{tracker, value};
+//!
+//! let response = value::from_bytes(b"d8:intervali1800e5:peers6:\x7f\x00\x00\x01\x1a\xe1e").unwrap();
+//!
+//! assert_eq!(
+//!     tracker::render(&response).to_string(),
+//!     r#"{"interval":1800,"peers":["127.0.0.1:6881"]}"#
+//! );
+//! ```
+//!
+//! Other values are written like the parser does.
+use std::net::SocketAddr;
+
+use crate::{
+    compact::{decode_peers, decode_peers6},
+    metainfo::SHA1_LENGTH,
+    value::{bytes_to_json_string, Value},
+};
+
+/// The fields of a tracker response that tell the client whether the request
+/// succeeded and when to announce again.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Status {
+    /// The reason why the request failed (`failure reason`).
+    pub failure_reason: Option<String>,
+
+    /// A warning about the request (`warning message`).
+    pub warning_message: Option<String>,
+
+    /// Seconds the client should wait between announces (`interval`).
+    pub interval: Option<i64>,
+
+    /// Seconds the client must wait between announces (`min interval`).
+    pub min_interval: Option<i64>,
+}
+
+impl Status {
+    /// It returns the status fields of a tracker response. Fields that are
+    /// missing or have another type are `None`.
+    #[must_use]
+    pub fn from_value(response: &Value) -> Self {
+        let string = |key: &str| {
+            response
+                .get(key)
+                .and_then(Value::as_bytes)
+                .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+        };
+
+        Self {
+            failure_reason: string("failure reason"),
+            warning_message: string("warning message"),
+            interval: response.get("interval").and_then(Value::as_integer),
+            min_interval: response.get("min interval").and_then(Value::as_integer),
+        }
+    }
+}
+
+/// It returns the JSON of a tracker announce or scrape response.
+///
+/// Compact peers with a length that is not a multiple of the address length
+/// are written like other strings.
+#[must_use]
+pub fn render(response: &Value) -> serde_json::Value {
+    let Value::Dict(entries) = response else {
+        return response.to_json();
+    };
+
+    serde_json::Value::Object(
+        entries
+            .iter()
+            .map(|(key, value)| {
+                let json = match (key.as_slice(), value) {
+                    (b"peers", Value::String(bytes)) => render_peers(decode_peers(bytes), value),
+                    (b"peers6", Value::String(bytes)) => render_peers(decode_peers6(bytes), value),
+                    (b"files", Value::Dict(files)) => serde_json::Value::Object(
+                        files
+                            .iter()
+                            .map(|(infohash, stats)| (render_infohash(infohash), stats.to_json()))
+                            .collect(),
+                    ),
+                    _ => value.to_json(),
+                };
+
+                (bytes_to_json_string(key), json)
+            })
+            .collect(),
+    )
+}
+
+fn render_peers(peers: Option<Vec<SocketAddr>>, value: &Value) -> serde_json::Value {
+    match peers {
+        Some(peers) => peers.iter().map(ToString::to_string).collect(),
+        None => value.to_json(),
+    }
+}
+
+fn render_infohash(key: &[u8]) -> String {
+    if key.len() == SHA1_LENGTH {
+        hex::encode(key)
+    } else {
+        bytes_to_json_string(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should {
+        use serde_json::json;
+
+        use crate::{
+            tracker::{render, Status},
+            value::from_bytes,
+        };
+
+        #[test]
+        fn render_compact_ipv4_and_ipv6_peers_as_addresses() {
+            let mut input = b"d5:peers6:\x0a\x00\x00\x01\x1a\xe16:peers618:".to_vec();
+            input.extend_from_slice(&[0; 15]);
+            input.extend_from_slice(b"\x01\x00\x50e");
+
+            assert_eq!(
+                render(&from_bytes(&input).unwrap()),
+                json!({"peers": ["10.0.0.1:6881"], "peers6": ["[::1]:80"]})
+            );
+        }
+
+        #[test]
+        fn keep_peers_in_the_dictionary_format() {
+            let input = b"d5:peersld2:ip8:10.0.0.17:peer id3:abc4:porti6881eeee";
+
+            assert_eq!(
+                render(&from_bytes(input).unwrap()),
+                json!({"peers": [{"ip": "10.0.0.1", "peer id": "abc", "port": 6881}]})
+            );
+        }
+
+        #[test]
+        fn keep_compact_peers_with_an_invalid_length_as_strings() {
+            assert_eq!(
+                render(&from_bytes(b"d5:peers5:abcdee").unwrap()),
+                json!({"peers": "abcde"})
+            );
+        }
+
+        #[test]
+        fn render_the_infohashes_of_scrape_responses_in_hex() {
+            let input = b"d5:filesd20:aaaaaaaaaaaaaaaaaaaad8:completei5e10:downloadedi50e10:incompletei10eeee";
+
+            assert_eq!(
+                render(&from_bytes(input).unwrap()),
+                json!({"files": {"6161616161616161616161616161616161616161": {"complete": 5, "downloaded": 50, "incomplete": 10}}})
+            );
+        }
+
+        #[test]
+        fn return_the_status_fields() {
+            let input = b"d14:failure reason6:banned8:intervali1800e12:min intervali60e15:warning message4:slowe";
+
+            assert_eq!(
+                Status::from_value(&from_bytes(input).unwrap()),
+                Status {
+                    failure_reason: Some("banned".to_string()),
+                    warning_message: Some("slow".to_string()),
+                    interval: Some(1800),
+                    min_interval: Some(60),
+                }
+            );
+        }
+
+        #[test]
+        fn ignore_missing_status_fields() {
+            assert_eq!(
+                Status::from_value(&from_bytes(b"de").unwrap()),
+                Status::default()
+            );
+        }
+    }
+}
@@ -0,0 +1,538 @@
+//! In-memory bencoded values.
+//!
+//! The crate converts Bencode to JSON without building the values in memory.
+//! When the values are needed, for example to decode a `.torrent` file into
+//! typed fields, the [`ValueBuilder`] writer builds them from the tokens
+//! reported by the parser:
+//!
+//! ```rust
+//! use torrust_bencode2json::value::{from_bytes, Value};
+//!
+//! let value = from_bytes(b"d4:spaml1:ai42eee").unwrap();
+//!
+//! assert_eq!(
+//!     value.get("spam").and_then(Value::as_list),
+//!     Some(&[Value::String(b"a".to_vec()), Value::Integer(42)][..])
+//! );
+//! ```
+//!
+//! Values are dropped and converted into JSON recursively, so the builder
+//! doesn't build values with more than [`MAX_DEPTH`] nested lists and
+//! dictionaries, even if the parser has no depth limit.
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead},
+};
+
+use thiserror::Error;
+
+use crate::{
+    encoder,
+    parsers::{
+        error::Error as ParserError,
+        token::{Token, TokenKind},
+        BencodeParser,
+    },
+    rw::{error::Error as RwError, writer::Writer},
+};
+
+/// Maximum number of nested lists and dictionaries in the values built.
+pub const MAX_DEPTH: usize = 1024;
+
+/// A bencoded value.
+///
+/// Dictionaries are sorted by key, as Bencode requires. When a dictionary has
+/// the same key more than once, the last value is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Integer(i64),
+    String(Vec<u8>),
+    List(Vec<Value>),
+    Dict(BTreeMap<Vec<u8>, Value>),
+}
+
+impl Value {
+    /// It returns the integer, if the value is an integer.
+    #[must_use]
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    /// It returns the string bytes, if the value is a string.
+    #[must_use]
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::String(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// It returns the string, if the value is a valid UTF-8 string.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes()
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    /// It returns the items, if the value is a list.
+    #[must_use]
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// It returns the entries, if the value is a dictionary.
+    #[must_use]
+    pub fn as_dict(&self) -> Option<&BTreeMap<Vec<u8>, Value>> {
+        match self {
+            Value::Dict(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// It returns the value for the key, if the value is a dictionary with
+    /// that key.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_dict()
+            .and_then(|entries| entries.get(key.as_bytes()))
+    }
+
+    /// It returns the value as JSON, the same way the parser writes it with
+    /// the default options: strings that are not valid UTF-8, including
+    /// dictionary keys, are written in the `<hex>fafb</hex>` format.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Integer(integer) => serde_json::Value::from(*integer),
+            Value::String(bytes) => serde_json::Value::String(bytes_to_json_string(bytes)),
+            Value::List(items) => items.iter().map(Value::to_json).collect(),
+            Value::Dict(entries) => serde_json::Value::Object(
+                entries
+                    .iter()
+                    .map(|(key, value)| (bytes_to_json_string(key), value.to_json()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// It returns the name of the value type, like `integer` or `dictionary`.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "integer",
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Dict(_) => "dictionary",
+        }
+    }
+}
+
+/// It returns the string, or the bytes in the `<hex>fafb</hex>` format when
+/// they are not valid UTF-8 or when the string looks like the format, like
+/// the parser does.
+#[must_use]
+pub fn bytes_to_json_string(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(string) if encoder::decode_tagged_hex(string).is_none() => string.to_string(),
+        _ => format!("<hex>{}</hex>", hex::encode(bytes)),
+    }
+}
+
+/// Errors that can occur while building values.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The input is invalid Bencode, or it exceeds the parser limits.
+    #[error("{0}")]
+    Parser(#[from] ParserError),
+
+    /// An integer doesn't fit in 64 bits.
+    #[error("Integer out of the 64-bit range: {0}")]
+    IntegerOutOfRange(String),
+
+    /// The input doesn't have any value.
+    #[error("The input doesn't have any bencoded value")]
+    NoValue,
+
+    /// The input has more than one top-level value.
+    #[error("The input has {0} bencoded values instead of one")]
+    MoreThanOneValue(usize),
+
+    /// The value has more nested lists and dictionaries than
+    /// [`MAX_DEPTH`].
+    #[error("The value has more than {MAX_DEPTH} nested lists and dictionaries")]
+    TooDeep,
+}
+
+/// It builds the value in the input.
+///
+/// # Errors
+///
+/// Will return an error if the input is invalid Bencode, an integer doesn't
+/// fit in 64 bits, the value is nested too deeply, or the input doesn't have
+/// exactly one value.
+pub fn from_bytes(input: &[u8]) -> Result<Value, Error> {
+    from_parser(&mut BencodeParser::from_buf_read(input))
+}
+
+/// It builds the value parsed with a parser, so that the parser options
+/// (like the limits) apply.
+///
+/// # Errors
+///
+/// Will return an error if the input is invalid Bencode, an integer doesn't
+/// fit in 64 bits, the value is nested too deeply, or the input doesn't have
+/// exactly one value.
+pub fn from_parser<R: BufRead>(parser: &mut BencodeParser<R>) -> Result<Value, Error> {
+    let mut builder = ValueBuilder::default();
+
+    builder.parse(parser)?;
+
+    builder.into_value()
+}
+
+/// It builds the value at the beginning of the input, ignoring the bytes
+/// after it. It returns the value and the position where it ends, so the
+/// bytes after it are `&input[end..]`:
+///
+/// ```rust
+/// use torrust_bencode2json::value::from_prefix;
+///
+/// let input = b"d8:msg_typei1e5:piecei0eeDATA";
+///
+/// let (header, end) = from_prefix(input).unwrap();
+///
+/// assert_eq!(header.get("piece").and_then(|piece| piece.as_integer()), Some(0));
+/// assert_eq!(&input[end..], b"DATA");
+/// ```
+///
+/// # Errors
+///
+/// Will return an error if the value is invalid Bencode, an integer doesn't
+/// fit in 64 bits, the value is nested too deeply, or the input doesn't have
+/// any value.
+pub fn from_prefix(input: &[u8]) -> Result<(Value, usize), Error> {
+    let mut parser = BencodeParser::from_buf_read(input);
+    let mut builder = ValueBuilder::default();
+
+    let end = builder.parse_value(&mut parser)?.ok_or(Error::NoValue)?;
+
+    // The position is never beyond the end of the slice
+    let end = usize::try_from(end).unwrap_or(input.len());
+
+    Ok((builder.into_value()?, end))
+}
+
+/// A [`Writer`] that ignores the JSON output and builds the values from the
+/// tokens.
+#[derive(Debug, Default)]
+pub struct ValueBuilder {
+    /// The lists and dictionaries that have not been closed yet.
+    containers: Vec<Container>,
+
+    /// The top-level values completed.
+    values: Vec<Value>,
+
+    /// The error that stopped the parser.
+    error: Option<Error>,
+}
+
+#[derive(Debug)]
+enum Container {
+    List(Vec<Value>),
+    Dict {
+        entries: BTreeMap<Vec<u8>, Value>,
+        key: Option<Vec<u8>>,
+    },
+}
+
+impl ValueBuilder {
+    /// It builds the values parsed with the parser.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the input is invalid Bencode, an integer
+    /// doesn't fit in 64 bits, or a value is nested too deeply.
+    pub fn parse<R: BufRead>(&mut self, parser: &mut BencodeParser<R>) -> Result<(), Error> {
+        let result = parser.write_to(self);
+
+        if let Some(err) = self.take_error() {
+            return Err(err);
+        }
+
+        Ok(result?)
+    }
+
+    /// It builds only the next value parsed with the parser, leaving the bytes
+    /// after it in the input. It returns the position where the value ends,
+    /// or `None` if the input has no more values.
+    ///
+    /// See [`BencodeParser::write_value_to`].
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the value is invalid Bencode, an integer
+    /// doesn't fit in 64 bits, or the value is nested too deeply.
+    pub fn parse_value<R: BufRead>(
+        &mut self,
+        parser: &mut BencodeParser<R>,
+    ) -> Result<Option<u64>, Error> {
+        let result = parser.write_value_to(self);
+
+        if let Some(err) = self.take_error() {
+            return Err(err);
+        }
+
+        Ok(result?)
+    }
+
+    /// It returns the error that stopped the parser, if the builder stopped
+    /// it. It's needed when the builder is wrapped by another writer, instead
+    /// of using [`ValueBuilder::parse`].
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    /// It returns the top-level values completed.
+    #[must_use]
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
+    }
+
+    /// It returns the only top-level value.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if there isn't exactly one top-level value.
+    pub fn into_value(self) -> Result<Value, Error> {
+        let mut values = self.into_values();
+
+        match values.len() {
+            0 => Err(Error::NoValue),
+            1 => Ok(values.remove(0)),
+            count => Err(Error::MoreThanOneValue(count)),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        match self.containers.last_mut() {
+            None => self.values.push(value),
+            Some(Container::List(items)) => items.push(value),
+            Some(Container::Dict { entries, key }) => match key.take() {
+                Some(key) => {
+                    entries.insert(key, value);
+                }
+                None => {
+                    // The parser only accepts strings as keys
+                    if let Value::String(bytes) = value {
+                        *key = Some(bytes);
+                    }
+                }
+            },
+        }
+    }
+
+    fn fail(&mut self, err: Error) -> RwError {
+        let message = err.to_string();
+
+        self.error = Some(err);
+
+        RwError::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+    }
+}
+
+impl Writer for ValueBuilder {
+    fn write_byte(&mut self, _byte: u8) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, _bytes: &[u8]) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn write_str(&mut self, _value: &str) -> Result<(), RwError> {
+        Ok(())
+    }
+
+    fn output_byte_counter(&self) -> u64 {
+        0
+    }
+
+    fn captured_bytes(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn token(&mut self, token: &Token<'_>) -> Result<(), RwError> {
+        match token.kind {
+            TokenKind::Integer(digits) => {
+                let integer = digits
+                    .parse()
+                    .map_err(|_| self.fail(Error::IntegerOutOfRange(digits.to_string())))?;
+
+                self.push(Value::Integer(integer));
+            }
+            TokenKind::String(bytes) => self.push(Value::String(bytes.to_vec())),
+            TokenKind::BeginList | TokenKind::BeginDict if self.containers.len() >= MAX_DEPTH => {
+                return Err(self.fail(Error::TooDeep));
+            }
+            TokenKind::BeginList => self.containers.push(Container::List(Vec::new())),
+            TokenKind::BeginDict => self.containers.push(Container::Dict {
+                entries: BTreeMap::new(),
+                key: None,
+            }),
+            TokenKind::End => {
+                // The parser only accepts ends of open lists and dictionaries
+                if let Some(container) = self.containers.pop() {
+                    self.push(match container {
+                        Container::List(items) => Value::List(items),
+                        Container::Dict { entries, .. } => Value::Dict(entries),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod it_should_build {
+        use std::collections::BTreeMap;
+
+        use crate::value::{from_bytes, from_prefix, Value, MAX_DEPTH};
+
+        #[test]
+        fn integers() {
+            assert_eq!(from_bytes(b"i-42e").unwrap(), Value::Integer(-42));
+            assert_eq!(
+                from_bytes(b"i9223372036854775807e").unwrap(),
+                Value::Integer(i64::MAX)
+            );
+        }
+
+        #[test]
+        fn strings() {
+            assert_eq!(
+                from_bytes(b"4:\xff\xfe\xfd\xfc").unwrap(),
+                Value::String(vec![0xff, 0xfe, 0xfd, 0xfc])
+            );
+        }
+
+        #[test]
+        fn nested_lists_and_dictionaries() {
+            assert_eq!(
+                from_bytes(b"d1:bli1eld1:ci2eeee1:a0:e").unwrap(),
+                Value::Dict(BTreeMap::from([
+                    (b"a".to_vec(), Value::String(Vec::new())),
+                    (
+                        b"b".to_vec(),
+                        Value::List(vec![
+                            Value::Integer(1),
+                            Value::List(vec![Value::Dict(BTreeMap::from([(
+                                b"c".to_vec(),
+                                Value::Integer(2)
+                            )]))])
+                        ])
+                    ),
+                ]))
+            );
+        }
+
+        #[test]
+        fn values_that_are_converted_to_the_same_json_as_the_parser_output() {
+            let input = b"d4:spaml1:ai-42e2:\xff\xfee2:\xfe\xffdee";
+
+            let json: serde_json::Value =
+                serde_json::from_str(&crate::try_bencode_to_json(input).unwrap()).unwrap();
+
+            assert_eq!(from_bytes(input).unwrap().to_json(), json);
+        }
+
+        #[test]
+        fn values_with_accessors_for_each_type() {
+            let value = from_bytes(b"d3:inti1e4:listle3:str1:ae").unwrap();
+
+            assert_eq!(value.get("int").and_then(Value::as_integer), Some(1));
+            assert_eq!(value.get("str").and_then(Value::as_str), Some("a"));
+            assert_eq!(value.get("list").and_then(Value::as_list), Some(&[][..]));
+            assert_eq!(value.get("missing"), None);
+            assert_eq!(value.type_name(), "dictionary");
+        }
+
+        #[test]
+        fn values_with_the_maximum_depth() {
+            let input = [b"l".repeat(MAX_DEPTH), b"e".repeat(MAX_DEPTH)].concat();
+
+            let value = from_bytes(&input).unwrap();
+
+            assert_eq!(value.to_json().to_string().len(), 2 * MAX_DEPTH);
+        }
+
+        #[test]
+        fn the_value_at_the_beginning_of_the_input_with_its_end_position() {
+            let input = b"d5:piecei2eei3e\x00";
+
+            assert_eq!(
+                from_prefix(input).unwrap(),
+                (
+                    Value::Dict(BTreeMap::from([(b"piece".to_vec(), Value::Integer(2))])),
+                    12
+                )
+            );
+        }
+    }
+
+    mod it_should_fail {
+        use crate::value::{from_bytes, from_prefix, Error};
+
+        #[test]
+        fn when_the_input_is_invalid() {
+            assert!(matches!(from_bytes(b"li1e"), Err(Error::Parser(_))));
+        }
+
+        #[test]
+        fn when_an_integer_does_not_fit_in_64_bits() {
+            assert!(matches!(
+                from_bytes(b"li9223372036854775808ee"),
+                Err(Error::IntegerOutOfRange(digits)) if digits == "9223372036854775808"
+            ));
+        }
+
+        #[test]
+        fn when_there_is_no_value() {
+            assert!(matches!(from_bytes(b""), Err(Error::NoValue)));
+        }
+
+        #[test]
+        fn when_there_is_no_value_at_the_beginning_of_the_input() {
+            assert!(matches!(from_prefix(b""), Err(Error::NoValue)));
+            assert!(matches!(from_prefix(b"\x00i1e"), Err(Error::Parser(_))));
+        }
+
+        #[test]
+        fn when_there_is_more_than_one_value() {
+            assert!(matches!(
+                from_bytes(b"i1e\ni2e"),
+                Err(Error::MoreThanOneValue(2))
+            ));
+        }
+
+        #[test]
+        fn when_the_value_is_nested_too_deeply() {
+            let depth = 200_000;
+
+            let lists = [b"l".repeat(depth), b"e".repeat(depth)].concat();
+            let dicts = [b"d1:a".repeat(depth), b"i1e".to_vec(), b"e".repeat(depth)].concat();
+
+            assert!(matches!(from_bytes(&lists), Err(Error::TooDeep)));
+            assert!(matches!(from_bytes(&dicts), Err(Error::TooDeep)));
+            assert!(matches!(from_prefix(&lists), Err(Error::TooDeep)));
+        }
+    }
+}
@@ -94,4 +94,667 @@ mod it_should {
             .assert()
             .failure();
     }
+
+    mod exit_with_a_category_specific_code {
+        use assert_cmd::Command;
+        use tempfile::tempdir;
+
+        #[test]
+        fn when_it_cannot_read_the_input_file() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("-i")
+                .arg("non_existing_file.bencode")
+                .assert()
+                .code(1);
+        }
+
+        #[cfg(target_os = "linux")]
+        #[test]
+        fn when_it_cannot_write_the_buffered_output_to_the_output_file() {
+            // Writes to `/dev/full` fail because there is no space left
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("-o")
+                .arg("/dev/full")
+                .write_stdin("i42e")
+                .assert()
+                .code(1)
+                .stderr(predicates::str::contains("No space left on device"));
+        }
+
+        #[test]
+        fn when_it_cannot_create_the_output_file() {
+            let temp_dir = tempdir().unwrap();
+
+            let output_file = temp_dir.path().join("non_existing_dir/new_file.json");
+
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("-o")
+                .arg(output_file.to_str().unwrap())
+                .write_stdin("i42e")
+                .assert()
+                .code(1);
+        }
+
+        #[test]
+        fn when_the_input_ends_before_the_bencoded_value_ends() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.write_stdin("i42").assert().code(3);
+        }
+
+        #[test]
+        fn when_the_bencoded_value_is_malformed() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.write_stdin("a").assert().code(4);
+        }
+
+        #[test]
+        fn when_the_bencoded_value_is_not_in_canonical_form() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.write_stdin("i00e").assert().code(6);
+        }
+
+        #[test]
+        fn instead_of_crashing_when_the_value_is_nested_too_deeply_to_build_it() {
+            let depth = 200_000;
+            let input = format!("{}{}", "l".repeat(depth), "e".repeat(depth));
+
+            for (mode, code) in [
+                ("--magnet", 7),
+                ("--lint", 7),
+                ("--tracker", 1),
+                ("--krpc", 1),
+            ] {
+                let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+                cmd.arg(mode).write_stdin(input.clone()).assert().code(code);
+            }
+        }
+    }
+
+    mod recover_from_errors {
+        use assert_cmd::Command;
+        use serde_json::Value;
+
+        #[test]
+        fn printing_all_the_errors_and_the_best_effort_json() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--recover").write_stdin("li00eixei3ee");
+
+            let output = cmd.output().unwrap();
+
+            assert_eq!(
+                String::from_utf8(output.stdout).unwrap(),
+                r#"["<unreadable>","<unreadable>",3]"#
+            );
+            assert_eq!(String::from_utf8(output.stderr).unwrap().lines().count(), 2);
+        }
+
+        #[test]
+        fn exiting_with_the_code_for_the_first_error() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--recover").write_stdin("li00eixei3ee");
+
+            cmd.assert().code(6);
+        }
+
+        #[test]
+        fn exiting_successfully_when_there_are_no_errors() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--recover").write_stdin("li1ee");
+
+            cmd.assert().success().stdout("[1]");
+        }
+
+        #[test]
+        fn printing_the_errors_as_json() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--recover")
+                .arg("--error-format")
+                .arg("json")
+                .write_stdin("li00eixei3ee");
+
+            let output = cmd.output().unwrap();
+
+            let kinds: Vec<Value> = String::from_utf8(output.stderr)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap()["kind"].clone())
+                .collect();
+
+            assert_eq!(kinds, vec!["strictness", "syntax"]);
+        }
+    }
+
+    mod print_errors_as_json {
+        use assert_cmd::Command;
+        use serde_json::Value;
+
+        fn stderr_json(cmd: &mut Command) -> Value {
+            let output = cmd.output().unwrap();
+            serde_json::from_slice(&output.stderr).expect("stderr is not valid JSON")
+        }
+
+        #[test]
+        fn with_the_error_kind_message_positions_and_hex_context() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--error-format").arg("json").write_stdin("li1ei42");
+
+            let error = stderr_json(&mut cmd);
+
+            assert_eq!(error["kind"], "unexpected_eof");
+            assert!(error["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("Unexpected end of input parsing integer"));
+            assert_eq!(error["input_position"], 7);
+            assert_eq!(error["output_position"], 3);
+            assert_eq!(error["file"], Value::Null);
+            assert_eq!(error["value_path"], "$[1]");
+            assert_eq!(error["context"]["input"], hex::encode("li1ei42"));
+            assert_eq!(error["context"]["output"], hex::encode("[1,"));
+        }
+
+        #[test]
+        fn with_the_path_of_the_file_that_could_not_be_opened() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--error-format")
+                .arg("json")
+                .arg("-i")
+                .arg("non_existing_file.bencode");
+
+            let error = stderr_json(&mut cmd);
+
+            assert_eq!(error["kind"], "io");
+            assert_eq!(error["file"], "non_existing_file.bencode");
+            assert_eq!(error["input_position"], Value::Null);
+        }
+
+        #[test]
+        fn with_the_path_of_the_value_with_the_error() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--error-format")
+                .arg("json")
+                .write_stdin("d4:infod5:filesld6:lengthi1x");
+
+            let error = stderr_json(&mut cmd);
+
+            assert_eq!(error["kind"], "syntax");
+            assert_eq!(error["value_path"], "$.info.files[0].length");
+        }
+
+        #[cfg(target_os = "linux")]
+        #[test]
+        fn with_the_output_file_when_writing_the_output_fails() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--error-format")
+                .arg("json")
+                .arg("-o")
+                .arg("/dev/full")
+                .write_stdin("li1ee");
+
+            let output = cmd.output().unwrap();
+            let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+
+            assert_eq!(output.status.code(), Some(1));
+            assert_eq!(error["kind"], "io");
+            assert_eq!(error["file"], "/dev/full");
+            assert_eq!(error["input_position"], Value::Null);
+        }
+    }
+
+    mod compute_the_infohashes {
+        use assert_cmd::Command;
+        use predicates::prelude::*;
+        use serde_json::Value;
+
+        const V1: &str = "1b25c654df6064bca5fb2b5fa1f87dfffea5fa21";
+        const V2: &str = "3f06896d650dd63577b7d4746f11633e604e688bd549ce079ca68bce43101f7b";
+
+        fn stdout_json(cmd: &mut Command) -> Value {
+            let output = cmd.output().unwrap();
+            serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON")
+        }
+
+        #[test]
+        fn printing_them_to_stderr() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("-i")
+                .arg("tests/fixtures/sample.torrent")
+                .arg("--infohash")
+                .assert()
+                .success()
+                .stderr(
+                    predicate::str::contains(format!("infohash v1: {V1}"))
+                        .and(predicate::str::contains(format!("infohash v2: {V2}")))
+                        .and(predicate::str::contains(format!(
+                            "infohash v2 truncated: {}",
+                            &V2[..40]
+                        ))),
+                );
+        }
+
+        #[test]
+        fn adding_them_to_the_json_output() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("-i")
+                .arg("tests/fixtures/sample.torrent")
+                .arg("--infohash");
+
+            let json = stdout_json(&mut cmd);
+
+            assert_eq!(json["infohash"]["v1"], V1);
+            assert_eq!(json["infohash"]["v2"], V2);
+            assert_eq!(json["infohash"]["v2_truncated"], &V2[..40]);
+            assert_eq!(json["info"]["name"], "hello.txt");
+        }
+
+        #[test]
+        fn adding_them_under_a_custom_key() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("-i")
+                .arg("tests/fixtures/sample.torrent")
+                .arg("--infohash")
+                .arg("--infohash-key")
+                .arg("hashes");
+
+            let json = stdout_json(&mut cmd);
+
+            assert_eq!(json["hashes"]["v1"], V1);
+            assert_eq!(json.get("infohash"), None);
+        }
+
+        #[test]
+        fn failing_when_recovering_from_errors() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("-i")
+                .arg("tests/fixtures/sample.torrent")
+                .arg("--infohash")
+                .arg("--recover")
+                .assert()
+                .code(2);
+        }
+    }
+
+    mod generate_magnet_uris {
+        use assert_cmd::Command;
+
+        #[test]
+        fn from_torrent_files() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("-i")
+                .arg("tests/fixtures/sample.torrent")
+                .arg("--magnet")
+                .assert()
+                .success()
+                .stdout("magnet:?xt=urn:btih:1b25c654df6064bca5fb2b5fa1f87dfffea5fa21&dn=hello.txt&xl=6&tr=udp%3A%2F%2Ftracker.example.com%3A1337\n");
+        }
+
+        #[test]
+        fn failing_with_a_specific_exit_code_when_the_torrent_is_invalid() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--magnet")
+                .write_stdin("d4:infodee")
+                .assert()
+                .code(7)
+                .stderr("Error: Missing required field $.info.piece length\n");
+        }
+
+        #[test]
+        fn failing_with_the_parser_exit_code_when_the_input_is_not_bencode() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--magnet").write_stdin("d4:info").assert().code(3);
+        }
+    }
+
+    mod lint_torrent_files {
+        use assert_cmd::Command;
+        use serde_json::{json, Value};
+
+        const INVALID_TORRENT: &str =
+            "d8:announce9:wss://t:14:infod6:lengthi5e4:name1:a12:piece lengthi4e6:pieces3:abcee";
+
+        #[test]
+        fn without_findings_for_valid_files() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("-i")
+                .arg("tests/fixtures/sample.torrent")
+                .arg("--lint")
+                .assert()
+                .success()
+                .stdout("");
+        }
+
+        #[test]
+        fn writing_the_findings_and_failing_when_there_are_errors() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--lint")
+                .write_stdin(INVALID_TORRENT)
+                .assert()
+                .code(7)
+                .stdout(
+                    "warning: $.announce: unsupported tracker URL scheme `wss`\n\
+                     error: $.info.pieces: the length is not a multiple of 20\n",
+                );
+        }
+
+        #[test]
+        fn succeeding_when_there_are_only_warnings() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--lint")
+                .write_stdin("d8:announce9:wss://t:14:infod6:lengthi0e4:name1:a12:piece lengthi4e6:pieces0:ee")
+                .assert()
+                .success();
+        }
+
+        #[test]
+        fn writing_the_findings_as_json() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--lint")
+                .arg("--lint-format")
+                .arg("json")
+                .write_stdin(INVALID_TORRENT);
+
+            let output = cmd.output().unwrap();
+            let findings: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+            assert_eq!(
+                findings[1],
+                json!({
+                    "severity": "error",
+                    "path": "$.info.pieces",
+                    "message": "the length is not a multiple of 20",
+                })
+            );
+        }
+    }
+
+    mod decode_tracker_responses {
+        use assert_cmd::Command;
+
+        #[test]
+        fn writing_compact_peers_as_addresses_and_the_intervals_to_stderr() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--tracker")
+                .write_stdin(
+                    &b"d8:intervali1800e12:min intervali60e5:peers6:\x7f\x00\x00\x01\x1a\xe1e"[..],
+                )
+                .assert()
+                .success()
+                .stdout("{\"interval\":1800,\"min interval\":60,\"peers\":[\"127.0.0.1:6881\"]}\n")
+                .stderr("interval: 1800\nmin interval: 60\n");
+        }
+
+        #[test]
+        fn printing_the_failure_reason() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--tracker")
+                .write_stdin("d14:failure reason6:bannede")
+                .assert()
+                .success()
+                .stderr("failure reason: banned\n");
+        }
+
+        #[test]
+        fn failing_with_the_parser_exit_code_when_the_input_is_not_bencode() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--tracker")
+                .write_stdin("d8:interval")
+                .assert()
+                .code(3)
+                .stderr(predicates::str::contains("Error:"));
+        }
+    }
+
+    mod decode_krpc_messages {
+        use assert_cmd::Command;
+
+        #[test]
+        fn writing_the_kind_method_and_decoded_message() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--krpc")
+                .write_stdin("d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe")
+                .assert()
+                .success()
+                .stdout("{\"kind\":\"query\",\"message\":{\"a\":{\"id\":\"6162636465666768696a30313233343536373839\"},\"q\":\"ping\",\"t\":\"6161\",\"y\":\"q\"},\"method\":\"ping\"}\n");
+        }
+
+        #[test]
+        fn writing_error_codes_with_their_meanings() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--krpc")
+                .write_stdin("d1:eli201e5:Oops!e1:t2:aa1:y1:ee")
+                .assert()
+                .success()
+                .stdout("{\"kind\":\"error\",\"message\":{\"e\":{\"code\":201,\"meaning\":\"Generic Error\",\"message\":\"Oops!\"},\"t\":\"6161\",\"y\":\"e\"},\"method\":null}\n");
+        }
+    }
+
+    mod rebuild_torrents_from_metadata_messages {
+        use assert_cmd::Command;
+        use std::fs;
+        use tempfile::tempdir;
+        use torrust_bencode2json::infohash::Infohash;
+
+        const PIECE_LENGTH: usize = 16 * 1024;
+
+        /// An info dictionary with two pieces.
+        fn info() -> Vec<u8> {
+            let name = "a".repeat(PIECE_LENGTH);
+
+            format!(
+                "d6:lengthi1e4:name{}:{name}12:piece lengthi16384e6:pieces20:{}e",
+                name.len(),
+                "p".repeat(20)
+            )
+            .into_bytes()
+        }
+
+        /// It writes the `data` messages of the pieces to files, returning
+        /// their paths.
+        fn write_messages(dir: &std::path::Path, info: &[u8], pieces: &[usize]) -> Vec<String> {
+            pieces
+                .iter()
+                .map(|piece| {
+                    let start = piece * PIECE_LENGTH;
+                    let end = (start + PIECE_LENGTH).min(info.len());
+
+                    let message = [
+                        format!(
+                            "d8:msg_typei1e5:piecei{piece}e10:total_sizei{}ee",
+                            info.len()
+                        )
+                        .as_bytes(),
+                        &info[start..end],
+                    ]
+                    .concat();
+
+                    let path = dir.join(format!("piece{piece}.bin"));
+                    fs::write(&path, message).unwrap();
+                    path.to_string_lossy().into_owned()
+                })
+                .collect()
+        }
+
+        #[test]
+        fn writing_the_torrent_with_the_trackers_of_the_magnet_uri() {
+            let dir = tempdir().unwrap();
+            let info = info();
+            let paths = write_messages(dir.path(), &info, &[1, 0]);
+
+            let magnet = format!(
+                "magnet:?xt=urn:btih:{}&tr=udp%3A%2F%2Ft%3A1",
+                Infohash::of(&info).v1_hex()
+            );
+
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--rebuild-metadata")
+                .args(&paths)
+                .arg("--magnet-uri")
+                .arg(magnet)
+                .assert()
+                .success()
+                .stdout([&b"d8:announce9:udp://t:14:info"[..], &info, b"e"].concat());
+        }
+
+        #[test]
+        fn failing_with_the_indices_of_the_missing_pieces() {
+            let dir = tempdir().unwrap();
+            let info = info();
+            let paths = write_messages(dir.path(), &info, &[1]);
+
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--rebuild-metadata")
+                .args(&paths)
+                .arg("--expected-infohash")
+                .arg(Infohash::of(&info).v1_hex())
+                .assert()
+                .code(7)
+                .stderr("Error: Missing pieces: [0], mismatched pieces: []\n");
+        }
+
+        #[test]
+        fn failing_when_the_infohash_is_not_the_expected_one() {
+            let dir = tempdir().unwrap();
+            let info = info();
+            let paths = write_messages(dir.path(), &info, &[0, 1]);
+
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--rebuild-metadata")
+                .args(&paths)
+                .arg("--expected-infohash")
+                .arg("00".repeat(20))
+                .assert()
+                .code(7)
+                .stderr(predicates::str::contains("expected 0000"));
+        }
+
+        #[test]
+        fn failing_without_the_expected_infohash() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--rebuild-metadata")
+                .arg("piece0.bin")
+                .assert()
+                .code(2);
+        }
+    }
+
+    mod extract_payloads_from_captures {
+        use assert_cmd::Command;
+        use serde_json::{json, Value};
+        use std::fs;
+
+        fn records(stdout: &[u8]) -> Vec<Value> {
+            String::from_utf8(stdout.to_vec())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+
+        fn extract(path: &str) -> Vec<Value> {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            let output = cmd.arg("--pcap").arg("-i").arg(path).assert().success();
+
+            records(&output.get_output().stdout)
+        }
+
+        #[test]
+        fn writing_krpc_messages_in_udp_datagrams() {
+            let records = extract("tests/fixtures/dht.pcap");
+
+            // The UDP tracker request is not bencoded
+            assert_eq!(records.len(), 2);
+            assert_eq!(
+                records[0],
+                json!({
+                    "timestamp": "1700000000.250000000",
+                    "flow": {"protocol": "udp", "src_ip": "10.0.0.2", "src_port": 6881, "dst_ip": "10.0.0.1", "dst_port": 6881},
+                    "kind": "krpc",
+                    "json": {
+                        "kind": "query",
+                        "method": "get_peers",
+                        "message": {
+                            "a": {"id": "6162636465666768696a30313233343536373839", "info_hash": "6d6e6f707172737475767778797a313233343536"},
+                            "q": "get_peers",
+                            "t": "6161",
+                            "y": "q",
+                        },
+                    },
+                })
+            );
+            assert_eq!(
+                records[1]["json"]["message"]["r"]["values"],
+                json!(["192.168.1.2:80"])
+            );
+        }
+
+        #[test]
+        fn writing_tracker_responses_in_reassembled_tcp_streams() {
+            let records = extract("tests/fixtures/tracker.pcapng");
+
+            assert_eq!(
+                records,
+                vec![json!({
+                    "timestamp": "1700000100.000006000",
+                    "flow": {"protocol": "tcp", "src_ip": "2001:db8::1", "src_port": 80, "dst_ip": "2001:db8::2", "dst_port": 50000},
+                    "kind": "tracker_response",
+                    "json": {"interval": 1800, "peers": ["10.0.0.5:6881"]},
+                })]
+            );
+        }
+
+        #[test]
+        fn writing_peer_wire_extension_messages() {
+            let records = extract("tests/fixtures/peer_wire.pcapng");
+
+            let kinds: Vec<_> = records
+                .iter()
+                .map(|record| record["kind"].clone())
+                .collect();
+
+            assert_eq!(
+                kinds,
+                vec![
+                    "extension_handshake",
+                    "extension_handshake",
+                    "ut_metadata",
+                    "ut_metadata",
+                    "ut_pex"
+                ]
+            );
+            assert_eq!(records[1]["json"]["yourip"], "10.0.0.2");
+            assert_eq!(
+                records[3]["json"],
+                json!({"msg_type": "data", "piece": 0, "total_size": 4, "data_length": 4})
+            );
+            assert_eq!(records[4]["json"]["added"], json!(["10.0.0.7:6881"]));
+        }
+
+        #[test]
+        fn writing_the_records_before_a_truncated_packet() {
+            let mut capture = fs::read("tests/fixtures/dht.pcap").unwrap();
+            capture.truncate(capture.len() - 10);
+
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            let output = cmd
+                .arg("--pcap")
+                .write_stdin(capture)
+                .assert()
+                .code(1)
+                .stderr("Error: The capture ends in the middle of a packet\n");
+
+            assert_eq!(records(&output.get_output().stdout).len(), 2);
+        }
+
+        #[test]
+        fn failing_when_the_input_is_not_a_capture() {
+            let mut cmd = Command::cargo_bin("torrust-bencode2json").unwrap();
+            cmd.arg("--pcap")
+                .arg("--error-format")
+                .arg("json")
+                .write_stdin("d4:spam4:eggse")
+                .assert()
+                .code(1)
+                .stderr(predicates::str::contains("\"kind\":\"invalid_capture\""));
+        }
+    }
 }